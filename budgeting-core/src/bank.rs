@@ -0,0 +1,242 @@
+//! Fetching transactions straight from the bank, through an open-banking bridge that gathers them
+//! from the bank's own API. Each bridge is a [`BankBridge`], and [`SimpleFin`] is the one built in.
+//! The fetched transactions are imported like those of a file (see
+//! [`Model::pending_bank_import`](crate::model::Model::pending_bank_import))
+
+use std::time::Duration;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, NaiveDate};
+use serde::Deserialize;
+use thiserror::Error;
+use ureq::{Agent, http::Uri};
+
+use crate::model::Transaction;
+
+/// How long a request can take before it is given up on. Bridges can take a while, as they ask
+/// the bank
+const TIMEOUT: Duration = Duration::from_mins(1);
+
+/// An account at the bank, with the transactions fetched from it
+#[derive(Debug, Clone)]
+pub struct BankAccount {
+	/// What the bridge tells the account apart by, which doesn't change
+	pub id: String,
+	/// The name of the account, for the user to pick it by
+	pub name: String,
+	pub transactions: Vec<BankTransaction>,
+}
+
+/// A transaction fetched from the bank
+#[derive(Debug, Clone)]
+pub struct BankTransaction {
+	/// What the bridge tells the transaction apart by, so it is only imported once
+	pub id: String,
+	pub transaction: Transaction,
+}
+
+/// A service that fetches transactions from banks
+pub trait BankBridge {
+	/// The name of the bridge, to show the user
+	fn name(&self) -> &str;
+
+	/// Fetches every account, with its transactions since the date
+	///
+	/// # Errors
+	/// If the bridge can't be reached, no longer accepts the credentials, or sends something that
+	/// can't be read
+	fn fetch(&self, since: NaiveDate) -> Result<Vec<BankAccount>, BankError>;
+}
+
+#[derive(Debug, Error)]
+pub enum BankError {
+	#[error("Couldn't reach the bank: {0}")]
+	Http(String),
+	#[error("The bank said {0}")]
+	Status(u16),
+	#[error("The bank no longer accepts the credentials, so connect it again")]
+	Unauthorized,
+	#[error("The setup token isn't valid: {0}")]
+	SetupToken(String),
+	#[error("Couldn't read what the bank sent: {0}")]
+	Response(String),
+}
+
+impl From<ureq::Error> for BankError {
+	fn from(e: ureq::Error) -> Self {
+		BankError::Http(e.to_string())
+	}
+}
+
+/// The [SimpleFIN](https://www.simplefin.org) bridge. It is connected to with a setup token from
+/// the bridge, which is claimed for an access URL holding the credentials. The access URL is all
+/// that is needed from then on, so it should be kept somewhere private
+#[derive(Debug, Clone)]
+pub struct SimpleFin {
+	access_url: String,
+}
+
+/// The accounts as `SimpleFIN` sends them
+#[derive(Debug, Deserialize)]
+struct AccountSet {
+	#[serde(default)]
+	errors: Vec<String>,
+	accounts: Vec<Account>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+	id: String,
+	name: String,
+	#[serde(default)]
+	transactions: Vec<SimpleFinTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimpleFinTransaction {
+	id: String,
+	/// When the transaction was posted, in seconds since the epoch
+	posted: i64,
+	/// The amount, as a decimal number
+	amount: String,
+	description: String,
+	payee: Option<String>,
+	#[serde(default)]
+	pending: bool,
+}
+
+impl SimpleFin {
+	/// Creates the bridge from an access URL, as returned by [`SimpleFin::access_url`]
+	#[must_use]
+	pub fn new(access_url: String) -> Self {
+		Self { access_url }
+	}
+
+	/// Connects to the bridge by claiming the setup token, which can only be done once
+	///
+	/// # Errors
+	/// If the token isn't valid or has already been claimed, or the bridge can't be reached
+	pub fn claim(setup_token: &str) -> Result<Self, BankError> {
+		let claim_url = BASE64_STANDARD
+			.decode(setup_token.trim())
+			.ok()
+			.and_then(|url| String::from_utf8(url).ok())
+			.ok_or_else(|| BankError::SetupToken("it isn't base64".to_string()))?;
+		let mut response = agent().post(&claim_url).send_empty()?;
+		match response.status().as_u16() {
+			200 => {}
+			403 => {
+				return Err(BankError::SetupToken(
+					"it has already been claimed".to_string(),
+				));
+			}
+			status => return Err(BankError::Status(status)),
+		}
+		let access_url = response.body_mut().read_to_string()?.trim().to_string();
+		tracing::info!("Connected to SimpleFIN");
+		Ok(Self { access_url })
+	}
+
+	/// The URL the bridge is reached at, which holds the credentials
+	#[must_use]
+	pub fn access_url(&self) -> &str {
+		&self.access_url
+	}
+}
+
+impl BankBridge for SimpleFin {
+	fn name(&self) -> &'static str {
+		"SimpleFIN"
+	}
+
+	fn fetch(&self, since: NaiveDate) -> Result<Vec<BankAccount>, BankError> {
+		// The credentials are in the URL, but are sent as a header like any other login
+		let uri: Uri = self
+			.access_url
+			.parse()
+			.map_err(|_| BankError::Unauthorized)?;
+		let authority = uri.authority().ok_or(BankError::Unauthorized)?;
+		let (login, host) = authority
+			.as_str()
+			.split_once('@')
+			.ok_or(BankError::Unauthorized)?;
+		let scheme = uri.scheme_str().unwrap_or("https");
+		let url = format!(
+			"{scheme}://{host}{}/accounts",
+			uri.path().trim_end_matches('/')
+		);
+		let start = since
+			.and_hms_opt(0, 0, 0)
+			.expect("Midnight is a valid time")
+			.and_utc()
+			.timestamp();
+		let mut response = agent()
+			.get(&url)
+			.query("start-date", start.to_string())
+			.header(
+				"Authorization",
+				format!("Basic {}", BASE64_STANDARD.encode(login)),
+			)
+			.call()?;
+		match response.status().as_u16() {
+			200 => {}
+			401 | 403 => return Err(BankError::Unauthorized),
+			status => return Err(BankError::Status(status)),
+		}
+		let text = response.body_mut().read_to_string()?;
+		let set: AccountSet =
+			serde_json::from_str(&text).map_err(|e| BankError::Response(e.to_string()))?;
+		for error in &set.errors {
+			tracing::warn!("SimpleFIN: {error}");
+		}
+		set.accounts
+			.into_iter()
+			.map(|account| {
+				let transactions = account
+					.transactions
+					.into_iter()
+					.map(SimpleFinTransaction::into_bank)
+					.collect::<Result<_, _>>()?;
+				Ok(BankAccount {
+					id: account.id,
+					name: account.name,
+					transactions,
+				})
+			})
+			.collect()
+	}
+}
+
+impl SimpleFinTransaction {
+	fn into_bank(self) -> Result<BankTransaction, BankError> {
+		let date = DateTime::from_timestamp(self.posted, 0)
+			.ok_or_else(|| BankError::Response(format!("invalid date {}", self.posted)))?
+			.date_naive();
+		let amount = self
+			.amount
+			.parse()
+			.map_err(|_| BankError::Response(format!("invalid amount {}", self.amount)))?;
+		let label = self
+			.payee
+			.filter(|payee| !payee.is_empty())
+			.unwrap_or(self.description);
+		Ok(BankTransaction {
+			id: self.id,
+			transaction: Transaction {
+				label,
+				date,
+				amount,
+				pending: self.pending,
+				..Transaction::default()
+			},
+		})
+	}
+}
+
+fn agent() -> Agent {
+	Agent::config_builder()
+		.http_status_as_error(false)
+		.timeout_global(Some(TIMEOUT))
+		.build()
+		.into()
+}
@@ -0,0 +1,34 @@
+//! The date and time as the program sees them. They come from the system clock, unless a date is
+//! set with [`set_today`] (e.g. with `--today` for demos, or in tests), so anything that depends on
+//! the date - default dates, scheduled and recurring transactions, reports and forecasts - can be
+//! reproduced
+
+use std::sync::{PoisonError, RwLock};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
+
+/// The date set with [`set_today`], if any
+static TODAY: RwLock<Option<NaiveDate>> = RwLock::new(None);
+
+/// Makes [`today`] (and [`now`]) give the date from now on, or the system's date again if given
+/// [`None`]. This is for the whole program, including the threads it has already started
+pub fn set_today(today: Option<NaiveDate>) {
+	*TODAY.write().unwrap_or_else(PoisonError::into_inner) = today;
+}
+
+/// Returns today's date
+#[must_use]
+pub fn today() -> NaiveDate {
+	now().date()
+}
+
+/// Returns the current date and time. If the date is set with [`set_today`], it is that date at the
+/// current time of day
+#[must_use]
+pub fn now() -> NaiveDateTime {
+	let now = Local::now().naive_local();
+	match *TODAY.read().unwrap_or_else(PoisonError::into_inner) {
+		Some(today) => today.and_time(now.time()),
+		None => now,
+	}
+}
@@ -0,0 +1,151 @@
+//! Rendering the whole file as a standalone HTML page, so it can be looked at in a browser by
+//! someone who doesn't use the terminal. The page is read-only, and everything it needs (styles,
+//! charts and the script filtering the tables) is inline, so it can be sent as a single file
+
+use std::{fs, path::Path};
+
+use chrono::NaiveDate;
+
+use crate::{
+	export::{ExportError, forecast_svg},
+	model::{Model, Sheet, format_currency},
+};
+
+/// The styles of the page
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 1250px; color: #222; }
+h1 { margin-bottom: 0; }
+.generated { color: #777; margin-top: 0.2em; }
+table { border-collapse: collapse; margin: 1em 0; width: 100%; }
+th, td { padding: 0.3em 0.6em; text-align: left; border-bottom: 1px solid #ddd; }
+th { background: #f2f2f2; }
+.amount { text-align: right; font-variant-numeric: tabular-nums; }
+.negative { color: #b00; }
+.pending { color: #777; font-style: italic; }
+.chart { overflow-x: auto; }
+#filter { font-size: 1em; padding: 0.4em; width: 100%; box-sizing: border-box; }
+";
+
+/// Hides the rows of the transaction tables that don't contain the text typed in the filter box
+const SCRIPT: &str = "
+document.getElementById('filter').addEventListener('input', function (event) {
+	const text = event.target.value.toLowerCase();
+	for (const row of document.querySelectorAll('table.transactions tbody tr')) {
+		row.hidden = !row.textContent.toLowerCase().includes(text);
+	}
+});
+";
+
+/// Writes the page for the model to the given file. Balances and forecasts are worked out as of
+/// `today`
+///
+/// # Errors
+/// If the file can't be written to
+pub fn export_html(model: &Model, today: NaiveDate, path: &Path) -> Result<(), ExportError> {
+	fs::write(path, html_page(model, today)).map_err(|e| ExportError::Io {
+		path: path.display().to_string(),
+		message: e.to_string(),
+	})
+}
+
+/// Returns the page for the model
+fn html_page(model: &Model, today: NaiveDate) -> String {
+	let title = escape(model.filename.as_deref().unwrap_or("Budget"));
+	let sheets: Vec<&Sheet> = std::iter::once(&model.main_sheet)
+		.chain(&model.sheets)
+		.collect();
+	let mut parts = vec![
+		"<!DOCTYPE html>".to_string(),
+		"<html lang=\"en\">".to_string(),
+		"<head>".to_string(),
+		"<meta charset=\"utf-8\">".to_string(),
+		"<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">".to_string(),
+		format!("<title>{title}</title>"),
+		format!("<style>{STYLE}</style>"),
+		"</head>".to_string(),
+		"<body>".to_string(),
+		format!("<h1>{title}</h1>"),
+		format!("<p class=\"generated\">As of {today}</p>"),
+		balances_table(&sheets, today),
+		"<input id=\"filter\" type=\"search\" placeholder=\"Filter transactions\">".to_string(),
+	];
+	parts.extend(sheets.iter().map(|sheet| sheet_section(sheet, today)));
+	parts.extend([
+		format!("<script>{SCRIPT}</script>"),
+		"</body>".to_string(),
+		"</html>".to_string(),
+	]);
+	parts.join("\n")
+}
+
+/// Returns a table of the current and projected balance of every sheet
+fn balances_table(sheets: &[&Sheet], today: NaiveDate) -> String {
+	let rows: Vec<String> = sheets
+		.iter()
+		.map(|sheet| {
+			let forecast = sheet.forecast(today);
+			let projected = forecast.points.last().map_or(0.0, |(_, balance)| *balance);
+			format!(
+				"<tr><td>{}</td>{}{}</tr>",
+				escape(&sheet.name),
+				amount_cell(forecast.current_balance()),
+				amount_cell(projected),
+			)
+		})
+		.collect();
+	format!(
+		"<h2>Balances</h2>\n<table>\n<thead><tr><th>Sheet</th><th class=\"amount\">Current</th>\
+		 <th class=\"amount\">Projected</th></tr></thead>\n<tbody>\n{}\n</tbody>\n</table>",
+		rows.join("\n")
+	)
+}
+
+/// Returns the section of the page for a sheet - its forecast chart and its transactions
+fn sheet_section(sheet: &Sheet, today: NaiveDate) -> String {
+	let name = escape(&sheet.name);
+	let chart = match forecast_svg(&sheet.forecast(today), sheet.min_balance, &sheet.name) {
+		Ok(svg) => format!("<div class=\"chart\">{svg}</div>"),
+		Err(ExportError::Empty) => "<p>There are no transactions yet</p>".to_string(),
+		Err(e) => format!("<p>{}</p>", escape(&e.to_string())),
+	};
+	let rows: Vec<String> = sheet
+		.transactions()
+		.iter()
+		.map(|t| {
+			format!(
+				"<tr{}><td>{}</td><td>{}</td>{}<td>{}</td><td>{}</td></tr>",
+				if t.pending { " class=\"pending\"" } else { "" },
+				t.date,
+				escape(&t.label),
+				amount_cell(t.amount),
+				escape(&t.category),
+				escape(&t.tags.join(", ")),
+			)
+		})
+		.collect();
+	format!(
+		"<h2>{name}</h2>\n{chart}\n<table class=\"transactions\">\n<thead><tr><th>Date</th>\
+		 <th>Label</th><th class=\"amount\">Amount</th><th>Category</th><th>Tags</th></tr>\
+		 </thead>\n<tbody>\n{}\n</tbody>\n</table>",
+		rows.join("\n")
+	)
+}
+
+/// Returns a table cell showing the amount, in red if it is negative
+fn amount_cell(amount: f64) -> String {
+	let class = if amount < 0.0 {
+		"amount negative"
+	} else {
+		"amount"
+	};
+	format!("<td class=\"{class}\">{}</td>", format_currency(amount))
+}
+
+/// Escapes the characters that have a meaning in HTML
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+		.replace('\'', "&#39;")
+}
@@ -0,0 +1,262 @@
+//! Exporting the file, its charts and reports to other formats, so they can be used outside the
+//! program - charts as images, reports and transactions as CSV, and the whole file as an HTML page
+
+use std::{fs, path::Path};
+
+use chrono::{Duration, NaiveDate};
+use plotters::{
+	coord::Shift,
+	prelude::{
+		BitMapBackend, ChartBuilder, Circle, DrawingArea, DrawingBackend, IntoDrawingArea,
+		LineSeries, PathElement, SVGBackend,
+	},
+	style::{BLACK, Color, GREEN, IntoFont, RED, RGBColor, WHITE},
+};
+use thiserror::Error;
+
+use crate::model::{Forecast, Report, Sheet, Totals, format_currency};
+
+mod html;
+
+pub use html::export_html;
+
+/// The size of exported images, in pixels
+const IMAGE_SIZE: (u32, u32) = (1200, 700);
+/// The color of upcoming transactions, matching the yellow they're shown in the terminal while
+/// still being readable on white
+const UPCOMING_COLOR: RGBColor = RGBColor(200, 150, 0);
+
+/// Renders the forecast chart to the given file, as an SVG or PNG depending on its extension
+///
+/// # Errors
+/// If the file has an unsupported extension, or can't be written to
+pub fn export_forecast(
+	forecast: &Forecast,
+	min_balance: Option<f64>,
+	title: &str,
+	path: &Path,
+) -> Result<(), ExportError> {
+	let extension = path
+		.extension()
+		.and_then(|e| e.to_str())
+		.map(str::to_ascii_lowercase);
+	match extension.as_deref() {
+		Some("svg") => draw_forecast(
+			&SVGBackend::new(path, IMAGE_SIZE).into_drawing_area(),
+			forecast,
+			min_balance,
+			title,
+		),
+		Some("png") => draw_forecast(
+			&BitMapBackend::new(path, IMAGE_SIZE).into_drawing_area(),
+			forecast,
+			min_balance,
+			title,
+		),
+		_ => Err(ExportError::UnknownFormat(path.display().to_string())),
+	}
+}
+
+/// Renders the forecast chart as the text of an SVG image, so it can be put in a page
+///
+/// # Errors
+/// If the forecast is empty
+pub fn forecast_svg(
+	forecast: &Forecast,
+	min_balance: Option<f64>,
+	title: &str,
+) -> Result<String, ExportError> {
+	let mut svg = String::new();
+	draw_forecast(
+		&SVGBackend::with_string(&mut svg, IMAGE_SIZE).into_drawing_area(),
+		forecast,
+		min_balance,
+		title,
+	)?;
+	Ok(svg)
+}
+
+/// Draws the forecast on any plotters backend. This mirrors the chart in the forecast popup - the
+/// balance as a line, upcoming transactions as points, and the minimum balance as a red line
+#[allow(clippy::cast_precision_loss)]
+fn draw_forecast<DB: DrawingBackend>(
+	root: &DrawingArea<DB, Shift>,
+	forecast: &Forecast,
+	min_balance: Option<f64>,
+	title: &str,
+) -> Result<(), ExportError> {
+	let draw_error = |e: &dyn std::fmt::Display| ExportError::Draw(e.to_string());
+	root.fill(&WHITE).map_err(|e| draw_error(&e))?;
+
+	let (Some((first, _)), Some((last, _))) = (forecast.points.first(), forecast.points.last())
+	else {
+		return Err(ExportError::Empty);
+	};
+	let x = |date: &NaiveDate| (*date - *first).num_days() as f64;
+	let max_x = x(last).max(1.0);
+	let (mut min_y, mut max_y) = forecast
+		.points
+		.iter()
+		.map(|(_, b)| *b)
+		.chain(min_balance)
+		.fold((f64::MAX, f64::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+	if (max_y - min_y).abs() < f64::EPSILON {
+		min_y -= 1.0;
+		max_y += 1.0;
+	}
+	// Leaves a little room above and below the line
+	let margin = (max_y - min_y) * 0.05;
+
+	let mut chart = ChartBuilder::on(root)
+		.caption(title, ("sans-serif", 28).into_font())
+		.margin(20)
+		.x_label_area_size(40)
+		.y_label_area_size(90)
+		.build_cartesian_2d(0.0..max_x, (min_y - margin)..(max_y + margin))
+		.map_err(|e| draw_error(&e))?;
+	chart
+		.configure_mesh()
+		.x_label_formatter(&|days| {
+			// The days are whole numbers, so truncating them loses nothing
+			#[allow(clippy::cast_possible_truncation)]
+			let date = *first + Duration::days(*days as i64);
+			date.to_string()
+		})
+		.y_label_formatter(&|balance| format_currency(*balance))
+		.draw()
+		.map_err(|e| draw_error(&e))?;
+
+	chart
+		.draw_series(LineSeries::new(
+			forecast.points.iter().map(|(d, b)| (x(d), *b)),
+			GREEN.stroke_width(2),
+		))
+		.map_err(|e| draw_error(&e))?
+		.label("Balance")
+		.legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], GREEN.stroke_width(2)));
+	chart
+		.draw_series(
+			forecast
+				.upcoming
+				.iter()
+				.map(|u| Circle::new((x(&u.date), u.balance), 4, UPCOMING_COLOR.filled())),
+		)
+		.map_err(|e| draw_error(&e))?
+		.label("Upcoming")
+		.legend(|(x, y)| Circle::new((x + 10, y), 4, UPCOMING_COLOR.filled()));
+	if let Some(min) = min_balance {
+		chart
+			.draw_series(LineSeries::new([(0.0, min), (max_x, min)], RED))
+			.map_err(|e| draw_error(&e))?
+			.label("Minimum")
+			.legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], RED));
+	}
+	chart
+		.configure_series_labels()
+		.background_style(WHITE.mix(0.8))
+		.border_style(BLACK)
+		.draw()
+		.map_err(|e| draw_error(&e))?;
+	root.present().map_err(|e| draw_error(&e))
+}
+
+/// Writes the report to the given file as CSV. Every row has the same columns, and the first
+/// column says which part of the report the row is from - the total, a category, a month or a payee
+///
+/// # Errors
+/// If the file can't be written to
+pub fn export_report_csv(report: &Report, path: &Path) -> Result<(), ExportError> {
+	let row = |section: &str, name: &str, totals: &Totals, change: Option<f64>| {
+		[
+			section.to_string(),
+			csv_field(name),
+			format!("{:.2}", totals.income),
+			format!("{:.2}", totals.expenses),
+			format!("{:.2}", totals.net()),
+			totals.count.to_string(),
+			change.map(|c| format!("{c:.2}")).unwrap_or_default(),
+		]
+		.join(",")
+	};
+	let mut lines = vec![
+		"Section,Name,Income,Expenses,Net,Count,Change".to_string(),
+		row(
+			"Total",
+			&format!("{} to {}", report.start, report.end),
+			&report.total,
+			None,
+		),
+	];
+	lines.extend(
+		report
+			.categories
+			.iter()
+			.map(|(name, totals)| row("Category", name, totals, None)),
+	);
+	lines.extend(
+		report
+			.months
+			.iter()
+			.enumerate()
+			.map(|(i, (month, totals))| {
+				row(
+					"Month",
+					&month.format("%Y-%m").to_string(),
+					totals,
+					report.month_change(i),
+				)
+			}),
+	);
+	lines.extend(
+		report
+			.payees
+			.iter()
+			.map(|(name, totals)| row("Payee", name, totals, None)),
+	);
+	lines.push(String::new());
+	fs::write(path, lines.join("\n")).map_err(|e| ExportError::Io {
+		path: path.display().to_string(),
+		message: e.to_string(),
+	})
+}
+
+/// Returns the transactions of the sheet as CSV, one row each with every column of the sheet,
+/// for other programs to read. Amounts are written without currency formatting
+pub fn transactions_csv(sheet: &Sheet) -> String {
+	let mut lines = vec!["Date,Label,Amount,Category,Tags,Pending,Scheduled".to_string()];
+	lines.extend(sheet.transactions().iter().map(|t| {
+		[
+			t.date.to_string(),
+			csv_field(&t.label),
+			format!("{:.2}", t.amount),
+			csv_field(&t.category),
+			csv_field(&t.tags.join(", ")),
+			t.pending.to_string(),
+			t.scheduled.to_string(),
+		]
+		.join(",")
+	}));
+	lines.push(String::new());
+	lines.join("\n")
+}
+
+/// Quotes a CSV field if it contains anything that would break the row
+fn csv_field(text: &str) -> String {
+	if text.contains([',', '"', '\n']) {
+		format!("\"{}\"", text.replace('"', "\"\""))
+	} else {
+		text.to_string()
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+	#[error("Can't export to \"{0}\" - the file must end in .svg or .png")]
+	UnknownFormat(String),
+	#[error("There is nothing to export")]
+	Empty,
+	#[error("Couldn't export the chart: {0}")]
+	Draw(String),
+	#[error("Couldn't write \"{path}\": {message}")]
+	Io { path: String, message: String },
+}
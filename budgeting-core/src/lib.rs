@@ -0,0 +1,24 @@
+//! The budgeting program's files, and everything done with them that doesn't need the terminal:
+//! reading and saving them (with their locks, backups, encryption and audit log), editing their
+//! sheets, importing bank statements, reports and forecasts, and exporting them to other formats.
+//! The terminal program is one frontend of it, and others (a GUI, a web page, scripts) can use
+//! it the same way:
+//!
+//! ```no_run
+//! use budgeting_core::model::{Access, Model};
+//!
+//! let model = Model::open("budget.json".to_string(), None, Access::ReadOnly)?;
+//! for title in model.sheet_titles() {
+//!     println!("{title}");
+//! }
+//! # Ok::<(), budgeting_core::model::SaveError>(())
+//! ```
+#![warn(clippy::pedantic, clippy::all, clippy::cargo, clippy::perf)]
+#![allow(clippy::module_name_repetitions, clippy::multiple_crate_versions)]
+
+pub mod bank;
+pub mod clock;
+pub mod export;
+pub mod model;
+pub mod rates;
+pub mod sync;
@@ -0,0 +1,80 @@
+//! What kind of account a sheet is for, what it held before its first transaction, and which
+//! account at the bank it is
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// The kind of account a sheet is for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+	#[default]
+	Checking,
+	Savings,
+	/// A credit card, whose balance is shown as what is owed on it
+	CreditCard,
+	Cash,
+}
+
+impl AccountType {
+	pub const ALL: [AccountType; 4] = [
+		AccountType::Checking,
+		AccountType::Savings,
+		AccountType::CreditCard,
+		AccountType::Cash,
+	];
+}
+
+impl Display for AccountType {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AccountType::Checking => write!(f, "checking"),
+			AccountType::Savings => write!(f, "savings"),
+			AccountType::CreditCard => write!(f, "credit card"),
+			AccountType::Cash => write!(f, "cash"),
+		}
+	}
+}
+
+impl FromStr for AccountType {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		AccountType::ALL
+			.into_iter()
+			.find(|kind| kind.to_string() == s.trim().to_lowercase())
+			.ok_or_else(|| format!("Unknown account type \"{s}\""))
+	}
+}
+
+/// The account a sheet is for
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Account {
+	pub kind: AccountType,
+	/// The balance of the account before the first transaction in the sheet, which every balance
+	/// of the sheet starts from
+	pub starting_balance: f64,
+	/// A note of the account's number, to tell which account at the bank the sheet is for
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub number: Option<String>,
+	/// Whether the amounts in imported files are the other way round, with money spent positive
+	/// (as on most credit card statements), so they are inverted as they are imported
+	pub invert_imports: bool,
+}
+
+impl Account {
+	/// Returns the balance as it is shown, which for a credit card is what is owed on it
+	#[must_use]
+	pub fn shown_balance(&self, balance: f64) -> f64 {
+		match self.kind {
+			AccountType::CreditCard => -balance,
+			_ => balance,
+		}
+	}
+
+	pub(super) fn is_default(&self) -> bool {
+		*self == Self::default()
+	}
+}
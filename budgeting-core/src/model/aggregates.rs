@@ -0,0 +1,120 @@
+//! Running totals of the transactions of a sheet, kept so the balances shown every frame aren't
+//! added up from scratch each time. The totals are worked out row by row as far as they are
+//! needed, and when a row changes, only the totals from that row on are thrown away
+
+use crate::model::{Sheet, Transaction};
+
+/// The balance of a sheet, with and without its scheduled transactions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Balance {
+	/// The balance leaving out scheduled transactions
+	pub actual: f64,
+	/// The balance including scheduled transactions
+	pub projected: f64,
+}
+
+impl Balance {
+	/// Returns the projected balance if `projected`, or the actual one otherwise
+	pub fn get(self, projected: bool) -> f64 {
+		if projected {
+			self.projected
+		} else {
+			self.actual
+		}
+	}
+}
+
+/// The totals of a sheet up to and including one of its rows
+#[derive(Debug, Clone, Copy, Default)]
+struct RunningTotal {
+	balance: Balance,
+	/// The amount of scheduled transactions
+	scheduled: usize,
+}
+
+impl RunningTotal {
+	/// Returns the totals after adding the transaction to these
+	fn then(self, transaction: &Transaction) -> Self {
+		Self {
+			balance: Balance {
+				actual: self.balance.actual
+					+ if transaction.scheduled {
+						0.0
+					} else {
+						transaction.amount
+					},
+				projected: self.balance.projected + transaction.amount,
+			},
+			scheduled: self.scheduled + usize::from(transaction.scheduled),
+		}
+	}
+}
+
+/// The running totals of a sheet, for as many of its rows as have been worked out
+#[derive(Debug, Clone, Default)]
+pub(super) struct Aggregates {
+	/// The totals up to and including each row, for the first rows of the sheet
+	running: Vec<RunningTotal>,
+}
+
+impl Aggregates {
+	/// Forgets the totals of the given row and every row after it, for when it has changed
+	pub(super) fn changed_from(&mut self, row: usize) {
+		self.running.truncate(row);
+	}
+
+	/// Returns the totals up to and including the given row, working out the totals of the rows
+	/// before it that haven't been yet, from the totals before the first row. `row` must be a row
+	/// of `transactions`
+	fn through(
+		&mut self,
+		row: usize,
+		transactions: &[Transaction],
+		start: RunningTotal,
+	) -> RunningTotal {
+		while self.running.len() <= row {
+			let previous = self.running.last().copied().unwrap_or(start);
+			self.running
+				.push(previous.then(&transactions[self.running.len()]));
+		}
+		self.running[row]
+	}
+}
+
+impl Sheet {
+	/// Returns the totals up to and including the given row, or of the whole sheet if it is past
+	/// the last row
+	fn running_total(&self, row: usize) -> RunningTotal {
+		let transactions = self.transactions();
+		let starting_balance = self.account().starting_balance;
+		let start = RunningTotal {
+			balance: Balance {
+				actual: starting_balance,
+				projected: starting_balance,
+			},
+			scheduled: 0,
+		};
+		let Some(last) = transactions.len().checked_sub(1) else {
+			return start;
+		};
+		self.aggregates
+			.borrow_mut()
+			.through(row.min(last), transactions, start)
+	}
+
+	/// Returns the balance of the whole sheet
+	pub fn total(&self) -> Balance {
+		self.running_total(usize::MAX).balance
+	}
+
+	/// Returns the balance of the sheet up to and including the given row. Rows past the last one
+	/// give the balance of the whole sheet
+	pub fn balance_at(&self, row: usize) -> Balance {
+		self.running_total(row).balance
+	}
+
+	/// Returns the amount of scheduled transactions in the sheet
+	pub fn scheduled_count(&self) -> usize {
+		self.running_total(usize::MAX).scheduled
+	}
+}
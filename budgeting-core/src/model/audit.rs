@@ -0,0 +1,336 @@
+//! The audit log, of every change made to the transactions of the sheets, so what changed (e.g.
+//! since last week) can be looked back on. Changes are found by comparing the sheets to how they
+//! were when they were last compared, rather than by every method that changes them, so nothing
+//! is missed. They are appended to a file beside the save file whenever it is saved, so only what
+//! was saved is logged, and the log is never rewritten
+
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Display,
+	fs::{self, OpenOptions},
+	io::{ErrorKind, Write},
+};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{
+	COLUMN_NAMES, Model, Sheet, Transaction, TransactionId,
+	encryption::{Encryption, Sealed},
+};
+
+/// A change recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+	/// When the change was made
+	pub time: NaiveDateTime,
+	/// The name of the sheet the change was made in
+	pub sheet: String,
+	pub change: AuditChange,
+	/// The transaction that was changed, if it was this session, so its row can be found even if
+	/// it has changed again since
+	#[serde(skip)]
+	pub id: Option<TransactionId>,
+}
+
+/// What was changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditChange {
+	Added {
+		row: RowSummary,
+	},
+	Deleted {
+		row: RowSummary,
+	},
+	/// A member of a transaction was changed. The row is as it was after the change
+	Edited {
+		row: RowSummary,
+		/// The name of the member, like the heading of its column
+		member: String,
+		before: String,
+		after: String,
+	},
+	SheetAdded,
+	SheetDeleted,
+}
+
+/// What a transaction looked like, to describe it in the log and find its row again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowSummary {
+	pub date: NaiveDate,
+	pub label: String,
+	pub amount: f64,
+}
+
+impl From<&Transaction> for RowSummary {
+	fn from(transaction: &Transaction) -> Self {
+		Self {
+			date: transaction.date,
+			label: transaction.label.clone(),
+			amount: transaction.amount,
+		}
+	}
+}
+
+impl RowSummary {
+	/// Returns whether the transaction looks like this
+	pub fn matches(&self, transaction: &Transaction) -> bool {
+		*self == RowSummary::from(transaction)
+	}
+}
+
+impl Display for RowSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} {} {}",
+			self.date,
+			crate::model::format_currency(self.amount),
+			self.label
+		)
+	}
+}
+
+impl AuditChange {
+	/// Returns the row the change was made to, if it was made to one
+	#[must_use]
+	pub fn row(&self) -> Option<&RowSummary> {
+		match self {
+			AuditChange::Added { row }
+			| AuditChange::Deleted { row }
+			| AuditChange::Edited { row, .. } => Some(row),
+			AuditChange::SheetAdded | AuditChange::SheetDeleted => None,
+		}
+	}
+}
+
+impl Display for AuditChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AuditChange::Added { row } => write!(f, "Added {row}"),
+			AuditChange::Deleted { row } => write!(f, "Deleted {row}"),
+			AuditChange::Edited {
+				row,
+				member,
+				before,
+				after,
+			} => write!(f, "{member} \"{before}\" → \"{after}\" ({row})"),
+			AuditChange::SheetAdded => write!(f, "Added the sheet"),
+			AuditChange::SheetDeleted => write!(f, "Deleted the sheet"),
+		}
+	}
+}
+
+/// The members of a transaction that changes are recorded to, with their names and values
+fn members(transaction: &Transaction) -> [(&'static str, String); 7] {
+	let flag = |set: bool| if set { "yes" } else { "no" }.to_string();
+	[
+		(COLUMN_NAMES[0], transaction.member_string(0)),
+		(COLUMN_NAMES[1], transaction.member_string(1)),
+		(COLUMN_NAMES[2], transaction.member_string(2)),
+		(COLUMN_NAMES[3], transaction.member_string(3)),
+		(COLUMN_NAMES[4], transaction.member_string(4)),
+		("Pending", flag(transaction.pending)),
+		("Scheduled", flag(transaction.scheduled)),
+	]
+}
+
+/// The transactions of a sheet as they were last compared, by their identities
+type Snapshot = HashMap<TransactionId, Transaction>;
+
+/// Records the changes made to the sheets, until they are written to the log file
+#[derive(Debug, Default)]
+pub struct AuditLog {
+	/// The changes recorded since the log file was last written to, oldest first
+	unsaved: Vec<AuditEntry>,
+	/// The transactions of each sheet, by its name, as they were when they were last compared
+	snapshots: Vec<(String, Snapshot)>,
+}
+
+impl AuditLog {
+	/// Starts recording the changes made to the sheets from how they are now
+	pub fn new<'a>(sheets: impl Iterator<Item = &'a Sheet>) -> Self {
+		Self {
+			unsaved: vec![],
+			snapshots: sheets
+				.map(|sheet| (sheet.name.clone(), Self::snapshot(sheet)))
+				.collect(),
+		}
+	}
+
+	fn snapshot(sheet: &Sheet) -> Snapshot {
+		sheet
+			.transactions()
+			.iter()
+			.map(|t| (t.id, t.clone()))
+			.collect()
+	}
+
+	/// Records every change made to the sheets since they were last compared, as made now
+	pub fn record<'a>(&mut self, sheets: impl Iterator<Item = &'a Sheet>, time: NaiveDateTime) {
+		let mut snapshots = Vec::with_capacity(self.snapshots.len());
+		for sheet in sheets {
+			let entry = |change, id| AuditEntry {
+				time,
+				sheet: sheet.name.clone(),
+				change,
+				id,
+			};
+			let Some(index) = self
+				.snapshots
+				.iter()
+				.position(|(name, _)| *name == sheet.name)
+			else {
+				self.unsaved.push(entry(AuditChange::SheetAdded, None));
+				snapshots.push((sheet.name.clone(), Self::snapshot(sheet)));
+				continue;
+			};
+			let (name, mut snapshot) = self.snapshots.swap_remove(index);
+			for transaction in sheet.transactions() {
+				let row = RowSummary::from(transaction);
+				let Some(old) = snapshot.get(&transaction.id) else {
+					self.unsaved
+						.push(entry(AuditChange::Added { row }, Some(transaction.id)));
+					snapshot.insert(transaction.id, transaction.clone());
+					continue;
+				};
+				let mut changed = false;
+				for ((member, before), (_, after)) in
+					members(old).into_iter().zip(members(transaction))
+				{
+					if before != after {
+						changed = true;
+						self.unsaved.push(entry(
+							AuditChange::Edited {
+								row: row.clone(),
+								member: member.to_string(),
+								before,
+								after,
+							},
+							Some(transaction.id),
+						));
+					}
+				}
+				if changed {
+					snapshot.insert(transaction.id, transaction.clone());
+				}
+			}
+			if snapshot.len() > sheet.transactions().len() {
+				let kept: HashSet<TransactionId> =
+					sheet.transactions().iter().map(|t| t.id).collect();
+				snapshot.retain(|id, old| {
+					let deleted = !kept.contains(id);
+					if deleted {
+						self.unsaved.push(entry(
+							AuditChange::Deleted {
+								row: RowSummary::from(&*old),
+							},
+							None,
+						));
+					}
+					!deleted
+				});
+			}
+			snapshots.push((name, snapshot));
+		}
+		for (name, _) in std::mem::replace(&mut self.snapshots, snapshots) {
+			self.unsaved.push(AuditEntry {
+				time,
+				sheet: name,
+				change: AuditChange::SheetDeleted,
+				id: None,
+			});
+		}
+	}
+
+	/// Appends the changes recorded since it was last written to the log file of the save file
+	/// with the given name, one per line, creating it if it doesn't exist. If the save file is
+	/// encrypted, each line is encrypted with its key
+	///
+	/// # Errors
+	/// If the log file can't be written to
+	pub fn write(
+		&mut self,
+		filename: &str,
+		encryption: Option<&Encryption>,
+	) -> std::io::Result<()> {
+		if self.unsaved.is_empty() {
+			return Ok(());
+		}
+		let mut text = String::new();
+		for entry in &self.unsaved {
+			let line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+			match encryption {
+				Some(encryption) => {
+					text.push_str(&encryption.seal(&line).map_err(std::io::Error::other)?);
+				}
+				None => text.push_str(&line),
+			}
+			text.push('\n');
+		}
+		OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(log_path(filename))?
+			.write_all(text.as_bytes())?;
+		self.unsaved.clear();
+		Ok(())
+	}
+
+	/// Returns every change in the log file of the save file with the given name (if it has one),
+	/// followed by the changes that haven't been written to it yet, oldest first. Lines of the
+	/// file that can't be read, like one cut short by a crash, or one encrypted with a key other
+	/// than the given one (e.g. before the passphrase was changed), are skipped
+	///
+	/// # Errors
+	/// If the log file exists but can't be read
+	pub fn entries(
+		&self,
+		filename: Option<&str>,
+		encryption: Option<&Encryption>,
+	) -> std::io::Result<Vec<AuditEntry>> {
+		let text = match filename.map(|filename| fs::read_to_string(log_path(filename))) {
+			Some(Ok(text)) => text,
+			Some(Err(e)) if e.kind() != ErrorKind::NotFound => return Err(e),
+			_ => String::new(),
+		};
+		let mut entries: Vec<AuditEntry> = text
+			.lines()
+			.filter_map(|line| match (Sealed::parse(line), encryption) {
+				(Some(sealed), Some(encryption)) => encryption.open(&sealed).ok(),
+				(Some(_), None) => None,
+				(None, _) => Some(line.to_string()),
+			})
+			.filter_map(|line| serde_json::from_str(&line).ok())
+			.collect();
+		entries.extend(self.unsaved.iter().cloned());
+		Ok(entries)
+	}
+}
+
+impl Model {
+	/// Returns the sheet (by its index) and row of the transaction the change was made to, if it
+	/// is still there. A transaction changed this session is found wherever it is, and others by
+	/// what they looked like after the change
+	pub fn audited_row(&self, entry: &AuditEntry) -> Option<(usize, usize)> {
+		let sheet_index = self
+			.all_sheets()
+			.position(|sheet| sheet.name == entry.sheet)?;
+		let sheet = self.get_sheet(sheet_index)?;
+		let row = match (&entry.change, entry.id) {
+			(AuditChange::Deleted { .. }, _) => None,
+			(_, Some(id)) => sheet.row_of(id),
+			(change, None) => {
+				let summary = change.row()?;
+				sheet.transactions().iter().position(|t| summary.matches(t))
+			}
+		}?;
+		Some((sheet_index, row))
+	}
+}
+
+/// Returns the name of the log file of the save file with the given name
+pub fn log_path(filename: &str) -> String {
+	format!("{filename}.audit")
+}
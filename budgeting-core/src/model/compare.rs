@@ -0,0 +1,128 @@
+//! Comparing two versions of a sheet transaction by transaction, e.g. a sheet and another one, or
+//! a sheet and how it was when the file was last saved. Transactions that are in both unchanged
+//! are matched up first, and those left over are taken as changed if they still share their date
+//! and label or amount, or their label and amount. The rest were added or removed
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::model::{Model, SaveError, Sheet, Transaction, external::row_key};
+
+/// How a transaction differs between the older and newer version of a sheet
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowDiff {
+	/// The transaction is in both, unchanged
+	Same(Transaction),
+	/// The transaction is only in the newer version
+	Added(Transaction),
+	/// The transaction is only in the older version
+	Removed(Transaction),
+	/// The transaction is in both, with some of its cells changed
+	Changed {
+		before: Transaction,
+		after: Transaction,
+	},
+}
+
+impl RowDiff {
+	/// Returns the date of the transaction, as it is in the newer version if it is in both
+	#[must_use]
+	pub fn date(&self) -> NaiveDate {
+		match self {
+			RowDiff::Same(t) | RowDiff::Added(t) | RowDiff::Removed(t) => t.date,
+			RowDiff::Changed { after, .. } => after.date,
+		}
+	}
+
+	/// Returns the columns whose cells changed, which is none unless the transaction did
+	#[must_use]
+	pub fn changed_columns(&self) -> Vec<usize> {
+		match self {
+			RowDiff::Changed { before, after } => (0..5)
+				.filter(|col| before.member_string(*col) != after.member_string(*col))
+				.collect(),
+			RowDiff::Same(_) | RowDiff::Added(_) | RowDiff::Removed(_) => vec![],
+		}
+	}
+}
+
+/// Returns how each transaction of the newer version of the sheet differs from the older one, and
+/// the transactions only in the older one, ordered by date
+#[must_use]
+pub fn compare_sheets(before: &Sheet, after: &Sheet) -> Vec<RowDiff> {
+	// The rows of each transaction in the older version, so repeated ones are matched one each
+	let mut unchanged: HashMap<String, Vec<usize>> = HashMap::new();
+	for (row, transaction) in before.transactions().iter().enumerate().rev() {
+		unchanged.entry(row_key(transaction)).or_default().push(row);
+	}
+	let mut diffs = vec![];
+	let mut added = vec![];
+	for transaction in after.transactions() {
+		match unchanged.get_mut(&row_key(transaction)).and_then(Vec::pop) {
+			Some(_) => diffs.push(RowDiff::Same(transaction.clone())),
+			None => added.push(Some(transaction)),
+		}
+	}
+	let mut rows: Vec<usize> = unchanged.into_values().flatten().collect();
+	rows.sort_unstable();
+	let mut removed: Vec<Option<&Transaction>> = rows
+		.iter()
+		.map(|row| before.transactions().get(*row))
+		.collect();
+
+	// The closest matches are taken first, so they aren't taken by looser ones
+	for matches in [
+		same_date as fn(&Transaction, &Transaction) -> bool,
+		same_label_and_amount,
+	] {
+		for new in &mut added {
+			let Some(transaction) = *new else {
+				continue;
+			};
+			let old = removed
+				.iter_mut()
+				.find(|old| old.is_some_and(|old| matches(old, transaction)))
+				.and_then(Option::take);
+			if let Some(old) = old {
+				diffs.push(RowDiff::Changed {
+					before: old.clone(),
+					after: transaction.clone(),
+				});
+				*new = None;
+			}
+		}
+	}
+	diffs.extend(removed.into_iter().flatten().cloned().map(RowDiff::Removed));
+	diffs.extend(added.into_iter().flatten().cloned().map(RowDiff::Added));
+	diffs.sort_by_key(RowDiff::date);
+	diffs
+}
+
+/// Returns whether the transactions are on the same date, with the same label or amount
+fn same_date(a: &Transaction, b: &Transaction) -> bool {
+	a.date == b.date && (a.label == b.label || a.member_string(2) == b.member_string(2))
+}
+
+/// Returns whether the transactions have the same label and amount
+fn same_label_and_amount(a: &Transaction, b: &Transaction) -> bool {
+	a.label == b.label && a.member_string(2) == b.member_string(2)
+}
+
+impl Model {
+	/// Returns the model as it was when it was last loaded or saved, to compare it to
+	///
+	/// # Errors
+	/// If it hasn't been saved, or its passphrase changed since, or what it was saved as can't be
+	/// read back, which shouldn't happen
+	pub fn saved_version(&self) -> Result<Model, SaveError> {
+		if self.saved_contents.is_empty() {
+			return Err(SaveError::NotSaved);
+		}
+		Self::parse(
+			self.filename.clone().unwrap_or_default(),
+			&self.saved_contents,
+			None,
+		)
+	}
+}
@@ -0,0 +1,56 @@
+//! The currencies of the file. Each sheet is kept in the file's base currency unless it is given
+//! its own, and sheets in other currencies are converted to the base one when they are added up,
+//! with the rates set by hand in the file or else those fetched online (see [`rates`](crate::rates))
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rates::ExchangeRates;
+
+/// The base currency of the file, and the exchange rates set by hand
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Currencies {
+	/// The currency sheets are kept in unless they are given their own, and totals are shown in,
+	/// as its code, e.g. `USD`
+	pub base: String,
+	/// How much one unit of each currency is worth in the base currency, as set by hand. These
+	/// are used in place of the fetched rates
+	pub overrides: BTreeMap<String, f64>,
+}
+
+impl Default for Currencies {
+	fn default() -> Self {
+		Self {
+			base: "USD".to_string(),
+			overrides: BTreeMap::new(),
+		}
+	}
+}
+
+impl Currencies {
+	/// Returns how much one unit of the currency is worth in the base currency, as set by hand or
+	/// else as fetched, if either knows it
+	#[must_use]
+	pub fn rate(&self, currency: &str, fetched: Option<&ExchangeRates>) -> Option<f64> {
+		if currency == self.base {
+			return Some(1.0);
+		}
+		self.overrides
+			.get(currency)
+			.copied()
+			.or_else(|| fetched?.rate(currency, &self.base))
+	}
+
+	pub(super) fn is_default(&self) -> bool {
+		*self == Self::default()
+	}
+}
+
+/// Returns the code of a currency as it is kept, e.g. `EUR` for `eur`, if it looks like one
+#[must_use]
+pub fn currency_code(text: &str) -> Option<String> {
+	let code = text.trim().to_ascii_uppercase();
+	(code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())).then_some(code)
+}
@@ -0,0 +1,437 @@
+//! How each sheet is displayed - its sort, grouping, visible columns, column widths and filter.
+//! These are saved with the sheet, so it opens the way it was left. The columns a sheet hasn't
+//! set itself follow the defaults in the config file
+
+use std::{
+	cmp::Ordering,
+	collections::{BTreeMap, BTreeSet},
+	fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Filter, Model, Sheet, Transaction, UpdateError};
+
+/// The headings of the columns of a sheet, by the index of the member they show (see
+/// [`Transaction::member_string`])
+pub const COLUMN_NAMES: [&str; 5] = ["Date", "Label", "Amount", "Category", "Tags"];
+
+/// The currency symbol used in front of the amounts
+const CURRENCY_SYMBOL: char = '$';
+
+/// A helper function to format currency according to accounting formatting
+/// E.g. -10.0 becomes "$(10.00)" and 10.0 becomes "$10.00"
+#[must_use]
+pub fn format_currency(a: f64) -> String {
+	if a >= 0.0 {
+		format!("{CURRENCY_SYMBOL}{a:05.2}")
+	} else {
+		format!("{}({:05.2})", CURRENCY_SYMBOL, -a)
+	}
+}
+
+/// The display settings of a sheet
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetDisplay {
+	/// The column the rows are sorted by when displayed, if any. Otherwise they are shown in the
+	/// order they are stored in
+	pub sort: Option<Sort>,
+	/// What the rows are grouped by, if anything. Groups are shown one after another, each under
+	/// a heading and sorted by [`SheetDisplay::sort`]
+	pub grouping: Option<Grouping>,
+	/// The groups that are folded, so only their headings are shown, by their names (see
+	/// [`Grouping::key`])
+	pub folded: BTreeSet<String>,
+	/// The columns that aren't shown, by the index of the member they show
+	pub hidden_columns: Vec<usize>,
+	/// The columns that are shown even though the config hides them by default
+	pub shown_columns: Vec<usize>,
+	/// The widths of the columns that don't use the default width, by the index of the member
+	/// they show
+	pub column_widths: BTreeMap<usize, u16>,
+	/// The filter applied to the sheet, if any. Only transactions matching it are shown
+	pub filter: Option<Filter>,
+}
+
+/// The columns every sheet shows, and how wide they are, unless the sheet sets its own. Columns
+/// are named by their headings (see [`COLUMN_NAMES`]), ignoring case
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnDefaults {
+	/// The columns that are hidden
+	pub hidden: Vec<String>,
+	/// The widths of the columns, which otherwise fit their contents (or fill the rest of the
+	/// table, for the label)
+	pub widths: BTreeMap<String, u16>,
+}
+
+/// Returns the index of the column with the given heading, ignoring case
+#[must_use]
+pub fn column_index(name: &str) -> Option<usize> {
+	COLUMN_NAMES
+		.iter()
+		.position(|column| column.eq_ignore_ascii_case(name.trim()))
+}
+
+impl ColumnDefaults {
+	/// Checks that every column named is one that exists
+	///
+	/// # Errors
+	/// With the first name that isn't a column
+	pub fn validate(&self) -> Result<(), String> {
+		match self
+			.hidden
+			.iter()
+			.chain(self.widths.keys())
+			.find(|name| column_index(name).is_none())
+		{
+			Some(name) => Err(format!(
+				"Unknown column \"{name}\", expected one of {}",
+				COLUMN_NAMES.join(", ")
+			)),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns whether the column is hidden by default
+	fn hides(&self, column: usize) -> bool {
+		self.hidden
+			.iter()
+			.any(|name| column_index(name) == Some(column))
+	}
+
+	/// Returns the default width of the column, if it has one
+	fn width(&self, column: usize) -> Option<u16> {
+		self.widths
+			.iter()
+			.find(|(name, _)| column_index(name) == Some(column))
+			.map(|(_, width)| *width)
+	}
+}
+
+/// A column to sort the displayed rows by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sort {
+	/// The index of the member to sort by
+	pub column: usize,
+	pub descending: bool,
+}
+
+impl Sort {
+	/// Compares two transactions by the sorted column
+	fn compare(self, a: &Transaction, b: &Transaction) -> Ordering {
+		let ordering = match self.column {
+			0 => a.date.cmp(&b.date),
+			2 => a.amount.total_cmp(&b.amount),
+			col => a
+				.member_string(col)
+				.to_lowercase()
+				.cmp(&b.member_string(col).to_lowercase()),
+		};
+		if self.descending {
+			ordering.reverse()
+		} else {
+			ordering
+		}
+	}
+}
+
+impl Display for Sort {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let name = COLUMN_NAMES.get(self.column).unwrap_or(&"?");
+		let direction = if self.descending {
+			"descending"
+		} else {
+			"ascending"
+		};
+		write!(f, "{name} ({direction})")
+	}
+}
+
+/// What the displayed rows can be grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Grouping {
+	Category,
+	Month,
+}
+
+impl Grouping {
+	/// Returns the name of the group the transaction is in. Uncategorised transactions are put in
+	/// a group with an empty name
+	#[must_use]
+	pub fn key(self, transaction: &Transaction) -> String {
+		match self {
+			Grouping::Category => transaction.category.clone(),
+			Grouping::Month => transaction.date.format("%Y-%m").to_string(),
+		}
+	}
+
+	/// Compares the groups of two transactions. Uncategorised transactions come last
+	fn compare(self, a: &Transaction, b: &Transaction) -> Ordering {
+		let (a, b) = (self.key(a), self.key(b));
+		(a.is_empty(), a.to_lowercase()).cmp(&(b.is_empty(), b.to_lowercase()))
+	}
+}
+
+/// A row of the table a sheet is shown in
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableRow {
+	/// The transaction with the given index
+	Transaction(usize),
+	/// The heading of a group, when the rows are grouped
+	Heading(GroupHeading),
+}
+
+/// The heading shown above each group of rows, with what is needed to show its subtotal, or to
+/// stand in for its rows when it is folded
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupHeading {
+	/// The name of the group. See [`Grouping::key`]
+	pub key: String,
+	/// The indices of the transactions in the group, in the order they are displayed in
+	pub rows: Vec<usize>,
+	/// Whether the rows of the group are hidden under the heading
+	pub folded: bool,
+}
+
+impl Display for Grouping {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Grouping::Category => write!(f, "Category"),
+			Grouping::Month => write!(f, "Month"),
+		}
+	}
+}
+
+impl SheetDisplay {
+	/// Returns the indices of the members shown as columns, in order
+	#[must_use]
+	pub fn visible_columns(&self, defaults: &ColumnDefaults) -> Vec<usize> {
+		(0..COLUMN_NAMES.len())
+			.filter(|col| !self.is_hidden(*col, defaults))
+			.collect()
+	}
+
+	/// Returns whether the column is hidden, by the sheet or by default
+	#[must_use]
+	pub fn is_hidden(&self, column: usize, defaults: &ColumnDefaults) -> bool {
+		self.hidden_columns.contains(&column)
+			|| (defaults.hides(column) && !self.shown_columns.contains(&column))
+	}
+
+	/// Returns the width of the column set by the sheet or by default, if it has one. Otherwise
+	/// the column fits its contents
+	#[must_use]
+	pub fn column_width(&self, column: usize, defaults: &ColumnDefaults) -> Option<u16> {
+		self.column_widths
+			.get(&column)
+			.copied()
+			.or_else(|| defaults.width(column))
+	}
+}
+
+impl Sheet {
+	/// Returns the indices (into [`Sheet::transactions`]) of every transaction that is displayed,
+	/// in the order they are displayed in
+	pub fn displayed_rows(&self) -> Vec<usize> {
+		let display = &self.display;
+		let mut rows: Vec<usize> = match &display.filter {
+			Some(filter) => self.filtered_rows(filter),
+			None => (0..self.transactions().len()).collect(),
+		};
+		if display.sort.is_some() || display.grouping.is_some() {
+			rows.sort_by(|a, b| {
+				let (a, b) = (&self.transactions()[*a], &self.transactions()[*b]);
+				let group = display
+					.grouping
+					.map_or(Ordering::Equal, |grouping| grouping.compare(a, b));
+				group.then_with(|| {
+					display
+						.sort
+						.map_or(Ordering::Equal, |sort| sort.compare(a, b))
+				})
+			});
+		}
+		rows
+	}
+
+	/// Returns the rows of the table the sheet is shown in: the displayed transactions, with a
+	/// heading above each group when they are grouped, and without the rows of folded groups
+	pub fn table_rows(&self) -> Vec<TableRow> {
+		let rows = self.displayed_rows();
+		let Some(grouping) = self.display.grouping else {
+			return rows.into_iter().map(TableRow::Transaction).collect();
+		};
+		let transactions = self.transactions();
+		let mut table = vec![];
+		for group in
+			rows.chunk_by(|a, b| grouping.key(&transactions[*a]) == grouping.key(&transactions[*b]))
+		{
+			let key = grouping.key(&transactions[group[0]]);
+			let folded = self.display.folded.contains(&key);
+			table.push(TableRow::Heading(GroupHeading {
+				key,
+				rows: group.to_vec(),
+				folded,
+			}));
+			if !folded {
+				table.extend(group.iter().copied().map(TableRow::Transaction));
+			}
+		}
+		table
+	}
+
+	/// Converts a displayed row into the index of the transaction it shows. Group headings don't
+	/// show one
+	pub fn displayed_row_index(&self, displayed_row: usize) -> Option<usize> {
+		if self.display == SheetDisplay::default() {
+			return (displayed_row < self.transactions().len()).then_some(displayed_row);
+		}
+		match self.table_rows().get(displayed_row)? {
+			TableRow::Transaction(row) => Some(*row),
+			TableRow::Heading(_) => None,
+		}
+	}
+
+	/// Returns the amount of rows that are displayed, including group headings
+	pub fn displayed_row_count(&self) -> usize {
+		if self.display.grouping.is_some() {
+			return self.table_rows().len();
+		}
+		match &self.display.filter {
+			Some(filter) => self.filtered_rows(filter).len(),
+			None => self.transactions().len(),
+		}
+	}
+}
+
+impl Model {
+	/// Sets the filter of the given sheet, or removes it if given [`None`]
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn set_filter(
+		&mut self,
+		sheet_index: usize,
+		filter: Option<Filter>,
+	) -> Result<(), UpdateError> {
+		self.checked_sheet_mut(sheet_index)?.display.filter = filter;
+		Ok(())
+	}
+
+	/// Sets the column the given sheet is sorted by when displayed, or shows it in its stored
+	/// order if given [`None`]
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn set_sort(&mut self, sheet_index: usize, sort: Option<Sort>) -> Result<(), UpdateError> {
+		self.checked_sheet_mut(sheet_index)?.display.sort = sort;
+		Ok(())
+	}
+
+	/// Sets what the rows of the given sheet are grouped by, if anything. Every group is unfolded,
+	/// as the old ones no longer exist
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn set_grouping(
+		&mut self,
+		sheet_index: usize,
+		grouping: Option<Grouping>,
+	) -> Result<(), UpdateError> {
+		let display = &mut self.checked_sheet_mut(sheet_index)?.display;
+		display.grouping = grouping;
+		display.folded.clear();
+		Ok(())
+	}
+
+	/// Folds or unfolds the group with the given name in the given sheet
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn set_folded(
+		&mut self,
+		sheet_index: usize,
+		key: &str,
+		folded: bool,
+	) -> Result<(), UpdateError> {
+		let display = &mut self.checked_sheet_mut(sheet_index)?.display;
+		if folded {
+			display.folded.insert(key.to_string());
+		} else {
+			display.folded.remove(key);
+		}
+		Ok(())
+	}
+
+	/// Folds every group of the given sheet, or unfolds them all
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn set_all_folded(&mut self, sheet_index: usize, folded: bool) -> Result<(), UpdateError> {
+		let sheet = self.checked_sheet_mut(sheet_index)?;
+		sheet.display.folded = if folded {
+			sheet
+				.table_rows()
+				.into_iter()
+				.filter_map(|row| match row {
+					TableRow::Heading(heading) => Some(heading.key),
+					TableRow::Transaction(_) => None,
+				})
+				.collect()
+		} else {
+			BTreeSet::new()
+		};
+		Ok(())
+	}
+
+	/// Shows the column if it is hidden, or hides it if it is shown. The last shown column can't
+	/// be hidden. Returns whether the column changed
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn toggle_column(
+		&mut self,
+		sheet_index: usize,
+		column: usize,
+		defaults: &ColumnDefaults,
+	) -> Result<bool, UpdateError> {
+		let display = &mut self.checked_sheet_mut(sheet_index)?.display;
+		if display.is_hidden(column, defaults) {
+			display.hidden_columns.retain(|c| *c != column);
+			if defaults.hides(column) {
+				display.shown_columns.push(column);
+				display.shown_columns.sort_unstable();
+			}
+		} else if display.visible_columns(defaults).len() > 1 {
+			display.shown_columns.retain(|c| *c != column);
+			if !defaults.hides(column) {
+				display.hidden_columns.push(column);
+				display.hidden_columns.sort_unstable();
+			}
+		} else {
+			return Ok(false);
+		}
+		Ok(true)
+	}
+
+	/// Sets the width of the column in the given sheet, or makes it use the default width if
+	/// given [`None`]
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn set_column_width(
+		&mut self,
+		sheet_index: usize,
+		column: usize,
+		width: Option<u16>,
+	) -> Result<(), UpdateError> {
+		let widths = &mut self.checked_sheet_mut(sheet_index)?.display.column_widths;
+		match width {
+			Some(width) => widths.insert(column, width),
+			None => widths.remove(&column),
+		};
+		Ok(())
+	}
+}
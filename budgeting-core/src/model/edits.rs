@@ -0,0 +1,105 @@
+use crate::model::{Model, ParseTransactionMemberError, Sheet, Transaction};
+
+/// A change to the value of a single cell, which can be previewed before it is applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellEdit {
+	/// The index of the transaction in the sheet
+	pub row: usize,
+	/// The column of the cell
+	pub col: usize,
+	/// The value of the cell before the edit
+	pub before: String,
+	/// The value of the cell after the edit
+	pub after: String,
+}
+
+impl Sheet {
+	/// Returns the edit of the cell in the given row and column to the given value, or [`None`]
+	/// if the cell already has that value
+	pub(super) fn edit(&self, row: usize, col: usize, after: String) -> Option<CellEdit> {
+		let before = self.transactions().get(row)?.member_string(col);
+		(before != after).then_some(CellEdit {
+			row,
+			col,
+			before,
+			after,
+		})
+	}
+
+	/// Returns the edits that set the cells in the given column of the given rows to `value`
+	///
+	/// # Errors
+	/// If the value isn't valid for the column
+	pub fn set_edits(
+		&self,
+		rows: &[usize],
+		col: usize,
+		value: &str,
+	) -> Result<Vec<CellEdit>, ParseTransactionMemberError> {
+		match col {
+			0 => {
+				Transaction::parse_date(value)?;
+			}
+			2 => {
+				Transaction::parse_amount(value)?;
+			}
+			_ => {}
+		}
+		Ok(rows
+			.iter()
+			.filter_map(|row| self.edit(*row, col, value.to_string()))
+			.collect())
+	}
+
+	/// Returns the edits that copy the value in the given column of the `source` row into the
+	/// rest of the given rows, whether they are above or below it
+	pub fn fill_down_edits(&self, source: usize, rows: &[usize], col: usize) -> Vec<CellEdit> {
+		let Some(source_row) = self.transactions().get(source) else {
+			return vec![];
+		};
+		let value = source_row.member_string(col);
+		rows.iter()
+			.filter(|row| **row != source)
+			.filter_map(|row| self.edit(*row, col, value.clone()))
+			.collect()
+	}
+
+	/// Returns the edits that replace every occurrence of `from` with `to` in the labels of the
+	/// given rows
+	pub fn replace_label_edits(&self, rows: &[usize], from: &str, to: &str) -> Vec<CellEdit> {
+		if from.is_empty() {
+			return vec![];
+		}
+		rows.iter()
+			.filter_map(|row| {
+				let label = &self.transactions().get(*row)?.label;
+				self.edit(*row, 1, label.replace(from, to))
+			})
+			.collect()
+	}
+}
+
+impl Model {
+	/// Applies all the given edits to the sheet with the given index. If any of them edit a date,
+	/// the sheet is then sorted if it is ordered by date
+	///
+	/// # Errors
+	/// If any of the edits has a value that isn't valid for its column. The edits before it are
+	/// still applied
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row with one of the edits in it
+	pub(crate) fn apply_edits(
+		&mut self,
+		sheet_index: usize,
+		edits: &[CellEdit],
+	) -> Result<(), ParseTransactionMemberError> {
+		for edit in edits {
+			self.update_transaction_member(sheet_index, edit.row, edit.col, edit.after.clone())?;
+		}
+		if edits.iter().any(|edit| edit.col == 0) {
+			self.get_sheet_mut(sheet_index).unwrap().sort_if_ordered();
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,309 @@
+//! Encrypting the save file with a passphrase, so the budget isn't stored in plaintext. The key is
+//! derived from the passphrase with Argon2id, once when the passphrase is given, and the contents
+//! are encrypted with ChaCha20-Poly1305. An encrypted file is still JSON, holding what is needed
+//! to decrypt it (apart from the passphrase) alongside the encrypted contents
+
+use std::{fmt::Debug, fs, io::ErrorKind};
+
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::{
+	AeadCore, ChaCha20Poly1305, Key, KeyInit, Nonce,
+	aead::{Aead, OsRng, rand_core::RngCore},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Model, SaveError, audit::log_path, backups, save::write_file};
+
+/// The way files are encrypted, recorded in each so another way could be added later
+const CIPHER: &str = "argon2id-chacha20poly1305";
+
+/// The key a file is encrypted with, and the salt it was derived from the passphrase with
+#[derive(Clone)]
+pub struct Encryption {
+	salt: [u8; 16],
+	key: Key,
+}
+
+impl Debug for Encryption {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Encryption")
+	}
+}
+
+/// The key the backups and audit log of the file were written with (or none, if they are in
+/// plaintext), kept when the passphrase is changed until the file is saved with the new one
+#[derive(Debug)]
+pub(super) struct ReplacedKey(Option<Encryption>);
+
+/// Encrypted text, as it is written to the file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sealed {
+	cipher: String,
+	salt: String,
+	nonce: String,
+	data: String,
+}
+
+impl Encryption {
+	/// Derives a new key from the passphrase, with a new salt
+	///
+	/// # Errors
+	/// If the key can't be derived
+	pub fn new(passphrase: &str) -> Result<Self, SaveError> {
+		let mut salt = [0; 16];
+		OsRng.fill_bytes(&mut salt);
+		Self::derive(passphrase, salt)
+	}
+
+	fn derive(passphrase: &str, salt: [u8; 16]) -> Result<Self, SaveError> {
+		let mut key = Key::default();
+		Argon2::default()
+			.hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+			.map_err(|e| SaveError::Encryption(e.to_string()))?;
+		Ok(Self { salt, key })
+	}
+
+	/// Returns whether the passphrase is the one the key was derived from
+	#[must_use]
+	pub fn matches(&self, passphrase: &str) -> bool {
+		Self::derive(passphrase, self.salt).is_ok_and(|derived| derived.key == self.key)
+	}
+
+	/// Encrypts the text, returning it as it is written to a file, on one line
+	///
+	/// # Errors
+	/// If the text can't be encrypted
+	pub fn seal(&self, text: &str) -> Result<String, SaveError> {
+		let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+		let data = ChaCha20Poly1305::new(&self.key)
+			.encrypt(&nonce, text.as_bytes())
+			.map_err(|e| SaveError::Encryption(e.to_string()))?;
+		let sealed = Sealed {
+			cipher: CIPHER.to_string(),
+			salt: STANDARD.encode(self.salt),
+			nonce: STANDARD.encode(nonce),
+			data: STANDARD.encode(data),
+		};
+		serde_json::to_string(&sealed).map_err(|e| SaveError::Json(e.to_string()))
+	}
+
+	/// Decrypts text sealed with this key
+	///
+	/// # Errors
+	/// If it was sealed with another key (e.g. before the passphrase was changed), or has been
+	/// tampered with
+	pub fn open(&self, sealed: &Sealed) -> Result<String, SaveError> {
+		let nonce = decode(&sealed.nonce)?;
+		if nonce.len() != 12 {
+			return Err(SaveError::Encryption("Invalid nonce".to_string()));
+		}
+		let text = ChaCha20Poly1305::new(&self.key)
+			.decrypt(Nonce::from_slice(&nonce), decode(&sealed.data)?.as_slice())
+			.map_err(|_| SaveError::WrongPassphrase)?;
+		String::from_utf8(text).map_err(|e| SaveError::Encryption(e.to_string()))
+	}
+}
+
+impl Sealed {
+	/// Reads text written by [`Encryption::seal`], or returns [`None`] if it wasn't encrypted
+	pub fn parse(text: &str) -> Option<Sealed> {
+		serde_json::from_str(text).ok()
+	}
+
+	/// Derives the key from the passphrase, and decrypts the text with it, returning the text and
+	/// the key to encrypt it with again
+	///
+	/// # Errors
+	/// If the passphrase is wrong, or the text was encrypted in a way this program can't read
+	pub fn unlock(&self, passphrase: &str) -> Result<(String, Encryption), SaveError> {
+		if self.cipher != CIPHER {
+			return Err(SaveError::Encryption(format!(
+				"The file is encrypted with {}, which this program can't read",
+				self.cipher
+			)));
+		}
+		let salt = decode(&self.salt)?
+			.try_into()
+			.map_err(|_| SaveError::Encryption("Invalid salt".to_string()))?;
+		let encryption = Encryption::derive(passphrase, salt)?;
+		Ok((encryption.open(self)?, encryption))
+	}
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, SaveError> {
+	STANDARD
+		.decode(text)
+		.map_err(|e| SaveError::Encryption(e.to_string()))
+}
+
+impl Model {
+	/// Returns the key the file is encrypted with, if it is
+	pub fn encryption(&self) -> Option<&Encryption> {
+		self.encryption.as_ref()
+	}
+
+	/// Returns whether the passphrase is the one the file is encrypted with. Always true if it
+	/// isn't encrypted
+	pub fn passphrase_matches(&self, passphrase: &str) -> bool {
+		self.encryption
+			.as_ref()
+			.is_none_or(|encryption| encryption.matches(passphrase))
+	}
+
+	/// Encrypts the file with the passphrase from when it is next saved, or stops encrypting it if
+	/// there is none. The model then has unsaved changes, so the file isn't left as it was. The
+	/// backups and audit log already written are encrypted again with the new passphrase (or
+	/// decrypted, if there is none) once the file is saved with it, so until then they are all
+	/// still readable with the passphrase the file is in
+	///
+	/// # Errors
+	/// If the key can't be derived from the passphrase
+	pub fn set_passphrase(&mut self, passphrase: Option<&str>) -> Result<(), SaveError> {
+		let encryption = passphrase.map(Encryption::new).transpose()?;
+		let replaced = std::mem::replace(&mut self.encryption, encryption);
+		// Changing it again before saving leaves the history in the key it was first in
+		if self.replaced_key.is_none() {
+			self.replaced_key = Some(ReplacedKey(replaced));
+		}
+		self.set_saved_contents(String::new());
+		Ok(())
+	}
+
+	/// Encrypts the backups and audit log of the file with the key it has just been saved with,
+	/// if the passphrase was changed since they were written
+	///
+	/// # Errors
+	/// If the backups or audit log can't be rewritten, in which case it is tried again on the
+	/// next save
+	pub(super) fn reseal_history(&mut self, filename: &str) -> Result<(), SaveError> {
+		let Some(ReplacedKey(old)) = &self.replaced_key else {
+			return Ok(());
+		};
+		reseal_history(filename, old.as_ref(), self.encryption.as_ref())?;
+		self.replaced_key = None;
+		Ok(())
+	}
+}
+
+/// Encrypts the backups and audit log of the save file with the given name with the new key, or
+/// decrypts them if there is none, having decrypted them with the old key. Backups and lines
+/// encrypted with some other key can't be decrypted, so they are left as they are
+fn reseal_history(
+	filename: &str,
+	old: Option<&Encryption>,
+	new: Option<&Encryption>,
+) -> Result<(), SaveError> {
+	let io_error = |e: std::io::Error| SaveError::Io(e.to_string());
+	for (backup, _) in backups(filename) {
+		let text = fs::read_to_string(&backup).map_err(io_error)?;
+		if let Some(text) = reseal(&text, old, new)? {
+			write_file(&backup, &text).map_err(io_error)?;
+		}
+	}
+	let log = log_path(filename);
+	let text = match fs::read_to_string(&log) {
+		Ok(text) => text,
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(io_error(e)),
+	};
+	let mut resealed = String::new();
+	for line in text.lines() {
+		resealed.push_str(&reseal(line, old, new)?.unwrap_or_else(|| line.to_string()));
+		resealed.push('\n');
+	}
+	write_file(&log, &resealed).map_err(io_error)
+}
+
+/// Returns the text encrypted with the new key, or in plaintext if there is none, having decrypted
+/// it with the old key if it was encrypted. Returns [`None`] if it was encrypted with another key
+fn reseal(
+	text: &str,
+	old: Option<&Encryption>,
+	new: Option<&Encryption>,
+) -> Result<Option<String>, SaveError> {
+	let plain = match (Sealed::parse(text), old) {
+		(Some(sealed), Some(old)) => match old.open(&sealed) {
+			Ok(plain) => plain,
+			Err(_) => return Ok(None),
+		},
+		(Some(_), None) => return Ok(None),
+		(None, _) => text.to_string(),
+	};
+	match new {
+		Some(new) => new.seal(&plain).map(Some),
+		None => Ok(Some(plain)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::{Access, Message, Transaction, audit::log_path, save::backup_path};
+
+	#[test]
+	fn opens_what_it_sealed_only_with_the_same_passphrase() {
+		let encryption = Encryption::new("hunter2").unwrap();
+		let text = encryption.seal("Salary").unwrap();
+		assert!(!text.contains("Salary"));
+		let sealed = Sealed::parse(&text).unwrap();
+		assert_eq!(encryption.open(&sealed).unwrap(), "Salary");
+		let (opened, unlocked) = sealed.unlock("hunter2").unwrap();
+		assert_eq!(opened, "Salary");
+		assert!(unlocked.matches("hunter2") && !unlocked.matches("hunter3"));
+
+		assert!(matches!(
+			sealed.unlock("hunter3"),
+			Err(SaveError::WrongPassphrase)
+		));
+		// The same passphrase with another salt is another key
+		let other = Encryption::new("hunter2").unwrap();
+		assert!(matches!(
+			other.open(&sealed),
+			Err(SaveError::WrongPassphrase)
+		));
+		assert!(Sealed::parse("{\"version\": 1}").is_none());
+	}
+
+	#[test]
+	fn changes_the_encryption_of_the_history_when_the_file_is_saved() {
+		let dir =
+			std::env::temp_dir().join(format!("budgeting-core-encrypt-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("budget.json").display().to_string();
+		let history = [path.clone(), backup_path(&path, 1), log_path(&path)];
+		let sealed = |file: &str| {
+			let text = fs::read_to_string(file).unwrap();
+			text.lines().all(|line| Sealed::parse(line).is_some())
+		};
+		let mut model = Model::scratch();
+		model.set_filename(path.clone()).unwrap();
+		model.set_passphrase(Some("hunter2")).unwrap();
+		model.save(3).unwrap();
+		let transaction = Transaction {
+			label: "Salary".to_string(),
+			amount: 2000.0,
+			..Transaction::default()
+		};
+		model
+			.update(Message::InsertRow {
+				sheet: 0,
+				row: 0,
+				transaction,
+			})
+			.unwrap();
+		model.save(3).unwrap();
+		assert!(history.iter().all(|file| sealed(file)));
+
+		// Until it is saved, the file and its history are all still in the old passphrase
+		model.set_passphrase(None).unwrap();
+		assert!(history.iter().all(|file| sealed(file)));
+		model.save(3).unwrap();
+		let plain = history.iter().all(|file| !sealed(file));
+		let reopened = Model::load(path.clone(), None, Access::ReadOnly);
+		fs::remove_dir_all(dir).unwrap();
+		assert!(plain);
+		let reopened = reopened.unwrap().get_main_sheet().transactions().to_vec();
+		assert!(reopened.iter().any(|t| t.label == "Salary"));
+	}
+}
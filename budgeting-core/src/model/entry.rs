@@ -0,0 +1,63 @@
+//! What a sheet fills in for the transactions entered into it by hand, like making every amount
+//! negative on a sheet of expenses, or giving every row the category of a sheet kept for it
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// The sign given to the amounts of new transactions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountSign {
+	/// Amounts are kept as they are typed
+	#[default]
+	AsTyped,
+	/// Amounts are made negative, so spending doesn't need a minus
+	Negative,
+	/// Amounts are made positive
+	Positive,
+}
+
+impl AmountSign {
+	pub const ALL: [AmountSign; 3] = [
+		AmountSign::AsTyped,
+		AmountSign::Negative,
+		AmountSign::Positive,
+	];
+
+	/// Returns the amount with this sign
+	#[must_use]
+	pub fn apply(self, amount: f64) -> f64 {
+		match self {
+			AmountSign::AsTyped => amount,
+			AmountSign::Negative => -amount.abs(),
+			AmountSign::Positive => amount.abs(),
+		}
+	}
+}
+
+impl Display for AmountSign {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AmountSign::AsTyped => write!(f, "as typed"),
+			AmountSign::Negative => write!(f, "negative"),
+			AmountSign::Positive => write!(f, "positive"),
+		}
+	}
+}
+
+/// What is filled in for the transactions entered into a sheet by hand
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryDefaults {
+	pub sign: AmountSign,
+	/// The category new transactions start with, if not empty
+	#[serde(skip_serializing_if = "String::is_empty")]
+	pub category: String,
+}
+
+impl EntryDefaults {
+	pub(super) fn is_default(&self) -> bool {
+		*self == Self::default()
+	}
+}
@@ -0,0 +1,124 @@
+//! Changes made to the file on disk by something other than this instance, like another program
+//! or a sync service, so they aren't silently overwritten by the next save. The file is compared
+//! to the model as it was last loaded or saved, and the changes are listed by transaction
+
+use std::{collections::HashMap, fmt::Display, fs, io::ErrorKind};
+
+use crate::model::{Model, SaveError, Transaction, audit::RowSummary, encryption::Sealed};
+
+/// A difference between the file on disk and the model
+#[derive(Debug, Clone)]
+pub enum DiskChange {
+	/// A sheet only in the file on disk
+	SheetAdded(String),
+	/// A sheet only in the model
+	SheetRemoved(String),
+	/// A transaction only in the file on disk, in the sheet with the given name
+	RowAdded(String, RowSummary),
+	/// A transaction only in the model, in the sheet with the given name
+	RowRemoved(String, RowSummary),
+}
+
+impl Display for DiskChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DiskChange::SheetAdded(sheet) => write!(f, "+ {sheet}: the sheet was added"),
+			DiskChange::SheetRemoved(sheet) => write!(f, "- {sheet}: the sheet was deleted"),
+			DiskChange::RowAdded(sheet, row) => write!(f, "+ {sheet}: {row}"),
+			DiskChange::RowRemoved(sheet, row) => write!(f, "- {sheet}: {row}"),
+		}
+	}
+}
+
+/// Returns what tells a transaction apart from another, for finding it in the other model
+pub(super) fn row_key(transaction: &Transaction) -> String {
+	serde_json::to_string(transaction).unwrap_or_default()
+}
+
+impl Model {
+	/// Reads the file as it is on disk, if it has changed since the model was last loaded or
+	/// saved. A file that has been deleted, or only reformatted, hasn't changed
+	///
+	/// # Errors
+	/// If the file can't be read, or has been encrypted with a passphrase the model doesn't have
+	pub fn read_disk(&self) -> Result<Option<Model>, SaveError> {
+		let Some(filename) = &self.filename else {
+			return Ok(None);
+		};
+		let text = match fs::read_to_string(filename) {
+			Ok(text) => text,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(SaveError::Io(e.to_string())),
+		};
+		let disk = self.read_copy(&text)?;
+		Ok((disk.saved_contents != self.saved_contents).then_some(disk))
+	}
+
+	/// Reads another copy of the file from its contents, e.g. one kept on a server, decrypting it
+	/// with the model's key if it is encrypted
+	///
+	/// # Errors
+	/// If the contents aren't a save file, or have been encrypted with a passphrase the model
+	/// doesn't have
+	pub fn read_copy(&self, text: &str) -> Result<Model, SaveError> {
+		let text = match (Sealed::parse(text), &self.encryption) {
+			(Some(sealed), Some(encryption)) => encryption.open(&sealed)?,
+			(Some(_), None) => return Err(SaveError::PassphraseNeeded),
+			(None, _) => text.to_string(),
+		};
+		let filename = self.filename.clone().unwrap_or_default();
+		Self::parse(filename, &text, self.encryption.clone())
+	}
+
+	/// Returns the differences between the model and the file on disk, as read by
+	/// [`Model::read_disk`]. A transaction that was changed is listed as removed and added
+	pub fn disk_changes(&self, disk: &Model) -> Vec<DiskChange> {
+		let mut changes = vec![];
+		for sheet in self.all_sheets() {
+			if !disk.all_sheets().any(|other| other.name == sheet.name) {
+				changes.push(DiskChange::SheetRemoved(sheet.name.clone()));
+			}
+		}
+		for disk_sheet in disk.all_sheets() {
+			let name = &disk_sheet.name;
+			let Some(sheet) = self.all_sheets().find(|sheet| sheet.name == *name) else {
+				changes.push(DiskChange::SheetAdded(name.clone()));
+				continue;
+			};
+			// How many of each transaction the model has, so repeated ones are counted
+			let mut own: HashMap<String, Vec<&Transaction>> = HashMap::new();
+			for transaction in sheet.transactions() {
+				own.entry(row_key(transaction))
+					.or_default()
+					.push(transaction);
+			}
+			for transaction in disk_sheet.transactions() {
+				let matched = own
+					.get_mut(&row_key(transaction))
+					.and_then(Vec::pop)
+					.is_some();
+				if !matched {
+					changes.push(DiskChange::RowAdded(name.clone(), transaction.into()));
+				}
+			}
+			for transaction in own.into_values().flatten() {
+				changes.push(DiskChange::RowRemoved(name.clone(), transaction.into()));
+			}
+		}
+		changes
+	}
+
+	/// Replaces the model with the file on disk, as read by [`Model::read_disk`], discarding any
+	/// unsaved changes. The lock and key of the file are kept
+	pub fn reload(&mut self, mut disk: Model) {
+		disk.lock = self.lock.take();
+		disk.read_only = self.read_only;
+		self.replace(disk);
+	}
+
+	/// Keeps the model over the file on disk, as read by [`Model::read_disk`], so it has unsaved
+	/// changes until it is saved over the file
+	pub fn keep_over_disk(&mut self, disk: Model) {
+		self.set_saved_contents(disk.saved_contents);
+	}
+}
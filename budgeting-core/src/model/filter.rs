@@ -0,0 +1,99 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{ParseTransactionMemberError, Transaction, search::SearchEntry};
+
+/// The tolerance used by `~` amount searches when none is given
+const DEFAULT_TOLERANCE: f64 = 1.0;
+
+/// A filter that can be applied to a sheet, to only show the transactions that match it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Filter {
+	/// Only shows transactions whose label is exactly the given label
+	Label(String),
+	/// Only shows transactions whose label or amount contains the given text, ignoring case
+	Text(String),
+	/// Only shows transactions whose amount matches the query, ignoring the sign
+	Amount(AmountQuery),
+}
+
+/// A way of matching an amount. All queries compare the absolute value of the amount, as a
+/// remembered price is usually positive even if it was recorded as an expense
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AmountQuery {
+	/// Matches amounts equal to the value, to the cent
+	Exact(f64),
+	/// Matches amounts that round to the value
+	Rounded(f64),
+	/// Matches amounts within the tolerance of the value
+	Within { value: f64, tolerance: f64 },
+}
+
+impl AmountQuery {
+	pub fn matches(&self, amount: f64) -> bool {
+		let amount = amount.abs();
+		match self {
+			AmountQuery::Exact(value) => (amount - value).abs() < 0.005,
+			AmountQuery::Rounded(value) => (amount.round() - value).abs() < f64::EPSILON,
+			AmountQuery::Within { value, tolerance } => (amount - value).abs() <= *tolerance,
+		}
+	}
+}
+
+impl Filter {
+	/// Returns whether the given transaction passes the filter
+	#[must_use]
+	pub fn matches(&self, transaction: &Transaction) -> bool {
+		match self {
+			Filter::Label(label) => transaction.label == *label,
+			Filter::Text(text) => SearchEntry::new(transaction).contains(&text.to_lowercase()),
+			Filter::Amount(query) => query.matches(transaction.amount),
+		}
+	}
+
+	/// Parses a search query into a filter
+	/// - `=23.50` matches amounts of exactly 23.50
+	/// - `=23` matches amounts that round to 23
+	/// - `~23.5` matches amounts within 1 of 23.5, and `~23.5±2` (or `~23.5+-2`) within 2
+	/// - Anything else matches labels or amounts containing the text
+	///
+	/// # Errors
+	/// If the query starts with `=` or `~` but the amount (or tolerance) can't be parsed
+	pub fn parse_search(query: &str) -> Result<Filter, ParseTransactionMemberError> {
+		let query = query.trim();
+		if let Some(amount) = query.strip_prefix('=') {
+			let value = Transaction::parse_amount(amount.trim())?.abs();
+			return Ok(Filter::Amount(if amount.contains('.') {
+				AmountQuery::Exact(value)
+			} else {
+				AmountQuery::Rounded(value)
+			}));
+		}
+		if let Some(amount) = query.strip_prefix('~') {
+			let (value, tolerance) = match amount.split_once('±').or(amount.split_once("+-")) {
+				Some((value, tolerance)) => (value, Transaction::parse_amount(tolerance.trim())?),
+				None => (amount, DEFAULT_TOLERANCE),
+			};
+			return Ok(Filter::Amount(AmountQuery::Within {
+				value: Transaction::parse_amount(value.trim())?.abs(),
+				tolerance: tolerance.abs(),
+			}));
+		}
+		Ok(Filter::Text(query.to_string()))
+	}
+}
+
+impl Display for Filter {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Filter::Label(label) => write!(f, "label = \"{label}\""),
+			Filter::Text(text) => write!(f, "contains \"{text}\""),
+			Filter::Amount(AmountQuery::Exact(value)) => write!(f, "amount = {value:.2}"),
+			Filter::Amount(AmountQuery::Rounded(value)) => write!(f, "amount ≈ {value}"),
+			Filter::Amount(AmountQuery::Within { value, tolerance }) => {
+				write!(f, "amount = {value}±{tolerance}")
+			}
+		}
+	}
+}
@@ -0,0 +1,108 @@
+use chrono::{Months, NaiveDate};
+
+use crate::model::{Model, Sheet};
+
+/// The projected balance of a sheet over time, including transactions that haven't happened yet
+#[derive(Debug, Clone)]
+pub struct Forecast {
+	/// The running balance of the sheet at the end of each date that has a transaction, in date
+	/// order
+	pub points: Vec<(NaiveDate, f64)>,
+	/// The transactions dated after `today`, with the balance right after each of them
+	pub upcoming: Vec<Upcoming>,
+	/// The date the forecast was made from
+	pub today: NaiveDate,
+	/// The balance before the first transaction
+	pub start: f64,
+}
+
+/// A transaction that hasn't happened yet, and its impact on the balance
+#[derive(Debug, Clone)]
+pub struct Upcoming {
+	pub date: NaiveDate,
+	pub label: String,
+	pub amount: f64,
+	/// The balance of the sheet right after this transaction
+	pub balance: f64,
+}
+
+impl Forecast {
+	/// Returns the first projected (after `today`) point at which the balance is below `min`
+	#[must_use]
+	pub fn first_dip_below(&self, min: f64) -> Option<(NaiveDate, f64)> {
+		self.points
+			.iter()
+			.find(|(date, balance)| *date > self.today && *balance < min)
+			.copied()
+	}
+
+	/// Returns the balance as of the end of `today`
+	#[must_use]
+	pub fn current_balance(&self) -> f64 {
+		self.points
+			.iter()
+			.take_while(|(date, _)| *date <= self.today)
+			.last()
+			.map_or(self.start, |(_, balance)| *balance)
+	}
+}
+
+impl Sheet {
+	/// Projects the balance of the sheet, treating every transaction dated after `today` as
+	/// upcoming. Transactions are taken in date order, keeping the sheet order for equal dates.
+	/// If the sheet accrues interest, the interest of the next year (or up to the last
+	/// transaction, if that is later) is projected as well
+	pub fn forecast(&self, today: NaiveDate) -> Forecast {
+		let until = self
+			.transactions()
+			.iter()
+			.map(|t| t.date)
+			.max()
+			.unwrap_or(today)
+			.max(today.checked_add_months(Months::new(12)).unwrap_or(today));
+		let interest = self.interest_between(today, until);
+		let mut transactions: Vec<_> = self.transactions().iter().chain(interest.iter()).collect();
+		transactions.sort_by_key(|t| t.date);
+
+		let mut points: Vec<(NaiveDate, f64)> = vec![];
+		let mut upcoming = vec![];
+		let start = self.account().starting_balance;
+		let mut balance = start;
+		for transaction in transactions {
+			balance += transaction.amount;
+			match points.last_mut() {
+				Some((date, b)) if *date == transaction.date => *b = balance,
+				_ => points.push((transaction.date, balance)),
+			}
+			if transaction.date > today {
+				upcoming.push(Upcoming {
+					date: transaction.date,
+					label: transaction.label.clone(),
+					amount: transaction.amount,
+					balance,
+				});
+			}
+		}
+
+		Forecast {
+			points,
+			upcoming,
+			today,
+			start,
+		}
+	}
+}
+
+impl Model {
+	/// Marks the transaction in the given row as scheduled, or as having happened
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn set_scheduled(&mut self, sheet_index: usize, row: usize, scheduled: bool) {
+		self.get_sheet_mut(sheet_index)
+			.unwrap()
+			.transaction_mut(row)
+			.unwrap()
+			.scheduled = scheduled;
+	}
+}
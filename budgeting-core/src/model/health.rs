@@ -0,0 +1,52 @@
+use chrono::NaiveDate;
+
+use crate::model::Model;
+
+/// A summary of the state of the whole file, shown when it is opened
+#[derive(Debug, Clone)]
+pub struct Health {
+	/// The amount of sheets, including the main sheet
+	pub sheets: usize,
+	/// The amount of transactions across all sheets
+	pub transactions: usize,
+	/// The amount of transactions that haven't been categorised
+	pub uncategorised: usize,
+	/// The statements that were due before today but have no payment recorded, as (sheet name,
+	/// due date)
+	pub overdue: Vec<(String, NaiveDate)>,
+}
+
+impl Model {
+	/// Summarises the state of every sheet as of `today`
+	pub fn health(&self, today: NaiveDate) -> Health {
+		let sheets: Vec<_> = (0..self.sheet_count())
+			.filter_map(|i| self.get_sheet(i))
+			.collect();
+
+		let overdue = sheets
+			.iter()
+			.flat_map(|sheet| {
+				sheet
+					.statements()
+					.into_iter()
+					.filter(|s| s.due < today && s.balance != 0.0)
+					.filter(|s| {
+						let label = s.payment_label(&sheet.name);
+						!sheet.transactions().iter().any(|t| t.label == label)
+					})
+					.map(|s| (sheet.name.clone(), s.due))
+			})
+			.collect();
+
+		Health {
+			sheets: sheets.len(),
+			transactions: sheets.iter().map(|s| s.transactions().len()).sum(),
+			uncategorised: sheets
+				.iter()
+				.flat_map(|s| s.transactions())
+				.filter(|t| t.category.trim().is_empty())
+				.count(),
+			overdue,
+		}
+	}
+}
@@ -0,0 +1,121 @@
+//! Importing the transactions fetched from a bank (see [`bank`](crate::bank)). Each sheet can be
+//! linked to an account, which its transactions are then imported from. The transactions already
+//! imported from the account are remembered, so fetching the same days again doesn't import them
+//! twice, even once they have been edited
+
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	bank::BankAccount,
+	clock,
+	model::{
+		Model, PendingImport,
+		import::{ImportError, ImportSource},
+	},
+};
+
+/// How long the transactions imported from an account are remembered, in days before the newest
+/// one. Fetches only go back a little before the newest one, so older ones aren't fetched again
+const REMEMBERED_DAYS: u64 = 120;
+
+/// How far before the newest imported transaction each fetch goes back, in days, to pick up those
+/// the bank posted late
+const OVERLAP_DAYS: u64 = 14;
+
+/// How far back the first fetch from an account goes, in days
+const FIRST_FETCH_DAYS: u64 = 30;
+
+/// A bank account linked to a sheet, which its transactions are imported into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BankLink {
+	/// The name of the sheet
+	pub sheet: String,
+	/// What the bridge tells the account apart by
+	pub account: String,
+	/// The name of the account
+	pub name: String,
+	/// The transactions already imported from the account, by what the bridge tells them apart
+	/// by, with their dates
+	#[serde(default)]
+	pub imported: BTreeMap<String, NaiveDate>,
+}
+
+impl BankLink {
+	/// Returns the date to fetch the account's transactions from
+	#[must_use]
+	pub fn fetch_since(&self) -> NaiveDate {
+		match self.imported.values().max() {
+			Some(newest) => *newest - Days::new(OVERLAP_DAYS),
+			None => clock::today() - Days::new(FIRST_FETCH_DAYS),
+		}
+	}
+}
+
+impl Model {
+	/// Returns the account linked to the sheet with the given name, if it is linked to one
+	#[must_use]
+	pub fn bank_link(&self, sheet: &str) -> Option<&BankLink> {
+		self.bank_links.iter().find(|link| link.sheet == sheet)
+	}
+
+	/// Applies the rules and payees to the transactions fetched from the account that haven't been
+	/// imported into the sheet yet, and finds which of them are probably already in it (e.g.
+	/// entered by hand), like [`Model::pending_import`]. Finishing the import links the account to
+	/// the sheet
+	///
+	/// # Errors
+	/// If the sheet doesn't exist
+	pub fn pending_bank_import(
+		&self,
+		sheet: &str,
+		account: BankAccount,
+	) -> Result<PendingImport, ImportError> {
+		let (sheet_index, sheet_ref) = self
+			.all_sheets()
+			.enumerate()
+			.find(|(_, s)| s.name == sheet)
+			.ok_or_else(|| ImportError::NoSheet(sheet.to_string()))?;
+		let mut link = self
+			.bank_link(sheet)
+			.filter(|link| link.account == account.id)
+			.cloned()
+			.unwrap_or_else(|| BankLink {
+				sheet: sheet.to_string(),
+				account: account.id.clone(),
+				name: account.name.clone(),
+				imported: BTreeMap::new(),
+			});
+		link.name = account.name;
+		let mut transactions = vec![];
+		for fetched in account.transactions {
+			if link.imported.contains_key(&fetched.id) {
+				continue;
+			}
+			link.imported.insert(fetched.id, fetched.transaction.date);
+			let mut transaction = fetched.transaction;
+			self.rules.apply(&mut transaction);
+			self.normalize_payee(&mut transaction);
+			transactions.push(transaction);
+		}
+		Ok(PendingImport::new(
+			ImportSource::Bank(link),
+			sheet_index,
+			sheet_ref,
+			transactions,
+		))
+	}
+
+	/// Links the account to its sheet, in place of any account it was linked to before, and
+	/// forgets the transactions imported from it long enough ago
+	pub(super) fn link_bank_account(&mut self, mut link: BankLink) {
+		if let Some(newest) = link.imported.values().max().copied() {
+			let oldest = newest - Days::new(REMEMBERED_DAYS);
+			link.imported.retain(|_, date| *date >= oldest);
+		}
+		self.bank_links.retain(|other| other.sheet != link.sheet);
+		self.bank_links.push(link);
+	}
+}
@@ -0,0 +1,80 @@
+//! Reading transactions from CSV files, where the layout of the columns is given by the preset
+
+use chrono::NaiveDate;
+
+use crate::model::{Transaction, import::ImportError};
+
+/// The layout of the columns of a CSV file
+#[derive(Debug, Clone)]
+pub struct CsvColumns {
+	/// The index of the date column
+	pub date: usize,
+	/// The index of the label column
+	pub label: usize,
+	/// The index of the amount column
+	pub amount: usize,
+	/// Whether the first line is a header, and should be skipped
+	pub has_header: bool,
+	/// The character separating the columns
+	pub delimiter: char,
+}
+
+/// Parses the contents of a CSV file into transactions
+pub(super) fn parse(
+	text: &str,
+	columns: &CsvColumns,
+	date_format: &str,
+) -> Result<Vec<Transaction>, ImportError> {
+	text.lines()
+		.enumerate()
+		.skip(usize::from(columns.has_header))
+		.filter(|(_, line)| !line.trim().is_empty())
+		.map(|(i, line)| {
+			let fields = split_line(line, columns.delimiter);
+			let field = |column: usize| {
+				fields
+					.get(column)
+					.map(|f| f.trim())
+					.ok_or(ImportError::MissingColumn {
+						line: i + 1,
+						column,
+					})
+			};
+			let date_text = field(columns.date)?;
+			let date = NaiveDate::parse_from_str(date_text, date_format).map_err(|_| {
+				ImportError::Date {
+					line: i + 1,
+					value: date_text.to_string(),
+				}
+			})?;
+			Ok(Transaction {
+				label: field(columns.label)?.to_string(),
+				date,
+				amount: super::parse_amount(field(columns.amount)?, i + 1)?,
+				..Transaction::default()
+			})
+		})
+		.collect()
+}
+
+/// Splits a line of a CSV file into its fields, handling double-quoted fields (which can contain
+/// the delimiter, and `""` for a literal quote)
+fn split_line(line: &str, delimiter: char) -> Vec<String> {
+	let mut fields = vec![];
+	let mut field = String::new();
+	let mut quoted = false;
+	let mut chars = line.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if quoted && chars.peek() == Some(&'"') => {
+				field.push('"');
+				chars.next();
+			}
+			'"' => quoted = !quoted,
+			c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+			c => field.push(c),
+		}
+	}
+	fields.push(field);
+	fields
+}
@@ -0,0 +1,186 @@
+//! Finding imported transactions that are probably already in the sheet, so importing a file that
+//! overlaps with an earlier one (or with transactions entered by hand) doesn't add them twice
+
+use std::collections::HashSet;
+
+use crate::model::{Message, Model, Sheet, Transaction, UpdateError, import::ImportSource};
+
+/// What to do with an imported transaction that looks like one already in the sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Resolution {
+	/// Don't import it
+	#[default]
+	Skip,
+	/// Import it anyway, as a separate transaction
+	Keep,
+	/// Don't import it, but give the existing transaction the imported label
+	Merge,
+}
+
+impl Resolution {
+	/// The resolution after this one, for cycling through them
+	#[must_use]
+	pub fn next(self) -> Self {
+		match self {
+			Resolution::Skip => Resolution::Keep,
+			Resolution::Keep => Resolution::Merge,
+			Resolution::Merge => Resolution::Skip,
+		}
+	}
+}
+
+/// An imported transaction that looks like one already in the sheet
+#[derive(Debug, Clone)]
+pub struct Conflict {
+	/// The transaction from the file
+	pub imported: Transaction,
+	/// The transaction it looks like, as it was when the import was read. It is found again by
+	/// its id when merging, as rows can be added, moved or deleted while the import is pending
+	pub existing: Transaction,
+	pub resolution: Resolution,
+}
+
+/// An import that has been read from its file, but not yet added to its sheet, so that its
+/// conflicts can be resolved first
+#[derive(Debug, Clone)]
+pub struct PendingImport {
+	pub source: ImportSource,
+	pub sheet_index: usize,
+	/// The transactions that don't look like any already in the sheet
+	pub transactions: Vec<Transaction>,
+	pub conflicts: Vec<Conflict>,
+}
+
+/// How many transactions an import added, merged and skipped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportCounts {
+	pub added: usize,
+	pub merged: usize,
+	pub skipped: usize,
+}
+
+/// Returns whether the labels share at least half the words of the shorter one, ignoring case
+/// and punctuation. An empty label is similar to anything, as it was probably entered by hand
+/// without one
+fn similar_labels(a: &str, b: &str) -> bool {
+	let words = |label: &str| -> HashSet<String> {
+		label
+			.split(|c: char| !c.is_alphanumeric())
+			.filter(|w| !w.is_empty())
+			.map(str::to_lowercase)
+			.collect()
+	};
+	let (a, b) = (words(a), words(b));
+	if a.is_empty() || b.is_empty() {
+		return true;
+	}
+	let shared = a.intersection(&b).count();
+	shared > 0 && shared * 2 >= a.len().min(b.len())
+}
+
+/// Returns whether the transactions are probably the same one - they're on the same day, for the
+/// same amount, with similar labels
+fn likely_duplicate(a: &Transaction, b: &Transaction) -> bool {
+	a.date == b.date && (a.amount - b.amount).abs() < 0.005 && similar_labels(&a.label, &b.label)
+}
+
+impl PendingImport {
+	/// Splits the transactions into those that look like a transaction already in the sheet, and
+	/// those that don't. Each transaction in the sheet is only matched once, so importing the same
+	/// purchase made twice in a day only conflicts with as many copies as are already there
+	pub(super) fn new(
+		source: ImportSource,
+		sheet_index: usize,
+		sheet: &Sheet,
+		imported: Vec<Transaction>,
+	) -> Self {
+		let mut matched = HashSet::new();
+		let mut transactions = vec![];
+		let mut conflicts = vec![];
+		for transaction in imported {
+			let existing_row =
+				sheet
+					.transactions()
+					.iter()
+					.enumerate()
+					.position(|(row, existing)| {
+						!matched.contains(&row) && likely_duplicate(existing, &transaction)
+					});
+			match existing_row {
+				Some(row) => {
+					matched.insert(row);
+					conflicts.push(Conflict {
+						imported: transaction,
+						existing: sheet.transactions()[row].clone(),
+						resolution: Resolution::default(),
+					});
+				}
+				None => transactions.push(transaction),
+			}
+		}
+		Self {
+			source,
+			sheet_index,
+			transactions,
+			conflicts,
+		}
+	}
+}
+
+impl Model {
+	/// Adds a pending import to its sheet, resolving each of its conflicts as chosen, and
+	/// remembers its settings so the import can be repeated, or links the bank account it came
+	/// from to the sheet
+	///
+	/// # Errors
+	/// If its sheet has been deleted since the import was read, or a transaction one of its
+	/// conflicts is merged into has been deleted from it, in which case nothing is imported
+	pub fn finish_import(&mut self, pending: PendingImport) -> Result<ImportCounts, UpdateError> {
+		let mut counts = ImportCounts {
+			added: pending.transactions.len(),
+			..ImportCounts::default()
+		};
+		let sheet_index = pending.sheet_index;
+		let sheet = self
+			.get_sheet(sheet_index)
+			.ok_or(UpdateError::Sheet(sheet_index))?;
+		let mut transactions = pending.transactions;
+		let mut messages = vec![];
+		for conflict in pending.conflicts {
+			match conflict.resolution {
+				Resolution::Skip => counts.skipped += 1,
+				Resolution::Keep => {
+					counts.added += 1;
+					transactions.push(conflict.imported);
+				}
+				Resolution::Merge => {
+					counts.merged += 1;
+					let row = sheet
+						.row_of(conflict.existing.id)
+						.ok_or(UpdateError::MergedTransaction(sheet_index))?;
+					let mut merged = sheet.transactions()[row].clone();
+					merged.update_label(conflict.imported.label);
+					messages.push(Message::ReplaceTransaction {
+						sheet: sheet_index,
+						row,
+						transaction: merged,
+					});
+				}
+			}
+		}
+		// Merges are done before anything is inserted, as inserting can move the existing rows
+		messages.push(Message::InsertRows {
+			sheet: sheet_index,
+			row: sheet.transactions().len(),
+			transactions,
+		});
+		for message in messages {
+			self.update(message)?;
+		}
+		match pending.source {
+			ImportSource::File(settings) => self.last_import = Some(settings),
+			ImportSource::Bank(link) => self.link_bank_account(link),
+		}
+		Ok(counts)
+	}
+}
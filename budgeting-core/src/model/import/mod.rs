@@ -0,0 +1,539 @@
+//! Importing transactions from files exported by banks
+
+use std::{
+	fs::{self, File},
+	io::Read,
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::{Model, Scripts, Transaction, scripts::ScriptPreset};
+
+mod bank;
+mod csv;
+mod duplicates;
+mod ofx;
+mod qif;
+
+pub use bank::BankLink;
+pub use csv::CsvColumns;
+pub use duplicates::{ImportCounts, PendingImport, Resolution};
+
+/// The kind of file a preset reads
+#[derive(Debug, Clone)]
+pub enum ImportFormat {
+	/// A CSV file, with the given layout of columns
+	Csv(CsvColumns),
+	/// A QIF file. Its dates are read with the preset's date format
+	Qif,
+	/// An OFX file (version 1 or 2). Its dates are always `YYYYMMDD`, so the preset's date
+	/// format is unused
+	Ofx,
+	/// A file read by a function of the user's scripts. See [`Scripts`]
+	Script(ScriptPreset),
+}
+
+impl ImportFormat {
+	/// The file extension used by files in this format
+	pub fn extension(&self) -> &str {
+		match self {
+			ImportFormat::Csv(_) => "csv",
+			ImportFormat::Qif => "qif",
+			ImportFormat::Ofx => "ofx",
+			ImportFormat::Script(preset) => preset.extension(),
+		}
+	}
+}
+
+/// Describes how a file exported by a bank maps onto [`Transaction`]s
+#[derive(Debug, Clone)]
+pub struct ImportPreset {
+	/// The name the preset is chosen by
+	pub name: String,
+	/// The kind of file the preset reads
+	pub format: ImportFormat,
+	/// The [`chrono::format::strftime`] format of the dates
+	pub date_format: String,
+}
+
+impl ImportPreset {
+	/// Returns the builtin presets
+	#[must_use]
+	pub fn builtin() -> Vec<ImportPreset> {
+		let csv = |name: &str, date_format: &str| ImportPreset {
+			name: name.to_string(),
+			format: ImportFormat::Csv(CsvColumns {
+				date: 0,
+				label: 1,
+				amount: 2,
+				has_header: true,
+				delimiter: ',',
+			}),
+			date_format: date_format.to_string(),
+		};
+		let qif = |name: &str, date_format: &str| ImportPreset {
+			name: name.to_string(),
+			format: ImportFormat::Qif,
+			date_format: date_format.to_string(),
+		};
+		vec![
+			csv("date,label,amount (ISO dates)", "%Y-%m-%d"),
+			csv("date,label,amount (dd/mm/yyyy)", "%d/%m/%Y"),
+			csv("date,label,amount (mm/dd/yyyy)", "%m/%d/%Y"),
+			qif("QIF (mm/dd/yyyy)", "%m/%d/%Y"),
+			qif("QIF (dd/mm/yyyy)", "%d/%m/%Y"),
+			ImportPreset {
+				name: "OFX".to_string(),
+				format: ImportFormat::Ofx,
+				date_format: "%Y%m%d".to_string(),
+			},
+		]
+	}
+
+	/// Returns the builtin presets, followed by those added by the user's scripts
+	#[must_use]
+	pub fn all(scripts: &Scripts) -> Vec<ImportPreset> {
+		let mut presets = Self::builtin();
+		presets.extend(scripts.presets());
+		presets
+	}
+
+	/// Finds a preset by name, either builtin or added by the scripts
+	#[must_use]
+	pub fn find(name: &str, scripts: &Scripts) -> Option<ImportPreset> {
+		Self::all(scripts).into_iter().find(|p| p.name == name)
+	}
+
+	/// Finds the first preset that reads files with the given extension, like `csv`
+	#[must_use]
+	pub fn for_extension(extension: &str, scripts: &Scripts) -> Option<ImportPreset> {
+		let extension = extension.to_ascii_lowercase();
+		Self::all(scripts)
+			.into_iter()
+			.find(|p| p.format.extension() == extension)
+	}
+
+	/// Parses the contents of a file into transactions
+	///
+	/// # Errors
+	/// If an entry is missing a field, or has a date or amount that can't be parsed
+	pub fn parse(&self, text: &str) -> Result<Vec<Transaction>, ImportError> {
+		match &self.format {
+			ImportFormat::Csv(columns) => csv::parse(text, columns, &self.date_format),
+			ImportFormat::Qif => qif::parse(text, &self.date_format),
+			ImportFormat::Ofx => ofx::parse(text),
+			ImportFormat::Script(preset) => preset.parse(text),
+		}
+	}
+}
+
+/// Parses an amount, ignoring currency symbols, spaces and thousands separators. When an amount
+/// has both commas and dots, whichever comes last is the decimal separator, as in `1.234,5` or
+/// `1,234.50`. With only commas, a comma followed by exactly two digits at the end is a decimal
+/// comma, as in `12,50 €`, and any other is a thousands separator, as in `1,234`
+fn parse_amount(text: &str, line: usize) -> Result<f64, ImportError> {
+	let amount: String = text
+		.chars()
+		.filter(|c| !matches!(c, '$' | '£' | '€' | ' '))
+		.collect();
+	let decimal_comma = match (amount.rfind(','), amount.rfind('.')) {
+		(Some(comma), Some(dot)) => comma > dot,
+		(Some(comma), None) => {
+			let cents = &amount[comma + 1..];
+			cents.len() == 2 && cents.bytes().all(|b| b.is_ascii_digit())
+		}
+		(None, _) => false,
+	};
+	let amount: String = if decimal_comma {
+		amount
+			.chars()
+			.filter(|c| *c != '.')
+			.map(|c| if c == ',' { '.' } else { c })
+			.collect()
+	} else {
+		amount.replace(',', "")
+	};
+	amount.parse().map_err(|_| ImportError::Amount {
+		line,
+		value: amount,
+	})
+}
+
+/// Where the transactions of an import came from
+#[derive(Debug, Clone)]
+pub enum ImportSource {
+	/// A file, read with the settings
+	File(ImportSettings),
+	/// A bank account, with the link to the sheet it is imported into as it will be once the
+	/// import is finished
+	Bank(BankLink),
+}
+
+impl ImportSource {
+	/// Returns the name of the sheet the transactions are imported into
+	#[must_use]
+	pub fn sheet(&self) -> &str {
+		match self {
+			ImportSource::File(settings) => &settings.sheet,
+			ImportSource::Bank(link) => &link.sheet,
+		}
+	}
+}
+
+/// The settings of an import, kept so it can be repeated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSettings {
+	/// The path of the file to import. The file name can contain `*` wildcards, in which case
+	/// the newest matching file is imported
+	pub pattern: String,
+	/// The name of the [`ImportPreset`] used to read the file
+	pub preset: String,
+	/// The name of the sheet the transactions are added to
+	pub sheet: String,
+}
+
+impl ImportSettings {
+	/// Returns the file to import - the newest file matching the pattern
+	///
+	/// # Errors
+	/// If no file matches the pattern
+	pub fn resolve(&self) -> Result<PathBuf, ImportError> {
+		let path = Path::new(&self.pattern);
+		let Some(file_pattern) = path.file_name().and_then(|f| f.to_str()) else {
+			return Err(ImportError::NoMatch(self.pattern.clone()));
+		};
+		if !file_pattern.contains('*') {
+			return if path.is_file() {
+				Ok(path.to_path_buf())
+			} else {
+				Err(ImportError::NoMatch(self.pattern.clone()))
+			};
+		}
+		let dir = match path.parent() {
+			Some(dir) if !dir.as_os_str().is_empty() => dir,
+			_ => Path::new("."),
+		};
+		fs::read_dir(dir)
+			.map_err(|e| ImportError::Io(e.to_string()))?
+			.filter_map(Result::ok)
+			.filter(|entry| {
+				entry
+					.file_name()
+					.to_str()
+					.is_some_and(|name| wildcard_match(file_pattern, name))
+			})
+			.filter_map(|entry| {
+				let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+				Some((modified, entry.path()))
+			})
+			.max_by_key(|(modified, _): &(SystemTime, PathBuf)| *modified)
+			.map(|(_, path)| path)
+			.ok_or_else(|| ImportError::NoMatch(self.pattern.clone()))
+	}
+}
+
+/// Matches text against a pattern where `*` matches any amount of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+	let parts: Vec<&str> = pattern.split('*').collect();
+	let (first, rest) = parts.split_first().expect("split always returns one part");
+	let Some(mut text) = text.strip_prefix(first) else {
+		return false;
+	};
+	let Some((last, middle)) = rest.split_last() else {
+		return text.is_empty();
+	};
+	for part in middle {
+		match text.find(part) {
+			Some(i) => text = &text[i + part.len()..],
+			None => return false,
+		}
+	}
+	text.ends_with(last)
+}
+
+impl ImportSettings {
+	/// Reads the file described by the settings and parses its transactions. This doesn't need
+	/// the model (only the scripts, for their presets), so it can be done on another thread.
+	/// `progress` is given the amount of bytes read so far and the size of the file, and returns
+	/// whether to carry on reading
+	///
+	/// # Errors
+	/// If the file can't be found or read, its contents can't be parsed, the preset doesn't exist,
+	/// or `progress` stops the reading
+	pub fn read<F>(&self, scripts: &Scripts, progress: F) -> Result<Vec<Transaction>, ImportError>
+	where
+		F: Fn(u64, u64) -> bool,
+	{
+		/// The amount of bytes read between each report of the progress
+		const CHUNK_SIZE: usize = 64 * 1024;
+
+		let preset = ImportPreset::find(&self.preset, scripts)
+			.ok_or_else(|| ImportError::NoPreset(self.preset.clone()))?;
+		let path = self.resolve()?;
+		let mut file = File::open(&path).map_err(|e| ImportError::Io(e.to_string()))?;
+		let size = file.metadata().map_or(0, |m| m.len());
+		let mut bytes = Vec::new();
+		let mut chunk = vec![0; CHUNK_SIZE];
+		loop {
+			let read = file
+				.read(&mut chunk)
+				.map_err(|e| ImportError::Io(e.to_string()))?;
+			if read == 0 {
+				break;
+			}
+			bytes.extend_from_slice(&chunk[..read]);
+			if !progress(bytes.len() as u64, size) {
+				return Err(ImportError::Cancelled);
+			}
+		}
+		let text = String::from_utf8(bytes).map_err(|e| ImportError::Io(e.to_string()))?;
+		let transactions = preset.parse(&text)?;
+		tracing::info!(
+			"Read {} transactions from {} with the {} preset",
+			transactions.len(),
+			path.display(),
+			self.preset
+		);
+		Ok(transactions)
+	}
+}
+
+impl Model {
+	/// Applies the rules and payees to the transactions read by [`ImportSettings::read`], after
+	/// inverting their amounts if the sheet's account asks for it, and finds which of them are
+	/// probably already in the sheet. Nothing is added until the import is passed
+	/// to [`Model::finish_import`]
+	///
+	/// # Errors
+	/// If the sheet doesn't exist
+	pub fn pending_import(
+		&self,
+		settings: ImportSettings,
+		mut transactions: Vec<Transaction>,
+	) -> Result<PendingImport, ImportError> {
+		let (sheet_index, sheet) = self
+			.all_sheets()
+			.enumerate()
+			.find(|(_, sheet)| sheet.name == settings.sheet)
+			.ok_or_else(|| ImportError::NoSheet(settings.sheet.clone()))?;
+		let invert = sheet.account().invert_imports;
+		for transaction in &mut transactions {
+			if invert {
+				transaction.amount = -transaction.amount;
+			}
+			self.rules.apply(transaction);
+			self.normalize_payee(transaction);
+		}
+		Ok(PendingImport::new(
+			ImportSource::File(settings),
+			sheet_index,
+			sheet,
+			transactions,
+		))
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+	#[error("No file matches \"{0}\"")]
+	NoMatch(String),
+	#[error("No import preset named \"{0}\"")]
+	NoPreset(String),
+	#[error("No sheet named \"{0}\"")]
+	NoSheet(String),
+	#[error("Couldn't read file: {0}")]
+	Io(String),
+	#[error("Line {line} has no column {column}")]
+	MissingColumn { line: usize, column: usize },
+	#[error("Line {line} has an invalid date \"{value}\"")]
+	Date { line: usize, value: String },
+	#[error("Line {line} has an invalid amount \"{value}\"")]
+	Amount { line: usize, value: String },
+	#[error("The entry ending on line {line} has no {field}")]
+	MissingField { line: usize, field: &'static str },
+	#[error("The import script failed: {0}")]
+	Script(String),
+	#[error("The import was cancelled")]
+	Cancelled,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// What an importer should read from a file: its transactions as (date, label, amount,
+	/// category), or the error it should fail with
+	type Expected = Result<Vec<(&'static str, &'static str, f64, &'static str)>, &'static str>;
+
+	/// Parses each text with the preset, checking it gives what is expected
+	fn check(preset: &ImportPreset, cases: &[(&str, Expected)]) {
+		for (text, expected) in cases {
+			let parsed = preset
+				.parse(text)
+				.map(|transactions| {
+					transactions
+						.iter()
+						.map(|t| {
+							(
+								t.date.to_string(),
+								t.label.clone(),
+								t.amount,
+								t.category.clone(),
+							)
+						})
+						.collect::<Vec<_>>()
+				})
+				.map_err(|e| e.to_string());
+			let expected = expected
+				.clone()
+				.map(|transactions| {
+					transactions
+						.into_iter()
+						.map(|(date, label, amount, category)| {
+							(
+								date.to_string(),
+								label.to_string(),
+								amount,
+								category.to_string(),
+							)
+						})
+						.collect::<Vec<_>>()
+				})
+				.map_err(str::to_string);
+			assert_eq!(parsed, expected, "parsing {text:?}");
+		}
+	}
+
+	#[test]
+	fn parses_csv_files() {
+		let preset = ImportPreset {
+			name: "test".to_string(),
+			format: ImportFormat::Csv(CsvColumns {
+				date: 1,
+				label: 2,
+				amount: 0,
+				has_header: true,
+				delimiter: ';',
+			}),
+			date_format: "%d/%m/%Y".to_string(),
+		};
+		check(
+			&preset,
+			&[
+				// The header is skipped, and the columns are read from where the preset says
+				(
+					"Amount;Date;Label\n-12.50;05/01/2024;Coffee\n\n+1000;06/01/2024;\"Pay; \"\"Jan\"\"\"\n",
+					Ok(vec![
+						("2024-01-05", "Coffee", -12.5, ""),
+						("2024-01-06", "Pay; \"Jan\"", 1000.0, ""),
+					]),
+				),
+				("Amount;Date;Label\n", Ok(vec![])),
+				(
+					"Amount;Date;Label\n-1;05/01/2024;A\n-1;2024-01-06;B",
+					Err("Line 3 has an invalid date \"2024-01-06\""),
+				),
+				(
+					"Amount;Date;Label\nlots;05/01/2024;A",
+					Err("Line 2 has an invalid amount \"lots\""),
+				),
+				(
+					"Amount;Date;Label\n-1;05/01/2024",
+					Err("Line 2 has no column 2"),
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn parses_qif_files() {
+		let preset = ImportPreset {
+			name: "test".to_string(),
+			format: ImportFormat::Qif,
+			date_format: "%d/%m/%Y".to_string(),
+		};
+		check(
+			&preset,
+			&[
+				// The payee is the label, or the memo if there is none
+				(
+					"!Type:Bank\nD05/01'24\nT-12.50\nPCoffee\nMLatte\nLFood\n^\nD 6/ 1/2024\nU1,000.00\nMSalary\n^\n",
+					Ok(vec![
+						("2024-01-05", "Coffee", -12.5, "Food"),
+						("2024-01-06", "Salary", 1000.0, ""),
+					]),
+				),
+				// The last entry doesn't have to be terminated
+				("D05.01.2024\nT5", Ok(vec![("2024-01-05", "", 5.0, "")])),
+				// Dates starting with the year are read as year, month and day whatever the
+				// preset's format
+				("D2024-01-05\nT5", Ok(vec![("2024-01-05", "", 5.0, "")])),
+				(
+					"D05/01/2024\nPCoffee\n^",
+					Err("The entry ending on line 3 has no amount"),
+				),
+				(
+					"T-1\nD31/02/2024\n^",
+					Err("Line 2 has an invalid date \"31/02/2024\""),
+				),
+				(
+					"D05/01/2024\nTfree\n^",
+					Err("Line 2 has an invalid amount \"free\""),
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn parses_ofx_files() {
+		let preset = ImportPreset::find("OFX", &Scripts::default()).unwrap();
+		check(
+			&preset,
+			&[
+				// Version 1 leaves values unclosed and version 2 closes them, and the name is the
+				// label, or the memo if there is none
+				(
+					"OFXHEADER:100\n<OFX>\n<STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20240105120000[-5:EST]\n<TRNAMT>-12.50\n<NAME>Tom &amp; Jerry's\n</STMTTRN>\n<stmttrn><DTPOSTED>20240106</DTPOSTED><TRNAMT>1000.00</TRNAMT><MEMO>Salary</MEMO></stmttrn>\n</OFX>",
+					Ok(vec![
+						("2024-01-05", "Tom & Jerry's", -12.5, ""),
+						("2024-01-06", "Salary", 1000.0, ""),
+					]),
+				),
+				// Tags outside of a transaction are ignored
+				("<OFX>\n<TRNAMT>-1\n</OFX>", Ok(vec![])),
+				(
+					"<STMTTRN>\n<TRNAMT>-1\n</STMTTRN>",
+					Err("The entry ending on line 3 has no date"),
+				),
+				(
+					"<STMTTRN>\n<DTPOSTED>2024\n<TRNAMT>-1\n</STMTTRN>",
+					Err("Line 2 has an invalid date \"2024\""),
+				),
+				(
+					"<STMTTRN>\n<DTPOSTED>20240105\n<TRNAMT>N/A\n</STMTTRN>",
+					Err("Line 3 has an invalid amount \"N/A\""),
+				),
+			],
+		);
+	}
+
+	#[test]
+	fn parses_decimal_commas() {
+		for (text, amount) in [
+			("12,50 €", 12.5),
+			("-1.234,50", -1234.5),
+			("1.234,5", 1234.5),
+			("1.234.567,891", 1_234_567.891),
+			("1,234,567.8", 1_234_567.8),
+			("$1,234.50", 1234.5),
+			("1,234", 1234.0),
+			("-0,05", -0.05),
+		] {
+			let parsed = parse_amount(text, 1).unwrap();
+			assert!((parsed - amount).abs() < 1e-9, "{text} parsed as {parsed}");
+		}
+	}
+}
@@ -0,0 +1,89 @@
+//! Reading transactions from OFX (Open Financial Exchange) files
+//!
+//! Both versions of the format are read the same way. Version 1 files are SGML, where elements
+//! holding a value aren't closed (`<TRNAMT>-12.50`), and version 2 files are XML, where they are
+//! (`<TRNAMT>-12.50</TRNAMT>`). Either way, every transaction is a `<STMTTRN>` aggregate, which is
+//! always closed, so the file is read as a flat list of tags and the values that follow them
+
+use chrono::NaiveDate;
+
+use crate::model::{Transaction, import::ImportError};
+
+/// The fields of the transaction currently being read
+#[derive(Default)]
+struct Entry {
+	date: Option<NaiveDate>,
+	amount: Option<f64>,
+	name: Option<String>,
+	memo: Option<String>,
+}
+
+/// Parses the contents of an OFX file into transactions
+pub(super) fn parse(text: &str) -> Result<Vec<Transaction>, ImportError> {
+	let mut transactions = vec![];
+	let mut entry: Option<Entry> = None;
+	let mut line = 1;
+	for chunk in text.split('<') {
+		let tag_line = line;
+		line += chunk.matches('\n').count();
+		let Some((tag, value)) = chunk.split_once('>') else {
+			continue;
+		};
+		let tag = tag.trim().to_ascii_uppercase();
+		let value = decode_entities(value.trim());
+		match tag.as_str() {
+			"STMTTRN" => entry = Some(Entry::default()),
+			"/STMTTRN" => {
+				let Some(finished) = entry.take() else {
+					continue;
+				};
+				transactions.push(Transaction {
+					date: finished.date.ok_or(ImportError::MissingField {
+						line: tag_line,
+						field: "date",
+					})?,
+					amount: finished.amount.ok_or(ImportError::MissingField {
+						line: tag_line,
+						field: "amount",
+					})?,
+					label: finished.name.or(finished.memo).unwrap_or_default(),
+					..Transaction::default()
+				});
+			}
+			_ => {
+				let Some(entry) = entry.as_mut() else {
+					continue;
+				};
+				match tag.as_str() {
+					// Dates are `YYYYMMDD`, optionally followed by the time and timezone
+					"DTPOSTED" => {
+						let date = value
+							.get(..8)
+							.and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+							.ok_or_else(|| ImportError::Date {
+								line: tag_line,
+								value: value.clone(),
+							})?;
+						entry.date = Some(date);
+					}
+					"TRNAMT" => entry.amount = Some(super::parse_amount(&value, tag_line)?),
+					"NAME" => entry.name = Some(value).filter(|n| !n.is_empty()),
+					"MEMO" => entry.memo = Some(value).filter(|m| !m.is_empty()),
+					_ => {}
+				}
+			}
+		}
+	}
+	Ok(transactions)
+}
+
+/// Replaces the escaped characters allowed in OFX values with the characters themselves
+fn decode_entities(value: &str) -> String {
+	value
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&apos;", "'")
+		.replace("&nbsp;", " ")
+		.replace("&amp;", "&")
+}
@@ -0,0 +1,124 @@
+//! Reading transactions from QIF (Quicken Interchange Format) files
+//!
+//! A QIF file is a list of entries, each made of lines starting with a character saying what the
+//! line holds (`D` for the date, `T` for the amount, `P` for the payee, `M` for the memo, `L` for
+//! the category, ...) and ending with a line holding only `^`. Lines starting with `!` describe the
+//! type of the entries that follow, and are skipped
+
+use chrono::NaiveDate;
+
+use crate::model::{Transaction, import::ImportError};
+
+/// The fields of the entry currently being read
+#[derive(Default)]
+struct Entry {
+	date: Option<NaiveDate>,
+	amount: Option<f64>,
+	payee: Option<String>,
+	memo: Option<String>,
+	category: Option<String>,
+}
+
+impl Entry {
+	/// Finishes the entry that ends on the given line
+	fn finish(self, line: usize) -> Result<Transaction, ImportError> {
+		Ok(Transaction {
+			date: self.date.ok_or(ImportError::MissingField {
+				line,
+				field: "date",
+			})?,
+			amount: self.amount.ok_or(ImportError::MissingField {
+				line,
+				field: "amount",
+			})?,
+			label: self.payee.or(self.memo).unwrap_or_default(),
+			category: self.category.unwrap_or_default(),
+			..Transaction::default()
+		})
+	}
+}
+
+/// Parses the contents of a QIF file into transactions. The date format is used for the `D`
+/// lines, after they are normalised by [`normalise_date`], unless they are in `YYYY-MM-DD` form
+pub(super) fn parse(text: &str, date_format: &str) -> Result<Vec<Transaction>, ImportError> {
+	let mut transactions = vec![];
+	let mut entry = Entry::default();
+	let mut started = false;
+	for (i, line) in text.lines().enumerate() {
+		let line = line.trim();
+		let Some(code) = line.chars().next() else {
+			continue;
+		};
+		let value = line[code.len_utf8()..].trim();
+		match code {
+			'!' => continue,
+			'^' => {
+				if started {
+					transactions.push(std::mem::take(&mut entry).finish(i + 1)?);
+				}
+				started = false;
+				continue;
+			}
+			'D' => {
+				let date = normalise_date(value);
+				let format = if is_iso_date(&date) {
+					"%Y-%m-%d"
+				} else {
+					date_format
+				};
+				entry.date = Some(NaiveDate::parse_from_str(&date, format).map_err(|_| {
+					ImportError::Date {
+						line: i + 1,
+						value: value.to_string(),
+					}
+				})?);
+			}
+			'T' | 'U' => entry.amount = Some(super::parse_amount(value, i + 1)?),
+			'P' => entry.payee = Some(value.to_string()).filter(|p| !p.is_empty()),
+			'M' => entry.memo = Some(value.to_string()).filter(|m| !m.is_empty()),
+			'L' => entry.category = Some(value.to_string()).filter(|c| !c.is_empty()),
+			_ => {}
+		}
+		started = true;
+	}
+	// The last entry of a file isn't always terminated
+	if started {
+		transactions.push(entry.finish(text.lines().count())?);
+	}
+	Ok(transactions)
+}
+
+/// Brings the different ways programs write QIF dates into one form, so a single format can parse
+/// them. Spaces are removed (`1/ 5/24`), the `'` some programs put before the year becomes a `/`
+/// (`1/5'24`), `-` and `.` separators become `/`, and two digit years are taken to be in the
+/// 2000s. Dates starting with a four digit year (`2024-01-05`) are left as they are
+fn normalise_date(date: &str) -> String {
+	let date: String = date.chars().filter(|c| !c.is_whitespace()).collect();
+	if is_iso_date(&date) {
+		return date;
+	}
+	let date: String = date
+		.chars()
+		.map(|c| {
+			if matches!(c, '\'' | '-' | '.') {
+				'/'
+			} else {
+				c
+			}
+		})
+		.collect();
+	let mut parts: Vec<String> = date.split('/').map(str::to_string).collect();
+	if let Some(year) = parts.last_mut()
+		&& year.len() <= 2
+		&& let Ok(short) = year.parse::<u32>()
+	{
+		*year = (2000 + short).to_string();
+	}
+	parts.join("/")
+}
+
+/// Returns whether the date starts with a four digit year followed by a `-`, as in `2024-01-05`
+fn is_iso_date(date: &str) -> bool {
+	date.split_once('-')
+		.is_some_and(|(year, _)| year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()))
+}
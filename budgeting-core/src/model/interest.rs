@@ -0,0 +1,220 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Message, Model, Sheet, Transaction};
+
+/// The label given to interest transactions posted by [`Model::accrue_interest`]
+pub const INTEREST_LABEL: &str = "Interest";
+
+/// How often interest is compounded (and posted) on a sheet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CompoundingPeriod {
+	Monthly,
+	Quarterly,
+	Yearly,
+}
+
+impl CompoundingPeriod {
+	/// The amount of periods in a year
+	fn per_year(self) -> f64 {
+		match self {
+			CompoundingPeriod::Monthly => 12.0,
+			CompoundingPeriod::Quarterly => 4.0,
+			CompoundingPeriod::Yearly => 1.0,
+		}
+	}
+
+	/// Returns the first day of the period after the one the given date is in. This is when the
+	/// interest of the period is posted
+	#[must_use]
+	pub fn next_period_start(self, date: NaiveDate) -> NaiveDate {
+		let months = match self {
+			CompoundingPeriod::Monthly => 1,
+			CompoundingPeriod::Quarterly => 3 - (date.month0() % 3),
+			CompoundingPeriod::Yearly => 13 - date.month(),
+		};
+		date.with_day(1)
+			.and_then(|d| d.checked_add_months(Months::new(months)))
+			.unwrap_or(NaiveDate::MAX)
+	}
+}
+
+impl Display for CompoundingPeriod {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			CompoundingPeriod::Monthly => write!(f, "monthly"),
+			CompoundingPeriod::Quarterly => write!(f, "quarterly"),
+			CompoundingPeriod::Yearly => write!(f, "yearly"),
+		}
+	}
+}
+
+impl FromStr for CompoundingPeriod {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim().to_lowercase().as_str() {
+			"m" | "month" | "monthly" => Ok(CompoundingPeriod::Monthly),
+			"q" | "quarter" | "quarterly" => Ok(CompoundingPeriod::Quarterly),
+			"y" | "year" | "yearly" => Ok(CompoundingPeriod::Yearly),
+			_ => Err("Period must be monthly, quarterly or yearly".to_string()),
+		}
+	}
+}
+
+/// The interest settings of a savings or loan sheet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterestSettings {
+	/// The annual interest rate, as a percentage
+	pub rate: f64,
+	/// How often interest is compounded
+	pub period: CompoundingPeriod,
+	/// The date interest was last posted on. Interest is accrued from the first transaction of
+	/// the sheet if it has never been posted
+	pub last_posted: Option<NaiveDate>,
+}
+
+impl Sheet {
+	/// Calculates the interest that would be posted after `from`, up to and including `until`,
+	/// without adding it to the sheet. Each posting is compounded into the balance used for the
+	/// next one
+	pub fn interest_between(&self, from: NaiveDate, until: NaiveDate) -> Vec<Transaction> {
+		let Some(settings) = &self.interest else {
+			return vec![];
+		};
+		let mut posted: Vec<Transaction> = vec![];
+		let mut date = settings.period.next_period_start(from);
+		while date <= until {
+			let balance = self.account().starting_balance
+				+ self
+					.transactions()
+					.iter()
+					.chain(posted.iter())
+					.filter(|t| t.date < date)
+					.map(|t| t.amount)
+					.sum::<f64>();
+			posted.push(Transaction {
+				label: INTEREST_LABEL.to_string(),
+				date,
+				amount: balance * settings.rate / 100.0 / settings.period.per_year(),
+				..Transaction::default()
+			});
+			date = settings.period.next_period_start(date);
+		}
+		posted
+	}
+}
+
+impl Model {
+	/// Posts the interest of every sheet with interest settings, for every period that has
+	/// ended since it was last posted. Each posting goes after the transactions on or before its
+	/// date, even in a sheet ordered by hand
+	pub fn accrue_interest(&mut self, today: NaiveDate) {
+		for index in 0..self.all_sheets().count() {
+			let Some(sheet) = self.get_sheet(index) else {
+				continue;
+			};
+			let Some(settings) = sheet.interest.clone() else {
+				continue;
+			};
+			let Some(from) = settings
+				.last_posted
+				.or_else(|| sheet.transactions().iter().map(|t| t.date).min())
+			else {
+				continue;
+			};
+			let posted = sheet.interest_between(from, today);
+			let Some(last) = posted.last() else {
+				continue;
+			};
+			let mut messages = vec![Message::SetInterest {
+				sheet: index,
+				interest: Some(InterestSettings {
+					last_posted: Some(last.date),
+					..settings
+				}),
+			}];
+			let mut dates: Vec<NaiveDate> = sheet.transactions().iter().map(|t| t.date).collect();
+			for transaction in posted {
+				let row = dates
+					.iter()
+					.rposition(|date| *date <= transaction.date)
+					.map_or(0, |i| i + 1);
+				dates.insert(row, transaction.date);
+				messages.push(Message::InsertRow {
+					sheet: index,
+					row,
+					transaction,
+				});
+			}
+			// The sheet and rows were just taken from the model, so these can't fail
+			for message in messages {
+				let _ = self.update(message);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn date(text: &str) -> NaiveDate {
+		text.parse().unwrap()
+	}
+
+	#[test]
+	fn posts_interest_for_each_period_that_has_ended() {
+		let transaction = |day: &str, label: &str, amount: f64| Transaction {
+			date: date(day),
+			label: label.to_string(),
+			amount,
+			..Transaction::default()
+		};
+		let mut model = Model::scratch_with(vec![
+			transaction("2024-01-01", "Opening", 1000.0),
+			transaction("2024-02-10", "Deposit", 100.0),
+		]);
+		let interest = InterestSettings {
+			rate: 12.0,
+			period: CompoundingPeriod::Monthly,
+			last_posted: None,
+		};
+		for message in [
+			Message::SetInterest {
+				sheet: 0,
+				interest: Some(interest),
+			},
+			Message::ToggleOrder { sheet: 0 },
+			Message::MoveRowDown { sheet: 0, row: 0 },
+		] {
+			model.update(message).unwrap();
+		}
+
+		model.accrue_interest(date("2024-03-15"));
+		let sheet = model.get_main_sheet();
+		let posted: Vec<_> = sheet
+			.transactions()
+			.iter()
+			.map(|t| (t.label.as_str(), t.date, (t.amount * 100.0).round() / 100.0))
+			.collect();
+		// The sheet is ordered by hand, so each posting goes after the rows on or before its date
+		assert_eq!(
+			posted,
+			[
+				("Deposit", date("2024-02-10"), 100.0),
+				("Opening", date("2024-01-01"), 1000.0),
+				(INTEREST_LABEL, date("2024-02-01"), 10.0),
+				(INTEREST_LABEL, date("2024-03-01"), 11.1),
+			]
+		);
+		let last_posted = sheet.interest.as_ref().unwrap().last_posted;
+		assert_eq!(last_posted, Some(date("2024-03-01")));
+
+		// Nothing is posted twice
+		model.accrue_interest(date("2024-03-31"));
+		assert_eq!(model.get_main_sheet().transactions().len(), 4);
+	}
+}
@@ -0,0 +1,162 @@
+//! Lock files, so the same file isn't edited by two instances of the program at once, where
+//! whichever saves last silently undoes the other's changes. While a file is open, a lock file
+//! beside it says which process has it. The lock is only advisory: another instance finding it
+//! asks whether to open the file read-only instead. A lock left by a process that has since
+//! stopped (e.g. it crashed) is stale, and is taken over as if it weren't there
+
+use std::{
+	fmt::Display,
+	fs::{self, OpenOptions},
+	io::{ErrorKind, Write},
+};
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Model, SaveError};
+
+/// How a file is opened, with regard to its lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Access {
+	/// Locked by this instance, unless another has it open
+	#[default]
+	Exclusive,
+	/// Not locked, and not saved to, leaving it to the instance that has it open
+	ReadOnly,
+	/// Locked by this instance even if another has it open, e.g. if its lock is wrong
+	TakeOver,
+}
+
+/// The process that has a file open, as written in its lock file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+	pub pid: u32,
+	/// The name of the machine the process is running on, if it could be found
+	pub host: String,
+	/// When the file was locked. Along with the process, this tells one lock from another
+	pub since: NaiveDateTime,
+}
+
+impl LockOwner {
+	fn this_process() -> Self {
+		Self {
+			pid: std::process::id(),
+			host: host_name(),
+			since: Local::now().naive_local(),
+		}
+	}
+
+	/// Returns whether the lock is stale, as it was left by a process on this machine that isn't
+	/// running any more. Processes on other machines can't be checked, so their locks never are
+	fn is_stale(&self) -> bool {
+		self.host == host_name() && !process_running(self.pid)
+	}
+}
+
+impl Display for LockOwner {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "process {}", self.pid)?;
+		if !self.host.is_empty() {
+			write!(f, " on {}", self.host)?;
+		}
+		write!(f, " (since {})", self.since.format("%Y-%m-%d %H:%M"))
+	}
+}
+
+/// The lock on an open file, which is removed when dropped
+#[derive(Debug)]
+pub struct FileLock {
+	path: String,
+	owner: LockOwner,
+}
+
+impl FileLock {
+	/// Locks the save file with the given name for this instance. If it's already locked by
+	/// another instance, the lock is only taken over if `take_over` is set (or it is stale)
+	///
+	/// # Errors
+	/// If the file is locked by another instance, or the lock file can't be written
+	pub fn acquire(filename: &str, take_over: bool) -> Result<FileLock, SaveError> {
+		let path = lock_path(filename);
+		let owner = LockOwner::this_process();
+		let text = serde_json::to_string(&owner).map_err(|e| SaveError::Json(e.to_string()))?;
+		let io_error = |e: std::io::Error| SaveError::Io(format!("Couldn't lock the file: {e}"));
+		match OpenOptions::new().write(true).create_new(true).open(&path) {
+			Ok(mut file) => file.write_all(text.as_bytes()).map_err(io_error)?,
+			Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+				if let Some(other) = read_owner(&path)
+					&& !take_over && other.pid != owner.pid
+					&& !other.is_stale()
+				{
+					return Err(SaveError::Locked(other));
+				}
+				fs::write(&path, text).map_err(io_error)?;
+			}
+			Err(e) => return Err(io_error(e)),
+		}
+		Ok(FileLock { path, owner })
+	}
+}
+
+impl Drop for FileLock {
+	/// Removes the lock file, unless another instance has taken it over since
+	fn drop(&mut self) {
+		if read_owner(&self.path).is_some_and(|owner| owner == self.owner) {
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+}
+
+/// Returns the process that has locked the file with the given lock file, if it is locked
+fn read_owner(path: &str) -> Option<LockOwner> {
+	serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Returns the name of the lock file of the save file with the given name
+fn lock_path(filename: &str) -> String {
+	format!("{filename}.lock")
+}
+
+/// Returns the name of this machine, or an empty string if it can't be found
+fn host_name() -> String {
+	fs::read_to_string("/etc/hostname")
+		.ok()
+		.or_else(|| std::env::var("HOSTNAME").ok())
+		.or_else(|| std::env::var("COMPUTERNAME").ok())
+		.map(|name| name.trim().to_string())
+		.unwrap_or_default()
+}
+
+/// Returns whether the process with the given id is running on this machine. Where that can't be
+/// told, it is assumed to be
+fn process_running(pid: u32) -> bool {
+	if cfg!(target_os = "linux") {
+		std::path::Path::new(&format!("/proc/{pid}")).exists()
+	} else if cfg!(unix) {
+		std::process::Command::new("kill")
+			.args(["-0", &pid.to_string()])
+			.stderr(std::process::Stdio::null())
+			.status()
+			.map_or(true, |status| status.success())
+	} else {
+		true
+	}
+}
+
+impl Model {
+	/// Returns whether the file was opened read-only, so it can't be saved
+	pub fn is_read_only(&self) -> bool {
+		self.read_only
+	}
+
+	/// Sets the file the model is saved to, locking it
+	///
+	/// # Errors
+	/// If another instance has the file open
+	pub fn set_filename(&mut self, filename: String) -> Result<(), SaveError> {
+		self.lock = Some(FileLock::acquire(&filename, false)?);
+		self.filename = Some(filename);
+		self.read_only = false;
+		Ok(())
+	}
+}
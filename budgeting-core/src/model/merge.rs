@@ -0,0 +1,176 @@
+//! Merging another copy of the file into the model, e.g. the copy on disk saved by another
+//! instance, or a conflicted copy left by a sync service. The sheets of each are compared by name
+//! (see [`compare_sheets`]), and for each transaction that differs, the model's version ("mine"),
+//! the other copy's ("theirs"), or both are kept
+
+use std::collections::HashMap;
+
+use crate::model::{
+	Model, RowDiff, Sheet, Transaction, UpdateError, compare_sheets, external::row_key,
+};
+
+/// Which versions of a transaction that differs between the copies are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pick {
+	/// Keep the model as it is
+	Mine,
+	/// Take the other copy's version instead
+	Theirs,
+	/// Keep both versions. Nothing is lost, so this is the default
+	#[default]
+	Both,
+}
+
+impl Pick {
+	/// Returns the next pick, to cycle through them
+	#[must_use]
+	pub fn next(self) -> Self {
+		match self {
+			Pick::Mine => Pick::Theirs,
+			Pick::Theirs => Pick::Both,
+			Pick::Both => Pick::Mine,
+		}
+	}
+}
+
+/// A transaction that differs between the copies, and which versions of it are kept. Added rows
+/// are only in the model, removed ones only in the other copy, and changed ones have the other
+/// copy's version before the model's
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeRow {
+	pub diff: RowDiff,
+	pub pick: Pick,
+}
+
+/// The transactions that differ in the sheet with the given name, which is created if only the
+/// other copy has it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetMerge {
+	pub name: String,
+	pub rows: Vec<MergeRow>,
+}
+
+impl Model {
+	/// Returns the transactions that differ between the model and the other copy of its file,
+	/// sheet by sheet, each to be kept as both versions. Sheets that are the same are left out
+	#[must_use]
+	pub fn merge_rows(&self, theirs: &Model) -> Vec<SheetMerge> {
+		let mut merges: Vec<SheetMerge> = self
+			.all_sheets()
+			.map(|mine| {
+				let diffs = match theirs.all_sheets().find(|sheet| sheet.name == mine.name) {
+					Some(their_sheet) => compare_sheets(their_sheet, mine),
+					None => mine
+						.transactions()
+						.iter()
+						.cloned()
+						.map(RowDiff::Added)
+						.collect(),
+				};
+				sheet_merge(&mine.name, diffs)
+			})
+			.collect();
+		merges.extend(
+			theirs
+				.all_sheets()
+				.filter(|sheet| !self.all_sheets().any(|mine| mine.name == sheet.name))
+				.map(|sheet| {
+					let diffs = sheet.transactions().iter().cloned().map(RowDiff::Removed);
+					sheet_merge(&sheet.name, diffs.collect())
+				}),
+		);
+		merges.retain(|merge| !merge.rows.is_empty());
+		merges
+	}
+
+	/// Keeps the picked versions of the transactions in each sheet. Transactions no longer kept
+	/// are deleted into the trash, and the other copy's are added by their date (or at the end of
+	/// sheets ordered by hand). A sheet only the other copy has is created if any of its
+	/// transactions are kept
+	///
+	/// # Errors
+	/// If a sheet goes missing partway through, though each is found or created before it is
+	/// merged into
+	pub(crate) fn merge(&mut self, merges: &[SheetMerge]) -> Result<(), UpdateError> {
+		for merge in merges {
+			let index = match (0..self.sheet_count())
+				.find(|i| self.get_sheet(*i).is_some_and(|s| s.name == merge.name))
+			{
+				Some(index) => index,
+				None if merge.rows.iter().any(|row| row.pick != Pick::Mine) => {
+					self.sheets.push(Sheet::new(merge.name.clone(), vec![]));
+					self.sheet_count() - 1
+				}
+				None => continue,
+			};
+			self.merge_sheet(index, &merge.rows)?;
+		}
+		Ok(())
+	}
+
+	fn merge_sheet(&mut self, sheet_index: usize, rows: &[MergeRow]) -> Result<(), UpdateError> {
+		let mut delete = vec![];
+		let mut replace = vec![];
+		let mut insert = vec![];
+		for row in rows {
+			match (&row.diff, row.pick) {
+				(RowDiff::Added(mine), Pick::Theirs) => delete.push(mine),
+				(RowDiff::Removed(theirs), Pick::Theirs | Pick::Both)
+				| (RowDiff::Changed { before: theirs, .. }, Pick::Both) => insert.push(theirs),
+				(RowDiff::Changed { before, after }, Pick::Theirs) => replace.push((after, before)),
+				_ => {}
+			}
+		}
+
+		let rows = self.find_rows(sheet_index, delete)?;
+		self.delete_rows(sheet_index, &rows);
+		// Rows move as they are replaced, so each is looked up once the previous one has been
+		for (mine, theirs) in replace {
+			if let Some(row) = self.find_rows(sheet_index, [mine])?.first() {
+				self.replace_transaction(sheet_index, *row, theirs.clone());
+			}
+		}
+		for theirs in insert {
+			let sheet = self
+				.get_sheet(sheet_index)
+				.ok_or(UpdateError::Sheet(sheet_index))?;
+			let end = sheet.transactions().len();
+			self.insert_row(sheet_index, end, theirs.clone());
+		}
+		Ok(())
+	}
+
+	/// Returns the rows of the sheet holding the given transactions, one each, ignoring their
+	/// identities as those aren't saved
+	fn find_rows<'a>(
+		&self,
+		sheet_index: usize,
+		transactions: impl IntoIterator<Item = &'a Transaction>,
+	) -> Result<Vec<usize>, UpdateError> {
+		let mut rows: HashMap<String, Vec<usize>> = HashMap::new();
+		let sheet = self
+			.get_sheet(sheet_index)
+			.ok_or(UpdateError::Sheet(sheet_index))?;
+		for (row, transaction) in sheet.transactions().iter().enumerate().rev() {
+			rows.entry(row_key(transaction)).or_default().push(row);
+		}
+		Ok(transactions
+			.into_iter()
+			.filter_map(|transaction| rows.get_mut(&row_key(transaction))?.pop())
+			.collect())
+	}
+}
+
+fn sheet_merge(name: &str, diffs: Vec<RowDiff>) -> SheetMerge {
+	SheetMerge {
+		name: name.to_string(),
+		rows: diffs
+			.into_iter()
+			.filter(|diff| !matches!(diff, RowDiff::Same(_)))
+			.map(|diff| MergeRow {
+				pick: Pick::default(),
+				diff,
+			})
+			.collect(),
+	}
+}
@@ -0,0 +1,584 @@
+use thiserror::Error;
+
+use crate::model::{
+	Account, CellEdit, DuplicateOptions, EntryDefaults, InterestSettings, Model,
+	ParseTransactionMemberError, Payee, RowOrder, Sheet, SheetMerge, StatementCycle, Transaction,
+};
+
+/// A change to the transactions or sheets of the model, as a value, so it can be logged or
+/// compared. Apply it with [`Model::update`]. Rows and sheets are given by index, as with
+/// [`Model::get_sheet`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+	/// Inserts the transaction at the row, or by its date if the sheet is ordered by date
+	InsertRow {
+		sheet: usize,
+		row: usize,
+		transaction: Transaction,
+	},
+	/// Inserts all the transactions in order, starting at the row, or each by its date if the
+	/// sheet is ordered by date
+	InsertRows {
+		sheet: usize,
+		row: usize,
+		transactions: Vec<Transaction>,
+	},
+	/// Replaces the transaction in the row, keeping its identity, and moves it into place by its
+	/// date if the sheet is ordered by date
+	ReplaceTransaction {
+		sheet: usize,
+		row: usize,
+		transaction: Transaction,
+	},
+	/// Sets a cell of the transaction in the row from its value as it is edited. See
+	/// [`Transaction::set_member`]
+	SetMember {
+		sheet: usize,
+		row: usize,
+		col: usize,
+		value: String,
+	},
+	/// Applies every edit in order, sorting the sheet after if any of them changed a date
+	ApplyEdits { sheet: usize, edits: Vec<CellEdit> },
+	/// Moves the transaction in the row into place by its date, if the sheet is ordered by date
+	SettleRow { sheet: usize, row: usize },
+	/// Swaps the transaction in the row with the one above it. If the sheet is ordered by date,
+	/// they have to be on the same date
+	MoveRowUp { sheet: usize, row: usize },
+	/// Swaps the transaction in the row with the one below it. If the sheet is ordered by date,
+	/// they have to be on the same date
+	MoveRowDown { sheet: usize, row: usize },
+	/// Deletes the transactions in the rows into the sheet's trash
+	DeleteRows { sheet: usize, rows: Vec<usize> },
+	/// Moves the transactions in the rows to another sheet, keeping their order
+	MoveRows {
+		sheet: usize,
+		rows: Vec<usize>,
+		to: usize,
+	},
+	/// Puts the transaction at the index of the sheet's trash back where it was deleted from
+	RestoreRow { sheet: usize, index: usize },
+	/// Splits the amount off the transaction in the row into a new one directly below it
+	SplitTransaction {
+		sheet: usize,
+		row: usize,
+		amount: f64,
+	},
+	/// Marks the transaction in the row as pending or cleared
+	SetPending {
+		sheet: usize,
+		row: usize,
+		pending: bool,
+	},
+	/// Marks the transaction in the row as scheduled, or as having happened
+	SetScheduled {
+		sheet: usize,
+		row: usize,
+		scheduled: bool,
+	},
+	/// Switches the sheet between date and manual ordering
+	ToggleOrder { sheet: usize },
+	/// Sets the balance the sheet should stay above, or clears it
+	SetMinBalance {
+		sheet: usize,
+		min_balance: Option<f64>,
+	},
+	/// Sets the statement cycle of the sheet, or clears it
+	SetStatementCycle {
+		sheet: usize,
+		cycle: Option<StatementCycle>,
+	},
+	/// Sets the interest settings of the sheet, or clears them
+	SetInterest {
+		sheet: usize,
+		interest: Option<InterestSettings>,
+	},
+	/// Sets the currency the sheet is kept in, or clears it so the sheet is in the base currency
+	SetCurrency {
+		sheet: usize,
+		currency: Option<String>,
+	},
+	/// Sets the account the sheet is for
+	SetAccount { sheet: usize, account: Account },
+	/// Sets what is filled in for the transactions entered into the sheet by hand
+	SetEntryDefaults {
+		sheet: usize,
+		defaults: EntryDefaults,
+	},
+	/// Sets the base currency of the file
+	SetBaseCurrency { currency: String },
+	/// Sets how much one unit of the currency is worth in the base currency by hand, or clears it
+	/// so the fetched rate is used
+	SetRate { currency: String, rate: Option<f64> },
+	/// Replaces the payee at the index, or adds it if the index is past the last payee. A
+	/// [`None`] payee removes the one at the index
+	SetPayee { index: usize, payee: Option<Payee> },
+	/// Adds a new, empty secondary sheet
+	CreateSheet,
+	/// Adds a copy of the sheet after the last one, with the given name
+	DuplicateSheet {
+		sheet: usize,
+		name: String,
+		options: DuplicateOptions,
+	},
+	/// Renames the sheet
+	RenameSheet { sheet: usize, name: String },
+	/// Deletes the secondary sheet
+	DeleteSheet { sheet: usize },
+	/// Archives the secondary sheet, or brings it back from the archive
+	ArchiveSheet { sheet: usize, archived: bool },
+	/// Keeps the picked versions of the transactions that differ from another copy of the file,
+	/// sheet by sheet. See [`Model::merge_rows`]
+	Merge { merges: Vec<SheetMerge> },
+}
+
+/// What applying a [`Message`] gave back
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Outcome {
+	/// The message has nothing to give back
+	#[default]
+	Done,
+	/// The row the transaction the message was about ended up in
+	Row(usize),
+	/// The transactions that were deleted, in the order of their rows
+	Deleted(Vec<Transaction>),
+}
+
+impl Outcome {
+	/// Returns the row the transaction ended up in, if the message was about one
+	#[must_use]
+	pub fn row(&self) -> Option<usize> {
+		match self {
+			Outcome::Row(row) => Some(*row),
+			Outcome::Done | Outcome::Deleted(_) => None,
+		}
+	}
+
+	/// Returns the deleted transactions, if the message deleted any
+	#[must_use]
+	pub fn into_deleted(self) -> Vec<Transaction> {
+		match self {
+			Outcome::Deleted(transactions) => transactions,
+			Outcome::Done | Outcome::Row(_) => Vec::new(),
+		}
+	}
+}
+
+/// Why a [`Message`] couldn't be applied
+#[derive(Debug, Error)]
+pub enum UpdateError {
+	#[error("No sheet with index {0}")]
+	Sheet(usize),
+	#[error("No row {row} in sheet {sheet}")]
+	Row { sheet: usize, row: usize },
+	#[error("No transaction {index} in the trash of sheet {sheet}")]
+	Trash { sheet: usize, index: usize },
+	#[error("Main sheet cannot be deleted")]
+	MainSheet,
+	#[error("Main sheet cannot be archived")]
+	ArchiveMainSheet,
+	#[error("There is already a sheet named \"{0}\"")]
+	SheetName(String),
+	#[error(
+		"Sheet {0} is ordered by date, so its rows can only be moved past others on the same date"
+	)]
+	DateOrder(usize),
+	#[error("A transaction to merge an imported one into is no longer in sheet {0}")]
+	MergedTransaction(usize),
+	#[error(transparent)]
+	Member(#[from] ParseTransactionMemberError),
+}
+
+impl Model {
+	/// Applies the message, returning what it gave back. The edits the user makes to the
+	/// transactions and sheets are all made through here, so each is a value that can be checked
+	/// or logged
+	///
+	/// # Errors
+	/// If the message is about a sheet, row or trashed transaction that doesn't exist, in which
+	/// case nothing is changed, or sets a cell to a value that isn't valid for its column. For
+	/// [`Message::ApplyEdits`], the edits before the invalid one are still applied
+	pub fn update(&mut self, message: Message) -> Result<Outcome, UpdateError> {
+		tracing::debug!("{message:?}");
+		self.check(&message)
+			.inspect_err(|e| tracing::warn!("Couldn't apply the message: {e}"))?;
+		Ok(match message {
+			Message::InsertRow {
+				sheet,
+				row,
+				transaction,
+			} => Outcome::Row(self.insert_row(sheet, row, transaction)),
+			Message::InsertRows {
+				sheet,
+				row,
+				transactions,
+			} => Outcome::Row(self.insert_rows(sheet, row, transactions)),
+			Message::ReplaceTransaction {
+				sheet,
+				row,
+				transaction,
+			} => Outcome::Row(self.replace_transaction(sheet, row, transaction)),
+			Message::SetMember {
+				sheet,
+				row,
+				col,
+				value,
+			} => {
+				self.update_transaction_member(sheet, row, col, value)?;
+				Outcome::Done
+			}
+			Message::ApplyEdits { sheet, edits } => {
+				self.apply_edits(sheet, &edits)?;
+				Outcome::Done
+			}
+			Message::SettleRow { sheet, row } => Outcome::Row(self.settle_row(sheet, row)),
+			Message::MoveRowUp { sheet, row } => {
+				self.move_transaction_up(sheet, row);
+				Outcome::Done
+			}
+			Message::MoveRowDown { sheet, row } => {
+				self.move_transaction_down(sheet, row);
+				Outcome::Done
+			}
+			Message::DeleteRows { sheet, rows } => Outcome::Deleted(self.delete_rows(sheet, &rows)),
+			Message::MoveRows { sheet, rows, to } => {
+				self.move_rows(sheet, &rows, to);
+				Outcome::Done
+			}
+			Message::RestoreRow { sheet, index } => Outcome::Row(self.restore_row(sheet, index)),
+			Message::SplitTransaction { sheet, row, amount } => {
+				Outcome::Row(self.split_transaction(sheet, row, amount))
+			}
+			Message::SetPending {
+				sheet,
+				row,
+				pending,
+			} => {
+				self.set_pending(sheet, row, pending);
+				Outcome::Done
+			}
+			Message::SetScheduled {
+				sheet,
+				row,
+				scheduled,
+			} => {
+				self.set_scheduled(sheet, row, scheduled);
+				Outcome::Done
+			}
+			Message::ToggleOrder { sheet } => {
+				self.toggle_order(sheet);
+				Outcome::Done
+			}
+			message @ (Message::SetMinBalance { .. }
+			| Message::SetStatementCycle { .. }
+			| Message::SetInterest { .. }
+			| Message::SetCurrency { .. }
+			| Message::SetAccount { .. }
+			| Message::ArchiveSheet { .. }
+			| Message::SetEntryDefaults { .. }
+			| Message::SetBaseCurrency { .. }
+			| Message::SetRate { .. }
+			| Message::SetPayee { .. }) => {
+				self.update_setting(message);
+				Outcome::Done
+			}
+			message @ (Message::CreateSheet
+			| Message::DuplicateSheet { .. }
+			| Message::RenameSheet { .. }
+			| Message::DeleteSheet { .. }) => {
+				self.update_sheets(message);
+				Outcome::Done
+			}
+			Message::Merge { merges } => {
+				self.merge(&merges)?;
+				Outcome::Done
+			}
+		})
+	}
+
+	/// Applies a message that adds, renames or deletes a sheet
+	fn update_sheets(&mut self, message: Message) {
+		match message {
+			Message::CreateSheet => self.create_sheet(),
+			Message::DuplicateSheet {
+				sheet,
+				name,
+				options,
+			} => self.duplicate_sheet(sheet, name, options),
+			Message::RenameSheet { sheet, name } => self.sheet_mut(sheet).name = name,
+			Message::DeleteSheet { sheet } => self.delete_sheet(sheet),
+			_ => unreachable!("{message:?} doesn't add, rename or delete a sheet"),
+		}
+	}
+
+	/// Applies a message that changes a setting of a sheet or of the file
+	fn update_setting(&mut self, message: Message) {
+		match message {
+			Message::SetMinBalance { sheet, min_balance } => {
+				self.sheet_mut(sheet).min_balance = min_balance;
+			}
+			Message::SetStatementCycle { sheet, cycle } => {
+				self.sheet_mut(sheet).statement_cycle = cycle;
+			}
+			Message::SetInterest { sheet, interest } => self.sheet_mut(sheet).interest = interest,
+			Message::SetCurrency { sheet, currency } => self.sheet_mut(sheet).currency = currency,
+			Message::SetAccount { sheet, account } => self.sheet_mut(sheet).set_account(account),
+			Message::ArchiveSheet { sheet, archived } => self.sheet_mut(sheet).archived = archived,
+			Message::SetEntryDefaults { sheet, defaults } => {
+				self.sheet_mut(sheet).entry_defaults = defaults;
+			}
+			Message::SetBaseCurrency { currency } => self.currencies.base = currency,
+			Message::SetRate { currency, rate } => {
+				match rate {
+					Some(rate) => self.currencies.overrides.insert(currency, rate),
+					None => self.currencies.overrides.remove(&currency),
+				};
+			}
+			Message::SetPayee { index, payee } => self.set_payee(index, payee),
+			_ => unreachable!("{message:?} isn't a setting"),
+		}
+	}
+
+	/// Returns the sheet with the given index, which [`Model::check`] has made sure exists
+	fn sheet_mut(&mut self, index: usize) -> &mut Sheet {
+		self.get_sheet_mut(index)
+			.expect("The sheet was checked before the message was applied")
+	}
+
+	/// Checks that the sheet, rows and trashed transaction the message is about all exist
+	fn check(&self, message: &Message) -> Result<(), UpdateError> {
+		match message {
+			Message::CreateSheet
+			| Message::Merge { .. }
+			| Message::SetBaseCurrency { .. }
+			| Message::SetRate { .. }
+			| Message::SetPayee { .. } => Ok(()),
+			Message::DeleteSheet { sheet: 0 } => Err(UpdateError::MainSheet),
+			Message::ArchiveSheet { sheet: 0, .. } => Err(UpdateError::ArchiveMainSheet),
+			Message::DuplicateSheet { name, .. }
+				if self.all_sheets().any(|sheet| sheet.name == *name) =>
+			{
+				Err(UpdateError::SheetName(name.clone()))
+			}
+			Message::ToggleOrder { sheet }
+			| Message::SetMinBalance { sheet, .. }
+			| Message::SetStatementCycle { sheet, .. }
+			| Message::SetInterest { sheet, .. }
+			| Message::SetCurrency { sheet, .. }
+			| Message::SetAccount { sheet, .. }
+			| Message::SetEntryDefaults { sheet, .. }
+			| Message::RenameSheet { sheet, .. }
+			| Message::ArchiveSheet { sheet, .. }
+			| Message::DuplicateSheet { sheet, .. }
+			| Message::DeleteSheet { sheet } => self.check_rows(*sheet, [], false).map(drop),
+			Message::InsertRow { sheet, row, .. } | Message::InsertRows { sheet, row, .. } => {
+				self.check_rows(*sheet, [*row], true).map(drop)
+			}
+			Message::ReplaceTransaction { sheet, row, .. }
+			| Message::SetMember { sheet, row, .. }
+			| Message::SettleRow { sheet, row }
+			| Message::SplitTransaction { sheet, row, .. }
+			| Message::SetPending { sheet, row, .. }
+			| Message::SetScheduled { sheet, row, .. } => self.check_rows(*sheet, [*row], false).map(drop),
+			Message::MoveRowUp { sheet, row } => {
+				self.check_swap(*sheet, *row, row.saturating_sub(1))
+			}
+			Message::MoveRowDown { sheet, row } => {
+				let len = self.check_rows(*sheet, [*row], false)?.transactions().len();
+				self.check_swap(*sheet, *row, (row + 1).min(len - 1))
+			}
+			Message::ApplyEdits { sheet, edits } => self
+				.check_rows(*sheet, edits.iter().map(|edit| edit.row), false)
+				.map(drop),
+			Message::DeleteRows { sheet, rows } => self
+				.check_rows(*sheet, rows.iter().copied(), false)
+				.map(drop),
+			Message::MoveRows { sheet, rows, to } => {
+				self.check_rows(*to, [], false)?;
+				self.check_rows(*sheet, rows.iter().copied(), false)
+					.map(drop)
+			}
+			Message::RestoreRow { sheet, index } => {
+				if *index < self.check_rows(*sheet, [], false)?.trash.len() {
+					Ok(())
+				} else {
+					Err(UpdateError::Trash {
+						sheet: *sheet,
+						index: *index,
+					})
+				}
+			}
+		}
+	}
+
+	/// Checks that the transactions in the two rows can be swapped, which they can't in a sheet
+	/// ordered by date unless they are on the same date, as the sheet would no longer be ordered
+	fn check_swap(&self, sheet_index: usize, a: usize, b: usize) -> Result<(), UpdateError> {
+		let sheet = self.check_rows(sheet_index, [a, b], false)?;
+		let date = |row: usize| sheet.transactions()[row].date;
+		if sheet.order == RowOrder::Date && date(a) != date(b) {
+			return Err(UpdateError::DateOrder(sheet_index));
+		}
+		Ok(())
+	}
+
+	/// Returns the sheet with the given index, if it has every given row. With `can_append`, a
+	/// row just past the end counts too, as transactions can be inserted there
+	fn check_rows(
+		&self,
+		sheet_index: usize,
+		rows: impl IntoIterator<Item = usize>,
+		can_append: bool,
+	) -> Result<&Sheet, UpdateError> {
+		let sheet = self
+			.get_sheet(sheet_index)
+			.ok_or(UpdateError::Sheet(sheet_index))?;
+		let len = sheet.transactions().len() + usize::from(can_append);
+		match rows.into_iter().find(|row| *row >= len) {
+			Some(row) => Err(UpdateError::Row {
+				sheet: sheet_index,
+				row,
+			}),
+			None => Ok(sheet),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::NaiveDate;
+
+	use super::*;
+
+	fn transaction(day: u32, label: &str) -> Transaction {
+		Transaction {
+			date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+			label: label.to_string(),
+			amount: -1.0,
+			..Transaction::default()
+		}
+	}
+
+	fn labels(model: &Model) -> Vec<&str> {
+		model
+			.main_sheet
+			.transactions()
+			.iter()
+			.map(|t| t.label.as_str())
+			.collect()
+	}
+
+	#[test]
+	fn inserts_rows_by_their_date() {
+		let mut model = Model::scratch_with(vec![transaction(1, "a"), transaction(3, "c")]);
+		let outcome = model.update(Message::InsertRow {
+			sheet: 0,
+			row: 0,
+			transaction: transaction(2, "b"),
+		});
+		assert_eq!(outcome.unwrap(), Outcome::Row(1));
+		assert_eq!(labels(&model), ["a", "b", "c"]);
+	}
+
+	#[test]
+	fn puts_rows_by_their_date() {
+		let mut model = Model::scratch_with(vec![transaction(1, "a"), transaction(3, "c")]);
+		let outcome = model.update(Message::InsertRows {
+			sheet: 0,
+			row: 0,
+			transactions: vec![transaction(4, "d"), transaction(2, "b")],
+		});
+		// The row of the first of them is given back
+		assert_eq!(outcome.unwrap(), Outcome::Row(3));
+		assert_eq!(labels(&model), ["a", "b", "c", "d"]);
+
+		// Unless the sheet is ordered by hand
+		model.update(Message::ToggleOrder { sheet: 0 }).unwrap();
+		let outcome = model.update(Message::InsertRows {
+			sheet: 0,
+			row: 0,
+			transactions: vec![transaction(5, "e")],
+		});
+		assert_eq!(outcome.unwrap(), Outcome::Row(0));
+		assert_eq!(labels(&model), ["e", "a", "b", "c", "d"]);
+	}
+
+	#[test]
+	fn only_moves_rows_past_others_on_the_same_date_when_ordered_by_date() {
+		let mut model = Model::scratch_with(vec![
+			transaction(1, "a"),
+			transaction(2, "b"),
+			transaction(2, "c"),
+		]);
+		assert!(matches!(
+			model.update(Message::MoveRowUp { sheet: 0, row: 1 }),
+			Err(UpdateError::DateOrder(0))
+		));
+		assert!(matches!(
+			model.update(Message::MoveRowDown { sheet: 0, row: 0 }),
+			Err(UpdateError::DateOrder(0))
+		));
+		assert_eq!(labels(&model), ["a", "b", "c"]);
+
+		model
+			.update(Message::MoveRowDown { sheet: 0, row: 1 })
+			.unwrap();
+		assert_eq!(labels(&model), ["a", "c", "b"]);
+
+		model.update(Message::ToggleOrder { sheet: 0 }).unwrap();
+		model
+			.update(Message::MoveRowUp { sheet: 0, row: 1 })
+			.unwrap();
+		assert_eq!(labels(&model), ["c", "a", "b"]);
+	}
+
+	#[test]
+	fn deletes_rows_into_the_trash() {
+		let mut model = Model::scratch_with(vec![
+			transaction(1, "a"),
+			transaction(2, "b"),
+			transaction(3, "c"),
+		]);
+		let outcome = model.update(Message::DeleteRows {
+			sheet: 0,
+			rows: vec![2, 0],
+		});
+		let deleted = outcome.unwrap().into_deleted();
+		let deleted: Vec<&str> = deleted.iter().map(|t| t.label.as_str()).collect();
+		assert_eq!(deleted, ["a", "c"]);
+		assert_eq!(labels(&model), ["b"]);
+		assert_eq!(model.main_sheet.trash.len(), 2);
+	}
+
+	#[test]
+	fn rejects_sheets_and_rows_that_dont_exist() {
+		let mut model = Model::scratch_with(vec![transaction(1, "a"), transaction(2, "b")]);
+		let insert = |sheet, row| Message::InsertRow {
+			sheet,
+			row,
+			transaction: transaction(3, "c"),
+		};
+		assert!(matches!(
+			model.update(insert(1, 0)),
+			Err(UpdateError::Sheet(1))
+		));
+		assert!(matches!(
+			model.update(insert(0, 3)),
+			Err(UpdateError::Row { sheet: 0, row: 3 })
+		));
+		let delete = Message::DeleteRows {
+			sheet: 0,
+			rows: vec![0, 2],
+		};
+		assert!(matches!(
+			model.update(delete),
+			Err(UpdateError::Row { sheet: 0, row: 2 })
+		));
+		// Nothing is changed by a message that is rejected
+		assert_eq!(labels(&model), ["a", "b"]);
+		assert!(model.main_sheet.trash.is_empty());
+
+		// A row can be inserted just past the end
+		assert_eq!(model.update(insert(0, 2)).unwrap(), Outcome::Row(2));
+	}
+}
@@ -0,0 +1,531 @@
+//! This module handles the internal state of the program, and has no interaction with the
+//! controller or state modules
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::clock;
+
+/// The id of a sheet - currently a string, which is the sheets name
+pub type SheetId = String;
+
+mod account;
+mod aggregates;
+mod audit;
+mod compare;
+mod currency;
+mod display;
+mod edits;
+mod encryption;
+mod entry;
+mod external;
+mod filter;
+mod forecast;
+mod health;
+mod import;
+mod interest;
+mod lock;
+mod merge;
+mod message;
+mod networth;
+mod payees;
+mod report;
+mod review;
+mod rules;
+mod save;
+mod scripts;
+mod search;
+mod sheets;
+mod statements;
+
+pub use account::{Account, AccountType};
+pub use audit::{AuditChange, AuditEntry, AuditLog};
+pub use compare::{RowDiff, compare_sheets};
+pub use currency::{Currencies, currency_code};
+pub use display::{
+	COLUMN_NAMES, ColumnDefaults, GroupHeading, Grouping, SheetDisplay, Sort, TableRow,
+	column_index, format_currency,
+};
+pub use edits::CellEdit;
+pub use encryption::Encryption;
+use encryption::ReplacedKey;
+pub use entry::{AmountSign, EntryDefaults};
+pub use filter::Filter;
+pub use forecast::Forecast;
+pub use import::{
+	BankLink, ImportCounts, ImportError, ImportPreset, ImportSettings, ImportSource, PendingImport,
+	Resolution,
+};
+pub use interest::{CompoundingPeriod, InterestSettings};
+use lock::FileLock;
+pub use lock::{Access, LockOwner};
+pub use merge::{MergeRow, Pick, SheetMerge};
+pub use message::{Message, Outcome, UpdateError};
+pub use networth::{HISTORY_MONTHS, NetWorth, Worth};
+pub use payees::{Payee, find_payee};
+pub use report::{Report, ReportKind, Totals};
+pub use review::{REVIEW_DAYS, ReviewItem, ReviewReason};
+pub use rules::{Rule, RuleSet};
+use save::Revision;
+pub use save::{SaveError, backups};
+pub use scripts::{ScriptCommand, Scripts};
+pub use sheets::{
+	DuplicateOptions, ParseTransactionMemberError, RowOrder, Sheet, TRASH_SIZE, Transaction,
+	TransactionId, TrashedRow,
+};
+pub use statements::StatementCycle;
+
+/// The internal state of the program
+#[derive(Debug)]
+pub struct Model {
+	/// The main sheet - this is one that all other sheets feed into, and is where the user will
+	/// handle high-level details
+	pub main_sheet: Sheet,
+	// All the secondary/non-main sheets of the model - these represent individual
+	// accounts/events/etc that can feed into other sheets or the main sheet
+	pub sheets: Vec<Sheet>,
+	// The name of the file currently being worked on. Can be None, in which case the work will not
+	// be saved
+	pub filename: Option<String>,
+	/// The settings of the last import, so it can be repeated
+	pub last_import: Option<ImportSettings>,
+	/// The bank accounts linked to sheets, which their transactions are imported into. See
+	/// [`bank`](crate::bank)
+	pub bank_links: Vec<BankLink>,
+	/// The base currency of the file, and the exchange rates set by hand
+	pub currencies: Currencies,
+	/// The payees, whose names replace the labels of imported transactions that contain their
+	/// aliases. See [`Payee`]
+	pub payees: Vec<Payee>,
+	/// The rules applied to imported transactions. These aren't part of the file
+	pub rules: RuleSet,
+	/// The user's scripts, which add commands and import presets. These aren't part of the file
+	pub scripts: Scripts,
+	/// The contents of the file as it was last loaded or saved, to tell whether there are unsaved
+	/// changes. See [`Model::has_unsaved_changes`]
+	saved_contents: String,
+	/// Whether the model had unsaved changes when it was last checked, and what it was like then,
+	/// so it is only compared with the file again once it changed
+	unsaved: RefCell<Option<(Revision, bool)>>,
+	/// The changes made to the transactions, until they are saved to the audit log. See
+	/// [`Model::record_changes`]
+	audit: AuditLog,
+	/// The key the file is encrypted with, if it is. See [`Model::set_passphrase`]
+	encryption: Option<Encryption>,
+	/// The key the backups and audit log are in, if the passphrase was changed since the file was
+	/// last saved
+	replaced_key: Option<ReplacedKey>,
+	/// The lock on the file, held while it is open, if it is locked by this instance
+	lock: Option<FileLock>,
+	/// Whether the file was opened read-only, as another instance has it open. See [`Access`]
+	read_only: bool,
+}
+
+impl Model {
+	/// Loads the model from a file if given Some(filename), or creates a new "scratch" session
+	/// with no associated file. See [`Model::load`]
+	///
+	/// # Errors
+	/// If the file exists but can't be loaded
+	pub fn new(filename: Option<String>) -> Result<Model, SaveError> {
+		match filename {
+			Some(filename) => Self::open(filename, None, Access::Exclusive),
+			None => Ok(Self::scratch()),
+		}
+	}
+
+	/// Creates a new model from the file with the given name, like [`Model::new`], decrypting it
+	/// with the passphrase if it is encrypted, and locking it as the access says
+	///
+	/// # Errors
+	/// If the file can't be loaded, the passphrase is missing or wrong, or another instance has
+	/// the file open
+	pub fn open(
+		filename: String,
+		passphrase: Option<&str>,
+		access: Access,
+	) -> Result<Model, SaveError> {
+		let mut model = Self::load(filename, passphrase, access)?;
+		model.accrue_interest(clock::today());
+		Ok(model)
+	}
+
+	/// Replaces the model with one loaded from another file (see [`Model::new`]). The import
+	/// rules and scripts are kept, as they aren't part of the file
+	pub fn replace(&mut self, mut model: Model) {
+		model.rules = std::mem::take(&mut self.rules);
+		model.scripts = std::mem::take(&mut self.scripts);
+		*self = model;
+	}
+
+	/// Creates a new model with a single empty sheet and no associated file
+	fn scratch() -> Model {
+		Self::scratch_with(vec![Transaction::default()])
+	}
+
+	/// Creates a new model with no associated file, like [`Model::new`] without a file, but with
+	/// the given transactions (e.g. piped in) in its sheet
+	#[must_use]
+	pub fn scratch_with(transactions: Vec<Transaction>) -> Model {
+		let mut main_sheet = Sheet::new("Sheet0".to_string(), transactions);
+		main_sheet.sort_if_ordered();
+		let mut model = Model {
+			main_sheet,
+			sheets: vec![],
+			filename: None,
+			last_import: None,
+			bank_links: vec![],
+			currencies: Currencies::default(),
+			payees: vec![],
+			rules: RuleSet::default(),
+			scripts: Scripts::default(),
+			saved_contents: String::new(),
+			unsaved: RefCell::default(),
+			audit: AuditLog::default(),
+			encryption: None,
+			replaced_key: None,
+			lock: None,
+			read_only: false,
+		};
+		model.mark_saved();
+		model.audit = AuditLog::new(model.all_sheets());
+		model
+	}
+
+	/// Returns every sheet, starting with the main sheet
+	pub fn all_sheets(&self) -> impl Iterator<Item = &Sheet> {
+		std::iter::once(&self.main_sheet).chain(&self.sheets)
+	}
+
+	/// Records the changes made to the transactions since this was last called (or the model was
+	/// loaded) in the audit log, as made now. They are written to the log file when the model is
+	/// saved
+	pub fn record_changes(&mut self) {
+		let sheets = std::iter::once(&self.main_sheet).chain(&self.sheets);
+		self.audit.record(sheets, clock::now());
+	}
+
+	/// Returns every change in the audit log of the file, and those not yet saved to it, oldest
+	/// first
+	///
+	/// # Errors
+	/// If the audit log can't be read
+	pub fn audit_entries(&self) -> Result<Vec<AuditEntry>, SaveError> {
+		self.audit
+			.entries(self.filename.as_deref(), self.encryption.as_ref())
+			.map_err(|e| SaveError::Io(e.to_string()))
+	}
+
+	/// Pushes a new sheet to the list of secondary sheets, with the name format "Sheet" + the
+	/// index of the sheet in the sheets vec + 1 (as the default/main sheet is always sheet 0)
+	pub(crate) fn create_sheet(&mut self) {
+		self.sheets.push(Sheet::new(
+			format!("Sheet{}", self.sheets.len() + 1),
+			vec![Transaction::default()],
+		));
+	}
+
+	/// Adds a copy of the sheet with the given index after the last sheet, with the given name
+	pub(crate) fn duplicate_sheet(
+		&mut self,
+		index: usize,
+		name: String,
+		options: DuplicateOptions,
+	) {
+		if let Some(sheet) = self.get_sheet(index) {
+			let copy = sheet.duplicate(name, options);
+			self.sheets.push(copy);
+		}
+	}
+
+	/// Deletes the secondary sheet with the given index (see [`Model::get_sheet`])
+	///
+	/// # Panics
+	/// If the index is 0, as the main sheet can't be deleted, or there is no sheet with it
+	pub(crate) fn delete_sheet(&mut self, index: usize) {
+		assert!(index != 0, "Cannot delete main sheet");
+		self.sheets.remove(index - 1);
+	}
+
+	/// Returns cloned titles of all the sheets
+	pub fn sheet_titles(&self) -> Vec<String> {
+		let mut titles = vec![self.main_sheet.name.clone()];
+		titles.extend(self.sheets.iter().map(|s| s.name.clone()));
+		titles
+	}
+
+	/// Gets a sheet by index, where 0 is the main sheet, and 1..MAX is the index of the secondary
+	/// sheet - 1. So an index of 3 would give the secondary sheet at self.sheets(2)
+	pub fn get_sheet(&self, index: usize) -> Option<&Sheet> {
+		if index == 0 {
+			Some(&self.main_sheet)
+		} else {
+			self.sheets.get(index - 1)
+		}
+	}
+
+	pub fn get_sheet_mut(&mut self, index: usize) -> Option<&mut Sheet> {
+		if index == 0 {
+			Some(&mut self.main_sheet)
+		} else {
+			self.sheets.get_mut(index - 1)
+		}
+	}
+
+	/// Returns the sheet with the given index, or an error saying there isn't one, for the
+	/// methods that take the index of a sheet from outside
+	pub(crate) fn checked_sheet_mut(&mut self, index: usize) -> Result<&mut Sheet, UpdateError> {
+		self.get_sheet_mut(index).ok_or(UpdateError::Sheet(index))
+	}
+
+	pub fn get_main_sheet(&self) -> &Sheet {
+		&self.main_sheet
+	}
+
+	pub fn get_main_sheet_mut(&mut self) -> &mut Sheet {
+		&mut self.main_sheet
+	}
+
+	/// Returns every value the given column has in any sheet, most used first, e.g. to suggest
+	/// the labels or categories that are already in use. Empty values are left out
+	pub fn values_of(&self, col: usize) -> Vec<String> {
+		let mut counts: HashMap<String, usize> = HashMap::new();
+		for transaction in self.all_sheets().flat_map(Sheet::transactions) {
+			let value = transaction.member_string(col);
+			if !value.is_empty() {
+				*counts.entry(value).or_default() += 1;
+			}
+		}
+		let mut values: Vec<(String, usize)> = counts.into_iter().collect();
+		values.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+		values.into_iter().map(|(value, _)| value).collect()
+	}
+
+	/// Returns the amount of sheets
+	pub fn sheet_count(&self) -> usize {
+		1 + self.sheets.len()
+	}
+
+	/// Sets the member of the transaction in the given row and column from its value as it is
+	/// edited. See [`Transaction::set_member`]
+	///
+	/// # Errors
+	/// If the value can't be parsed for the column
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn update_transaction_member(
+		&mut self,
+		sheet_index: usize,
+		row: usize,
+		col: usize,
+		new: String,
+	) -> anyhow::Result<(), sheets::ParseTransactionMemberError> {
+		let sheet = self.get_sheet_mut(sheet_index).unwrap();
+		let transaction = sheet.transaction_mut(row).unwrap();
+		transaction.set_member(col, new)
+	}
+
+	/// Replaces the transaction in the given row, moving it into place by its date if the sheet is
+	/// ordered by date. Returns the row the transaction ends up in
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn replace_transaction(
+		&mut self,
+		sheet_index: usize,
+		row: usize,
+		transaction: Transaction,
+	) -> usize {
+		let sheet = self.get_sheet_mut(sheet_index).unwrap();
+		let old = sheet.transaction_mut(row).unwrap();
+		*old = Transaction {
+			id: old.id,
+			..transaction
+		};
+		sheet.settle_row(row)
+	}
+
+	/// Swaps the transaction in the given row with the one above it
+	///
+	/// # Panics
+	/// If there is no sheet with the given index
+	pub(crate) fn move_transaction_up(&mut self, sheet_index: usize, row: usize) {
+		self.get_sheet_mut(sheet_index)
+			.unwrap()
+			.swap_rows(row, row.saturating_sub(1));
+	}
+
+	/// Swaps the transaction in the given row with the one below it
+	///
+	/// # Panics
+	/// If there is no sheet with the given index
+	pub(crate) fn move_transaction_down(&mut self, sheet_index: usize, row: usize) {
+		let sheet = self.get_sheet_mut(sheet_index).unwrap();
+		let max = sheet.transactions().len() - 1;
+		sheet.swap_rows(row, row.saturating_add(1).min(max));
+	}
+
+	/// Removes the transaction in the given row, returning it. It isn't put in the trash, unlike
+	/// with [`Model::delete_rows`]
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn delete_row(&mut self, sheet_index: usize, row: usize) -> Transaction {
+		self.get_sheet_mut(sheet_index)
+			.unwrap()
+			.remove_transaction(row)
+	}
+
+	/// Deletes every given row into the sheet's trash, returning the deleted transactions in the
+	/// order of the rows
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn delete_rows(&mut self, sheet_index: usize, rows: &[usize]) -> Vec<Transaction> {
+		let mut rows = rows.to_vec();
+		rows.sort_unstable();
+		rows.dedup();
+		let mut deleted: Vec<Transaction> = rows
+			.iter()
+			.rev()
+			.map(|row| self.delete_row(sheet_index, *row))
+			.collect();
+		deleted.reverse();
+
+		let trash = &mut self.get_sheet_mut(sheet_index).unwrap().trash;
+		trash.extend(
+			rows.iter()
+				.zip(&deleted)
+				.map(|(row, transaction)| TrashedRow {
+					row: *row,
+					transaction: transaction.clone(),
+				}),
+		);
+		let overflow = trash.len().saturating_sub(TRASH_SIZE);
+		trash.drain(..overflow);
+		deleted
+	}
+
+	/// Moves the transactions in the given rows to another sheet, keeping their order. They are
+	/// added after its last row, or by their dates if it is ordered by date
+	///
+	/// # Panics
+	/// If either sheet doesn't exist, or a row isn't in the sheet
+	pub(crate) fn move_rows(&mut self, sheet_index: usize, rows: &[usize], to: usize) {
+		let mut rows = rows.to_vec();
+		rows.sort_unstable();
+		rows.dedup();
+		let mut moved: Vec<Transaction> = rows
+			.iter()
+			.rev()
+			.map(|row| self.delete_row(sheet_index, *row))
+			.collect();
+		moved.reverse();
+
+		let target = self.get_sheet_mut(to).unwrap();
+		for transaction in moved {
+			target.insert_ordered(target.transactions().len(), transaction);
+		}
+	}
+
+	/// Takes the transaction at the given index out of the sheet's trash and puts it back where
+	/// it was deleted from (or by its date, if the sheet is ordered by date). Returns the row it
+	/// was restored to
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or transaction with the index in its trash
+	pub(crate) fn restore_row(&mut self, sheet_index: usize, index: usize) -> usize {
+		let sheet = self.get_sheet_mut(sheet_index).unwrap();
+		let TrashedRow { row, transaction } = sheet.trash.remove(index);
+		let row = row.min(sheet.transactions().len());
+		sheet.insert_ordered(row, transaction)
+	}
+
+	/// Inserts all the given transactions in order, starting at the given row, or each by its date
+	/// if the sheet is ordered by date. Returns the row the first of them ended up in
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn insert_rows(
+		&mut self,
+		sheet_index: usize,
+		row: usize,
+		values: Vec<Transaction>,
+	) -> usize {
+		self.get_sheet_mut(sheet_index)
+			.unwrap()
+			.insert_transactions(row, values)
+	}
+
+	/// Returns copies of the transactions in the given rows. Rows past the end are skipped
+	///
+	/// # Errors
+	/// If there is no sheet with the given index
+	pub fn copy_rows(
+		&self,
+		sheet_index: usize,
+		rows: &[usize],
+	) -> Result<Vec<Transaction>, UpdateError> {
+		let sheet = self
+			.get_sheet(sheet_index)
+			.ok_or(UpdateError::Sheet(sheet_index))?;
+		Ok(rows
+			.iter()
+			.filter_map(|row| sheet.transactions().get(*row).cloned())
+			.collect())
+	}
+
+	/// Inserts the transaction at the given row, or by its date if the sheet is ordered by date.
+	/// Returns the row it was inserted at
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn insert_row(
+		&mut self,
+		sheet_index: usize,
+		row: usize,
+		value: Transaction,
+	) -> usize {
+		self.get_sheet_mut(sheet_index)
+			.unwrap()
+			.insert_ordered(row, value)
+	}
+
+	/// Moves the transaction in the given row into place by its date, if the sheet is ordered by
+	/// date. Returns the row the transaction ends up in
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn settle_row(&mut self, sheet_index: usize, row: usize) -> usize {
+		self.get_sheet_mut(sheet_index).unwrap().settle_row(row)
+	}
+
+	/// Switches the sheet between date and manual ordering, sorting it if it is now ordered by
+	/// date
+	///
+	/// # Panics
+	/// If there is no sheet with the given index
+	pub(crate) fn toggle_order(&mut self, sheet_index: usize) {
+		let sheet = self.get_sheet_mut(sheet_index).unwrap();
+		sheet.order = match sheet.order {
+			RowOrder::Date => RowOrder::Manual,
+			RowOrder::Manual => RowOrder::Date,
+		};
+		sheet.sort_if_ordered();
+	}
+
+	/// Returns a copy of the transaction in the given row
+	///
+	/// # Errors
+	/// If there is no sheet with the given index, or row in it
+	pub fn copy_row(&self, sheet_index: usize, row: usize) -> Result<Transaction, UpdateError> {
+		self.get_sheet(sheet_index)
+			.ok_or(UpdateError::Sheet(sheet_index))?
+			.transactions()
+			.get(row)
+			.cloned()
+			.ok_or(UpdateError::Row {
+				sheet: sheet_index,
+				row,
+			})
+	}
+}
@@ -0,0 +1,127 @@
+//! The balances of every sheet together, and how they have changed recently
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+use crate::{
+	model::{Model, Sheet},
+	rates::ExchangeRates,
+};
+
+/// The amount of months of history kept for each sheet, including the current one
+pub const HISTORY_MONTHS: usize = 12;
+
+/// The balance of every sheet, and their combined total
+#[derive(Debug, Clone)]
+pub struct NetWorth {
+	pub sheets: Vec<Worth>,
+	/// The sum of every sheet, in the base currency
+	pub total: Worth,
+	/// The sheets left out of the total, as there is no rate for their currencies
+	pub unconverted: Vec<String>,
+}
+
+/// The balance of a sheet (or of all of them), and its history
+#[derive(Debug, Clone)]
+pub struct Worth {
+	pub name: String,
+	/// The currency the balance is in, if it isn't the base currency
+	pub currency: Option<String>,
+	pub balance: f64,
+	/// The actual balance at the end of each of the last [`HISTORY_MONTHS`] months, oldest first.
+	/// The last entry is the balance as of today
+	pub history: Vec<f64>,
+}
+
+impl Worth {
+	/// Returns how much the balance changed over its history
+	#[must_use]
+	pub fn change(&self) -> f64 {
+		self.history.last().copied().unwrap_or(0.0) - self.history.first().copied().unwrap_or(0.0)
+	}
+}
+
+impl Sheet {
+	/// Returns the actual balance of the sheet at the end of the given date, leaving out
+	/// scheduled transactions
+	pub fn balance_on(&self, date: NaiveDate) -> f64 {
+		self.account().starting_balance
+			+ self
+				.transactions()
+				.iter()
+				.filter(|t| !t.scheduled && t.date <= date)
+				.map(|t| t.amount)
+				.sum::<f64>()
+	}
+
+	/// Returns the actual balance at the end of each of the last [`HISTORY_MONTHS`] months, oldest
+	/// first, ending with the balance as of `today`
+	pub fn balance_history(&self, today: NaiveDate) -> Vec<f64> {
+		let first_of_month = today.with_day(1).unwrap_or(today);
+		let mut dates: Vec<NaiveDate> = (1..HISTORY_MONTHS)
+			.rev()
+			.filter_map(|months_ago| {
+				// The day before the start of the month after the one `months_ago` months back
+				first_of_month
+					.checked_sub_months(Months::new(u32::try_from(months_ago - 1).ok()?))?
+					.checked_sub_days(Days::new(1))
+			})
+			.collect();
+		dates.push(today);
+		dates
+			.into_iter()
+			.map(|date| self.balance_on(date))
+			.collect()
+	}
+}
+
+impl Model {
+	/// Returns the balance of every sheet and their total, as of `today`. The balances include
+	/// scheduled transactions if `projected`, but the history never does. Sheets in other
+	/// currencies are converted to the base currency for the total at today's rates, set by hand
+	/// or else `fetched`
+	pub fn net_worth(
+		&self,
+		today: NaiveDate,
+		projected: bool,
+		fetched: Option<&ExchangeRates>,
+	) -> NetWorth {
+		let sheets: Vec<Worth> = std::iter::once(&self.main_sheet)
+			.chain(&self.sheets)
+			.map(|sheet| Worth {
+				name: sheet.name.clone(),
+				currency: sheet
+					.currency
+					.clone()
+					.filter(|currency| *currency != self.currencies.base),
+				balance: sheet.total().get(projected),
+				history: sheet.balance_history(today),
+			})
+			.collect();
+		let mut total = Worth {
+			name: "Total".to_string(),
+			currency: None,
+			balance: 0.0,
+			history: vec![0.0; HISTORY_MONTHS],
+		};
+		let mut unconverted = vec![];
+		for worth in &sheets {
+			let rate = match &worth.currency {
+				Some(currency) => self.currencies.rate(currency, fetched),
+				None => Some(1.0),
+			};
+			let Some(rate) = rate else {
+				unconverted.push(worth.name.clone());
+				continue;
+			};
+			total.balance += worth.balance * rate;
+			for (sum, balance) in total.history.iter_mut().zip(&worth.history) {
+				*sum += balance * rate;
+			}
+		}
+		NetWorth {
+			sheets,
+			total,
+			unconverted,
+		}
+	}
+}
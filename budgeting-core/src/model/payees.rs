@@ -0,0 +1,125 @@
+//! The payees of the file, each with the aliases banks label its transactions with, like
+//! `AMZN MKTP` for Amazon. Imported labels containing an alias are replaced by the payee's name, so
+//! the same payee is always labelled the same way, and its transactions are grouped together in
+//! reports. Unlike the [rules](crate::model::RuleSet), payees are kept in the file
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{CellEdit, Model, Sheet, Transaction};
+
+/// A payee, and the aliases its transactions are labelled with. The name counts as an alias too
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Payee {
+	pub name: String,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub aliases: Vec<String>,
+}
+
+impl Payee {
+	/// Parses a payee written as its name, then `=` and its aliases separated by commas, like
+	/// `Amazon = AMZN MKTP, AMAZON.COM`
+	///
+	/// # Errors
+	/// If the name is empty
+	pub fn parse(text: &str) -> Result<Payee, String> {
+		let (name, aliases) = text.split_once('=').unwrap_or((text, ""));
+		let name = name.trim();
+		if name.is_empty() {
+			return Err("The payee has no name".to_string());
+		}
+		Ok(Payee {
+			name: name.to_string(),
+			aliases: aliases
+				.split(',')
+				.map(str::trim)
+				.filter(|alias| !alias.is_empty())
+				.map(str::to_string)
+				.collect(),
+		})
+	}
+
+	/// Returns the length of the longest alias the label contains, if it contains any. Labels
+	/// and aliases are compared by their letters and digits only, ignoring case, so `AMZN MKTP`
+	/// is in `Amzn Mktp US*2J4`
+	fn matches(&self, label: &str) -> Option<usize> {
+		let label = normalize(label);
+		std::iter::once(&self.name)
+			.chain(&self.aliases)
+			.map(|alias| normalize(alias))
+			.filter(|alias| !alias.is_empty() && contains_words(&label, alias))
+			.map(|alias| alias.len())
+			.max()
+	}
+}
+
+/// Shows the payee in the form read by [`Payee::parse`]
+impl Display for Payee {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} = {}", self.name, self.aliases.join(", "))
+	}
+}
+
+/// Returns the text in upper case, with every run of characters other than letters and digits
+/// replaced by a single space
+fn normalize(text: &str) -> String {
+	text.split(|c: char| !c.is_alphanumeric())
+		.filter(|word| !word.is_empty())
+		.map(str::to_uppercase)
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// Returns whether the normalized label contains the normalized alias, starting at the start of a
+/// word, so `AMAZON` isn't in `MAMAZON`
+fn contains_words(label: &str, alias: &str) -> bool {
+	label
+		.match_indices(alias)
+		.any(|(start, _)| start == 0 || label.as_bytes()[start - 1] == b' ')
+}
+
+/// Returns the payee the label belongs to, which is the one with the longest alias in it, if any
+#[must_use]
+pub fn find_payee<'a>(payees: &'a [Payee], label: &str) -> Option<&'a Payee> {
+	payees
+		.iter()
+		.filter_map(|payee| Some((payee.matches(label)?, payee)))
+		.max_by_key(|(len, _)| *len)
+		.map(|(_, payee)| payee)
+}
+
+impl Model {
+	/// Gives the transaction the name of the payee its label belongs to, if it belongs to one
+	pub fn normalize_payee(&self, transaction: &mut Transaction) {
+		if let Some(payee) = find_payee(&self.payees, &transaction.label) {
+			transaction.label.clone_from(&payee.name);
+		}
+	}
+
+	/// Replaces the payee at the given index, or adds it if the index is past the last payee. A
+	/// [`None`] payee removes the one at the index
+	pub(crate) fn set_payee(&mut self, index: usize, payee: Option<Payee>) {
+		match (payee, index < self.payees.len()) {
+			(Some(payee), true) => self.payees[index] = payee,
+			(Some(payee), false) => self.payees.push(payee),
+			(None, true) => {
+				self.payees.remove(index);
+			}
+			(None, false) => {}
+		}
+	}
+}
+
+impl Sheet {
+	/// Returns the edits that giving the given rows the names of their payees would make
+	pub fn payee_edits(&self, rows: &[usize], payees: &[Payee]) -> Vec<CellEdit> {
+		rows.iter()
+			.filter_map(|row| {
+				let transaction = self.transactions().get(*row)?;
+				let payee = find_payee(payees, &transaction.label)?;
+				self.edit(*row, 1, payee.name.clone())
+			})
+			.collect()
+	}
+}
@@ -0,0 +1,204 @@
+//! Reports of the income and expenses of a sheet over a range of dates, broken down by category,
+//! by month and by payee
+
+use std::fmt::Display;
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::model::{ParseTransactionMemberError, Sheet, Transaction};
+
+/// The income and expenses of a set of transactions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+	/// The sum of the positive amounts
+	pub income: f64,
+	/// The sum of the negative amounts, which is negative
+	pub expenses: f64,
+	/// The amount of transactions
+	pub count: usize,
+}
+
+impl Totals {
+	/// Adds the transaction to the totals
+	fn add(&mut self, transaction: &Transaction) {
+		if transaction.amount >= 0.0 {
+			self.income += transaction.amount;
+		} else {
+			self.expenses += transaction.amount;
+		}
+		self.count += 1;
+	}
+
+	/// Returns the income minus the expenses
+	#[must_use]
+	pub fn net(&self) -> f64 {
+		self.income + self.expenses
+	}
+}
+
+/// A report of a sheet between two dates (inclusive)
+#[derive(Debug, Clone)]
+pub struct Report {
+	pub start: NaiveDate,
+	pub end: NaiveDate,
+	/// The totals of every transaction in the range
+	pub total: Totals,
+	/// The totals of each category, largest expenses first. Uncategorised transactions are
+	/// under an empty name
+	pub categories: Vec<(String, Totals)>,
+	/// The totals of each month in the range, by the first day of the month, in order. Months
+	/// without transactions are included, so the changes between months are meaningful
+	pub months: Vec<(NaiveDate, Totals)>,
+	/// The totals of the expenses to each payee (the label of the transaction), largest first
+	pub payees: Vec<(String, Totals)>,
+}
+
+/// The ways a report can be shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+	/// The totals, by category and by month
+	Summary,
+	/// Where the money went, by payee
+	Payees,
+}
+
+impl ReportKind {
+	pub const ALL: [ReportKind; 2] = [ReportKind::Summary, ReportKind::Payees];
+}
+
+impl Display for ReportKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ReportKind::Summary => write!(f, "Income and expenses"),
+			ReportKind::Payees => write!(f, "Top payees"),
+		}
+	}
+}
+
+impl Report {
+	/// Returns how much the net total of the month at the given index changed from the month
+	/// before it, or [`None`] for the first month
+	#[must_use]
+	pub fn month_change(&self, index: usize) -> Option<f64> {
+		let previous = self.months.get(index.checked_sub(1)?)?;
+		Some(self.months.get(index)?.1.net() - previous.1.net())
+	}
+
+	/// Parses the range of a report, which is one of
+	/// - nothing or `ytd` - from the start of the year to today
+	/// - a year, like `2025`
+	/// - two dates separated by `..`, like `2025-04-01..2025-06-30`
+	///
+	/// # Errors
+	/// If the range isn't one of those, or ends before it starts
+	pub fn parse_range(
+		text: &str,
+		today: NaiveDate,
+	) -> Result<(NaiveDate, NaiveDate), ParseTransactionMemberError> {
+		let text = text.trim();
+		let invalid = || ParseTransactionMemberError {
+			message: format!("Invalid range \"{text}\""),
+		};
+		let (start, end) = if text.is_empty() || text.eq_ignore_ascii_case("ytd") {
+			(
+				NaiveDate::from_ymd_opt(today.year(), 1, 1).ok_or_else(invalid)?,
+				today,
+			)
+		} else if let Some((start, end)) = text.split_once("..") {
+			(
+				Transaction::parse_date(start.trim())?,
+				Transaction::parse_date(end.trim())?,
+			)
+		} else {
+			let year = text.parse().map_err(|_| invalid())?;
+			(
+				NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(invalid)?,
+				NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(invalid)?,
+			)
+		};
+		if end < start {
+			return Err(ParseTransactionMemberError {
+				message: "The range ends before it starts".to_string(),
+			});
+		}
+		Ok((start, end))
+	}
+}
+
+/// Adds the transaction to the totals of the group with the given name, adding the group if it
+/// isn't there yet
+fn add_to_group(groups: &mut Vec<(String, Totals)>, name: &str, transaction: &Transaction) {
+	if let Some((_, totals)) = groups.iter_mut().find(|(n, _)| n == name) {
+		totals.add(transaction);
+	} else {
+		let mut totals = Totals::default();
+		totals.add(transaction);
+		groups.push((name.to_string(), totals));
+	}
+}
+
+/// Returns the first day of the month the date is in
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+	date.with_day(1).unwrap_or(date)
+}
+
+impl Sheet {
+	/// Returns the totals of the month the date is in. Scheduled transactions are left out, as in
+	/// [`Sheet::report`]
+	pub fn month_totals(&self, date: NaiveDate) -> Totals {
+		let month = first_of_month(date);
+		let mut totals = Totals::default();
+		for transaction in self
+			.transactions()
+			.iter()
+			.filter(|t| !t.scheduled && first_of_month(t.date) == month)
+		{
+			totals.add(transaction);
+		}
+		totals
+	}
+
+	/// Reports the transactions between the two dates (inclusive). Scheduled transactions are left
+	/// out, as they haven't happened
+	pub fn report(&self, start: NaiveDate, end: NaiveDate) -> Report {
+		let mut months = vec![];
+		let mut month = first_of_month(start);
+		while month <= end {
+			months.push((month, Totals::default()));
+			let Some(next) = month.checked_add_months(Months::new(1)) else {
+				break;
+			};
+			month = next;
+		}
+
+		let mut total = Totals::default();
+		let mut categories: Vec<(String, Totals)> = vec![];
+		let mut payees: Vec<(String, Totals)> = vec![];
+		for transaction in self
+			.transactions()
+			.iter()
+			.filter(|t| !t.scheduled && (start..=end).contains(&t.date))
+		{
+			total.add(transaction);
+			add_to_group(&mut categories, &transaction.category, transaction);
+			if transaction.amount < 0.0 {
+				add_to_group(&mut payees, transaction.label.trim(), transaction);
+			}
+			let month = first_of_month(transaction.date);
+			if let Some((_, totals)) = months.iter_mut().find(|(m, _)| *m == month) {
+				totals.add(transaction);
+			}
+		}
+		categories.sort_by(|(_, a), (_, b)| a.net().total_cmp(&b.net()));
+		payees.sort_by(|(_, a), (_, b)| a.expenses.total_cmp(&b.expenses));
+
+		Report {
+			start,
+			end,
+			total,
+			categories,
+			months,
+			payees,
+		}
+	}
+}
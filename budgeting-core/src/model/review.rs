@@ -0,0 +1,150 @@
+//! Finding the transactions that need attention, for the weekly review
+
+use std::fmt::Display;
+
+use chrono::{Days, NaiveDate};
+
+use crate::model::{Model, Sheet, Transaction};
+
+/// How many days back the review looks
+pub const REVIEW_DAYS: u64 = 7;
+/// The least amount of other transactions with the same label needed to tell if an amount is
+/// unusual for it
+const MIN_HISTORY: usize = 3;
+/// How many times larger or smaller than usual an amount has to be to be unusual
+const ANOMALY_FACTOR: f64 = 2.0;
+
+/// Why a transaction needs reviewing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewReason {
+	/// It has no category
+	Uncategorised,
+	/// It hasn't cleared the bank yet
+	Pending,
+	/// Its amount is far from the usual amount for its label
+	Unusual {
+		/// The median amount of the other transactions with its label
+		typical: f64,
+	},
+}
+
+impl Display for ReviewReason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ReviewReason::Uncategorised => write!(f, "No category"),
+			ReviewReason::Pending => write!(f, "Not cleared yet"),
+			ReviewReason::Unusual { .. } => write!(f, "Unusual amount for its label"),
+		}
+	}
+}
+
+/// A transaction that needs reviewing
+#[derive(Debug, Clone)]
+pub struct ReviewItem {
+	/// The row of the transaction in its sheet
+	pub row: usize,
+	/// The transaction, as it was when it was last looked at
+	pub transaction: Transaction,
+	pub reasons: Vec<ReviewReason>,
+}
+
+/// Returns the median of the values, or [`None`] if there aren't any
+fn median(mut values: Vec<f64>) -> Option<f64> {
+	values.sort_by(f64::total_cmp);
+	let middle = values.len() / 2;
+	match values.len() {
+		0 => None,
+		n if n % 2 == 0 => Some(f64::midpoint(values[middle - 1], values[middle])),
+		_ => Some(values[middle]),
+	}
+}
+
+impl Sheet {
+	/// Returns the usual amount of the transaction's label (the median amount of the other
+	/// transactions with it) if the transaction's amount is far from it
+	fn unusual_amount(&self, row: usize, transaction: &Transaction) -> Option<f64> {
+		let others: Vec<f64> = self
+			.transactions()
+			.iter()
+			.enumerate()
+			.filter(|(i, t)| *i != row && t.label == transaction.label)
+			.map(|(_, t)| t.amount.abs())
+			.collect();
+		if others.len() < MIN_HISTORY {
+			return None;
+		}
+		let typical = median(others)?;
+		let amount = transaction.amount.abs();
+		(amount > typical * ANOMALY_FACTOR || amount * ANOMALY_FACTOR < typical).then_some(typical)
+	}
+
+	/// Returns the transactions of the week up to and including `today` that need reviewing, in
+	/// date order
+	pub fn review_items(&self, today: NaiveDate) -> Vec<ReviewItem> {
+		let since = today
+			.checked_sub_days(Days::new(REVIEW_DAYS - 1))
+			.unwrap_or(today);
+		let mut items: Vec<ReviewItem> = self
+			.transactions()
+			.iter()
+			.enumerate()
+			.filter(|(_, t)| (since..=today).contains(&t.date))
+			.filter_map(|(row, t)| {
+				let mut reasons = vec![];
+				if t.category.is_empty() {
+					reasons.push(ReviewReason::Uncategorised);
+				}
+				if t.pending {
+					reasons.push(ReviewReason::Pending);
+				}
+				if let Some(typical) = self.unusual_amount(row, t) {
+					reasons.push(ReviewReason::Unusual { typical });
+				}
+				(!reasons.is_empty()).then(|| ReviewItem {
+					row,
+					transaction: t.clone(),
+					reasons,
+				})
+			})
+			.collect();
+		items.sort_by_key(|item| self.transactions()[item.row].date);
+		items
+	}
+}
+
+impl Model {
+	/// Splits `amount` off the transaction in the given row into a new transaction with the same
+	/// date and label, directly below it. Returns the row of the new transaction
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn split_transaction(
+		&mut self,
+		sheet_index: usize,
+		row: usize,
+		amount: f64,
+	) -> usize {
+		let sheet = self.get_sheet_mut(sheet_index).unwrap();
+		let transaction = sheet.transaction_mut(row).unwrap();
+		transaction.amount -= amount;
+		let split = Transaction {
+			amount,
+			category: String::new(),
+			..transaction.clone()
+		};
+		sheet.insert_transaction(row + 1, split);
+		row + 1
+	}
+
+	/// Marks the transaction in the given row as pending or cleared
+	///
+	/// # Panics
+	/// If there is no sheet with the given index, or row in it
+	pub(crate) fn set_pending(&mut self, sheet_index: usize, row: usize, pending: bool) {
+		self.get_sheet_mut(sheet_index)
+			.unwrap()
+			.transaction_mut(row)
+			.unwrap()
+			.pending = pending;
+	}
+}
@@ -0,0 +1,356 @@
+//! Rules that categorise, tag and clean up the labels of transactions, which are applied to
+//! imported transactions, and can be re-run on a sheet
+//!
+//! Rules are kept in their own TOML file rather than the save file, so the same rules can be
+//! used for every file. Each rule is a `[[rule]]` table, like
+//!
+//! ```toml
+//! [[rule]]
+//! contains = "TESCO"
+//! max_amount = 0.0
+//! label = "Tesco"
+//! category = "Groceries"
+//! tags = ["food"]
+//! ```
+
+use std::{fmt::Display, fs, io::ErrorKind, path::PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::{CellEdit, Model, Sheet, Transaction};
+
+/// A rule, which changes every transaction it matches. A rule matches a transaction when all of
+/// the conditions it has are met, so a rule with no conditions matches everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+	/// Matches labels containing this text, ignoring case
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub contains: Option<String>,
+	/// Matches labels matching this regular expression
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub regex: Option<String>,
+	/// Matches amounts of at least this much
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub min_amount: Option<f64>,
+	/// Matches amounts of at most this much
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_amount: Option<f64>,
+	/// The label given to matching transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub label: Option<String>,
+	/// The category given to matching transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub category: Option<String>,
+	/// Tags added to matching transactions
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
+	/// The compiled [`Rule::regex`]
+	#[serde(skip)]
+	compiled: Option<Regex>,
+}
+
+impl Rule {
+	/// Parses a rule written as the inside of a TOML inline table, e.g.
+	/// `contains = "TESCO", category = "Groceries"`
+	///
+	/// # Errors
+	/// If the text isn't a valid rule, or its regex is invalid
+	pub fn parse(text: &str) -> Result<Rule, RuleError> {
+		#[derive(Deserialize)]
+		struct Wrapper {
+			rule: Rule,
+		}
+		let wrapper: Wrapper = toml::from_str(&format!("rule = {{ {text} }}"))
+			.map_err(|e| RuleError::Parse(e.message().to_string()))?;
+		let mut rule = wrapper.rule;
+		rule.compile()?;
+		Ok(rule)
+	}
+
+	/// Compiles the regex of the rule
+	fn compile(&mut self) -> Result<(), RuleError> {
+		self.compiled = self
+			.regex
+			.as_deref()
+			.map(Regex::new)
+			.transpose()
+			.map_err(|_| RuleError::Regex(self.regex.clone().unwrap_or_default()))?;
+		Ok(())
+	}
+
+	/// Returns whether the rule matches the transaction
+	#[must_use]
+	pub fn matches(&self, transaction: &Transaction) -> bool {
+		self.contains.as_ref().is_none_or(|text| {
+			transaction
+				.label
+				.to_lowercase()
+				.contains(&text.to_lowercase())
+		}) && self
+			.compiled
+			.as_ref()
+			.is_none_or(|regex| regex.is_match(&transaction.label))
+			&& self.min_amount.is_none_or(|min| transaction.amount >= min)
+			&& self.max_amount.is_none_or(|max| transaction.amount <= max)
+	}
+
+	/// Changes the transaction as the rule says, without checking if it matches
+	fn apply(&self, transaction: &mut Transaction) {
+		if let Some(label) = &self.label {
+			transaction.label.clone_from(label);
+		}
+		if let Some(category) = &self.category {
+			transaction.category.clone_from(category);
+		}
+		for tag in &self.tags {
+			if !transaction.tags.contains(tag) {
+				transaction.tags.push(tag.clone());
+			}
+		}
+	}
+}
+
+/// Shows the rule in the form read by [`Rule::parse`]
+impl Display for Rule {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let Ok(toml::Value::Table(table)) = toml::Value::try_from(self) else {
+			return Ok(());
+		};
+		let fields: Vec<String> = table
+			.iter()
+			.map(|(key, value)| format!("{key} = {value}"))
+			.collect();
+		write!(f, "{}", fields.join(", "))
+	}
+}
+
+/// The rules, and the file they're kept in
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+	pub rules: Vec<Rule>,
+	/// The file the rules are saved to when they're changed, if there is one
+	pub path: Option<PathBuf>,
+}
+
+/// The contents of a rules file
+#[derive(Serialize, Deserialize)]
+struct RulesFile {
+	#[serde(default, rename = "rule")]
+	rules: Vec<Rule>,
+}
+
+impl RuleSet {
+	/// Loads the rules from the given file. If it doesn't exist there are no rules, until some
+	/// are added and it is created
+	///
+	/// # Errors
+	/// If the file can't be read, or has an invalid rule in it
+	pub fn load(path: PathBuf) -> Result<RuleSet, RuleError> {
+		let text = match fs::read_to_string(&path) {
+			Ok(text) => text,
+			Err(e) if e.kind() == ErrorKind::NotFound => {
+				return Ok(RuleSet {
+					rules: vec![],
+					path: Some(path),
+				});
+			}
+			Err(e) => return Err(RuleError::Io(e.to_string())),
+		};
+		let mut file: RulesFile =
+			toml::from_str(&text).map_err(|e| RuleError::Parse(e.to_string()))?;
+		for rule in &mut file.rules {
+			rule.compile()?;
+		}
+		Ok(RuleSet {
+			rules: file.rules,
+			path: Some(path),
+		})
+	}
+
+	/// Saves the rules to their file, creating its directory if needed
+	///
+	/// # Errors
+	/// If the file can't be written to
+	pub fn save(&self) -> Result<(), RuleError> {
+		let Some(path) = &self.path else {
+			return Ok(());
+		};
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir).map_err(|e| RuleError::Io(e.to_string()))?;
+		}
+		let text = toml::to_string(&RulesFile {
+			rules: self.rules.clone(),
+		})
+		.map_err(|e| RuleError::Parse(e.to_string()))?;
+		fs::write(path, text).map_err(|e| RuleError::Io(e.to_string()))
+	}
+
+	/// Applies every rule matching the transaction, in order. Later rules override the label and
+	/// category given by earlier ones, and tags from all of them are kept
+	pub fn apply(&self, transaction: &mut Transaction) {
+		for rule in &self.rules {
+			if rule.matches(transaction) {
+				rule.apply(transaction);
+			}
+		}
+	}
+}
+
+impl Sheet {
+	/// Returns the edits that applying the rules to the given rows would make
+	pub fn rule_edits(&self, rows: &[usize], rules: &RuleSet) -> Vec<CellEdit> {
+		/// The columns rules can change - the label, category and tags
+		const COLUMNS: [usize; 3] = [1, 3, 4];
+
+		rows.iter()
+			.filter_map(|row| Some((*row, self.transactions().get(*row)?)))
+			.flat_map(|(row, transaction)| {
+				let mut changed = transaction.clone();
+				rules.apply(&mut changed);
+				COLUMNS
+					.iter()
+					.filter_map(|col| self.edit(row, *col, changed.member_string(*col)))
+					.collect::<Vec<_>>()
+			})
+			.collect()
+	}
+}
+
+impl Model {
+	/// Replaces the rule at the given index, or adds it if the index is past the last rule, and
+	/// saves the rules. A [`None`] rule removes the one at the index
+	///
+	/// # Errors
+	/// If the rules can't be saved
+	pub fn set_rule(&mut self, index: usize, rule: Option<Rule>) -> Result<(), RuleError> {
+		match (rule, index < self.rules.rules.len()) {
+			(Some(rule), true) => self.rules.rules[index] = rule,
+			(Some(rule), false) => self.rules.rules.push(rule),
+			(None, true) => {
+				self.rules.rules.remove(index);
+			}
+			(None, false) => {}
+		}
+		self.rules.save()
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum RuleError {
+	#[error("Couldn't read or write rules: {0}")]
+	Io(String),
+	#[error("Invalid rule: {0}")]
+	Parse(String),
+	#[error("Invalid regex \"{0}\"")]
+	Regex(String),
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::NaiveDate;
+
+	use super::*;
+
+	fn transaction(label: &str, amount: f64) -> Transaction {
+		Transaction {
+			date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+			label: label.to_string(),
+			amount,
+			..Transaction::default()
+		}
+	}
+
+	fn rules(rules: &[&str]) -> RuleSet {
+		RuleSet {
+			rules: rules
+				.iter()
+				.map(|rule| Rule::parse(rule).unwrap())
+				.collect(),
+			path: None,
+		}
+	}
+
+	#[test]
+	fn matches_every_condition_of_a_rule() {
+		let rule =
+			Rule::parse(r#"contains = "tesco", regex = "^TESCO \\d+$", max_amount = 0.0"#).unwrap();
+		for (label, amount, matches) in [
+			("TESCO 123", -10.0, true),
+			// The text matches whatever its case, but the regex doesn't
+			("Tesco 123", -10.0, false),
+			("TESCO 123", 10.0, false),
+			("TESCO EXPRESS", -10.0, false),
+			("ALDI 123", -10.0, false),
+		] {
+			assert_eq!(
+				rule.matches(&transaction(label, amount)),
+				matches,
+				"{label} {amount}"
+			);
+		}
+		// A rule without conditions matches everything
+		assert!(Rule::default().matches(&transaction("Anything", 1.0)));
+	}
+
+	#[test]
+	fn applies_matching_rules_in_order() {
+		let rules = rules(&[
+			r#"contains = "TESCO", label = "Tesco", category = "Groceries", tags = ["food"]"#,
+			// Later rules see what earlier ones changed, and override their label and category
+			r#"contains = "Tesco", min_amount = -5.0, category = "Snacks", tags = ["food", "small"]"#,
+			r#"contains = "ALDI", category = "Groceries""#,
+		]);
+		let mut big = transaction("TESCO STORES 123", -50.0);
+		rules.apply(&mut big);
+		assert_eq!(big.label, "Tesco");
+		assert_eq!(big.category, "Groceries");
+		assert_eq!(big.tags, ["food"]);
+
+		let mut small = transaction("TESCO STORES 123", -3.0);
+		rules.apply(&mut small);
+		assert_eq!(small.category, "Snacks");
+		// Tags from every rule are kept, once each
+		assert_eq!(small.tags, ["food", "small"]);
+
+		let mut other = transaction("Rent", -800.0);
+		let unchanged = other.clone();
+		rules.apply(&mut other);
+		assert_eq!(other, unchanged);
+	}
+
+	#[test]
+	fn rejects_invalid_rules() {
+		assert!(matches!(
+			Rule::parse(r#"regex = "(""#),
+			Err(RuleError::Regex(_))
+		));
+		assert!(matches!(
+			Rule::parse(r#"colour = "red""#),
+			Err(RuleError::Parse(_))
+		));
+		// And shows valid ones the way they are parsed
+		let rule = Rule::parse(r#"contains = "TESCO", category = "Groceries""#).unwrap();
+		assert_eq!(
+			Rule::parse(&rule.to_string()).unwrap().to_string(),
+			rule.to_string()
+		);
+	}
+
+	#[test]
+	fn previews_the_edits_rules_make_to_a_sheet() {
+		let model = Model::scratch_with(vec![
+			transaction("TESCO 1", -10.0),
+			transaction("Rent", -800.0),
+		]);
+		let rules = rules(&[r#"contains = "TESCO", label = "Tesco", category = "Groceries""#]);
+		let edits = model.get_main_sheet().rule_edits(&[0, 1], &rules);
+		let edits: Vec<_> = edits
+			.iter()
+			.map(|edit| (edit.row, edit.col, edit.after.as_str()))
+			.collect();
+		assert_eq!(edits, [(0, 1, "Tesco"), (0, 3, "Groceries")]);
+	}
+}
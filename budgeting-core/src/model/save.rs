@@ -0,0 +1,478 @@
+//! Saving the model to, and loading it from, JSON files
+//!
+//! Every file records the version of the format it was written in. When a file from an older
+//! version is opened, it is passed through [`MIGRATIONS`] one version at a time until it matches
+//! [`FORMAT_VERSION`], and only then decoded into the model. So when the format changes (e.g. a
+//! field is added to [`Transaction`](crate::model::Transaction)), add a migration that upgrades
+//! the previous version, which also bumps [`FORMAT_VERSION`]. New fields that can be defaulted
+//! should also be marked `#[serde(default)]`, so files saved without them still open
+
+use std::{cell::RefCell, fs, io::ErrorKind, path::Path, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::model::{
+	Access, AuditLog, BankLink, Currencies, ImportSettings, LockOwner, Model, Payee, RuleSet,
+	Scripts, Sheet,
+	encryption::{Encryption, Sealed},
+	lock::FileLock,
+};
+
+/// Upgrades a file from one version of the format to the next
+type Migration = fn(Value) -> Result<Value, SaveError>;
+
+/// Every migration, in order. The migration at index `i` upgrades version `i + 1` to version
+/// `i + 2`
+const MIGRATIONS: &[Migration] = &[];
+
+/// The version of the file format written by this version of the program
+pub const FORMAT_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+/// The contents of a save file
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveFile {
+	/// The version of the format the file was written in
+	version: u64,
+	main_sheet: Sheet,
+	sheets: Vec<Sheet>,
+	#[serde(default)]
+	last_import: Option<ImportSettings>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	bank_links: Vec<BankLink>,
+	#[serde(default, skip_serializing_if = "Currencies::is_default")]
+	currencies: Currencies,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	payees: Vec<Payee>,
+}
+
+/// A [`SaveFile`] borrowing the model, so the model can be written out without copying it
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+	version: u64,
+	main_sheet: &'a Sheet,
+	sheets: &'a [Sheet],
+	last_import: &'a Option<ImportSettings>,
+	#[serde(skip_serializing_if = "<[BankLink]>::is_empty")]
+	bank_links: &'a [BankLink],
+	#[serde(skip_serializing_if = "Currencies::is_default")]
+	currencies: &'a Currencies,
+	#[serde(skip_serializing_if = "<[Payee]>::is_empty")]
+	payees: &'a [Payee],
+}
+
+/// What the model was like when it was last checked for unsaved changes. See
+/// [`Model::has_unsaved_changes`]
+#[derive(Debug, PartialEq)]
+pub(super) struct Revision {
+	sheets: Vec<u64>,
+	outline: String,
+}
+
+/// Brings a file written in any older version of the format up to [`FORMAT_VERSION`]. Files
+/// without a version were written before it was recorded, so they are version 1
+fn migrate(mut value: Value) -> Result<Value, SaveError> {
+	let Some(file) = value.as_object_mut() else {
+		return Err(SaveError::Json("expected an object".to_string()));
+	};
+	let mut version = file
+		.get("version")
+		.map_or(Some(1), Value::as_u64)
+		.ok_or(SaveError::InvalidVersion)?;
+	if version == 0 || version > FORMAT_VERSION {
+		return Err(SaveError::UnsupportedVersion(version));
+	}
+	file.insert("version".to_string(), Value::from(version));
+	let already_applied = usize::try_from(version - 1).unwrap_or(usize::MAX);
+	for migration in MIGRATIONS.iter().skip(already_applied) {
+		value = migration(value)?;
+		version += 1;
+		value["version"] = Value::from(version);
+	}
+	Ok(value)
+}
+
+impl Model {
+	/// Loads the model from the file with the given name, migrating it from an older version of
+	/// the format if needed. If the file doesn't exist, a new model is created that will be saved
+	/// to it. An encrypted file is decrypted with the passphrase. The file is locked first, unless
+	/// it is opened read-only
+	///
+	/// # Errors
+	/// If the file can't be read, isn't a save file, or is from a newer version of the program, if
+	/// it is encrypted and the passphrase is missing or wrong, or if another instance has it open
+	pub fn load(
+		filename: String,
+		passphrase: Option<&str>,
+		access: Access,
+	) -> Result<Model, SaveError> {
+		tracing::info!("Opening {filename} ({access:?})");
+		let lock = match access {
+			Access::ReadOnly => None,
+			Access::Exclusive => Some(FileLock::acquire(&filename, false)?),
+			Access::TakeOver => Some(FileLock::acquire(&filename, true)?),
+		};
+		let text = match fs::read_to_string(Path::new(&filename)) {
+			Ok(text) => text,
+			Err(e) if e.kind() == ErrorKind::NotFound => {
+				let mut model = Model::scratch();
+				model.filename = Some(filename);
+				model.lock = lock;
+				model.read_only = access == Access::ReadOnly;
+				return Ok(model);
+			}
+			Err(e) => return Err(SaveError::Io(e.to_string())),
+		};
+		let mut model = match Sealed::parse(&text) {
+			Some(sealed) => {
+				let passphrase = passphrase.ok_or(SaveError::PassphraseNeeded)?;
+				let (text, encryption) = sealed.unlock(passphrase)?;
+				Self::parse(filename, &text, Some(encryption))?
+			}
+			None => Self::parse(filename, &text, None)?,
+		};
+		model.lock = lock;
+		model.read_only = access == Access::ReadOnly;
+		Ok(model)
+	}
+
+	/// Reads the model from the text of the file with the given name, which is encrypted with the
+	/// key if there is one
+	pub(super) fn parse(
+		filename: String,
+		text: &str,
+		encryption: Option<Encryption>,
+	) -> Result<Model, SaveError> {
+		let value = serde_json::from_str(text).map_err(|e| SaveError::Json(e.to_string()))?;
+		let file: SaveFile =
+			serde_json::from_value(migrate(value)?).map_err(|e| SaveError::Json(e.to_string()))?;
+		let mut model = Model {
+			main_sheet: file.main_sheet,
+			sheets: file.sheets,
+			filename: Some(filename),
+			last_import: file.last_import,
+			bank_links: file.bank_links,
+			currencies: file.currencies,
+			payees: file.payees,
+			rules: RuleSet::default(),
+			scripts: Scripts::default(),
+			saved_contents: String::new(),
+			unsaved: RefCell::default(),
+			audit: AuditLog::default(),
+			encryption,
+			replaced_key: None,
+			lock: None,
+			read_only: false,
+		};
+		model.mark_saved();
+		model.audit = AuditLog::new(model.all_sheets());
+		Ok(model)
+	}
+
+	/// Saves the model to its file, in the current version of the format, keeping the given number
+	/// of backups of what it replaces (see [`Model::write_save`])
+	///
+	/// # Errors
+	/// If the model has no file, or it can't be written to
+	pub fn save(&mut self, backups: usize) -> Result<(), SaveError> {
+		let (filename, text) = self.save_contents()?;
+		Self::write_save(&filename, &text, backups, self.encryption.as_ref())?;
+		self.set_saved_contents(text);
+		self.reseal_history(&filename)?;
+		self.write_audit(&filename)
+	}
+
+	/// Appends the changes recorded since the model was last saved to the audit log of the file
+	fn write_audit(&mut self, filename: &str) -> Result<(), SaveError> {
+		self.record_changes();
+		self.audit
+			.write(filename, self.encryption.as_ref())
+			.map_err(|e| SaveError::Io(format!("Couldn't write the audit log: {e}")))
+	}
+
+	/// Returns the file the model is saved to, and what it would be saved as, so it can be written
+	/// with [`Model::write_save`] away from the model (e.g. on another thread)
+	///
+	/// # Errors
+	/// If the model has no file, or it was opened read-only
+	pub fn save_contents(&self) -> Result<(String, String), SaveError> {
+		let filename = self.filename.clone().ok_or(SaveError::NoFilename)?;
+		if self.read_only {
+			return Err(SaveError::ReadOnly);
+		}
+		Ok((filename, self.contents()?))
+	}
+
+	/// Writes the contents returned by [`Model::save_contents`] to the file, encrypted with the
+	/// key of the model (see [`Model::set_passphrase`]) if it has one. What the file held before is
+	/// kept as its first backup (encrypted too, if it wasn't yet), the first as the second and so
+	/// on, up to the given number of backups, with the oldest dropped. Nothing is written if the
+	/// file is unchanged, so the backups aren't all the same
+	///
+	/// # Errors
+	/// If the file or its backups can't be written to
+	pub fn write_save(
+		filename: &str,
+		text: &str,
+		backups: usize,
+		encryption: Option<&Encryption>,
+	) -> Result<(), SaveError> {
+		let io_error = |e: std::io::Error| SaveError::Io(e.to_string());
+		let unchanged = |old: &str| match (Sealed::parse(old), encryption) {
+			(Some(sealed), Some(encryption)) => {
+				encryption.open(&sealed).is_ok_and(|old| old == text)
+			}
+			(None, None) => old == text,
+			_ => false,
+		};
+		match fs::read_to_string(filename) {
+			Ok(old) if unchanged(&old) => {
+				tracing::debug!("{filename} is unchanged, so it wasn't written");
+				return Ok(());
+			}
+			Ok(old) if backups > 0 => {
+				for n in (1..backups).rev() {
+					let backup = backup_path(filename, n);
+					if Path::new(&backup).exists() {
+						fs::rename(&backup, backup_path(filename, n + 1)).map_err(io_error)?;
+					}
+				}
+				// A file saved before it was encrypted is encrypted as it becomes a backup, so the
+				// budget is never left on disk in plaintext
+				match encryption {
+					Some(encryption) if Sealed::parse(&old).is_none() => {
+						write_file(&backup_path(filename, 1), &encryption.seal(&old)?)
+					}
+					_ => fs::copy(filename, backup_path(filename, 1)).map(drop),
+				}
+				.map_err(io_error)?;
+			}
+			_ => {}
+		}
+		match encryption {
+			Some(encryption) => write_file(filename, &encryption.seal(text)?),
+			None => write_file(filename, text),
+		}
+		.map_err(io_error)?;
+		tracing::info!("Saved {filename}");
+		Ok(())
+	}
+
+	/// Returns the model saved in a backup of its file (see [`backups`]), to replace it with. It
+	/// keeps the file, lock, key and audit log of the model, so it has unsaved changes until it is
+	/// saved over the file, and restoring is logged as every row being deleted and added again
+	///
+	/// # Errors
+	/// If the backup can't be read, or isn't a save file, or it is encrypted with another key than
+	/// the model (e.g. from before the passphrase was changed)
+	pub fn restored(&mut self, backup: &str) -> Result<Model, SaveError> {
+		let text = fs::read_to_string(backup).map_err(|e| SaveError::Io(e.to_string()))?;
+		let text = match (Sealed::parse(&text), &self.encryption) {
+			(Some(sealed), Some(encryption)) => encryption.open(&sealed)?,
+			(Some(_), None) => return Err(SaveError::PassphraseNeeded),
+			(None, _) => text,
+		};
+		let mut model = Self::parse(backup.to_string(), &text, self.encryption.clone())?;
+		model.filename.clone_from(&self.filename);
+		model.set_saved_contents(std::mem::take(&mut self.saved_contents));
+		model.audit = std::mem::take(&mut self.audit);
+		model.replaced_key = self.replaced_key.take();
+		model.lock = self.lock.take();
+		model.read_only = self.read_only;
+		Ok(model)
+	}
+
+	/// Records the contents written by [`Model::write_save`] as saved, if the model is still the
+	/// one in the file, so it has no unsaved changes unless it changed since, and appends the
+	/// changes made to the audit log of the file
+	///
+	/// # Errors
+	/// If the audit log can't be written to
+	pub fn saved_as(&mut self, filename: &str, text: String) -> Result<(), SaveError> {
+		if self.filename.as_deref() != Some(filename) {
+			return Ok(());
+		}
+		self.set_saved_contents(text);
+		self.reseal_history(filename)?;
+		self.write_audit(filename)
+	}
+
+	/// Returns whether the model has changed since it was last loaded or saved
+	///
+	/// This is checked every time the screen is drawn, so the answer is kept until the model
+	/// changes, rather than writing out every transaction again to compare
+	pub fn has_unsaved_changes(&self) -> bool {
+		let revision = self.revision();
+		if let Some((checked, unsaved)) = &*self.unsaved.borrow()
+			&& *checked == revision
+		{
+			return *unsaved;
+		}
+		let unsaved = self
+			.contents()
+			.is_ok_and(|contents| contents != self.saved_contents);
+		*self.unsaved.borrow_mut() = Some((revision, unsaved));
+		unsaved
+	}
+
+	/// Records the text of the file as it was last loaded or saved
+	pub(super) fn set_saved_contents(&mut self, text: String) {
+		self.saved_contents = text;
+		*self.unsaved.get_mut() = None;
+	}
+
+	/// Returns what tells the model apart from how it was before: the revisions of the
+	/// transactions of each sheet, and everything else written out
+	fn revision(&self) -> Revision {
+		let main_sheet = self.main_sheet.outline();
+		let sheets: Vec<Sheet> = self.sheets.iter().map(Sheet::outline).collect();
+		Revision {
+			sheets: self.all_sheets().map(Sheet::revision).collect(),
+			outline: serde_json::to_string(&self.file(&main_sheet, &sheets)).unwrap_or_default(),
+		}
+	}
+
+	/// Records the model as it is now as saved, so it has no unsaved changes
+	pub(super) fn mark_saved(&mut self) {
+		self.set_saved_contents(self.contents().unwrap_or_default());
+	}
+
+	/// Returns what the model would be saved as
+	fn contents(&self) -> Result<String, SaveError> {
+		let file = self.file(&self.main_sheet, &self.sheets);
+		serde_json::to_string_pretty(&file).map_err(|e| SaveError::Json(e.to_string()))
+	}
+
+	/// Returns the file the model is saved as, with the given sheets
+	fn file<'a>(&'a self, main_sheet: &'a Sheet, sheets: &'a [Sheet]) -> SaveFileRef<'a> {
+		SaveFileRef {
+			version: FORMAT_VERSION,
+			main_sheet,
+			sheets,
+			last_import: &self.last_import,
+			bank_links: &self.bank_links,
+			currencies: &self.currencies,
+			payees: &self.payees,
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+	#[error("No file to save to")]
+	NoFilename,
+	#[error("{0}")]
+	Io(String),
+	#[error("Invalid save file: {0}")]
+	Json(String),
+	#[error("Save file has an invalid format version")]
+	InvalidVersion,
+	#[error(
+		"Save file has format version {0}, but this program reads up to version {FORMAT_VERSION}"
+	)]
+	UnsupportedVersion(u64),
+	#[error("The file is encrypted, so it needs a passphrase")]
+	PassphraseNeeded,
+	#[error("Wrong passphrase")]
+	WrongPassphrase,
+	#[error("Couldn't encrypt or decrypt the file: {0}")]
+	Encryption(String),
+	#[error("The file is open in another instance of the program, {0}")]
+	Locked(LockOwner),
+	#[error("The file was opened read-only, so it can't be saved")]
+	ReadOnly,
+	#[error("The file hasn't been saved yet")]
+	NotSaved,
+}
+
+/// Writes the text to a temporary file beside the given one, then renames it over the file, so a
+/// crash partway through leaves the file as it was rather than cut short
+pub(super) fn write_file(filename: &str, text: &str) -> std::io::Result<()> {
+	let temporary = format!("{filename}.tmp");
+	fs::write(&temporary, text)?;
+	fs::rename(&temporary, filename)
+}
+
+/// Returns the name of the backup of the save file with the given name, the first being the most
+/// recent. See [`Model::write_save`]
+pub fn backup_path(filename: &str, n: usize) -> String {
+	format!("{filename}.bak{n}")
+}
+
+/// Returns the backups of the save file with the given name, most recent first, with when each was
+/// made
+#[must_use]
+pub fn backups(filename: &str) -> Vec<(String, SystemTime)> {
+	(1..)
+		.map(|n| backup_path(filename, n))
+		.map_while(|backup| {
+			let modified = fs::metadata(&backup).and_then(|m| m.modified()).ok()?;
+			Some((backup, modified))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::{Message, Transaction};
+
+	#[test]
+	fn only_writes_out_the_model_again_once_it_has_changed() {
+		let mut model = Model::scratch_with(vec![Transaction::default()]);
+		model.mark_saved();
+		assert!(!model.has_unsaved_changes());
+		// While nothing changes, the last answer is given without comparing the contents again
+		model.saved_contents = String::new();
+		assert!(!model.has_unsaved_changes());
+
+		model
+			.update(Message::SetMember {
+				sheet: 0,
+				row: 0,
+				col: 1,
+				value: "Coffee".to_string(),
+			})
+			.unwrap();
+		assert!(model.has_unsaved_changes());
+		model.saved_contents = model.contents().unwrap();
+		assert!(model.has_unsaved_changes());
+		// Saving forgets the last answer
+		model.mark_saved();
+		assert!(!model.has_unsaved_changes());
+	}
+
+	#[test]
+	fn migrates_only_the_versions_it_knows() {
+		let mut file: Value = serde_json::from_str(&Model::scratch().contents().unwrap()).unwrap();
+		let mut with_version = |version: Option<u64>| {
+			match version {
+				Some(version) => file["version"] = Value::from(version),
+				None => drop(file.as_object_mut().unwrap().remove("version")),
+			}
+			file.clone()
+		};
+
+		// Files from before the version was recorded are the first version
+		let unversioned = with_version(None);
+		let text = unversioned.to_string();
+		assert_eq!(migrate(unversioned).unwrap()["version"], FORMAT_VERSION);
+		assert!(Model::parse("budget.json".to_string(), &text, None).is_ok());
+
+		for version in [0, FORMAT_VERSION + 1] {
+			let error = migrate(with_version(Some(version))).unwrap_err();
+			assert!(matches!(error, SaveError::UnsupportedVersion(v) if v == version));
+		}
+		assert_eq!(
+			SaveError::UnsupportedVersion(FORMAT_VERSION + 1).to_string(),
+			format!(
+				"Save file has format version {}, but this program reads up to version \
+				 {FORMAT_VERSION}",
+				FORMAT_VERSION + 1
+			)
+		);
+		file["version"] = Value::from("1");
+		assert!(matches!(migrate(file), Err(SaveError::InvalidVersion)));
+	}
+}
@@ -0,0 +1,321 @@
+//! The user's scripts, written in [Rhai](https://rhai.rs), which add their own commands and import
+//! presets. The scripts are run once when they are loaded, registering what they add:
+//!
+//! ```rhai
+//! // Typed after the leader. Given the selected row, returns it changed
+//! command("t", "tidy label", |row| { row.label = row.label.trim(); row });
+//! // Listed with the builtin presets. Given the text of the file, returns its transactions
+//! import_preset("My bank", "csv", |text| {
+//!     text.split("\n").filter(|line| line != "").map(|line| {
+//!         let fields = line.split(";");
+//!         #{ date: fields[0], label: fields[1], amount: parse_float(fields[2]) }
+//!     })
+//! });
+//! ```
+//!
+//! A transaction is given to scripts as a map with its `date` (as text, like `2024-06-01`),
+//! `label`, `amount`, `category`, `tags` (an array of text), `pending` and `scheduled`. A map
+//! returned can leave any of them out, to keep their value (or their default, when importing)
+
+use std::{
+	fmt::Debug,
+	fs,
+	io::ErrorKind,
+	path::Path,
+	sync::{Arc, Mutex},
+};
+
+use rhai::{AST, Array, Dynamic, Engine, FnPtr, Map};
+use thiserror::Error;
+
+use crate::model::{
+	CellEdit, ImportPreset, Sheet, Transaction,
+	import::{ImportError, ImportFormat},
+};
+
+/// The commands and import presets the user's scripts add, which are shared by every model, and
+/// with the worker thread for imports
+#[derive(Clone, Default)]
+pub struct Scripts {
+	/// The scripts, if there are any
+	loaded: Option<Arc<Loaded>>,
+}
+
+/// The scripts, and what they registered when they were run
+struct Loaded {
+	engine: Engine,
+	ast: AST,
+	commands: Vec<ScriptCommand>,
+	/// The name of each import preset, the extension of the files it reads, and its function
+	presets: Vec<(String, String, FnPtr)>,
+}
+
+/// A command added by a script, run on the selected rows
+#[derive(Debug, Clone)]
+pub struct ScriptCommand {
+	/// The keys of the command, after the leader, written like the keys of the mappings in the
+	/// config (e.g. `<C-x>`)
+	pub keys: String,
+	/// A few words naming the command, for the hints
+	pub name: String,
+	function: FnPtr,
+}
+
+/// An import preset added by a script, which reads the text of a file into transactions
+#[derive(Debug, Clone)]
+pub struct ScriptPreset {
+	name: String,
+	/// The file extension of the files it reads, like `csv`
+	extension: String,
+	function: FnPtr,
+	/// The scripts it was added by, which it is run with
+	scripts: Scripts,
+}
+
+impl Debug for Scripts {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Scripts")
+	}
+}
+
+impl Scripts {
+	/// Loads and runs the scripts in the given file. If it doesn't exist there are no scripts
+	///
+	/// # Errors
+	/// If the file can't be read, or the scripts have an error
+	pub fn load(path: &Path) -> Result<Scripts, ScriptError> {
+		let text = match fs::read_to_string(path) {
+			Ok(text) => text,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Scripts::default()),
+			Err(e) => return Err(ScriptError::Io(e.to_string())),
+		};
+		Self::compile(&text).map_err(|e| match e {
+			ScriptError::Parse(message) => {
+				ScriptError::Parse(format!("{}: {message}", path.display()))
+			}
+			ScriptError::Run(message) => ScriptError::Run(format!("{}: {message}", path.display())),
+			e => e,
+		})
+	}
+
+	/// Runs the scripts in the text, returning what they registered
+	fn compile(text: &str) -> Result<Scripts, ScriptError> {
+		let commands = Arc::new(Mutex::new(vec![]));
+		let presets = Arc::new(Mutex::new(vec![]));
+		let mut engine = Engine::new();
+		// Anything printed would be drawn over the screen
+		engine.on_print(|_| {});
+		engine.on_debug(|_, _, _| {});
+		let registered = Arc::clone(&commands);
+		engine.register_fn("command", move |keys: &str, name: &str, function: FnPtr| {
+			registered
+				.lock()
+				.expect("not poisoned")
+				.push(ScriptCommand {
+					keys: keys.to_string(),
+					name: name.to_string(),
+					function,
+				});
+		});
+		let registered = Arc::clone(&presets);
+		engine.register_fn(
+			"import_preset",
+			move |name: &str, extension: &str, function: FnPtr| {
+				registered.lock().expect("not poisoned").push((
+					name.to_string(),
+					extension.to_ascii_lowercase(),
+					function,
+				));
+			},
+		);
+		let ast = engine
+			.compile(text)
+			.map_err(|e| ScriptError::Parse(e.to_string()))?;
+		engine
+			.run_ast(&ast)
+			.map_err(|e| ScriptError::Run(e.to_string()))?;
+		let commands = std::mem::take(&mut *commands.lock().expect("not poisoned"));
+		let presets = std::mem::take(&mut *presets.lock().expect("not poisoned"));
+		Ok(Scripts {
+			loaded: Some(Arc::new(Loaded {
+				engine,
+				ast,
+				commands,
+				presets,
+			})),
+		})
+	}
+
+	/// Returns the commands the scripts added
+	#[must_use]
+	pub fn commands(&self) -> &[ScriptCommand] {
+		self.loaded.as_ref().map_or(&[], |loaded| &loaded.commands)
+	}
+
+	/// Returns the import presets the scripts added
+	#[must_use]
+	pub fn presets(&self) -> Vec<ImportPreset> {
+		let Some(loaded) = &self.loaded else {
+			return vec![];
+		};
+		loaded
+			.presets
+			.iter()
+			.map(|(name, extension, function)| ImportPreset {
+				name: name.clone(),
+				format: ImportFormat::Script(ScriptPreset {
+					name: name.clone(),
+					extension: extension.clone(),
+					function: function.clone(),
+					scripts: self.clone(),
+				}),
+				date_format: String::new(),
+			})
+			.collect()
+	}
+
+	/// Calls the function with the argument, with the engine and the rest of the scripts
+	fn call(&self, function: &FnPtr, argument: Dynamic) -> Result<Dynamic, ScriptError> {
+		let loaded = self.loaded.as_ref().ok_or(ScriptError::NotLoaded)?;
+		function
+			.call(&loaded.engine, &loaded.ast, (argument,))
+			.map_err(|e| ScriptError::Run(e.to_string()))
+	}
+}
+
+impl ScriptPreset {
+	/// The file extension of the files the preset reads
+	pub fn extension(&self) -> &str {
+		&self.extension
+	}
+
+	/// Reads the text of a file into transactions, with the preset's function
+	///
+	/// # Errors
+	/// If the function fails, or doesn't return an array of transactions
+	pub(super) fn parse(&self, text: &str) -> Result<Vec<Transaction>, ImportError> {
+		let error = |message: String| ImportError::Script(format!("{}: {message}", self.name));
+		let result = self
+			.scripts
+			.call(&self.function, text.into())
+			.map_err(|e| error(e.to_string()))?;
+		let rows: Array = result
+			.try_cast()
+			.ok_or_else(|| error("it didn't return an array".to_string()))?;
+		rows.into_iter()
+			.enumerate()
+			.map(|(i, row)| {
+				from_map(row, Transaction::default())
+					.map_err(|message| error(format!("transaction {}: {message}", i + 1)))
+			})
+			.collect()
+	}
+}
+
+impl Sheet {
+	/// Returns the edits that running the script command with the given index on the given rows
+	/// would make. Only the cells can be changed - the date, label, amount, category and tags
+	///
+	/// # Errors
+	/// If the command fails on any of the rows, or returns something that isn't a transaction
+	pub fn script_edits(
+		&self,
+		rows: &[usize],
+		scripts: &Scripts,
+		command: usize,
+	) -> Result<Vec<CellEdit>, ScriptError> {
+		/// The columns of the cells
+		const COLUMNS: usize = 5;
+
+		let command = &scripts.commands()[command];
+		let mut edits = vec![];
+		for row in rows {
+			let Some(transaction) = self.transactions().get(*row) else {
+				continue;
+			};
+			let result = scripts.call(&command.function, to_map(transaction))?;
+			let changed = from_map(result, transaction.clone())
+				.map_err(|message| ScriptError::Run(format!("{}: {message}", command.name)))?;
+			edits.extend(
+				(0..COLUMNS).filter_map(|col| self.edit(*row, col, changed.member_string(col))),
+			);
+		}
+		Ok(edits)
+	}
+}
+
+/// Returns the transaction as it is given to scripts
+fn to_map(transaction: &Transaction) -> Dynamic {
+	let mut map = Map::new();
+	map.insert("date".into(), transaction.date.to_string().into());
+	map.insert("label".into(), transaction.label.clone().into());
+	map.insert("amount".into(), transaction.amount.into());
+	map.insert("category".into(), transaction.category.clone().into());
+	let tags: Array = transaction
+		.tags
+		.iter()
+		.cloned()
+		.map(Dynamic::from)
+		.collect();
+	map.insert("tags".into(), tags.into());
+	map.insert("pending".into(), transaction.pending.into());
+	map.insert("scheduled".into(), transaction.scheduled.into());
+	map.into()
+}
+
+/// Reads a transaction returned by a script, taking whatever it leaves out from `base`
+fn from_map(value: Dynamic, base: Transaction) -> Result<Transaction, String> {
+	let type_name = value.type_name();
+	let map: Map = value
+		.try_cast()
+		.ok_or_else(|| format!("expected a transaction, but got {type_name}"))?;
+	let mut transaction = base;
+	for (key, value) in map {
+		let invalid = || format!("invalid {key}");
+		match key.as_str() {
+			"date" => {
+				let text = value.into_string().map_err(|_| invalid())?;
+				transaction.date =
+					Transaction::parse_date(text.trim()).map_err(|e| format!("{key}: {e}"))?;
+			}
+			"label" => transaction.label = value.into_string().map_err(|_| invalid())?,
+			"category" => transaction.category = value.into_string().map_err(|_| invalid())?,
+			"amount" => {
+				#[allow(clippy::cast_precision_loss)]
+				let amount = value
+					.as_float()
+					.or_else(|_| value.as_int().map(|amount| amount as f64))
+					.map_err(|_| invalid())?;
+				transaction.amount = amount;
+			}
+			"tags" => {
+				transaction.tags = if value.is_string() {
+					Transaction::parse_tags(&value.into_string().map_err(|_| invalid())?)
+				} else {
+					value
+						.into_array()
+						.map_err(|_| invalid())?
+						.into_iter()
+						.map(|tag| tag.into_string().map_err(|_| invalid()))
+						.collect::<Result<_, _>>()?
+				};
+			}
+			"pending" => transaction.pending = value.as_bool().map_err(|_| invalid())?,
+			"scheduled" => transaction.scheduled = value.as_bool().map_err(|_| invalid())?,
+			_ => return Err(format!("unknown field {key}")),
+		}
+	}
+	Ok(transaction)
+}
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+	#[error("Couldn't read the scripts: {0}")]
+	Io(String),
+	#[error("Invalid script: {0}")]
+	Parse(String),
+	#[error("{0}")]
+	Run(String),
+	#[error("The scripts aren't loaded")]
+	NotLoaded,
+}
@@ -0,0 +1,81 @@
+//! An index of the text that a text search looks through in each transaction of a sheet, so
+//! filtering a sheet with tens of thousands of rows doesn't lowercase every label and format every
+//! amount each time it is drawn. Like the [`Aggregates`](crate::model::aggregates::Aggregates),
+//! the index is built as far as it is needed, and when a row changes, only the entries from that
+//! row on are thrown away
+
+use crate::model::{Filter, Sheet, Transaction};
+
+/// The text of a transaction that a text search looks through
+#[derive(Debug, Clone)]
+pub(super) struct SearchEntry {
+	/// The label, lowercased
+	label: String,
+	/// The amount, as it is searched for
+	amount: String,
+}
+
+impl SearchEntry {
+	pub(super) fn new(transaction: &Transaction) -> Self {
+		Self {
+			label: transaction.label.to_lowercase(),
+			amount: format!("{:.2}", transaction.amount),
+		}
+	}
+
+	/// Returns whether the label or amount contains the text, which must already be lowercased
+	pub(super) fn contains(&self, text: &str) -> bool {
+		self.label.contains(text) || self.amount.contains(text)
+	}
+}
+
+/// The search entries of a sheet, for as many of its rows as have been indexed
+#[derive(Debug, Clone, Default)]
+pub(super) struct SearchIndex {
+	/// The entry of each row, for the first rows of the sheet
+	entries: Vec<SearchEntry>,
+	/// The last text searched for, and the rows that contain it, as the same search is made
+	/// several times each frame while a filter is set
+	last_search: Option<(String, Vec<usize>)>,
+}
+
+impl SearchIndex {
+	/// Forgets the entries of the given row and every row after it, for when it has changed
+	pub(super) fn changed_from(&mut self, row: usize) {
+		self.entries.truncate(row);
+		self.last_search = None;
+	}
+
+	/// Returns the rows of every transaction whose label or amount contains the text, which must
+	/// already be lowercased. Rows that haven't been indexed yet are indexed first
+	fn search(&mut self, text: &str, transactions: &[Transaction]) -> Vec<usize> {
+		if let Some((last, rows)) = &self.last_search
+			&& last == text
+		{
+			return rows.clone();
+		}
+		let indexed = self.entries.len();
+		self.entries
+			.extend(transactions[indexed..].iter().map(SearchEntry::new));
+		let rows: Vec<usize> = (0..self.entries.len())
+			.filter(|row| self.entries[*row].contains(text))
+			.collect();
+		self.last_search = Some((text.to_string(), rows.clone()));
+		rows
+	}
+}
+
+impl Sheet {
+	/// Returns the rows of every transaction that passes the filter, in order
+	pub fn filtered_rows(&self, filter: &Filter) -> Vec<usize> {
+		match filter {
+			Filter::Text(text) => self
+				.search_index
+				.borrow_mut()
+				.search(&text.to_lowercase(), self.transactions()),
+			_ => (0..self.transactions().len())
+				.filter(|row| filter.matches(&self.transactions()[*row]))
+				.collect(),
+		}
+	}
+}
@@ -0,0 +1,559 @@
+use std::{
+	cell::RefCell,
+	collections::{HashMap, HashSet},
+	num::ParseFloatError,
+	str::FromStr,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use chrono::{Months, NaiveDate, ParseError, format::ParseErrorKind};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+	clock,
+	model::{
+		Account, EntryDefaults, InterestSettings, SheetDisplay, StatementCycle,
+		aggregates::Aggregates, search::SearchIndex,
+	},
+};
+
+/// How many deleted transactions each sheet keeps in its trash
+pub const TRASH_SIZE: usize = 50;
+
+/// A single sheet, representing any series of transactions the user wants to record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sheet {
+	/// The name of the sheet
+	pub name: String,
+	/// All of the transactions recorded in the sheet. They are changed through the methods of the
+	/// sheet, so its [`Aggregates`] are kept up to date
+	transactions: Vec<Transaction>,
+	/// The balance the user wants to stay above. The forecast warns if the projected balance dips
+	/// below it
+	#[serde(default)]
+	pub min_balance: Option<f64>,
+	/// The statement cycle of the sheet, if it is a credit card
+	#[serde(default)]
+	pub statement_cycle: Option<StatementCycle>,
+	/// The interest settings of the sheet, if it is a savings account or loan
+	#[serde(default)]
+	pub interest: Option<InterestSettings>,
+	/// The currency the sheet is kept in, as its code, if it isn't the file's base currency. See
+	/// [`Currencies`](crate::model::Currencies)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub currency: Option<String>,
+	/// Whether the sheet is archived, which hides it from the tabs and from moving between sheets
+	/// unless archived sheets are shown. It is still saved, and counted in the net worth and
+	/// reports across sheets
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub archived: bool,
+	/// The account the sheet is for. Changed with [`Sheet::set_account`], so the balances are
+	/// worked out again from its starting balance
+	#[serde(default, skip_serializing_if = "Account::is_default")]
+	account: Account,
+	/// What is filled in for the transactions entered into the sheet by hand
+	#[serde(default, skip_serializing_if = "EntryDefaults::is_default")]
+	pub entry_defaults: EntryDefaults,
+	/// Where new and edited transactions are placed
+	#[serde(default)]
+	pub order: RowOrder,
+	/// How the sheet is displayed
+	#[serde(default)]
+	pub display: SheetDisplay,
+	/// The transactions most recently deleted from the sheet, newest last, so they can be
+	/// restored. Only kept until the file is closed
+	#[serde(skip)]
+	pub trash: Vec<TrashedRow>,
+	/// The running totals of the transactions, as far as they have been worked out
+	#[serde(skip)]
+	pub(super) aggregates: RefCell<Aggregates>,
+	/// The text searched by text filters, as far as it has been indexed
+	#[serde(skip)]
+	pub(super) search_index: RefCell<SearchIndex>,
+	/// Tells the transactions of the sheet apart from any they were before, as it changes with
+	/// every change to them. See [`Sheet::revision`]
+	#[serde(skip, default = "Sheet::next_revision")]
+	revision: u64,
+}
+
+/// How a sheet is changed as it is duplicated, to start the next month from the rows of the last
+/// one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DuplicateOptions {
+	/// Whether the amounts of the copies are set to zero, to be filled in as they are known
+	pub clear_amounts: bool,
+	/// Whether the copies are dated a month after the originals
+	pub next_month: bool,
+}
+
+/// A transaction deleted from a sheet, along with the row it was deleted from
+#[derive(Debug, Clone)]
+pub struct TrashedRow {
+	pub row: usize,
+	pub transaction: Transaction,
+}
+
+/// How the transactions of a sheet are ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RowOrder {
+	/// New and edited transactions are moved into place by their date. Transactions with the same
+	/// date keep the order they were entered in
+	#[default]
+	Date,
+	/// Transactions stay wherever they are put, and are only moved by the user (e.g. with J/K)
+	Manual,
+}
+
+impl Sheet {
+	/// A nicer way to create a sheet
+	pub(super) fn new(name: String, transactions: Vec<Transaction>) -> Self {
+		Self {
+			name,
+			transactions,
+			min_balance: None,
+			statement_cycle: None,
+			interest: None,
+			currency: None,
+			archived: false,
+			account: Account::default(),
+			entry_defaults: EntryDefaults::default(),
+			order: RowOrder::default(),
+			display: SheetDisplay::default(),
+			trash: vec![],
+			aggregates: RefCell::default(),
+			search_index: RefCell::default(),
+			revision: Self::next_revision(),
+		}
+	}
+
+	/// Returns a revision no other transactions have had
+	fn next_revision() -> u64 {
+		static NEXT: AtomicU64 = AtomicU64::new(0);
+		NEXT.fetch_add(1, Ordering::Relaxed)
+	}
+
+	/// Returns the revision of the transactions of the sheet. While it stays the same, so do they
+	pub(super) fn revision(&self) -> u64 {
+		self.revision
+	}
+
+	/// Returns a copy of the sheet without its transactions, to tell whether anything else about
+	/// it changed
+	pub(super) fn outline(&self) -> Self {
+		Self {
+			name: self.name.clone(),
+			transactions: vec![],
+			min_balance: self.min_balance,
+			statement_cycle: self.statement_cycle.clone(),
+			interest: self.interest.clone(),
+			currency: self.currency.clone(),
+			archived: self.archived,
+			account: self.account.clone(),
+			entry_defaults: self.entry_defaults.clone(),
+			order: self.order,
+			display: self.display.clone(),
+			trash: vec![],
+			aggregates: RefCell::default(),
+			search_index: RefCell::default(),
+			revision: self.revision,
+		}
+	}
+
+	/// Returns a copy of the sheet with the given name, changed as the options say. Its
+	/// transactions get identities of their own, and it isn't archived or given the trash
+	pub(super) fn duplicate(&self, name: String, options: DuplicateOptions) -> Self {
+		let mut copy = self.clone();
+		copy.name = name;
+		copy.archived = false;
+		copy.trash.clear();
+		for transaction in copy.transactions_mut() {
+			transaction.id = TransactionId::unique();
+			if options.clear_amounts {
+				transaction.amount = 0.0;
+			}
+			if options.next_month {
+				transaction.date = transaction
+					.date
+					.checked_add_months(Months::new(1))
+					.unwrap_or(transaction.date);
+			}
+		}
+		copy
+	}
+
+	/// Forgets what is cached about the given row and every row after it, for when it has changed
+	fn changed_from(&mut self, row: usize) {
+		self.revision = Self::next_revision();
+		self.aggregates.get_mut().changed_from(row);
+		self.search_index.get_mut().changed_from(row);
+	}
+
+	/// Returns the transactions recorded in the sheet
+	pub fn transactions(&self) -> &[Transaction] {
+		&self.transactions
+	}
+
+	/// Returns the account the sheet is for
+	pub fn account(&self) -> &Account {
+		&self.account
+	}
+
+	/// Sets the account the sheet is for
+	pub(super) fn set_account(&mut self, account: Account) {
+		self.account = account;
+		self.changed_from(0);
+	}
+
+	/// Returns the transaction in the given row to be changed, if there is one
+	pub fn transaction_mut(&mut self, row: usize) -> Option<&mut Transaction> {
+		self.changed_from(row);
+		self.transactions.get_mut(row)
+	}
+
+	/// Returns the transactions of the sheet to be changed in any way, such as sorting them
+	pub fn transactions_mut(&mut self) -> &mut Vec<Transaction> {
+		self.changed_from(0);
+		&mut self.transactions
+	}
+
+	/// Gives the transaction a new [`TransactionId`] if a transaction in the sheet already has its
+	/// own, as it is a copy of it, so only a transaction that was moved keeps its identity
+	fn make_unique(&self, transaction: &mut Transaction) {
+		if self.transactions.iter().any(|t| t.id == transaction.id) {
+			transaction.id = TransactionId::unique();
+		}
+	}
+
+	/// Inserts the transaction at the given row, moving the rows after it down
+	pub fn insert_transaction(&mut self, row: usize, mut transaction: Transaction) {
+		self.make_unique(&mut transaction);
+		self.changed_from(row);
+		self.transactions.insert(row, transaction);
+	}
+
+	/// Inserts all the given transactions in order, starting at the given row, or each by its date
+	/// if the sheet is ordered by date. Returns the row the first of them ended up in
+	pub fn insert_transactions(&mut self, row: usize, mut transactions: Vec<Transaction>) -> usize {
+		let mut ids: HashSet<TransactionId> = self.transactions.iter().map(|t| t.id).collect();
+		for transaction in &mut transactions {
+			if !ids.insert(transaction.id) {
+				transaction.id = TransactionId::unique();
+			}
+		}
+		if self.order == RowOrder::Date {
+			let first = transactions.first().map(|t| t.id);
+			for transaction in transactions {
+				self.insert_by_date(transaction);
+			}
+			return first.and_then(|id| self.row_of(id)).unwrap_or(row);
+		}
+		self.changed_from(row);
+		self.transactions.splice(row..row, transactions);
+		row
+	}
+
+	/// Adds the transaction after the last row
+	pub fn push_transaction(&mut self, mut transaction: Transaction) {
+		self.make_unique(&mut transaction);
+		self.changed_from(self.transactions.len());
+		self.transactions.push(transaction);
+	}
+
+	/// Returns the row of the transaction with the given identity, if it is still in the sheet
+	pub fn row_of(&self, id: TransactionId) -> Option<usize> {
+		self.transactions.iter().position(|t| t.id == id)
+	}
+
+	/// Removes the transaction in the given row, moving the rows after it up
+	pub fn remove_transaction(&mut self, row: usize) -> Transaction {
+		self.changed_from(row);
+		self.transactions.remove(row)
+	}
+
+	/// Swaps the transactions in the two rows
+	pub fn swap_rows(&mut self, a: usize, b: usize) {
+		self.changed_from(a.min(b));
+		self.transactions.swap(a, b);
+	}
+
+	/// Inserts the transaction after the last transaction dated on or before it, so an ordered
+	/// sheet stays ordered. Returns the row it was inserted at
+	pub fn insert_by_date(&mut self, transaction: Transaction) -> usize {
+		let index = self
+			.transactions
+			.iter()
+			.rposition(|t| t.date <= transaction.date)
+			.map_or(0, |i| i + 1);
+		self.insert_transaction(index, transaction);
+		index
+	}
+
+	/// Inserts the transaction by its date if the sheet is ordered by date, or at the given row
+	/// otherwise. Returns the row it was inserted at
+	pub fn insert_ordered(&mut self, row: usize, transaction: Transaction) -> usize {
+		match self.order {
+			RowOrder::Date => self.insert_by_date(transaction),
+			RowOrder::Manual => {
+				self.insert_transaction(row, transaction);
+				row
+			}
+		}
+	}
+
+	/// Moves the transaction in the given row into place by its date if the sheet is ordered by
+	/// date and the row is out of place. Returns the row the transaction ends up in
+	pub fn settle_row(&mut self, row: usize) -> usize {
+		if self.order == RowOrder::Manual || row >= self.transactions.len() {
+			return row;
+		}
+		let date = self.transactions[row].date;
+		let after_previous = row == 0 || self.transactions[row - 1].date <= date;
+		let before_next = self
+			.transactions
+			.get(row + 1)
+			.is_none_or(|t| date <= t.date);
+		if after_previous && before_next {
+			return row;
+		}
+		let transaction = self.remove_transaction(row);
+		self.insert_by_date(transaction)
+	}
+
+	/// Sorts the transactions by date if the sheet is ordered by date. The sort is stable, so
+	/// transactions with the same date keep their order
+	pub fn sort_if_ordered(&mut self) {
+		if self.order == RowOrder::Date {
+			self.transactions_mut().sort_by_key(|t| t.date);
+		}
+	}
+
+	/// Returns the index of the first transaction in the sheet that is dated before the one above
+	/// it. It and every transaction after it are unordered by the date. If it is all ordered,
+	/// returns [`None`]
+	pub fn first_unordered_item(&self) -> Option<usize> {
+		self.transactions
+			.windows(2)
+			.position(|pair| pair[0].date > pair[1].date)
+			.map(|i| i + 1)
+	}
+
+	/// Returns every distinct label in the sheet, along with how many times it was used and the
+	/// sum of the amounts of those transactions. Sorted by count (most frequent first), then label
+	pub fn label_summary(&self) -> Vec<LabelSummary> {
+		let mut summaries: HashMap<&str, LabelSummary> = HashMap::new();
+		for transaction in &self.transactions {
+			let summary = summaries
+				.entry(transaction.label.as_str())
+				.or_insert_with(|| LabelSummary {
+					label: transaction.label.clone(),
+					count: 0,
+					total: 0.0,
+				});
+			summary.count += 1;
+			summary.total += transaction.amount;
+		}
+		let mut summaries: Vec<LabelSummary> = summaries.into_values().collect();
+		summaries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.label.cmp(&b.label)));
+		summaries
+	}
+}
+
+/// The usage of a single label within a sheet
+#[derive(Debug, Clone)]
+pub struct LabelSummary {
+	/// The label itself
+	pub label: String,
+	/// The amount of transactions with this label
+	pub count: usize,
+	/// The sum of the amounts of all transactions with this label
+	pub total: f64,
+}
+
+/// Identifies a transaction for as long as the program runs, wherever it is moved to, unlike its
+/// row. It isn't saved, as nothing refers to it across runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransactionId(u64);
+
+impl TransactionId {
+	/// Returns an identity no other transaction has
+	pub fn unique() -> Self {
+		static NEXT: AtomicU64 = AtomicU64::new(0);
+		Self(NEXT.fetch_add(1, Ordering::Relaxed))
+	}
+}
+
+/// A single transaction that the user can record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+	/// The identity of the transaction. A copy shares it until it is added to a sheet that holds the
+	/// original, where it gets its own
+	#[serde(skip, default = "TransactionId::unique")]
+	pub id: TransactionId,
+	/// Whatever label the user chooses to give it
+	pub label: String,
+	/// The date of the transaction
+	pub date: NaiveDate,
+	/// The amount of the transaction
+	pub amount: f64,
+	/// The category the transaction belongs to, which is empty if it hasn't been categorised
+	#[serde(default)]
+	pub category: String,
+	/// Any tags given to the transaction
+	#[serde(default)]
+	pub tags: Vec<String>,
+	/// Whether the transaction hasn't cleared the bank yet. Transactions entered by hand start
+	/// out pending, and imported ones don't, as the bank has already recorded them
+	#[serde(default)]
+	pub pending: bool,
+	/// Whether the transaction is planned for a future date, rather than having happened. Scheduled
+	/// transactions are left out of the actual balance, and only count towards the projected one
+	#[serde(default)]
+	pub scheduled: bool,
+}
+
+impl Default for Transaction {
+	fn default() -> Self {
+		Self {
+			id: TransactionId::unique(),
+			label: String::new(),
+			date: clock::today(),
+			amount: 0.0,
+			category: String::new(),
+			tags: vec![],
+			pending: false,
+			scheduled: false,
+		}
+	}
+}
+
+impl Transaction {
+	/// Returns the value of the member in the given column as it is edited, i.e. without any
+	/// currency formatting
+	#[must_use]
+	pub fn member_string(&self, col: usize) -> String {
+		match col {
+			0 => self.date.to_string(),
+			1 => self.label.clone(),
+			2 => self.amount.to_string(),
+			3 => self.category.clone(),
+			4 => self.tags.join(", "),
+			_ => String::new(),
+		}
+	}
+
+	/// Sets the member in the given column from its value as it is edited. See
+	/// [`Self::member_string`]
+	///
+	/// # Errors
+	/// If the value isn't a valid date or amount, for those columns
+	pub fn set_member(
+		&mut self,
+		col: usize,
+		new: String,
+	) -> Result<(), ParseTransactionMemberError> {
+		match col {
+			0 => self.update_date(&new),
+			1 => {
+				self.update_label(new);
+				Ok(())
+			}
+			2 => self.update_amount(&new),
+			3 => {
+				self.update_category(new);
+				Ok(())
+			}
+			4 => {
+				self.update_tags(&new);
+				Ok(())
+			}
+			_ => Ok(()),
+		}
+	}
+
+	pub(super) fn update_label(&mut self, new_value: String) {
+		self.label = new_value;
+	}
+
+	pub(super) fn update_category(&mut self, new_value: String) {
+		self.category = new_value;
+	}
+
+	/// Sets the tags from a comma separated list
+	pub(super) fn update_tags(&mut self, new_value: &str) {
+		self.tags = Self::parse_tags(new_value);
+	}
+
+	pub(super) fn update_date(
+		&mut self,
+		new_value: &str,
+	) -> anyhow::Result<(), ParseTransactionMemberError> {
+		self.date = NaiveDate::from_str(new_value)?;
+		Ok(())
+	}
+
+	pub(super) fn update_amount(
+		&mut self,
+		new_value: &str,
+	) -> anyhow::Result<(), ParseTransactionMemberError> {
+		self.amount = f64::from_str(new_value)?;
+		Ok(())
+	}
+
+	/// Parses a date, written like `2024-06-01`
+	///
+	/// # Errors
+	/// If it isn't a valid date
+	pub fn parse_date(s: &str) -> anyhow::Result<NaiveDate, ParseTransactionMemberError> {
+		Ok(NaiveDate::from_str(s)?)
+	}
+
+	/// Splits a comma separated list of tags, ignoring empty ones
+	pub fn parse_tags(s: &str) -> Vec<String> {
+		s.split(',')
+			.map(str::trim)
+			.filter(|t| !t.is_empty())
+			.map(str::to_string)
+			.collect()
+	}
+
+	/// Parses an amount, written like `-4.5`
+	///
+	/// # Errors
+	/// If it isn't a number
+	pub fn parse_amount(s: &str) -> anyhow::Result<f64, ParseTransactionMemberError> {
+		Ok(f64::from_str(s)?)
+	}
+}
+
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct ParseTransactionMemberError {
+	pub message: String,
+}
+
+impl From<ParseError> for ParseTransactionMemberError {
+	fn from(value: ParseError) -> Self {
+		Self {
+			message: match value.kind() {
+				ParseErrorKind::OutOfRange => "Date is out of range".to_string(),
+				ParseErrorKind::Impossible => "Date is impossible".to_string(),
+				ParseErrorKind::NotEnough => "Not enough information".to_string(),
+				ParseErrorKind::Invalid => "Invalid characters found".to_string(),
+				ParseErrorKind::TooShort => "Input too short".to_string(),
+				ParseErrorKind::TooLong => "Input too long".to_string(),
+				ParseErrorKind::BadFormat => "Bad format".to_string(),
+				// kind is non-exhaustive
+				_ => "Error parsing date from input".to_string(),
+			},
+		}
+	}
+}
+
+impl From<ParseFloatError> for ParseTransactionMemberError {
+	fn from(value: ParseFloatError) -> Self {
+		Self {
+			message: format!("{value}"),
+		}
+	}
+}
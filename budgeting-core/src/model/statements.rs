@@ -0,0 +1,319 @@
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::{Message, Model, Sheet, Transaction, UpdateError};
+
+/// The statement cycle of a credit card sheet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatementCycle {
+	/// The day of the month the statement closes on. Clamped to the last day of shorter months
+	pub closing_day: u32,
+	/// The day of the month the statement payment is due, in the month after it closes (or the
+	/// same month, if it comes after the closing day)
+	pub due_day: u32,
+	/// The name of the sheet that statements are paid from
+	pub payment_sheet: Option<String>,
+}
+
+/// A single statement period of a credit card sheet
+#[derive(Debug, Clone)]
+pub struct Statement {
+	/// The first day of the period
+	pub start: NaiveDate,
+	/// The day the statement closes, which is the last day of the period
+	pub close: NaiveDate,
+	/// The day the payment of the statement is due
+	pub due: NaiveDate,
+	/// The sum of all the transactions in the period
+	pub balance: f64,
+	/// The amount of transactions in the period
+	pub count: usize,
+}
+
+/// Returns the given day of the month, or the last day of the month if it is too short
+fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+	(1..=day.clamp(1, 31))
+		.rev()
+		.find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+		.expect("Every month has a first day")
+}
+
+impl StatementCycle {
+	/// Returns the first closing date on or after the given date
+	#[must_use]
+	pub fn close_on_or_after(&self, date: NaiveDate) -> NaiveDate {
+		let close = day_in_month(date.year(), date.month(), self.closing_day);
+		if close >= date {
+			close
+		} else {
+			let next = date
+				.with_day(1)
+				.and_then(|d| d.checked_add_months(Months::new(1)))
+				.unwrap_or(date);
+			day_in_month(next.year(), next.month(), self.closing_day)
+		}
+	}
+
+	/// Returns the date the statement closing on the given date is due
+	#[must_use]
+	pub fn due_after(&self, close: NaiveDate) -> NaiveDate {
+		let due = day_in_month(close.year(), close.month(), self.due_day);
+		if due > close {
+			due
+		} else {
+			let next = close
+				.with_day(1)
+				.and_then(|d| d.checked_add_months(Months::new(1)))
+				.unwrap_or(close);
+			day_in_month(next.year(), next.month(), self.due_day)
+		}
+	}
+}
+
+impl Statement {
+	/// Returns the label of the payment of this statement, on the card with the given name
+	pub fn payment_label(&self, card_name: &str) -> String {
+		format!("Statement payment ({card_name}, closed {})", self.close)
+	}
+}
+
+impl Sheet {
+	/// Groups the transactions of the sheet into its statement periods, oldest first. Returns an
+	/// empty vec if the sheet has no statement cycle
+	pub fn statements(&self) -> Vec<Statement> {
+		let Some(cycle) = &self.statement_cycle else {
+			return vec![];
+		};
+		let (Some(first), Some(last)) = (
+			self.transactions().iter().map(|t| t.date).min(),
+			self.transactions().iter().map(|t| t.date).max(),
+		) else {
+			return vec![];
+		};
+
+		let mut statements = vec![];
+		let mut start = first;
+		loop {
+			let close = cycle.close_on_or_after(start);
+			let in_period = self
+				.transactions()
+				.iter()
+				.filter(|t| t.date >= start && t.date <= close);
+			statements.push(Statement {
+				start,
+				close,
+				due: cycle.due_after(close),
+				balance: in_period.clone().map(|t| t.amount).sum(),
+				count: in_period.count(),
+			});
+			if close >= last {
+				break;
+			}
+			start = close.succ_opt().unwrap_or(close);
+		}
+		statements
+	}
+}
+
+impl Model {
+	/// Adds the payment of a statement to the sheet with the given index, and the matching
+	/// transfer out of its payment sheet, both dated on the due date of the statement and put in
+	/// place by it. They are marked as scheduled if they are due after `today`, as they haven't
+	/// happened yet
+	///
+	/// # Errors
+	/// If the sheet has no statement cycle, its payment sheet doesn't exist, or the payment of the
+	/// statement is already in the sheet
+	pub fn schedule_statement_payment(
+		&mut self,
+		sheet_index: usize,
+		statement: &Statement,
+		today: NaiveDate,
+	) -> Result<(), StatementPaymentError> {
+		let sheet = self
+			.get_sheet(sheet_index)
+			.ok_or(StatementPaymentError::SheetIndex(sheet_index))?;
+		let payment_sheet = sheet
+			.statement_cycle
+			.as_ref()
+			.ok_or(StatementPaymentError::MissingCycle)?
+			.payment_sheet
+			.clone()
+			.ok_or(StatementPaymentError::MissingPaymentSheet)?;
+		let label = statement.payment_label(&sheet.name);
+		if sheet.transactions().iter().any(|t| t.label == label) {
+			return Err(StatementPaymentError::AlreadyScheduled(statement.close));
+		}
+		let payment_index = self
+			.sheet_titles()
+			.iter()
+			.position(|name| *name == payment_sheet)
+			.ok_or(StatementPaymentError::SheetName(payment_sheet))?;
+
+		let sides = [
+			(sheet_index, -statement.balance),
+			(payment_index, statement.balance),
+		];
+		for (index, amount) in sides {
+			let row = self.get_sheet(index).map_or(0, |s| s.transactions().len());
+			self.update(Message::InsertRow {
+				sheet: index,
+				row,
+				transaction: Transaction {
+					label: label.clone(),
+					date: statement.due,
+					amount,
+					scheduled: statement.due > today,
+					..Transaction::default()
+				},
+			})?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum StatementPaymentError {
+	#[error("No sheet with index {0}")]
+	SheetIndex(usize),
+	#[error("Sheet has no statement cycle")]
+	MissingCycle,
+	#[error("Sheet has no payment sheet")]
+	MissingPaymentSheet,
+	#[error("No sheet named \"{0}\"")]
+	SheetName(String),
+	#[error("The payment of the statement closed {0} is already scheduled")]
+	AlreadyScheduled(NaiveDate),
+	#[error(transparent)]
+	Update(#[from] UpdateError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn date(text: &str) -> NaiveDate {
+		text.parse().unwrap()
+	}
+
+	fn transaction(day: &str, amount: f64) -> Transaction {
+		Transaction {
+			date: date(day),
+			label: "Card".to_string(),
+			amount,
+			..Transaction::default()
+		}
+	}
+
+	/// A card on the main sheet, closing on the 15th and due on the 5th, paid from Sheet1
+	fn card() -> Model {
+		let mut model = Model::scratch_with(vec![
+			transaction("2024-01-03", -10.0),
+			transaction("2024-01-15", -20.0),
+			transaction("2024-01-16", -5.0),
+			transaction("2024-02-20", -7.0),
+		]);
+		model.update(Message::CreateSheet).unwrap();
+		let cycle = StatementCycle {
+			closing_day: 15,
+			due_day: 5,
+			payment_sheet: Some("Sheet1".to_string()),
+		};
+		model
+			.update(Message::SetStatementCycle {
+				sheet: 0,
+				cycle: Some(cycle),
+			})
+			.unwrap();
+		model
+	}
+
+	#[test]
+	fn groups_transactions_into_statements() {
+		let model = card();
+		let statements: Vec<_> = model
+			.get_main_sheet()
+			.statements()
+			.iter()
+			.map(|s| (s.start, s.close, s.due, s.balance, s.count))
+			.collect();
+		assert_eq!(
+			statements,
+			[
+				// The closing day is the last day of its period
+				(
+					date("2024-01-03"),
+					date("2024-01-15"),
+					date("2024-02-05"),
+					-30.0,
+					2
+				),
+				(
+					date("2024-01-16"),
+					date("2024-02-15"),
+					date("2024-03-05"),
+					-5.0,
+					1
+				),
+				(
+					date("2024-02-16"),
+					date("2024-03-15"),
+					date("2024-04-05"),
+					-7.0,
+					1
+				),
+			]
+		);
+
+		// Closing days past the end of a month close on its last day, and a due day after the
+		// closing day is in the same month
+		let cycle = StatementCycle {
+			closing_day: 31,
+			due_day: 20,
+			payment_sheet: None,
+		};
+		let close = cycle.close_on_or_after(date("2024-02-10"));
+		assert_eq!(close, date("2024-02-29"));
+		assert_eq!(cycle.due_after(close), date("2024-03-20"));
+		assert_eq!(cycle.due_after(date("2024-01-10")), date("2024-01-20"));
+	}
+
+	#[test]
+	fn schedules_both_sides_of_a_payment_once() {
+		let mut model = card();
+		let statements = model.get_main_sheet().statements();
+		model
+			.schedule_statement_payment(0, &statements[0], date("2024-01-31"))
+			.unwrap();
+
+		// The card is paid off, in date order, by a transfer out of the payment sheet
+		let label = statements[0].payment_label("Sheet0");
+		let card = model.get_main_sheet().transactions();
+		let payment = card.iter().position(|t| t.label == label).unwrap();
+		assert_eq!(payment, 3);
+		assert_eq!(card[payment].date, date("2024-02-05"));
+		assert!((card[payment].amount - 30.0).abs() < 1e-9);
+		assert!(card[payment].scheduled);
+		let transfers = |model: &Model, label: &str| -> Vec<Transaction> {
+			let sheet = model.get_sheet(1).unwrap().transactions();
+			sheet.iter().filter(|t| t.label == label).cloned().collect()
+		};
+		let transfer = &transfers(&model, &label)[0];
+		assert!((transfer.amount + 30.0).abs() < 1e-9);
+		assert!(transfer.scheduled);
+
+		assert!(matches!(
+			model.schedule_statement_payment(0, &statements[0], date("2024-01-31")),
+			Err(StatementPaymentError::AlreadyScheduled(_))
+		));
+		assert_eq!(transfers(&model, &label).len(), 1);
+
+		// A payment that was due already has happened, so it isn't scheduled
+		model
+			.schedule_statement_payment(0, &statements[1], date("2024-04-01"))
+			.unwrap();
+		let label = statements[1].payment_label("Sheet0");
+		assert!(!transfers(&model, &label)[0].scheduled);
+	}
+}
@@ -0,0 +1,204 @@
+//! Exchange rates between currencies, so sheets kept in different currencies can be added up. They
+//! are fetched from a provider online, set up in the config file, and rates set by hand in the file
+//! (see [`Currencies`](crate::model::Currencies)) are used in their place
+
+use std::{collections::BTreeMap, env, time::Duration};
+
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use ureq::Agent;
+
+/// How long a request can take before it is given up on
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The daily reference rates of the European Central Bank, against the euro
+const ECB_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+/// The latest rates of exchangerate.host, against the currency the account is set up with
+const EXCHANGERATE_HOST_URL: &str = "https://api.exchangerate.host/live";
+
+/// Where exchange rates are fetched from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum RateProvider {
+	/// The daily reference rates of the European Central Bank, which need no account
+	#[default]
+	#[serde(rename = "ecb")]
+	Ecb,
+	/// exchangerate.host, which needs an access key
+	#[serde(rename = "exchangerate.host")]
+	ExchangerateHost,
+}
+
+/// Where exchange rates are fetched from, as set in the `[rates]` table of the config
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateSource {
+	#[serde(default)]
+	pub provider: RateProvider,
+	/// The access key, for exchangerate.host. Better left out of the config, in favour of
+	/// `access_key_env`
+	pub access_key: Option<String>,
+	/// The environment variable the access key is read from, if it isn't in the config
+	pub access_key_env: Option<String>,
+}
+
+/// Exchange rates as fetched from a provider, each the amount of its currency one unit of the
+/// base currency is worth
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRates {
+	pub base: String,
+	/// The day the rates are for
+	pub date: NaiveDate,
+	pub rates: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Error)]
+pub enum RatesError {
+	#[error("Couldn't reach the rate provider: {0}")]
+	Http(String),
+	#[error("The rate provider said {0}")]
+	Status(u16),
+	#[error("Couldn't read the rates: {0}")]
+	Response(String),
+	#[error("{0} isn't set, so there is no access key for the rate provider")]
+	NoAccessKey(String),
+}
+
+impl From<ureq::Error> for RatesError {
+	fn from(e: ureq::Error) -> Self {
+		RatesError::Http(e.to_string())
+	}
+}
+
+impl ExchangeRates {
+	/// Returns how much one unit of the `from` currency is worth in the `to` currency, if the
+	/// rates include both
+	#[must_use]
+	pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+		let per_base = |currency: &str| {
+			if currency == self.base {
+				Some(1.0)
+			} else {
+				self.rates.get(currency).copied()
+			}
+		};
+		Some(per_base(to)? / per_base(from)?)
+	}
+}
+
+impl RateSource {
+	/// Fetches the latest rates from the provider
+	///
+	/// # Errors
+	/// If the provider can't be reached, refuses the request, or sends something that can't be read
+	pub fn fetch(&self) -> Result<ExchangeRates, RatesError> {
+		let rates = match self.provider {
+			RateProvider::Ecb => {
+				let text = get(agent().get(ECB_URL))?;
+				parse_ecb(&text)?
+			}
+			RateProvider::ExchangerateHost => {
+				let request = agent()
+					.get(EXCHANGERATE_HOST_URL)
+					.query("access_key", self.access_key()?);
+				parse_exchangerate_host(&get(request)?)?
+			}
+		};
+		tracing::info!("Fetched exchange rates for {}", rates.date);
+		Ok(rates)
+	}
+
+	/// Returns the access key, from the config or the environment
+	fn access_key(&self) -> Result<String, RatesError> {
+		match (&self.access_key, &self.access_key_env) {
+			(Some(key), _) => Ok(key.clone()),
+			(None, Some(var)) => env::var(var).map_err(|_| RatesError::NoAccessKey(var.clone())),
+			(None, None) => Err(RatesError::NoAccessKey("access_key".to_string())),
+		}
+	}
+}
+
+/// Reads the rates out of the ECB's XML, where each is an element like
+/// `<Cube currency='USD' rate='1.0813'/>` inside one with the date, like `<Cube time='2024-01-31'>`
+fn parse_ecb(text: &str) -> Result<ExchangeRates, RatesError> {
+	let attribute = |text: &str, name: &str| -> Option<String> {
+		let start = text.find(&format!("{name}='"))? + name.len() + 2;
+		Some(text[start..].split('\'').next()?.to_string())
+	};
+	let date = attribute(text, "time")
+		.and_then(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok())
+		.ok_or_else(|| RatesError::Response("no date".to_string()))?;
+	let rates = text
+		.split("<Cube ")
+		.filter_map(|cube| {
+			let currency = attribute(cube, "currency")?;
+			let rate = attribute(cube, "rate")?.parse().ok()?;
+			Some((currency, rate))
+		})
+		.collect::<BTreeMap<_, _>>();
+	if rates.is_empty() {
+		return Err(RatesError::Response("no rates".to_string()));
+	}
+	Ok(ExchangeRates {
+		base: "EUR".to_string(),
+		date,
+		rates,
+	})
+}
+
+/// The rates as exchangerate.host sends them, with each quote named by the source currency
+/// followed by its own, like `USDEUR`
+#[derive(Debug, Deserialize)]
+struct Live {
+	success: bool,
+	source: Option<String>,
+	timestamp: Option<i64>,
+	#[serde(default)]
+	quotes: BTreeMap<String, f64>,
+	error: Option<LiveError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LiveError {
+	info: String,
+}
+
+fn parse_exchangerate_host(text: &str) -> Result<ExchangeRates, RatesError> {
+	let live: Live = serde_json::from_str(text).map_err(|e| RatesError::Response(e.to_string()))?;
+	if !live.success {
+		let info = live
+			.error
+			.map_or_else(|| "it failed".to_string(), |e| e.info);
+		return Err(RatesError::Response(info));
+	}
+	let (Some(base), Some(timestamp)) = (live.source, live.timestamp) else {
+		return Err(RatesError::Response("no source currency".to_string()));
+	};
+	let date = DateTime::from_timestamp(timestamp, 0)
+		.ok_or_else(|| RatesError::Response(format!("invalid date {timestamp}")))?
+		.date_naive();
+	let rates = live
+		.quotes
+		.into_iter()
+		.filter_map(|(pair, rate)| Some((pair.strip_prefix(&base)?.to_string(), rate)))
+		.collect();
+	Ok(ExchangeRates { base, date, rates })
+}
+
+/// Sends the request, returning the body of the response
+fn get(request: ureq::RequestBuilder<ureq::typestate::WithoutBody>) -> Result<String, RatesError> {
+	let mut response = request.call()?;
+	match response.status().as_u16() {
+		200 => Ok(response.body_mut().read_to_string()?),
+		status => Err(RatesError::Status(status)),
+	}
+}
+
+fn agent() -> Agent {
+	Agent::config_builder()
+		.http_status_as_error(false)
+		.timeout_global(Some(TIMEOUT))
+		.build()
+		.into()
+}
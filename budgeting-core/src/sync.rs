@@ -0,0 +1,313 @@
+//! Syncing the file with a copy of it kept on a server, so it can be used from more than one
+//! machine. The server is a `WebDAV` server (e.g. Nextcloud) or an S3-compatible bucket, set up in
+//! the config file. Syncing uploads the file if only it changed since it was last synced, and
+//! downloads the copy if only that did. If both changed, they conflict, and are merged (see
+//! [`Model::merge_rows`](crate::model::Model::merge_rows)) before the result is uploaded.
+//!
+//! Changes are told apart by the server's `ETag` of the copy, and a hash of the file, as they were
+//! when last synced. Uploads only replace the copy that was downloaded, so a copy uploaded from
+//! elsewhere in the meantime is never overwritten
+
+use std::{env, fmt::Write, time::Duration};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use ureq::{Agent, RequestBuilder, http::Uri};
+
+/// How long a request can take before it is given up on
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The kind of server the copy is kept on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+	/// A `WebDAV` server, logged into with a username and password
+	#[default]
+	WebDav,
+	/// An S3-compatible bucket, with the username as the access key and the password as the secret
+	/// key
+	S3,
+}
+
+/// Where the copy of the file is kept, and how to log in, as set in the `[sync]` table of the
+/// config
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Remote {
+	/// The URL of the copy, e.g. `https://cloud.example.com/remote.php/dav/files/me/budget.json`,
+	/// or `https://s3.example.com/bucket/budget.json` for a bucket (path-style)
+	pub url: String,
+	#[serde(default)]
+	pub provider: Provider,
+	pub username: Option<String>,
+	/// The password, or secret key. Better left out of the config, in favour of `password_env`
+	pub password: Option<String>,
+	/// The environment variable the password is read from, if it isn't in the config
+	pub password_env: Option<String>,
+	/// The region of the bucket, for S3
+	#[serde(default = "default_region")]
+	pub region: String,
+}
+
+fn default_region() -> String {
+	"us-east-1".to_string()
+}
+
+/// The copy of the file on the server
+#[derive(Debug, Clone)]
+pub struct RemoteFile {
+	pub text: String,
+	pub etag: String,
+}
+
+/// What the file and its copy were when they were last synced, to tell which has changed since
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncState {
+	/// The `ETag` the server gave the copy
+	pub etag: String,
+	/// The hash of the file, as returned by [`content_hash`]
+	pub hash: String,
+}
+
+/// What syncing did, or has left to do
+#[derive(Debug, Clone)]
+pub enum SyncOutcome {
+	/// The file and its copy are the same
+	InSync(SyncState),
+	/// The file was uploaded, as only it had changed
+	Pushed(SyncState),
+	/// The copy has changed, and the file hasn't, so the file should be replaced by the copy
+	Pulled(RemoteFile),
+	/// Both have changed, so they need merging
+	Conflict(RemoteFile),
+}
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+	#[error("Couldn't reach the server: {0}")]
+	Http(String),
+	#[error("The server said {0}")]
+	Status(u16),
+	#[error("The copy on the server changed while syncing, so sync again to merge it")]
+	Conflict,
+	#[error("The server didn't give the copy an ETag, so changes to it can't be told apart")]
+	NoEtag,
+	#[error("{0} isn't set, so there is no password to log in with")]
+	NoPassword(String),
+	#[error("Invalid sync URL {0}")]
+	Url(String),
+}
+
+impl From<ureq::Error> for SyncError {
+	fn from(e: ureq::Error) -> Self {
+		SyncError::Http(e.to_string())
+	}
+}
+
+/// Returns a hash of the contents of the file, to tell whether it has changed since it was synced
+#[must_use]
+pub fn content_hash(text: &str) -> String {
+	hex(&Sha256::digest(text.as_bytes()))
+}
+
+impl Remote {
+	/// Syncs the file, whose contents (as on disk) are given, with its copy, returning what was
+	/// done. The file is uploaded if it is the only one that changed since `last`, or if there is
+	/// no copy yet. Without `last`, a copy that differs conflicts
+	///
+	/// # Errors
+	/// If the server can't be reached or refuses the request, or the copy is uploaded to elsewhere
+	/// at the same time
+	pub fn sync(&self, local: &str, last: Option<&SyncState>) -> Result<SyncOutcome, SyncError> {
+		let hash = content_hash(local);
+		let Some(remote) = self.fetch()? else {
+			let etag = self.push(local, None)?;
+			tracing::info!("Uploaded the file to {}", self.url);
+			return Ok(SyncOutcome::Pushed(SyncState { etag, hash }));
+		};
+		if remote.text == local {
+			return Ok(SyncOutcome::InSync(SyncState {
+				etag: remote.etag,
+				hash,
+			}));
+		}
+		let remote_changed = last.is_none_or(|last| last.etag != remote.etag);
+		let local_changed = last.is_none_or(|last| last.hash != hash);
+		Ok(match (remote_changed, local_changed) {
+			(false, false) => SyncOutcome::InSync(SyncState {
+				etag: remote.etag,
+				hash,
+			}),
+			(false, true) => {
+				let etag = self.push(local, Some(&remote.etag))?;
+				tracing::info!("Uploaded the file to {}", self.url);
+				SyncOutcome::Pushed(SyncState { etag, hash })
+			}
+			(true, false) => {
+				tracing::info!("Downloaded the file from {}", self.url);
+				SyncOutcome::Pulled(remote)
+			}
+			(true, true) => {
+				tracing::warn!("The file and its copy at {} both changed", self.url);
+				SyncOutcome::Conflict(remote)
+			}
+		})
+	}
+
+	/// Downloads the copy, if there is one
+	///
+	/// # Errors
+	/// If the server can't be reached or refuses the request
+	pub fn fetch(&self) -> Result<Option<RemoteFile>, SyncError> {
+		let request = agent().get(&self.url);
+		let mut response = self.sign(request, "GET", b"")?.call()?;
+		match response.status().as_u16() {
+			404 => Ok(None),
+			200 => {
+				let etag = etag(response.headers()).ok_or(SyncError::NoEtag)?;
+				let text = response.body_mut().read_to_string()?;
+				Ok(Some(RemoteFile { text, etag }))
+			}
+			status => Err(SyncError::Status(status)),
+		}
+	}
+
+	/// Uploads the file over the copy with the `ETag`, or as a new copy if not given one, returning
+	/// the `ETag` of the uploaded copy
+	///
+	/// # Errors
+	/// If the copy isn't the one with the `ETag` (or there is one when there shouldn't be), or the
+	/// server can't be reached or refuses the request
+	pub fn push(&self, text: &str, replacing: Option<&str>) -> Result<String, SyncError> {
+		let request = agent().put(&self.url);
+		let request = match replacing {
+			Some(etag) => request.header("If-Match", etag),
+			None => request.header("If-None-Match", "*"),
+		};
+		let response = self.sign(request, "PUT", text.as_bytes())?.send(text)?;
+		match response.status().as_u16() {
+			412 => Err(SyncError::Conflict),
+			200..300 => match etag(response.headers()) {
+				Some(etag) => Ok(etag),
+				// Some servers only give the ETag when the copy is downloaded
+				None => self.fetch()?.map(|copy| copy.etag).ok_or(SyncError::NoEtag),
+			},
+			status => Err(SyncError::Status(status)),
+		}
+	}
+
+	/// Returns the password, from the config or the environment
+	fn password(&self) -> Result<String, SyncError> {
+		match (&self.password, &self.password_env) {
+			(Some(password), _) => Ok(password.clone()),
+			(None, Some(var)) => env::var(var).map_err(|_| SyncError::NoPassword(var.clone())),
+			(None, None) => Err(SyncError::NoPassword("password".to_string())),
+		}
+	}
+
+	/// Adds the login to the request
+	fn sign<B>(
+		&self,
+		request: RequestBuilder<B>,
+		method: &str,
+		body: &[u8],
+	) -> Result<RequestBuilder<B>, SyncError> {
+		let Some(username) = &self.username else {
+			return Ok(request);
+		};
+		let password = self.password()?;
+		Ok(match self.provider {
+			Provider::WebDav => {
+				let login = BASE64_STANDARD.encode(format!("{username}:{password}"));
+				request.header("Authorization", format!("Basic {login}"))
+			}
+			Provider::S3 => {
+				let signature = self.aws_signature(method, body, username, &password)?;
+				signature
+					.into_iter()
+					.fold(request, |request, (name, value)| {
+						request.header(name, value)
+					})
+			}
+		})
+	}
+
+	/// Returns the headers signing the request for S3, with AWS Signature Version 4
+	fn aws_signature(
+		&self,
+		method: &str,
+		body: &[u8],
+		access_key: &str,
+		secret_key: &str,
+	) -> Result<Vec<(&'static str, String)>, SyncError> {
+		let uri: Uri = self
+			.url
+			.parse()
+			.map_err(|_| SyncError::Url(self.url.clone()))?;
+		let host = uri
+			.authority()
+			.ok_or_else(|| SyncError::Url(self.url.clone()))?
+			.to_string();
+		let now = Utc::now();
+		let date = now.format("%Y%m%d").to_string();
+		let time = now.format("%Y%m%dT%H%M%SZ").to_string();
+		let payload = hex(&Sha256::digest(body));
+		let scope = format!("{date}/{}/s3/aws4_request", self.region);
+		let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+		let request = format!(
+			"{method}\n{}\n{}\nhost:{host}\nx-amz-content-sha256:{payload}\nx-amz-date:{time}\n\n{signed_headers}\n{payload}",
+			uri.path(),
+			uri.query().unwrap_or_default(),
+		);
+		let to_sign = format!(
+			"AWS4-HMAC-SHA256\n{time}\n{scope}\n{}",
+			hex(&Sha256::digest(request.as_bytes()))
+		);
+		let key = [date.as_str(), &self.region, "s3", "aws4_request"]
+			.iter()
+			.fold(format!("AWS4{secret_key}").into_bytes(), |key, part| {
+				hmac(&key, part.as_bytes())
+			});
+		let signature = hex(&hmac(&key, to_sign.as_bytes()));
+		Ok(vec![
+			("x-amz-date", time),
+			("x-amz-content-sha256", payload),
+			(
+				"Authorization",
+				format!(
+					"AWS4-HMAC-SHA256 Credential={access_key}/{scope}, SignedHeaders={signed_headers}, Signature={signature}"
+				),
+			),
+		])
+	}
+}
+
+fn agent() -> Agent {
+	Agent::config_builder()
+		.http_status_as_error(false)
+		.timeout_global(Some(TIMEOUT))
+		.build()
+		.into()
+}
+
+/// Returns the `ETag` in the headers of a response, if there is one
+fn etag(headers: &ureq::http::HeaderMap) -> Option<String> {
+	Some(headers.get("ETag")?.to_str().ok()?.to_string())
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+	let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+	mac.update(data);
+	mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().fold(String::new(), |mut hex, byte| {
+		let _ = write!(hex, "{byte:02x}");
+		hex
+	})
+}
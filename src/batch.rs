@@ -0,0 +1,204 @@
+//! Running key sequences against a file without the terminal, so anything that can be done in the
+//! program can be scripted (e.g. `--execute "gU<CR><C-s>"` to apply the import rules and save).
+//! The keys are typed into the same controller the program uses, and the screen is drawn after each
+//! one to a buffer that isn't shown, so they do exactly what they would if typed. The tests type
+//! keys the same way, through a [`Harness`], and check what was drawn
+
+use std::{fs, path::Path, sync::mpsc};
+
+use anyhow::{Context, Result, bail};
+use ratatui::{Terminal, backend::TestBackend, crossterm::event::Event};
+
+use crate::{
+	config::Config,
+	controller::{
+		Controller, ControllerState,
+		keys::{Key, KeySequence},
+		popup::Popup,
+	},
+	events::AppEvent,
+	model::Model,
+	view::View,
+};
+
+/// The size of the screen the keys are typed into, which decides how far moves like half a page go
+const SCREEN_SIZE: (u16, u16) = (120, 40);
+
+/// A key sequence to type, and the line it was given on, to say which one went wrong
+pub struct Step {
+	pub line: usize,
+	pub keys: KeySequence,
+}
+
+/// Reads the key sequences given with `--execute`, or in the script file, one per line. Blank lines
+/// and lines starting with `#` are skipped
+///
+/// # Errors
+/// If the script can't be read, or a line isn't a key sequence
+pub fn read_script(execute: &[String], script: Option<&Path>) -> Result<Vec<Step>> {
+	let lines = match script {
+		Some(path) => fs::read_to_string(path)
+			.with_context(|| format!("Couldn't read {}", path.display()))?
+			.lines()
+			.map(str::to_string)
+			.collect(),
+		None => execute.to_vec(),
+	};
+	let mut steps = vec![];
+	for (i, line) in lines.iter().enumerate() {
+		let line = line.trim_end();
+		if line.trim().is_empty() || line.trim_start().starts_with('#') {
+			continue;
+		}
+		let keys = line.parse().with_context(|| format!("Line {}", i + 1))?;
+		steps.push(Step { line: i + 1, keys });
+	}
+	Ok(steps)
+}
+
+/// The program running on a model without the terminal, drawing to a buffer that isn't shown
+pub struct Harness {
+	pub model: Model,
+	pub view: View,
+	pub controller: Controller,
+	terminal: Terminal<TestBackend>,
+	/// The results of the jobs the keys start on the worker
+	events: mpsc::Receiver<AppEvent>,
+}
+
+impl Harness {
+	/// Starts the program on the model, set up as the config says
+	///
+	/// # Errors
+	/// If the screen can't be drawn
+	pub fn new(model: Model, config: &Config, safe_mode: bool) -> Result<Self> {
+		let view = crate::new_view(config, safe_mode);
+		let (sender, events) = mpsc::channel();
+		let mut controller = crate::new_controller(config, &model.scripts, sender);
+		// A key sequence that could still go on (like `g` before `gg`) is run once it is typed
+		controller.state.key_timeout = Some(std::time::Duration::ZERO);
+		let mut harness = Self {
+			model,
+			view,
+			controller,
+			terminal: Terminal::new(TestBackend::new(SCREEN_SIZE.0, SCREEN_SIZE.1))?,
+			events,
+		};
+		harness.draw()?;
+		Ok(harness)
+	}
+
+	/// Types the key, waiting for anything it starts on the worker (like saving) to finish, and
+	/// draws the screen
+	///
+	/// # Errors
+	/// If the screen can't be drawn
+	pub fn press(&mut self, key: Key) -> Result<()> {
+		self.controller
+			.handle_events(&Event::Key(key.to_event()), &mut self.model, &mut self.view);
+		while self.controller.state.is_busy() {
+			let Ok(AppEvent::Finished { task, finish }) = self.events.recv() else {
+				continue;
+			};
+			self.controller
+				.state
+				.finish_job(task, finish, &mut self.view, &mut self.model);
+		}
+		self.draw()
+	}
+
+	/// Types every key of the sequence, then runs it if it could still go on (like `g` before
+	/// `gg`), as it would be once the wait for the next key times out
+	///
+	/// # Errors
+	/// If the keys aren't a key sequence, or the screen can't be drawn
+	pub fn type_keys(&mut self, keys: &str) -> Result<()> {
+		let keys: KeySequence = keys.parse()?;
+		for key in keys.keys() {
+			self.press(*key)?;
+		}
+		self.finish_keys()
+	}
+
+	/// Runs the key sequence being typed, if any, as if the wait for its next key timed out
+	///
+	/// # Errors
+	/// If the screen can't be drawn
+	pub fn finish_keys(&mut self) -> Result<()> {
+		self.controller
+			.expire_pending_keys(&mut self.model, &mut self.view);
+		self.draw()
+	}
+
+	/// Returns the text drawn on the screen, a line for each row
+	pub fn screen(&self) -> String {
+		let buffer = self.terminal.backend().buffer();
+		let mut screen = String::new();
+		for y in 0..buffer.area.height {
+			for x in 0..buffer.area.width {
+				screen.push_str(buffer[(x, y)].symbol());
+			}
+			screen.push('\n');
+		}
+		screen
+	}
+
+	fn draw(&mut self) -> Result<()> {
+		self.terminal.draw(|frame| {
+			self.view.render(frame, &self.model, &self.controller.state);
+		})?;
+		Ok(())
+	}
+}
+
+/// Types each step's keys into the model, printing the messages the program shows as they come,
+/// and waiting for anything the keys start on the worker (like saving) to finish before the next
+/// key. Stops early if the keys quit the program
+///
+/// # Errors
+/// If any of the keys went wrong, after all of them have been typed
+pub fn run(model: Model, config: &Config, safe_mode: bool, steps: &[Step]) -> Result<()> {
+	let mut harness = Harness::new(model, config, safe_mode)?;
+	let mut failures = 0;
+	'steps: for step in steps {
+		for key in step.keys.keys() {
+			harness.press(*key)?;
+			failures += report(&mut harness.controller.state, step.line);
+			if harness.controller.state.exit {
+				break 'steps;
+			}
+		}
+		harness.finish_keys()?;
+		failures += report(&mut harness.controller.state, step.line);
+	}
+	let model = harness.model;
+	if model.has_unsaved_changes() && model.filename.is_some() {
+		eprintln!("The changes weren't saved - end the keys with <C-s> to save them");
+	}
+	if failures > 0 {
+		bail!("There were {failures} errors");
+	}
+	Ok(())
+}
+
+/// Prints the messages shown since it was last called, and the error of the popup shown if it has
+/// one (closing it, so the keys after it aren't typed into it). Returns how many errors there were
+fn report(cs: &mut ControllerState, line: usize) -> usize {
+	let mut errors = 0;
+	for notification in cs.notifications.drain(..) {
+		if notification.error {
+			errors += 1;
+			eprintln!("{line}: {}", notification.message);
+		} else {
+			println!("{line}: {}", notification.message);
+		}
+	}
+	if let Some(Popup::Info(info)) = &cs.popup
+		&& let Some(error) = info.error()
+	{
+		errors += 1;
+		eprintln!("{line}: {}: {error}", info.title());
+		cs.popup = None;
+	}
+	errors
+}
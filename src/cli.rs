@@ -0,0 +1,339 @@
+//! Commands that are run from the command line without opening the program, so a file can be used
+//! from scripts. They share the model with the program, so a file is read, changed and saved the
+//! same way (including its lock, backups and audit log)
+
+use std::{fs, io, path::Path};
+
+use anyhow::{Context, Result, bail};
+use budgeting_core::clock;
+use chrono::{Months, NaiveDate};
+use clap::{CommandFactory, Subcommand};
+use clap_complete::Shell;
+
+use crate::{
+	Args,
+	config::Config,
+	model::{
+		Access, ImportPreset, ImportSettings, Message, Model, Report, Scripts, Totals, Transaction,
+	},
+	view::{self, format_currency},
+};
+
+/// Things that can be done with a file without opening the program
+#[derive(Subcommand, Debug)]
+pub enum Command {
+	/// Export a file to another format
+	#[command(subcommand)]
+	Export(ExportFormat),
+	/// Print a report of the income and expenses of a sheet, by category and by payee
+	Report {
+		/// File to report on
+		filename: String,
+		/// Sheet to report on. Defaults to the first
+		#[arg(short, long)]
+		sheet: Option<String>,
+		/// Month to report on, like 2024-06
+		#[arg(short, long, conflicts_with = "range")]
+		month: Option<String>,
+		/// Range to report on: ytd, a year, or start..end. Defaults to ytd
+		#[arg(short, long)]
+		range: Option<String>,
+	},
+	/// Add a transaction, given as its label and amount (like "coffee -4.50"), optionally starting
+	/// with its date. It is added as pending, like transactions entered in the program
+	Add {
+		/// File to add to
+		filename: String,
+		/// The transaction
+		transaction: String,
+		/// Sheet to add to. Defaults to the first
+		#[arg(short, long)]
+		sheet: Option<String>,
+		/// Category of the transaction
+		#[arg(short, long)]
+		category: Option<String>,
+	},
+	/// Import the transactions in a file exported by a bank, skipping those that look like ones
+	/// already in the sheet. The import rules are applied to them
+	Import {
+		/// File to import into
+		filename: String,
+		/// File to import. Its name can contain * wildcards, in which case the newest matching file
+		/// is imported
+		bank_file: String,
+		/// Sheet to import into. Defaults to the one last imported into, or the first
+		#[arg(short, long)]
+		sheet: Option<String>,
+		/// Name of the import preset to read the file with. Defaults to the one last imported with,
+		/// or the first one for the kind of file
+		#[arg(short, long)]
+		preset: Option<String>,
+	},
+	/// Print the completions of the commands and options for a shell, to be sourced by it (e.g.
+	/// `budgeting-app completions bash > ~/.local/share/bash-completion/completions/budgeting-app`)
+	Completions {
+		/// Shell to complete in
+		shell: Shell,
+	},
+	/// Print the man page, with every command and option (e.g. `budgeting-app man | man -l -`)
+	Man,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportFormat {
+	/// A standalone, read-only HTML page with balances, charts and the transactions, which can be
+	/// opened in any browser
+	Html {
+		/// File to export
+		filename: String,
+		/// Where to write the page. Defaults to the file with an .html extension
+		#[arg(short, long)]
+		output: Option<std::path::PathBuf>,
+	},
+	/// The transactions of a sheet, as CSV
+	Csv {
+		/// File to export
+		filename: String,
+		/// Sheet to export. Defaults to the first
+		#[arg(short, long)]
+		sheet: Option<String>,
+		/// Where to write the CSV. Defaults to printing it
+		#[arg(short, long)]
+		output: Option<std::path::PathBuf>,
+	},
+}
+
+/// Runs a command given on the command line, instead of the program
+///
+/// # Errors
+/// If the file can't be opened or saved, or the command fails
+pub fn run(command: Command, config: &Config, safe_mode: bool) -> Result<()> {
+	let today = clock::today();
+	match command {
+		Command::Export(ExportFormat::Html { filename, output }) => {
+			let output = output.unwrap_or_else(|| Path::new(&filename).with_extension("html"));
+			let model = open(filename, Access::ReadOnly)?;
+			view::export_html(&model, today, &output)?;
+			println!("Exported to {}", output.display());
+		}
+		Command::Export(ExportFormat::Csv {
+			filename,
+			sheet,
+			output,
+		}) => {
+			let model = open(filename, Access::ReadOnly)?;
+			let sheet = find_sheet(&model, sheet.as_deref())?;
+			let csv = view::transactions_csv(model.get_sheet(sheet).expect("Sheet was just found"));
+			match output {
+				Some(output) => fs::write(&output, csv)
+					.with_context(|| format!("Couldn't write {}", output.display()))?,
+				None => print!("{csv}"),
+			}
+		}
+		Command::Report {
+			filename,
+			sheet,
+			month,
+			range,
+		} => {
+			let model = open(filename, Access::ReadOnly)?;
+			let sheet = model
+				.get_sheet(find_sheet(&model, sheet.as_deref())?)
+				.expect("Sheet was just found");
+			let (start, end) = match month {
+				Some(month) => month_range(&month)?,
+				None => Report::parse_range(range.as_deref().unwrap_or_default(), today)?,
+			};
+			print!("{}", report_text(&sheet.name, &sheet.report(start, end)));
+		}
+		Command::Add {
+			filename,
+			transaction,
+			sheet,
+			category,
+		} => {
+			let mut model = open(filename, Access::Exclusive)?;
+			let sheet = find_sheet(&model, sheet.as_deref())?;
+			let mut transaction = parse_transaction(&transaction, today)?;
+			transaction.category = category.unwrap_or_default();
+			let summary = format!(
+				"Added {} {} on {} to {}",
+				transaction.label,
+				format_currency(transaction.amount),
+				transaction.date,
+				model.sheet_titles()[sheet]
+			);
+			let row = model.get_sheet(sheet).map_or(0, |s| s.transactions().len());
+			model.update(Message::InsertRow {
+				sheet,
+				row,
+				transaction,
+			})?;
+			model.save(config.backups)?;
+			println!("{summary}");
+		}
+		Command::Import {
+			filename,
+			bank_file,
+			sheet,
+			preset,
+		} => {
+			let mut model = open(filename, Access::Exclusive)?;
+			if !safe_mode {
+				crate::load_rules_and_scripts(&mut model, config)?;
+			}
+			let settings = import_settings(&model, bank_file, sheet, preset)?;
+			let sheet = settings.sheet.clone();
+			let transactions = settings.read(&model.scripts, |_, _| true)?;
+			let counts = model.finish_import(model.pending_import(settings, transactions)?)?;
+			model.save(config.backups)?;
+			let mut parts = vec![format!(
+				"Imported {} transactions into {sheet}",
+				counts.added
+			)];
+			if counts.skipped > 0 {
+				parts.push(format!("skipped {} duplicates", counts.skipped));
+			}
+			println!("{}", parts.join(", "));
+		}
+		Command::Completions { shell } => {
+			clap_complete::generate(
+				shell,
+				&mut Args::command(),
+				env!("CARGO_PKG_NAME"),
+				&mut io::stdout(),
+			);
+		}
+		Command::Man => clap_mangen::Man::new(Args::command()).render(&mut io::stdout())?,
+	}
+	Ok(())
+}
+
+/// Opens the file with the given name. Unlike in the program, a file that doesn't exist isn't
+/// created
+fn open(filename: String, access: Access) -> Result<Model> {
+	if !Path::new(&filename).exists() {
+		bail!("Couldn't open file: {filename} doesn't exist");
+	}
+	Ok(Model::open(filename, None, access)?)
+}
+
+/// Returns the index of the sheet with the given name, or of the first sheet if there is none
+fn find_sheet(model: &Model, name: Option<&str>) -> Result<usize> {
+	let Some(name) = name else {
+		return Ok(0);
+	};
+	model
+		.sheet_titles()
+		.iter()
+		.position(|title| title == name)
+		.with_context(|| format!("No sheet named \"{name}\""))
+}
+
+/// Returns the first and last days of a month, given like 2024-06
+fn month_range(month: &str) -> Result<(NaiveDate, NaiveDate)> {
+	let start = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+		.with_context(|| format!("Invalid month \"{month}\", which should be like 2024-06"))?;
+	let end = start
+		.checked_add_months(Months::new(1))
+		.and_then(|next| next.pred_opt())
+		.context("The month is out of range")?;
+	Ok((start, end))
+}
+
+/// Parses a transaction written as its label and amount, like `coffee -4.50`, optionally starting
+/// with its date, like `2024-06-01 coffee -4.50`. It is dated today if no date is given
+fn parse_transaction(text: &str, today: NaiveDate) -> Result<Transaction> {
+	let mut words: Vec<&str> = text.split_whitespace().collect();
+	let amount = words
+		.pop()
+		.and_then(|amount| Transaction::parse_amount(amount).ok())
+		.with_context(|| format!("\"{text}\" doesn't end with an amount, like \"coffee -4.50\""))?;
+	let date = match words.first().map(|date| Transaction::parse_date(date)) {
+		Some(Ok(date)) => {
+			words.remove(0);
+			date
+		}
+		_ => today,
+	};
+	Ok(Transaction {
+		label: words.join(" "),
+		date,
+		amount,
+		pending: true,
+		..Transaction::default()
+	})
+}
+
+/// Returns the settings to import the file with, filling in whatever wasn't given from the last
+/// import into the file
+fn import_settings(
+	model: &Model,
+	pattern: String,
+	sheet: Option<String>,
+	preset: Option<String>,
+) -> Result<ImportSettings> {
+	let last = model.last_import.as_ref();
+	let sheet = sheet
+		.or_else(|| last.map(|last| last.sheet.clone()))
+		.unwrap_or_else(|| model.sheet_titles()[0].clone());
+	let preset = preset.or_else(|| last.map(|last| last.preset.clone()));
+	let preset = match preset {
+		Some(preset) => preset,
+		None => default_preset(&pattern, &model.scripts)?,
+	};
+	Ok(ImportSettings {
+		pattern,
+		preset,
+		sheet,
+	})
+}
+
+/// Returns the name of the first preset that reads files like the one with the given name
+fn default_preset(pattern: &str, scripts: &Scripts) -> Result<String> {
+	let extension = Path::new(pattern)
+		.extension()
+		.and_then(|e| e.to_str())
+		.unwrap_or_default();
+	ImportPreset::for_extension(extension, scripts)
+		.map(|preset| preset.name)
+		.with_context(|| format!("No import preset reads .{extension} files"))
+}
+
+/// Returns the report as text, with a section for the total, each category and each payee
+fn report_text(sheet: &str, report: &Report) -> String {
+	let line = |name: &str, totals: &Totals| {
+		format!(
+			"  {name:<30} {:>14} {:>14} {:>14}\n",
+			format_currency(totals.income),
+			format_currency(totals.expenses),
+			format_currency(totals.net())
+		)
+	};
+	let header = format!(
+		"  {:<30} {:>14} {:>14} {:>14}\n",
+		"", "Income", "Expenses", "Net"
+	);
+	let mut text = format!("{sheet}, {} to {}\n\n", report.start, report.end);
+	text.push_str(&header);
+	text.push_str(&line(
+		&format!("Total ({} transactions)", report.total.count),
+		&report.total,
+	));
+	text.push_str("\nBy category\n");
+	for (category, totals) in &report.categories {
+		let name = if category.is_empty() {
+			"(uncategorised)"
+		} else {
+			category
+		};
+		text.push_str(&line(name, totals));
+	}
+	if !report.payees.is_empty() {
+		text.push_str("\nTop payees\n");
+		for (payee, totals) in &report.payees {
+			text.push_str(&line(payee, totals));
+		}
+	}
+	text
+}
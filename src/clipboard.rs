@@ -0,0 +1,42 @@
+//! Reading the system clipboard, by running the program each platform provides for it, so no
+//! clipboard library (or connection to the display server) is needed
+
+use std::process::Command;
+
+use thiserror::Error;
+
+/// The programs that print the text in the clipboard, with their arguments, tried in order until
+/// one of them runs
+const PASTE_COMMANDS: [(&str, &[&str]); 5] = [
+	("pbpaste", &[]),
+	("wl-paste", &["--no-newline"]),
+	("xclip", &["-selection", "clipboard", "-out"]),
+	("xsel", &["--clipboard", "--output"]),
+	(
+		"powershell.exe",
+		&["-NoProfile", "-Command", "Get-Clipboard"],
+	),
+];
+
+/// Returns the text in the system clipboard
+///
+/// # Errors
+/// If none of the clipboard programs could be run, or the clipboard doesn't hold text
+pub fn paste() -> Result<String, ClipboardError> {
+	for (program, args) in PASTE_COMMANDS {
+		if let Ok(output) = Command::new(program).args(args).output()
+			&& output.status.success()
+		{
+			return String::from_utf8(output.stdout).map_err(|_| ClipboardError::NotText);
+		}
+	}
+	Err(ClipboardError::Unavailable)
+}
+
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+	#[error("Couldn't read the clipboard (it needs pbpaste, wl-paste, xclip or xsel)")]
+	Unavailable,
+	#[error("The clipboard doesn't hold text")]
+	NotText,
+}
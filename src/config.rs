@@ -0,0 +1,198 @@
+//! The user's configuration, read from a TOML file at startup
+
+use std::{env, fs, io::ErrorKind, path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+	controller::keys::{Key, KeySequence},
+	model::ColumnDefaults,
+	rates::RateSource,
+	sync::Remote,
+	view::{Highlight, Theme, ThemeConfig},
+};
+
+/// The settings of the program. Every field has a default, so the config file only needs to
+/// contain the ones the user wants to change
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	/// How often to redraw the screen while what is shown changes on its own (the progress of a
+	/// task, or messages waiting to expire), in milliseconds. Otherwise the screen is only redrawn
+	/// when something happens
+	pub poll_interval_ms: u64,
+	/// Whether to stop redrawing entirely while the terminal doesn't have focus, until it gets it
+	/// back or a key is pressed
+	pub suspend_when_unfocused: bool,
+	/// How long to wait for the next key of a command, in milliseconds, before giving up on it. If
+	/// the keys typed so far are a command of their own (as well as the start of longer ones), it
+	/// is run, otherwise they are cleared. 0 waits forever
+	pub key_timeout_ms: u64,
+	/// The keys that start the user's own commands, which can't start any builtin command, so the
+	/// two can never clash
+	pub leader: KeySequence,
+	/// The user's own commands, each typed after the leader
+	#[serde(rename = "map")]
+	pub mappings: Vec<Mapping>,
+	/// Whether to capture the mouse on startup, for scrolling and clicking on rows. It can be
+	/// released with `gm` to select text natively
+	pub mouse: bool,
+	/// Whether deleting a single row with `dd` is confirmed first. Deleting more than one row
+	/// always is
+	pub confirm_delete: bool,
+	/// How many backups of the file to keep, each made from what a save replaces. Restored with
+	/// `gB`
+	pub backups: usize,
+	/// The file the import rules are kept in. Defaults to `rules.toml` next to the config file
+	pub rules_file: Option<PathBuf>,
+	/// The file the user's scripts are kept in. Defaults to `scripts.rhai` next to the config file
+	pub scripts_file: Option<PathBuf>,
+	/// The columns shown in every sheet, and their widths, unless the sheet sets its own
+	pub columns: ColumnDefaults,
+	/// The colours the program is drawn in
+	pub theme: ThemeConfig,
+	/// The rules that style the rows of sheets, in the order they are applied
+	#[serde(rename = "highlight")]
+	pub highlights: Vec<Highlight>,
+	/// The server a copy of the file is kept on, synced with `gP`. Syncing is off without it
+	pub sync: Option<Remote>,
+	/// Where exchange rates are fetched from, for sheets in other currencies. Without it, rates
+	/// are only set by hand with `gX`
+	pub rates: Option<RateSource>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			poll_interval_ms: 100,
+			suspend_when_unfocused: true,
+			key_timeout_ms: 1000,
+			leader: KeySequence::from(vec![Key::char(' ')]),
+			mappings: vec![],
+			mouse: true,
+			confirm_delete: false,
+			backups: 3,
+			rules_file: None,
+			scripts_file: None,
+			columns: ColumnDefaults::default(),
+			theme: ThemeConfig::default(),
+			highlights: vec![],
+			sync: None,
+			rates: None,
+		}
+	}
+}
+
+impl Config {
+	/// Returns the default location of the config file, which is `budgeting-app/config.toml` in
+	/// `$XDG_CONFIG_HOME` (or `~/.config` if that isn't set)
+	pub fn default_path() -> Option<PathBuf> {
+		env::var_os("XDG_CONFIG_HOME")
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+			.map(|dir| dir.join("budgeting-app").join("config.toml"))
+	}
+
+	/// Loads the config from the given file, or the default location if not given one. If there
+	/// is no file at the default location, the default config is used
+	///
+	/// # Errors
+	/// If the file can't be read, or isn't a valid config
+	pub fn load(path: Option<PathBuf>) -> Result<Self, ConfigError> {
+		let explicit = path.is_some();
+		let Some(path) = path.or_else(Self::default_path) else {
+			return Ok(Self::default());
+		};
+		match fs::read_to_string(&path) {
+			Ok(text) => {
+				let mut config: Self = toml::from_str(&text).map_err(|e| ConfigError::Parse {
+					path: path.clone(),
+					message: e.to_string(),
+				})?;
+				config
+					.columns
+					.validate()
+					.and_then(|()| config.theme.theme().map(|_| ()))
+					.and_then(|()| config.validate_mappings())
+					.and_then(|()| {
+						config
+							.highlights
+							.iter_mut()
+							.try_for_each(Highlight::compile)
+					})
+					.map_err(|message| ConfigError::Parse { path, message })?;
+				Ok(config)
+			}
+			Err(e) if e.kind() == ErrorKind::NotFound && !explicit => Ok(Self::default()),
+			Err(e) => Err(ConfigError::Io {
+				path,
+				message: e.to_string(),
+			}),
+		}
+	}
+
+	/// Checks that no two of the user's commands have the same keys
+	fn validate_mappings(&self) -> Result<(), String> {
+		for (i, mapping) in self.mappings.iter().enumerate() {
+			if self.mappings[..i].iter().any(|m| m.keys == mapping.keys) {
+				return Err(format!(
+					"More than one command is mapped to {}",
+					mapping.keys
+				));
+			}
+		}
+		Ok(())
+	}
+
+	/// The file the import rules are kept in. See [`Config::rules_file`]
+	pub fn rules_path(&self) -> Option<PathBuf> {
+		self.rules_file.clone().or_else(|| {
+			Self::default_path().and_then(|path| path.parent().map(|dir| dir.join("rules.toml")))
+		})
+	}
+
+	/// The file the user's scripts are kept in. See [`Config::scripts_file`]
+	pub fn scripts_path(&self) -> Option<PathBuf> {
+		self.scripts_file.clone().or_else(|| {
+			Self::default_path().and_then(|path| path.parent().map(|dir| dir.join("scripts.rhai")))
+		})
+	}
+
+	/// The colours the program is drawn in. See [`ThemeConfig`]
+	pub fn theme(&self) -> Theme {
+		// Invalid themes are caught when the config is loaded
+		self.theme.theme().unwrap_or_default()
+	}
+
+	/// The time to wait for an event before redrawing. See [`Config::poll_interval_ms`]
+	pub fn poll_interval(&self) -> Duration {
+		Duration::from_millis(self.poll_interval_ms)
+	}
+
+	/// How long to wait for the next key of a command, if at all. See [`Config::key_timeout_ms`]
+	pub fn key_timeout(&self) -> Option<Duration> {
+		(self.key_timeout_ms > 0).then(|| Duration::from_millis(self.key_timeout_ms))
+	}
+}
+
+/// A command of the user's own, typed after [`Config::leader`], which types other keys as if the
+/// user had typed them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+	/// The keys of the command, after the leader
+	pub keys: KeySequence,
+	/// The keys the command types
+	pub run: KeySequence,
+	/// A few words naming the command, for the hints. Defaults to the keys it types
+	pub name: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+	#[error("Couldn't read {}: {message}", path.display())]
+	Io { path: PathBuf, message: String },
+	#[error("Invalid config in {}: {message}", path.display())]
+	Parse { path: PathBuf, message: String },
+}
@@ -0,0 +1,129 @@
+//! The actions run by the builtin commands that don't open a popup. See [`super::popup::defaults`]
+//! for the ones that do
+
+use crate::{
+	controller::ControllerState,
+	model::{Message, Model, Outcome},
+	view::View,
+};
+
+/// Moves the selected row down by the count
+pub fn move_row_down(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	for _ in 0..count.unwrap_or(1) {
+		let sheet = view.get_selected_sheet(model);
+		if let Some(row) = view.get_selected_row(sheet) {
+			let message = Message::MoveRowDown {
+				sheet: sheet_index,
+				row,
+			};
+			if cs.send(model, message).is_none() {
+				break;
+			}
+			view.next_row(model);
+		}
+	}
+}
+
+/// Moves the selected row up by the count
+pub fn move_row_up(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	for _ in 0..count.unwrap_or(1) {
+		let sheet = view.get_selected_sheet(model);
+		if let Some(row) = view.get_selected_row(sheet) {
+			let message = Message::MoveRowUp {
+				sheet: sheet_index,
+				row,
+			};
+			if cs.send(model, message).is_none() {
+				break;
+			}
+			view.previous_row(model);
+		}
+	}
+}
+
+/// Yanks the given rows into the register
+pub fn yank(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	rows: &[usize],
+) -> anyhow::Result<()> {
+	cs.register = model.copy_rows(view.selected_sheet, rows)?;
+	Ok(())
+}
+
+/// Puts the register below the selected row, `count` times, or by their dates if the sheet is
+/// ordered by date, selecting the first row put
+pub fn put_below(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	if let Some(row) = view.get_selected_row(sheet)
+		&& !cs.register.is_empty()
+	{
+		let message = Message::InsertRows {
+			sheet: sheet_index,
+			row: row + 1,
+			transactions: cs.register_repeated(count.unwrap_or(1)),
+		};
+		if let Some(row) = cs.send(model, message).as_ref().and_then(Outcome::row) {
+			view.select_row(row, model);
+		}
+	}
+}
+
+/// Puts the register above the selected row, `count` times, or by their dates if the sheet is
+/// ordered by date, selecting the first row put
+pub fn put_above(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	if let Some(row) = view.get_selected_row(sheet)
+		&& !cs.register.is_empty()
+	{
+		let message = Message::InsertRows {
+			sheet: sheet_index,
+			row,
+			transactions: cs.register_repeated(count.unwrap_or(1)),
+		};
+		if let Some(row) = cs.send(model, message).as_ref().and_then(Outcome::row) {
+			view.select_row(row, model);
+		}
+	}
+}
+
+/// Yanks the value of the selected cell into the cell register
+pub fn yank_cell(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet = view.get_selected_sheet(model);
+	if let Some((row, col)) = view.get_selected_cell(sheet) {
+		cs.cell_register = Some(crate::view::get_string_of_transaction_member(
+			&sheet.transactions()[row],
+			col,
+		));
+	}
+}
@@ -1,80 +1,392 @@
-use std::{collections::HashMap, fmt::Debug, str::Chars};
+use std::{collections::HashMap, fmt::Debug};
 
-use crate::{controller::ControllerState, model::Model, view::View};
+use anyhow::Result;
 
-pub(super) trait ActionFn: Fn(&mut View, &mut Model, &mut ControllerState) {}
-impl<T> ActionFn for T where T: Fn(&mut View, &mut Model, &mut ControllerState) {}
-pub(super) type Action = dyn ActionFn;
+use crate::{
+	controller::{
+		ControllerState,
+		keys::{Key, KeySequence},
+	},
+	model::Model,
+	view::View,
+};
+
+/// What an action or operator returns - nothing if it can't fail, or a [`Result`] if it can, in
+/// which case the controller shows the error rather than the program crashing
+pub trait ActionResult {
+	fn into_result(self) -> Result<()>;
+}
+
+impl ActionResult for () {
+	fn into_result(self) -> Result<()> {
+		Ok(())
+	}
+}
+
+impl ActionResult for Result<()> {
+	fn into_result(self) -> Result<()> {
+		self
+	}
+}
+
+/// An action run by a command. The last argument is the count typed before the command, if any
+pub trait ActionFn<R: ActionResult>:
+	Fn(&mut View, &mut Model, &mut ControllerState, Option<usize>) -> R
+{
+}
+impl<T, R: ActionResult> ActionFn<R> for T where
+	T: Fn(&mut View, &mut Model, &mut ControllerState, Option<usize>) -> R
+{
+}
+pub(super) type Action = dyn ActionFn<Result<()>>;
 impl Debug for Action {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		write!(f, "<action>")
 	}
 }
 
+/// An operator, which is applied to the rows (as indices of the transactions in the sheet) covered
+/// by the motion typed after it
+pub trait OperatorFn<R: ActionResult>:
+	Fn(&mut View, &mut Model, &mut ControllerState, &[usize]) -> R
+{
+}
+impl<T, R: ActionResult> OperatorFn<R> for T where
+	T: Fn(&mut View, &mut Model, &mut ControllerState, &[usize]) -> R
+{
+}
+pub(super) type Operator = dyn OperatorFn<Result<()>>;
+
+/// Boxes an action, whatever it returns, as one returning a [`Result`]
+pub(super) fn boxed_action<F, R>(action: F) -> Box<Action>
+where
+	F: ActionFn<R> + 'static,
+	R: ActionResult,
+{
+	Box::new(
+		move |view: &mut View, model: &mut Model, cs: &mut ControllerState, count| {
+			action(view, model, cs, count).into_result()
+		},
+	)
+}
+
+/// Boxes an operator, whatever it returns, as one returning a [`Result`]
+pub(super) fn boxed_operator<F, R>(operator: F) -> Box<Operator>
+where
+	F: OperatorFn<R> + 'static,
+	R: ActionResult,
+{
+	Box::new(
+		move |view: &mut View, model: &mut Model, cs: &mut ControllerState, rows: &[usize]| {
+			operator(view, model, cs, rows).into_result()
+		},
+	)
+}
+impl Debug for Operator {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "<operator>")
+	}
+}
+
+/// What a command does once its key sequence is complete
+#[derive(Debug)]
+pub enum Command {
+	/// Runs the action
+	Action(Box<Action>),
+	/// Runs the action, which changes the model and so can be repeated with `.`
+	Repeatable(Box<Action>),
+	/// Runs the action, which moves the cursor and so can follow an operator
+	Motion(Box<Action>),
+	/// Waits for a motion, then applies the operator to the rows it covers. If it changes the
+	/// model, it can be repeated with `.`
+	Operator {
+		operator: Box<Operator>,
+		repeatable: bool,
+	},
+	/// Types the keys, as if the user had typed them instead. This is how the user's own commands
+	/// from the config file run
+	Keys(KeySequence),
+}
+
+impl Command {
+	/// The kinds of command, as they are described in the help popup
+	const KINDS: [&str; 5] = [
+		"Motions (can follow an operator)",
+		"Operators (take a motion)",
+		"Changes (repeatable with <.>)",
+		"Other commands",
+		"Your commands (from the config file)",
+	];
+
+	/// Returns the kind of the command. See [`Command::KINDS`]
+	fn kind(&self) -> &'static str {
+		match self {
+			Command::Motion(_) => Self::KINDS[0],
+			Command::Operator { .. } => Self::KINDS[1],
+			Command::Repeatable(_) => Self::KINDS[2],
+			Command::Action(_) => Self::KINDS[3],
+			Command::Keys(_) => Self::KINDS[4],
+		}
+	}
+}
+
+/// The keys of a command, and optionally its name and description. The keys are written in the
+/// notation of [`KeySequence`]. A command can be added with just its keys (`"gd"`), or with its
+/// keys, name and description (`("gd", "dashboard", "show the net worth of every sheet")`)
+#[derive(Debug, Clone, Copy)]
+pub struct Binding<'a> {
+	pub keys: &'a str,
+	/// A few words naming the command, shown in the hints while a key sequence is being typed
+	pub name: Option<&'a str>,
+	/// What the command does, shown in the help and the command palette
+	pub description: Option<&'a str>,
+}
+
+impl<'a> From<&'a str> for Binding<'a> {
+	fn from(keys: &'a str) -> Self {
+		Self {
+			keys,
+			name: None,
+			description: None,
+		}
+	}
+}
+
+impl<'a> From<(&'a str, &'a str, &'a str)> for Binding<'a> {
+	fn from((keys, name, description): (&'a str, &'a str, &'a str)) -> Self {
+		Self {
+			keys,
+			name: Some(name),
+			description: Some(description),
+		}
+	}
+}
+
 #[derive(Default, Debug)]
 pub struct CommandTrie {
-	children: HashMap<char, CommandTrie>,
-	action: Option<Box<Action>>,
+	children: HashMap<Key, CommandTrie>,
+	command: Option<Command>,
+	/// The name of the command, or of the group of commands starting with these keys. See
+	/// [`Binding::name`] and [`CommandTrie::name_group`]
+	name: Option<String>,
+	/// What the command does. See [`Binding::description`]
+	description: Option<String>,
 }
 
 impl CommandTrie {
-	/// Add a new function to the Trie
-	/// This is a fluent setter
+	/// Add a new function to the Trie, that changes the model and so can be repeated with `.`
+	/// This is a fluent setter. The program's own actions are added through the
+	/// [`ActionRegistry`](crate::controller::registry::ActionRegistry) instead
 	///
 	/// # Panics
-	/// If initial command is empty, or has whitespace,
+	/// If the keys of the command can't be parsed as a [`KeySequence`],
 	/// or if final node already has an action
-	///
-	/// # Examples
-	/// ```
-	/// let commands: CommandTrie = CommandTrie::default()
-	///     .add("j", |_, _, _| {})
-	///     .add("k", |_, _, _| {});
-	/// ```
-	pub fn add<F>(mut self, command: &str, action: F) -> Self
+	pub fn add_repeatable<'a, F, R>(mut self, binding: impl Into<Binding<'a>>, action: F) -> Self
 	where
-		F: ActionFn + 'static,
+		F: ActionFn<R> + 'static,
+		R: ActionResult,
 	{
-		assert!(!(command.is_empty()), "Command must have some char(s)");
-		assert!(
-			!command.as_bytes().iter().any(u8::is_ascii_whitespace),
-			"Command must not have whitespace"
-		);
+		self.add_command(binding.into(), Command::Repeatable(boxed_action(action)));
+		self
+	}
 
-		self.add_recursive(command.chars(), Box::new(action));
+	/// Add a command that types other keys, as if the user had typed them instead
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// See [`CommandTrie::add_repeatable`]
+	pub fn add_keys<'a>(mut self, binding: impl Into<Binding<'a>>, keys: KeySequence) -> Self {
+		self.add_command(binding.into(), Command::Keys(keys));
 		self
 	}
 
-	pub fn traverse<I>(&self, chars: I) -> Option<&Self>
-	where
-		I: IntoIterator<Item = char>,
-	{
+	/// Names the group of commands starting with the given keys, for the hints shown while a key
+	/// sequence is being typed
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// If the keys can't be parsed, or no command starts with them
+	pub fn name_group(mut self, keys: &str, name: &str) -> Self {
+		let sequence: KeySequence = keys
+			.parse()
+			.unwrap_or_else(|e| panic!("Invalid group {keys}: {e}"));
+		let mut node = &mut self;
+		for key in sequence.keys() {
+			node = node
+				.children
+				.get_mut(key)
+				.unwrap_or_else(|| panic!("No command starts with {keys}"));
+		}
+		node.name = Some(name.to_string());
+		self
+	}
+
+	/// Returns the keys that can be typed next, with the name of what each does, sorted by key.
+	/// Keys that start more than one command are named by their group, or by how many commands
+	/// they start if it has no name
+	pub fn hints(&self) -> Vec<(String, String)> {
+		let mut hints: Vec<(String, String)> = self
+			.children
+			.iter()
+			.map(|(key, child)| {
+				let name = match (&child.name, child.has_children()) {
+					(Some(name), false) => name.clone(),
+					(Some(name), true) => format!("+{name}"),
+					(None, _) => format!("+{} commands", child.bindings().len()),
+				};
+				(key.to_string(), name)
+			})
+			.collect();
+		hints.sort();
+		merge_ranges(hints)
+	}
+
+	pub fn traverse(&self, keys: &[Key]) -> Option<&Self> {
 		let mut node = self;
-		for c in chars {
-			node = node.children.get(&c)?;
+		for key in keys {
+			node = node.children.get(key)?;
 		}
 		Some(node)
 	}
 
-	pub fn next(&self, char: char) -> Option<&Self> {
-		self.children.get(&char)
+	pub fn next(&self, key: Key) -> Option<&Self> {
+		self.children.get(&key)
 	}
 
 	pub fn has_children(&self) -> bool {
 		!self.children.is_empty()
 	}
 
-	pub fn action(&self) -> Option<&Action> {
-		self.action.as_deref()
+	pub fn command(&self) -> Option<&Command> {
+		self.command.as_ref()
+	}
+
+	/// Returns the name of the command. See [`Binding::name`]
+	pub fn name(&self) -> Option<&str> {
+		self.name.as_deref()
 	}
 
-	fn add_recursive(&mut self, mut command: Chars<'_>, action: Box<Action>) {
-		if let Some(c) = command.next() {
-			let child = self.children.entry(c).or_default();
-			child.add_recursive(command, action);
+	/// Returns what the command does. See [`Binding::description`]
+	pub fn description(&self) -> Option<&str> {
+		self.description.as_deref()
+	}
+
+	/// Returns the key sequence of every command, sorted, with the node of the trie it ends at
+	pub fn bindings(&self) -> Vec<(String, &CommandTrie)> {
+		let mut bindings = vec![];
+		self.collect_bindings(&mut vec![], &mut bindings);
+		let mut bindings: Vec<(String, &CommandTrie)> = bindings
+			.into_iter()
+			.map(|(keys, node)| (KeySequence::from(keys).to_string(), node))
+			.collect();
+		bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+		bindings
+	}
+
+	/// Lists every command and its description, grouped by their kind, for the help popup. As this
+	/// is read from the trie itself, it always matches the keys that are bound
+	pub fn describe_bindings(&self) -> String {
+		let bindings = self.bindings();
+		Command::KINDS
+			.iter()
+			.filter_map(|kind| {
+				let kind_bindings = bindings
+					.iter()
+					.filter(|(_, node)| node.command().is_some_and(|c| c.kind() == *kind))
+					.map(|(keys, node)| (keys.clone(), &node.description))
+					.collect();
+				let lines: Vec<String> = merge_ranges(kind_bindings)
+					.into_iter()
+					.map(|(keys, description)| match description {
+						Some(description) => format!("        <{keys}> - {description}"),
+						None => format!("        <{keys}>"),
+					})
+					.collect();
+				(!lines.is_empty()).then(|| format!("    {kind}\n{}", lines.join("\n")))
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+
+	fn collect_bindings<'a>(
+		&'a self,
+		keys: &mut Vec<Key>,
+		bindings: &mut Vec<(Vec<Key>, &'a Self)>,
+	) {
+		if self.command.is_some() {
+			bindings.push((keys.clone(), self));
+		}
+		for (key, child) in &self.children {
+			keys.push(*key);
+			child.collect_bindings(keys, bindings);
+			keys.pop();
+		}
+	}
+
+	pub(super) fn add_command(&mut self, binding: Binding<'_>, action: Command) {
+		let sequence: KeySequence = binding
+			.keys
+			.parse()
+			.unwrap_or_else(|e| panic!("Invalid command {}: {e}", binding.keys));
+
+		self.add_recursive(sequence.keys().iter(), binding, action);
+	}
+
+	fn add_recursive<'k>(
+		&mut self,
+		mut command: impl Iterator<Item = &'k Key>,
+		binding: Binding<'_>,
+		action: Command,
+	) {
+		if let Some(key) = command.next() {
+			let child = self.children.entry(*key).or_default();
+			child.add_recursive(command, binding, action);
 		} else {
-			assert!(self.action.is_none(), "Duplicate command found");
-			self.action = Some(action);
+			assert!(self.command.is_none(), "Duplicate command found");
+			self.command = Some(action);
+			self.name = binding.name.map(str::to_string);
+			self.description = binding.description.map(str::to_string);
+		}
+	}
+}
+
+/// Merges each run of at least 3 entries (sorted by their keys) whose keys differ only in their
+/// last letter, which are consecutive, and that do the same thing, into one entry with the range
+/// of letters in brackets, like `m[a-z]` for setting each mark
+fn merge_ranges<T: PartialEq>(entries: Vec<(String, T)>) -> Vec<(String, T)> {
+	/// The smallest run of entries that is merged
+	const MIN_RUN: usize = 3;
+
+	let mut merged = vec![];
+	let mut run: Vec<(String, T)> = vec![];
+	let flush = |run: &mut Vec<(String, T)>, merged: &mut Vec<(String, T)>| {
+		if run.len() < MIN_RUN {
+			merged.append(run);
+			return;
+		}
+		let last = run.pop().map(|(keys, _)| keys).unwrap_or_default();
+		let (first, value) = run.swap_remove(0);
+		run.clear();
+		let (prefix, from) = first.split_at(first.len() - 1);
+		let to = &last[last.len() - 1..];
+		merged.push((format!("{prefix}[{from}-{to}]"), value));
+	};
+	for (keys, value) in entries {
+		let continues = run.last().is_some_and(|(last_keys, last_value)| {
+			let (Some(a), Some(b)) = (last_keys.chars().last(), keys.chars().last()) else {
+				return false;
+			};
+			a.is_ascii_alphanumeric()
+				&& b.is_ascii_alphanumeric()
+				&& b as u32 == a as u32 + 1
+				&& last_keys[..last_keys.len() - 1] == keys[..keys.len() - 1]
+				&& *last_value == value
+		});
+		if !continues {
+			flush(&mut run, &mut merged);
 		}
+		run.push((keys, value));
 	}
+	flush(&mut run, &mut merged);
+	merged
 }
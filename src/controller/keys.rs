@@ -0,0 +1,281 @@
+//! The keys commands are bound to, written in vim's notation: plain characters (and spaces) stand
+//! for themselves, and other keys go in angle brackets, like `<C-w>` (Ctrl+w), `<A-x>` (Alt+x),
+//! `<C-A-x>`, `<F1>` to `<F12>`, `<Del>`, `<Space>`, `<CR>` (Enter), `<Esc>`, `<Tab>`, `<BS>`
+//! (Backspace), and `<lt>` for `<` itself
+
+use std::{fmt::Display, str::FromStr};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// A key that can be part of a command, along with the modifiers held with it. Shift isn't
+/// recorded, as it is already part of the character typed (e.g. `G`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key {
+	pub name: KeyName,
+	pub ctrl: bool,
+	pub alt: bool,
+}
+
+/// The key itself, without its modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyName {
+	Char(char),
+	/// A function key, from 1 to 12
+	F(u8),
+	Delete,
+	/// Enter, Escape, Tab and Backspace don't start commands when typed, as they cancel them or do
+	/// nothing, but can be typed by the user's own commands, into popups
+	Enter,
+	Esc,
+	Tab,
+	Backspace,
+}
+
+impl Key {
+	/// A character typed without Ctrl or Alt
+	pub const fn char(c: char) -> Self {
+		Self {
+			name: KeyName::Char(c),
+			ctrl: false,
+			alt: false,
+		}
+	}
+
+	/// A character typed with Ctrl
+	pub const fn ctrl(c: char) -> Self {
+		Self {
+			name: KeyName::Char(c),
+			ctrl: true,
+			alt: false,
+		}
+	}
+
+	/// Returns the keys typed by the key event. Some keys stand in for the keys of the command
+	/// they are expected to run, like the arrow keys for `hjkl` and Home for `gg`. Keys that no
+	/// command can use, like Enter, type nothing
+	pub fn from_event(event: &KeyEvent) -> Vec<Self> {
+		let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+		let alt = event.modifiers.contains(KeyModifiers::ALT);
+		let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+		let name = match event.code {
+			KeyCode::Char(c) => KeyName::Char(c),
+			KeyCode::F(n) if (1..=12).contains(&n) => KeyName::F(n),
+			KeyCode::Delete => KeyName::Delete,
+			code => {
+				return match (code, ctrl, shift) {
+					(KeyCode::Up, true, _) => vec![Self::ctrl('k')],
+					(KeyCode::Down, true, _) => vec![Self::ctrl('j')],
+					(KeyCode::Left, true, _) | (KeyCode::BackTab, ..) => vec![Self::ctrl('h')],
+					(KeyCode::Right, true, _) | (KeyCode::Tab, ..) => vec![Self::ctrl('l')],
+					(KeyCode::Up, _, true) => vec![Self::char('K')],
+					(KeyCode::Down, _, true) => vec![Self::char('J')],
+					(KeyCode::Left, _, true) => vec![Self::char('H')],
+					(KeyCode::Right, _, true) => vec![Self::char('L')],
+					(KeyCode::Up, ..) => vec![Self::char('k')],
+					(KeyCode::Down, ..) => vec![Self::char('j')],
+					(KeyCode::Left, ..) => vec![Self::char('h')],
+					(KeyCode::Right, ..) => vec![Self::char('l')],
+					(KeyCode::PageUp, ..) => vec![Self::ctrl('u')],
+					(KeyCode::PageDown, ..) => vec![Self::ctrl('d')],
+					(KeyCode::Home, ..) => vec![Self::char('g'), Self::char('g')],
+					(KeyCode::End, ..) => vec![Self::char('G')],
+					_ => vec![],
+				};
+			}
+		};
+		vec![Self { name, ctrl, alt }]
+	}
+
+	/// Returns the key event of typing the key, for typing the keys of the user's own commands
+	pub fn to_event(self) -> KeyEvent {
+		let code = match self.name {
+			KeyName::Char(c) => KeyCode::Char(c),
+			KeyName::F(n) => KeyCode::F(n),
+			KeyName::Delete => KeyCode::Delete,
+			KeyName::Enter => KeyCode::Enter,
+			KeyName::Esc => KeyCode::Esc,
+			KeyName::Tab => KeyCode::Tab,
+			KeyName::Backspace => KeyCode::Backspace,
+		};
+		let mut modifiers = KeyModifiers::NONE;
+		modifiers.set(KeyModifiers::CONTROL, self.ctrl);
+		modifiers.set(KeyModifiers::ALT, self.alt);
+		KeyEvent::new(code, modifiers)
+	}
+
+	/// Returns the digit typed, if the key is one typed without any modifiers, for counts
+	pub fn digit(self) -> Option<u32> {
+		match self {
+			Self {
+				name: KeyName::Char(c),
+				ctrl: false,
+				alt: false,
+			} => c.to_digit(10),
+			_ => None,
+		}
+	}
+
+	/// Parses what is between the angle brackets of a key, like `C-w` or `F1`
+	fn parse_bracketed(text: &str) -> Option<Self> {
+		let mut key = Self::char(' ');
+		let mut rest = text;
+		loop {
+			if let Some(after) = rest.strip_prefix("C-").or(rest.strip_prefix("c-")) {
+				key.ctrl = true;
+				rest = after;
+			} else if let Some(after) = ["A-", "a-", "M-", "m-"]
+				.iter()
+				.find_map(|prefix| rest.strip_prefix(prefix))
+			{
+				key.alt = true;
+				rest = after;
+			} else {
+				break;
+			}
+		}
+		let mut chars = rest.chars();
+		key.name = match (chars.next(), chars.next()) {
+			(Some(c), None) => KeyName::Char(c),
+			_ if rest.eq_ignore_ascii_case("lt") => KeyName::Char('<'),
+			_ if rest.eq_ignore_ascii_case("space") => KeyName::Char(' '),
+			_ if rest.eq_ignore_ascii_case("del") => KeyName::Delete,
+			_ if ["cr", "enter", "return"]
+				.iter()
+				.any(|name| rest.eq_ignore_ascii_case(name)) =>
+			{
+				KeyName::Enter
+			}
+			_ if rest.eq_ignore_ascii_case("esc") => KeyName::Esc,
+			_ if rest.eq_ignore_ascii_case("tab") => KeyName::Tab,
+			_ if rest.eq_ignore_ascii_case("bs") || rest.eq_ignore_ascii_case("backspace") => {
+				KeyName::Backspace
+			}
+			_ => {
+				let n: u8 = rest.strip_prefix(['F', 'f'])?.parse().ok()?;
+				(1..=12).contains(&n).then_some(KeyName::F(n))?
+			}
+		};
+		// A plain character doesn't go in brackets, except `<` and space
+		let plain =
+			!key.ctrl && !key.alt && matches!(key.name, KeyName::Char(c) if c != '<' && c != ' ');
+		(!plain).then_some(key)
+	}
+}
+
+impl Display for Key {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if !self.ctrl
+			&& !self.alt
+			&& let KeyName::Char(c) = self.name
+			&& c != '<'
+			&& c != ' '
+		{
+			return write!(f, "{c}");
+		}
+		write!(f, "<")?;
+		if self.ctrl {
+			write!(f, "C-")?;
+		}
+		if self.alt {
+			write!(f, "A-")?;
+		}
+		match self.name {
+			KeyName::Char('<') => write!(f, "lt")?,
+			KeyName::Char(' ') => write!(f, "Space")?,
+			KeyName::Char(c) => write!(f, "{c}")?,
+			KeyName::F(n) => write!(f, "F{n}")?,
+			KeyName::Delete => write!(f, "Del")?,
+			KeyName::Enter => write!(f, "CR")?,
+			KeyName::Esc => write!(f, "Esc")?,
+			KeyName::Tab => write!(f, "Tab")?,
+			KeyName::Backspace => write!(f, "BS")?,
+		}
+		write!(f, ">")
+	}
+}
+
+/// The keys of a command, in the order they are typed. In the config file, they are written as a
+/// string in the notation of the [module](self) docs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeySequence(Vec<Key>);
+
+impl KeySequence {
+	pub fn keys(&self) -> &[Key] {
+		&self.0
+	}
+
+	pub fn push(&mut self, key: Key) {
+		self.0.push(key);
+	}
+
+	pub fn clear(&mut self) {
+		self.0.clear();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl TryFrom<String> for KeySequence {
+	type Error = KeyParseError;
+
+	fn try_from(text: String) -> Result<Self, Self::Error> {
+		text.parse()
+	}
+}
+
+impl From<Vec<Key>> for KeySequence {
+	fn from(keys: Vec<Key>) -> Self {
+		Self(keys)
+	}
+}
+
+impl FromStr for KeySequence {
+	type Err = KeyParseError;
+
+	/// Parses keys written in vim's notation. See the [module](self) docs
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		if text.is_empty() {
+			return Err(KeyParseError::Empty);
+		}
+		let mut keys = vec![];
+		let mut rest = text;
+		while let Some(c) = rest.chars().next() {
+			if c.is_whitespace() && c != ' ' {
+				return Err(KeyParseError::Whitespace(text.to_string()));
+			}
+			if c == '<' {
+				let (key, after) = rest[1..]
+					.split_once('>')
+					.and_then(|(inside, after)| Some((Key::parse_bracketed(inside)?, after)))
+					.ok_or_else(|| KeyParseError::Invalid(text.to_string()))?;
+				keys.push(key);
+				rest = after;
+			} else {
+				keys.push(Key::char(c));
+				rest = &rest[c.len_utf8()..];
+			}
+		}
+		Ok(Self(keys))
+	}
+}
+
+impl Display for KeySequence {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.0.iter().try_for_each(|key| write!(f, "{key}"))
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum KeyParseError {
+	#[error("No keys given")]
+	Empty,
+	#[error("Keys can't contain whitespace other than spaces: \"{0}\"")]
+	Whitespace(String),
+	#[error("Invalid key in \"{0}\" (use <lt> for < and <Space> for space)")]
+	Invalid(String),
+}
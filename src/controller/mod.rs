@@ -1,257 +1,824 @@
 //! This module handles input from the user, and directs the model/view appropriately
 
-use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::mpsc::Sender,
+	time::{Duration, Instant},
+};
+
+use anyhow::{Result, bail};
+use ratatui::crossterm::event::{
+	Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 
 use crate::{
+	config::Mapping,
 	controller::{
-		commands::CommandTrie,
+		commands::{Binding, Command, CommandTrie},
+		keys::{Key, KeySequence},
 		popup::{Popup, PopupBehaviour},
+		registry::ActionRegistry,
 	},
-	model::{Model, Transaction},
+	controller::{tasks::Task, workspaces::Workspace},
+	events::AppEvent,
+	model::{Message, Model, Outcome, ScriptCommand, Transaction},
+	rates::RateSource,
+	sync::Remote,
 	view::View,
 };
 
-mod commands;
+mod actions;
+pub mod commands;
+pub mod keys;
 pub mod popup;
+pub mod registry;
+mod tasks;
+mod workspaces;
+
+pub use tasks::{TaskFinish, Worker};
 
 #[derive(Default)]
 pub struct Controller {
 	pub state: ControllerState,
 	commands: CommandTrie,
+	/// Whether the keys of one of the user's own commands are being typed, which stops them
+	/// running another (or the same) one forever. See [`Command::Keys`]
+	typing_keys: bool,
 }
 
 #[derive(Default)]
 pub struct ControllerState {
 	pub last_nums: Vec<u32>,
-	pub last_chars: Vec<char>,
+	pub last_keys: KeySequence,
 	pub popup: Option<Popup>,
 	pub exit: bool,
-	register: Option<Transaction>,
+	/// The rows last yanked or deleted, which are put with `p`/`P`
+	register: Vec<Transaction>,
+	/// The value of the cell last yanked with `gy`, which is put with `gp`
+	cell_register: Option<String>,
+	/// The last change made to the model, which can be repeated with `.`
+	pub last_change: Option<Change>,
+	/// The operator waiting for a motion, if one has been typed
+	pub pending_operator: Option<PendingOperator>,
+	/// The row (as the index of its transaction) the cursor was on before the motion of the
+	/// operator being applied, as the cursor is moved to the top of the rows it covers before the
+	/// operator is. [`None`] outside of an operator
+	pub operator_origin: Option<usize>,
+	/// Whether the program should capture the mouse. When it doesn't, the terminal handles the
+	/// mouse itself, so text can be selected and copied natively
+	pub mouse_capture: bool,
+	/// Whether deleting a single row is confirmed first, like deleting more than one is
+	pub confirm_delete: bool,
+	/// How many backups of the file to keep when saving. See [`Model::write_save`]
+	pub backups: usize,
+	/// The server a copy of the file is kept on, if syncing is set up. See [`sync`](crate::sync)
+	pub sync: Option<Remote>,
+	/// Where exchange rates are fetched from, if a provider is set up. See [`rates`](crate::rates)
+	pub rates: Option<RateSource>,
+	/// The files open alongside the one being shown, in the order they are cycled through
+	workspaces: Vec<Workspace>,
+	/// Every key that is bound, for the help popup. See [`CommandTrie::describe_bindings`]
+	bound_keys: String,
+	/// The keys that can follow the key sequence being typed, and what each does. Empty when no
+	/// sequence is being typed
+	pub key_hints: Vec<(String, String)>,
+	/// How long to wait for the next key of a sequence before giving up on it, or [`None`] to
+	/// wait forever. See [`Controller::expire_pending_keys`]
+	pub key_timeout: Option<Duration>,
+	/// When the key sequence being typed times out, if one is being typed
+	key_deadline: Option<Instant>,
+	/// The text submitted to each kind of input popup, oldest first, to go back to with the arrow
+	/// keys. See [`InputInner::with_history`](popup::InputInner::with_history)
+	input_history: HashMap<String, Vec<String>>,
+	/// The messages shown briefly over the sheet, oldest first. See [`ControllerState::notify`]
+	pub notifications: VecDeque<Notification>,
+	/// The task being done by the worker, if any. See [`ControllerState::start_task`]
+	task: Option<Task>,
+	/// The thread doing tasks and background jobs. Only [`None`] before [`Controller::new`] has
+	/// started it
+	worker: Option<Worker>,
+	/// What is going on with the file being shown on disk. See [`ControllerState::check_disk`]
+	disk: DiskState,
+}
+
+/// What is going on with the file being shown on disk, to tell changes made by something else
+/// from this instance's own saves
+#[derive(Debug, Default)]
+struct DiskState {
+	/// Whether the file was touched since it was last checked for changes
+	touched: bool,
+	/// How many saves are being written by the worker
+	saving: usize,
+}
+
+/// A message shown briefly over the sheet, like that the file was saved, which goes away on its
+/// own after [`Notification::DURATION`]
+#[derive(Debug, Clone)]
+pub struct Notification {
+	pub message: String,
+	/// Whether the message is about something that went wrong
+	pub error: bool,
+	/// When the message was first shown
+	pub shown_at: Instant,
+}
+
+impl Notification {
+	/// How long each message is shown for
+	pub const DURATION: Duration = Duration::from_secs(4);
+	/// The most messages shown at once. Older ones are dropped to make room for new ones
+	const LIMIT: usize = 3;
+}
+
+/// An operator that has been typed, and is waiting for a motion
+#[derive(Debug, Clone)]
+pub struct PendingOperator {
+	/// The keys of the operator
+	pub keys: KeySequence,
+	/// The count typed before the operator
+	pub count: Option<usize>,
+}
+
+/// What the keys typed next go to, as shown in the status line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+	/// Moving around and running commands on the sheet
+	Normal,
+	/// An operator has been typed, and is waiting for a motion
+	Operator,
+	/// Typing text into a popup
+	Insert,
+	/// Choosing from, or reading, a popup
+	Popup,
+}
+
+impl std::fmt::Display for Mode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Mode::Normal => write!(f, "NORMAL"),
+			Mode::Operator => write!(f, "OPERATOR"),
+			Mode::Insert => write!(f, "INSERT"),
+			Mode::Popup => write!(f, "POPUP"),
+		}
+	}
+}
+
+/// A change to the model that can be repeated with `.`
+#[derive(Debug, Clone)]
+pub enum Change {
+	/// A repeatable command from the [`CommandTrie`], replayed by its key sequence
+	Command { keys: KeySequence, count: Vec<u32> },
+	/// An operator applied over a motion, replayed by applying it over the same motion again
+	Operator {
+		operator: KeySequence,
+		motion: KeySequence,
+		count: Option<usize>,
+	},
+	/// A row inserted with `o`/`O`, replayed by inserting the same transaction again
+	Insert {
+		transaction: Transaction,
+		below: bool,
+	},
+	/// A cell edited with `i`, replayed by setting the same column of the selected row to the same
+	/// value
+	Edit { col: usize, value: String },
+}
+
+impl Change {
+	/// Replays an insert or edit on the current selection `count` times. Commands are replayed by
+	/// the [`Controller`], as they need the [`CommandTrie`]
+	fn replay(&self, view: &mut View, model: &mut Model, cs: &mut ControllerState, count: usize) {
+		let sheet_index = view.selected_sheet;
+		match self {
+			Change::Command { .. } | Change::Operator { .. } => {}
+			Change::Insert { transaction, below } => {
+				for _ in 0..count {
+					let sheet = view.get_selected_sheet(model);
+					let row = view.get_selected_row(sheet).unwrap_or(0);
+					let row = if *below {
+						(row + 1).min(sheet.transactions().len())
+					} else {
+						row
+					};
+					let message = Message::InsertRow {
+						sheet: sheet_index,
+						row,
+						transaction: transaction.clone(),
+					};
+					if let Some(row) = cs.send(model, message).and_then(|o| o.row()) {
+						view.select_row(row, model);
+					}
+				}
+			}
+			Change::Edit { col, value } => {
+				let sheet = view.get_selected_sheet(model);
+				if let Some(row) = view.get_selected_row(sheet) {
+					// The value was valid when it was first entered, so it is still valid now
+					cs.send(
+						model,
+						Message::SetMember {
+							sheet: sheet_index,
+							row,
+							col: *col,
+							value: value.clone(),
+						},
+					);
+					let message = Message::SettleRow {
+						sheet: sheet_index,
+						row,
+					};
+					if let Some(row) = cs.send(model, message).and_then(|o| o.row()) {
+						view.select_row(row, model);
+					}
+				}
+			}
+		}
+	}
 }
 
 impl ControllerState {
+	/// Shows a message briefly over the sheet
+	pub fn notify<S: Into<String>>(&mut self, message: S) {
+		self.push_notification(message.into(), false);
+	}
+
+	/// Shows a message about something that went wrong briefly over the sheet, for errors that
+	/// don't need a popup
+	pub fn notify_error<S: Into<String>>(&mut self, message: S) {
+		let message = message.into();
+		tracing::warn!("{message}");
+		self.push_notification(message, true);
+	}
+
+	/// Applies the message to the model, showing why it couldn't be if it can't. See
+	/// [`Model::update`]
+	pub fn send(&mut self, model: &mut Model, message: Message) -> Option<Outcome> {
+		model
+			.update(message)
+			.inspect_err(|e| self.notify_error(e.to_string()))
+			.ok()
+	}
+
+	/// Returns whether the worker is doing anything whose result is waited for - a task, or a save
+	pub fn is_busy(&self) -> bool {
+		self.has_running_task() || self.disk.saving > 0
+	}
+
+	/// Records that the file being shown was touched on disk, so it is checked for changes. See
+	/// [`ControllerState::check_disk`]
+	pub fn file_touched(&mut self) {
+		self.disk.touched = true;
+	}
+
+	/// Checks whether the file being shown was changed on disk by something else since it was
+	/// touched, asking what to do about it if it was. This waits until no popup is shown and the
+	/// file isn't being saved, so this instance's own saves aren't taken for changes. Returns
+	/// whether anything shown changed
+	pub fn check_disk(&mut self, model: &Model) -> bool {
+		if !self.disk.touched || self.popup.is_some() || self.disk.saving > 0 {
+			return false;
+		}
+		self.disk.touched = false;
+		match model.read_disk() {
+			Ok(Some(disk)) => {
+				self.popup = Some(popup::defaults::disk_changed(model, &disk));
+				true
+			}
+			Ok(None) => false,
+			Err(e) => {
+				self.notify_error(format!(
+					"The file changed on disk, but couldn't be read: {e}"
+				));
+				true
+			}
+		}
+	}
+
+	fn push_notification(&mut self, message: String, error: bool) {
+		if self.notifications.len() == Notification::LIMIT {
+			self.notifications.pop_front();
+		}
+		self.notifications.push_back(Notification {
+			message,
+			error,
+			shown_at: Instant::now(),
+		});
+	}
+
+	/// Removes the messages that have been shown for long enough, returning whether any were
+	pub fn expire_notifications(&mut self) -> bool {
+		let count = self.notifications.len();
+		self.notifications
+			.retain(|notification| notification.shown_at.elapsed() < Notification::DURATION);
+		self.notifications.len() != count
+	}
+
+	/// Returns whether what is shown changes on its own, without any event, so the screen has to
+	/// be checked on regularly - while a task's progress is shown, or messages are waiting to
+	/// expire
+	pub fn changes_over_time(&self) -> bool {
+		self.has_running_task() || !self.notifications.is_empty() || self.key_deadline.is_some()
+	}
+
+	/// Starts waiting for the next key of the sequence being typed, if one is. See
+	/// [`ControllerState::key_timeout`]
+	fn restart_key_timeout(&mut self) {
+		self.key_deadline = self
+			.key_timeout
+			.filter(|_| self.popup.is_none() && !self.last_keys.is_empty())
+			.map(|timeout| Instant::now() + timeout);
+	}
+
+	/// Returns how long is left before the key sequence being typed times out, if one is
+	pub fn key_time_left(&self) -> Option<Duration> {
+		self.key_deadline
+			.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+	}
+
+	/// The most inputs remembered for each kind of input popup
+	const INPUT_HISTORY_LIMIT: usize = 100;
+
+	/// Remembers the text submitted to the kind of input popup with the given name, moving it to
+	/// the end if it was already remembered. Blank text isn't
+	fn remember_input(&mut self, name: &str, text: &str) {
+		let text = text.trim();
+		if text.is_empty() {
+			return;
+		}
+		let history = self.input_history.entry(name.to_string()).or_default();
+		history.retain(|input| input != text);
+		history.push(text.to_string());
+		if history.len() > Self::INPUT_HISTORY_LIMIT {
+			history.remove(0);
+		}
+	}
+
+	/// Returns the text submitted to the kind of input popup with the given name, oldest first
+	fn input_history(&self, name: &str) -> &[String] {
+		self.input_history.get(name).map_or(&[], Vec::as_slice)
+	}
+
+	/// Shows the error of an action that failed, returning whether it succeeded
+	fn report(&mut self, result: Result<()>) -> bool {
+		match result {
+			Ok(()) => true,
+			Err(e) => {
+				self.notify_error(format!("{e:#}"));
+				false
+			}
+		}
+	}
+
+	/// Returns the mode the keys typed next are handled in
+	pub fn mode(&self) -> Mode {
+		match (&self.popup, &self.pending_operator) {
+			(Some(Popup::Input(_) | Popup::Form(_)), _) => Mode::Insert,
+			(Some(_), _) => Mode::Popup,
+			(None, Some(_)) => Mode::Operator,
+			(None, None) => Mode::Normal,
+		}
+	}
+
+	/// Returns the keys of the command being typed, with any counts, in the order they were typed
+	pub fn pending_keys(&self) -> String {
+		let mut keys = String::new();
+		if let Some(pending) = &self.pending_operator {
+			if let Some(count) = pending.count {
+				keys.push_str(&count.to_string());
+			}
+			keys.push_str(&pending.keys.to_string());
+		}
+		keys.extend(self.last_nums.iter().map(ToString::to_string));
+		keys.push_str(&self.last_keys.to_string());
+		keys
+	}
+
 	pub fn get_count_amount(&self) -> usize {
 		self.last_nums
 			.iter()
 			.fold(0, |acc: u32, d| acc.saturating_mul(10).saturating_add(*d)) as usize
 	}
+
+	/// Returns the register repeated `count` times, for pasting with a count
+	fn register_repeated(&self, count: usize) -> Vec<Transaction> {
+		(0..count)
+			.flat_map(|_| self.register.iter().cloned())
+			.collect()
+	}
+
+	/// Returns the count typed before the command, or [`None`] if there wasn't one
+	pub fn get_count(&self) -> Option<usize> {
+		(!self.last_nums.is_empty()).then(|| self.get_count_amount())
+	}
 }
 
 impl Controller {
-	pub fn handle_events(&mut self, event: &Event, model: &mut Model, view: &mut View) {
-		match event {
+	/// Handles an event from the terminal, returning whether it could have changed what is shown,
+	/// so the screen needs to be redrawn
+	pub fn handle_events(&mut self, event: &Event, model: &mut Model, view: &mut View) -> bool {
+		let changed = match event {
 			Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
 				self.handle_key_event(key_event, model, view);
+				self.state.restart_key_timeout();
+				true
+			}
+			Event::Mouse(mouse_event) => self.handle_mouse_event(*mouse_event, model, view),
+			Event::Resize(..) => {
+				view.resize();
+				true
+			}
+			// Text pasted into the terminal only goes into inputs, rather than running every key
+			// in it as a command
+			Event::Paste(text) => match &mut self.state.popup {
+				Some(Popup::Input(input)) => {
+					input.paste(text);
+					true
+				}
+				_ => false,
+			},
+			_ => false,
+		};
+		self.state.key_hints = self.key_hints();
+		changed
+	}
+
+	/// Gives up on the key sequence being typed once no key has followed it for
+	/// [`ControllerState::key_timeout`]. If the keys are a command of their own, as well as the
+	/// start of longer ones, that command is run, like vim's `timeoutlen`. Otherwise they are
+	/// cleared. Returns whether the sequence timed out
+	pub fn expire_pending_keys(&mut self, model: &mut Model, view: &mut View) -> bool {
+		if self
+			.state
+			.key_deadline
+			.is_none_or(|deadline| Instant::now() < deadline)
+		{
+			return false;
+		}
+		self.state.key_deadline = None;
+		self.try_action(model, view, true);
+		if !self.state.last_keys.is_empty() {
+			self.reset_command();
+		}
+		self.state.key_hints = self.key_hints();
+		true
+	}
+
+	/// Returns the keys that can follow the key sequence being typed, if one is. See
+	/// [`ControllerState::key_hints`]
+	fn key_hints(&self) -> Vec<(String, String)> {
+		if self.state.popup.is_some() || self.state.last_keys.is_empty() {
+			return vec![];
+		}
+		self.commands
+			.traverse(self.state.last_keys.keys())
+			.filter(|node| node.has_children())
+			.map(CommandTrie::hints)
+			.unwrap_or_default()
+	}
+
+	/// Scrolls the table with the mouse wheel, and selects the row that is clicked on. Returns
+	/// whether the event did anything, as moving the mouse doesn't
+	fn handle_mouse_event(
+		&mut self,
+		mouse_event: MouseEvent,
+		model: &Model,
+		view: &mut View,
+	) -> bool {
+		/// The amount of rows scrolled by each step of the mouse wheel
+		const SCROLL_ROWS: usize = 3;
+
+		if self.state.popup.is_some() || !self.state.mouse_capture || view.dashboard {
+			return false;
+		}
+		match mouse_event.kind {
+			MouseEventKind::ScrollDown => view.down_by(SCROLL_ROWS, model),
+			MouseEventKind::ScrollUp => view.up_by(SCROLL_ROWS, model),
+			MouseEventKind::Down(MouseButton::Left) => {
+				view.click(mouse_event.column, mouse_event.row, model);
 			}
-			_ => {}
+			_ => return false,
 		}
+		true
 	}
 
 	fn handle_key_event(&mut self, key_event: &KeyEvent, model: &mut Model, view: &mut View) {
 		if let Some(popup) = self.state.popup.take() {
-			self.state.popup = popup.handle_key_event(key_event, model);
+			self.state.popup = popup.handle_key_event(key_event, view, model, &mut self.state);
 			return;
 		}
-		match key_event.code {
-			KeyCode::Char(c) => {
-				if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-					self.handle_modified_char(c, key_event.modifiers);
-				} else {
-					if let Some(d) = c.to_digit(10)
-						&& d < 10
-					{
-						self.state.last_nums.push(d);
-						return;
-					}
-					self.state.last_chars.push(c);
-				}
-			}
-			KeyCode::Backspace | KeyCode::Esc => self.reset_command(),
-			_ => {
-				self.handle_special_key(key_event);
+		if view.dashboard
+			&& self.state.last_keys.is_empty()
+			&& handle_dashboard_key(key_event, model, view)
+		{
+			return;
+		}
+		if matches!(key_event.code, KeyCode::Backspace | KeyCode::Esc) {
+			self.cancel_command();
+		}
+		let keys = Key::from_event(key_event);
+		tracing::trace!("Key {}", KeySequence::from(keys.clone()));
+		for key in keys {
+			if let Some(d) = key.digit() {
+				self.state.last_nums.push(d);
+				return;
 			}
+			self.state.last_keys.push(key);
 		}
-		self.try_action(model, view);
+		self.try_action(model, view, false);
 	}
 
-	fn try_action(&mut self, model: &mut Model, view: &mut View) {
-		if let Some(command) = self
-			.commands
-			.traverse(self.state.last_chars.iter().copied())
-			&& !command.has_children()
+	/// Runs the command typed, if it is complete. A command that is also the start of longer
+	/// ones is only run once the sequence has `timed_out`
+	fn try_action(&mut self, model: &mut Model, view: &mut View, timed_out: bool) {
+		if self.state.last_keys.keys() == [Key::char('.')] && self.state.pending_operator.is_none()
 		{
-			{
-				if let Some(action) = command.action() {
-					(action)(view, model, &mut self.state);
-				}
-				self.reset_command();
+			self.repeat_last_change(model, view);
+			return;
+		}
+		let Some(node) = self.commands.traverse(self.state.last_keys.keys()) else {
+			// No command starts with the keys, so no more keys can complete them
+			self.state
+				.notify_error(format!("Unknown command: {}", self.state.pending_keys()));
+			self.cancel_command();
+			return;
+		};
+		if node.has_children() && !(timed_out && node.command().is_some()) {
+			return;
+		}
+		tracing::debug!(
+			"Running {} ({})",
+			node.name().unwrap_or("a command"),
+			self.state.pending_keys()
+		);
+		if let Some(Command::Keys(keys)) = node.command() {
+			let keys = keys.clone();
+			if self.typing_keys {
+				self.state
+					.notify_error("Your commands can't run each other");
+				self.cancel_command();
+				return;
 			}
-		} else {
-			self.state.last_nums.clear();
+			self.state.last_keys.clear();
+			self.typing_keys = true;
+			self.type_keys(&keys, model, view);
+			self.typing_keys = false;
+			return;
 		}
-	}
+		let count = self.state.get_count();
 
-	fn handle_modified_char(&mut self, char: char, modifiers: KeyModifiers) {
-		self.state.last_chars.push('<');
-		if modifiers.contains(KeyModifiers::CONTROL) {
-			self.state.last_chars.push('C');
-			self.state.last_chars.push('-');
+		if let Some(pending) = self.state.pending_operator.take() {
+			let motion = self.state.last_keys.clone();
+			let count = match (pending.count, count) {
+				(None, None) => None,
+				(a, b) => Some(a.unwrap_or(1).saturating_mul(b.unwrap_or(1))),
+			};
+			if self.apply_operator(&pending.keys, &motion, count, model, view) {
+				self.state.last_change = Some(Change::Operator {
+					operator: pending.keys,
+					motion,
+					count,
+				});
+			}
+			self.reset_command();
+			return;
 		}
-		// I don't think this is necessary to check for? e.g. <S-H> can also just be, H
-		// And <C-S-_> isn't possible without messing around with some other stuff
-		// if modifiers.contains(KeyModifiers::SHIFT) {
-		// 	self.state.last_chars.push('S');
-		// 	self.state.last_chars.push('-');
-		// }
 
-		self.state.last_chars.push(char);
-		self.state.last_chars.push('>');
-	}
-
-	fn handle_special_key(&mut self, key_event: &KeyEvent) {
-		match (key_event.modifiers, key_event.code) {
-			(KeyModifiers::CONTROL, KeyCode::Up) => {
-				self.handle_modified_char('k', KeyModifiers::CONTROL);
-			}
-			(KeyModifiers::CONTROL, KeyCode::Down) => {
-				self.handle_modified_char('j', KeyModifiers::CONTROL);
+		match node.command() {
+			Some(Command::Operator { .. }) => {
+				self.state.pending_operator = Some(PendingOperator {
+					keys: self.state.last_keys.clone(),
+					count,
+				});
 			}
-			(KeyModifiers::CONTROL, KeyCode::Left) | (_, KeyCode::BackTab) => {
-				self.handle_modified_char('h', KeyModifiers::CONTROL);
+			Some(Command::Action(action) | Command::Motion(action)) => {
+				let result = (action)(view, model, &mut self.state, count);
+				self.state.report(result);
 			}
-			(KeyModifiers::CONTROL, KeyCode::Right) | (_, KeyCode::Tab) => {
-				self.handle_modified_char('l', KeyModifiers::CONTROL);
+			Some(Command::Repeatable(action)) => {
+				let result = (action)(view, model, &mut self.state, count);
+				if self.state.report(result) {
+					self.state.last_change = Some(Change::Command {
+						keys: self.state.last_keys.clone(),
+						count: self.state.last_nums.clone(),
+					});
+				}
 			}
-			(KeyModifiers::CONTROL, KeyCode::Delete) => {
-				self.state.last_chars.push('<');
-				self.state.last_chars.push('C');
-				self.state.last_chars.push('-');
-				self.state.last_chars.push('D');
-				self.state.last_chars.push('e');
-				self.state.last_chars.push('l');
-				self.state.last_chars.push('>');
+			Some(Command::Keys(_)) | None => {}
+		}
+		self.reset_command();
+	}
+
+	/// Types the keys, as if the user had typed them, keeping the count typed before them. See
+	/// [`Command::Keys`]
+	fn type_keys(&mut self, keys: &KeySequence, model: &mut Model, view: &mut View) {
+		for key in keys.keys() {
+			self.handle_key_event(&key.to_event(), model, view);
+		}
+	}
+
+	/// Adds the user's own commands, under the leader: the mappings of the config (see
+	/// [`crate::config::Config::mappings`]), and the commands added by their scripts (see
+	/// [`crate::model::Scripts`])
+	///
+	/// # Errors
+	/// If the leader starts a builtin command, in which case none are added, or the keys of any of
+	/// the script commands are invalid or already used, in which case the others are still added
+	pub fn add_mappings(
+		&mut self,
+		leader: &KeySequence,
+		mappings: &[Mapping],
+		scripts: &[ScriptCommand],
+	) -> Result<()> {
+		let Some(first) = leader
+			.keys()
+			.first()
+			.filter(|_| !mappings.is_empty() || !scripts.is_empty())
+		else {
+			return Ok(());
+		};
+		if self.commands.next(*first).is_some() {
+			bail!("The leader {leader} can't be used, as builtin commands start with {first}");
+		}
+		let mut trie = std::mem::take(&mut self.commands);
+		for mapping in mappings {
+			let keys = format!("{leader}{}", mapping.keys);
+			let run = mapping.run.to_string();
+			let binding = Binding {
+				keys: &keys,
+				name: Some(mapping.name.as_deref().unwrap_or(&run)),
+				description: Some(&format!("type {run}")),
+			};
+			trie = trie.add_keys(binding, mapping.run.clone());
+		}
+		let mut invalid = vec![];
+		for (index, command) in scripts.iter().enumerate() {
+			let keys = format!("{leader}{}", command.keys);
+			let used = keys.parse::<KeySequence>().map(|sequence| {
+				trie.traverse(sequence.keys())
+					.and_then(CommandTrie::command)
+					.is_some()
+			});
+			if used.unwrap_or(true) {
+				invalid.push(command.keys.as_str());
+				continue;
 			}
-			(KeyModifiers::SHIFT, KeyCode::Up) => {
-				self.state.last_chars.push('K');
+			trie = trie.add_repeatable(
+				(
+					keys.as_str(),
+					command.name.as_str(),
+					"run a command from your scripts",
+				),
+				move |view: &mut View, model: &mut Model, cs: &mut ControllerState, count| {
+					popup::defaults::run_script_command(index, view, model, cs, count)
+				},
+			);
+		}
+		self.commands = trie.name_group(&leader.to_string(), "your commands");
+		self.state.bound_keys = self.commands.describe_bindings();
+		if !invalid.is_empty() {
+			bail!(
+				"The keys of the script commands {} are invalid or already used",
+				invalid.join(", ")
+			);
+		}
+		Ok(())
+	}
+
+	/// Applies the operator with the given keys over the rows covered by the motion with the
+	/// given keys. If the motion is the operator itself (e.g. `dd`), it is applied to `count` rows
+	/// starting at the cursor instead. Does nothing if the motion isn't a motion. Returns whether
+	/// an operator that can be repeated was applied
+	fn apply_operator(
+		&mut self,
+		operator: &KeySequence,
+		motion: &KeySequence,
+		count: Option<usize>,
+		model: &mut Model,
+		view: &mut View,
+	) -> bool {
+		let Some(Command::Operator {
+			operator: operator_fn,
+			repeatable,
+		}) = self
+			.commands
+			.traverse(operator.keys())
+			.and_then(CommandTrie::command)
+		else {
+			return false;
+		};
+		let motion_command = self
+			.commands
+			.traverse(motion.keys())
+			.and_then(CommandTrie::command);
+
+		let rows = match motion_command {
+			_ if motion == operator => {
+				let sheet = view.get_selected_sheet(model);
+				view.get_selected_rows(sheet, count.unwrap_or(1))
 			}
-			(KeyModifiers::SHIFT, KeyCode::Down) => {
-				self.state.last_chars.push('J');
+			Some(Command::Motion(motion_fn)) => {
+				let Some(start) = view.get_selected_display_row(model) else {
+					return false;
+				};
+				self.state.operator_origin =
+					view.get_selected_sheet(model).displayed_row_index(start);
+				let result = (motion_fn)(view, model, &mut self.state, count);
+				if !self.state.report(result) {
+					return false;
+				}
+				let Some(end) = view.get_selected_display_row(model) else {
+					return false;
+				};
+				let (from, to) = (start.min(end), start.max(end));
+				view.select_display_row(from, model);
+				let sheet = view.get_selected_sheet(model);
+				view.get_selected_rows(sheet, to - from + 1)
 			}
-			(KeyModifiers::SHIFT, KeyCode::Left) => {
-				self.state.last_chars.push('H');
+			_ => return false,
+		};
+		if rows.is_empty() {
+			return false;
+		}
+		self.state.operator_origin.get_or_insert(rows[0]);
+		let result = (operator_fn)(view, model, &mut self.state, &rows);
+		self.state.operator_origin = None;
+		self.state.report(result) && *repeatable
+	}
+
+	/// Repeats the last change, using the new count if one was given
+	fn repeat_last_change(&mut self, model: &mut Model, view: &mut View) {
+		match self.state.last_change.clone() {
+			Some(Change::Command { keys, count }) => {
+				if self.state.last_nums.is_empty() {
+					self.state.last_nums = count;
+				}
+				self.state.last_keys = keys;
+				// The keys were run as a command before, even if only once they timed out
+				self.try_action(model, view, true);
 			}
-			(KeyModifiers::SHIFT, KeyCode::Right) => {
-				self.state.last_chars.push('L');
+			Some(Change::Operator {
+				operator,
+				motion,
+				count,
+			}) => {
+				let count = self.state.get_count().or(count);
+				self.apply_operator(&operator, &motion, count, model, view);
+				self.reset_command();
 			}
-			(_, KeyCode::Up) => self.state.last_chars.push('k'),
-
-			(_, KeyCode::Down) => self.state.last_chars.push('j'),
-
-			(_, KeyCode::Left) => self.state.last_chars.push('h'),
-
-			(_, KeyCode::Right) => self.state.last_chars.push('l'),
-			(_, KeyCode::PageUp) => self.handle_modified_char('u', KeyModifiers::CONTROL),
-			(_, KeyCode::PageDown) => self.handle_modified_char('d', KeyModifiers::CONTROL),
-			(_, KeyCode::Home) => {
-				self.state.last_chars.push('g');
-				self.state.last_chars.push('g');
+			Some(change) => {
+				let count = self.state.get_count_amount().max(1);
+				change.replay(view, model, &mut self.state, count);
+				self.reset_command();
 			}
-			(_, KeyCode::End) => self.state.last_chars.push('G'),
-
-			_ => {}
+			None => self.reset_command(),
 		}
 	}
 
 	fn reset_command(&mut self) {
-		self.state.last_chars.clear();
+		self.state.last_keys.clear();
 		self.state.last_nums.clear();
 	}
 
-	pub fn new() -> Self {
-		let trie = CommandTrie::default()
-			.add("q", |_view, _model, cs| cs.exit = true)
-			.add("<C-c>", |_view, _model, cs| cs.exit = true)
-			.add("j", |view, model, cs| {
-				if cs.last_nums.is_empty() {
-					view.next_row(model);
-					return;
-				}
-				view.down_by(cs.get_count_amount(), model);
-			})
-			.add("k", |view, model, cs| {
-				if cs.last_nums.is_empty() {
-					view.previous_row(model);
-					return;
-				}
-				view.up_by(cs.get_count_amount(), model);
-			})
-			.add("h", |view, model, _cs| view.previous_column(model))
-			.add("l", |view, model, _cs| view.next_column(model))
-			.add("i", popup::defaults::insert_action)
-			.add("gg", |view, model, _cs| view.first_row(model))
-			.add("G", |view, model, _cs| view.last_row(model))
-			.add("H", |view, model, _cs| view.previous_sheet(model))
-			.add("L", |view, model, _cs| view.next_sheet(model))
-			.add("J", |view, model, _cs| {
-				let sheet_index = view.selected_sheet;
-				let sheet = view.get_selected_sheet(model);
-				if let Some(row) = view.get_selected_row(sheet) {
-					model.move_transaction_down(sheet_index, row);
-					view.next_row(model);
-				}
-			})
-			.add("K", |view, model, _cs| {
-				let sheet_index = view.selected_sheet;
-				let sheet = view.get_selected_sheet(model);
-				if let Some(row) = view.get_selected_row(sheet) {
-					model.move_transaction_up(sheet_index, row);
-					view.previous_row(model);
-				}
-			})
-			.add("y", |view, model, cs| {
-				let sheet_index = view.selected_sheet;
-				let sheet = view.get_selected_sheet(model);
-				if let Some(row) = view.get_selected_row(sheet) {
-					cs.register = Some(model.copy_row(sheet_index, row));
-				}
-			})
-			.add("d", |view, model, cs| {
-				let sheet_index = view.selected_sheet;
-				let sheet = view.get_selected_sheet(model);
-				if let Some(row) = view.get_selected_row(sheet) {
-					cs.register = Some(model.delete_row(sheet_index, row));
-				}
-			})
-			.add("p", |view, model, cs| {
-				let sheet_index = view.selected_sheet;
-				let sheet = view.get_selected_sheet(model);
-				if let Some(row) = view.get_selected_row(sheet)
-					&& let Some(transaction) = cs.register.clone()
-				{
-					model.insert_row(sheet_index, row + 1, transaction);
-					view.next_row(model);
-				}
-			})
-			.add("P", |view, model, cs| {
-				let sheet_index = view.selected_sheet;
-				let sheet = view.get_selected_sheet(model);
-				if let Some(row) = view.get_selected_row(sheet)
-					&& let Some(transaction) = cs.register.clone()
-				{
-					model.insert_row(sheet_index, row, transaction);
-				}
-			})
-			.add("o", popup::defaults::new_row_below)
-			.add("O", popup::defaults::new_row_above)
-			.add("<C-d>", |view, model, _cs| view.half_down(model))
-			.add("<C-u>", |view, model, _cs| view.half_up(model))
-			.add("<C-t>", |_view, model, _cs| model.create_sheet())
-			.add("<C-r>", popup::defaults::rename_sheet)
-			.add("<C-Del>", popup::defaults::delete_sheet)
-			.add("?", popup::defaults::help);
-		Self {
+	/// Clears the current command, and any pending operator
+	fn cancel_command(&mut self) {
+		self.reset_command();
+		self.state.pending_operator = None;
+	}
+
+	/// Creates the controller with the builtin actions (see [`ActionRegistry::builtin`]), starting
+	/// the worker thread, which sends the results of its jobs to `events`
+	pub fn new(events: Sender<AppEvent>) -> Self {
+		Self::with_registry(events, ActionRegistry::builtin())
+			.expect("The builtin actions are bound to different keys")
+	}
+
+	/// Creates the controller like [`Controller::new`], with the actions of the registry instead
+	/// of the builtin ones
+	///
+	/// # Errors
+	/// If two of the actions are bound to the same keys
+	pub fn with_registry(events: Sender<AppEvent>, registry: ActionRegistry) -> Result<Self> {
+		let trie = registry.into_trie()?;
+		Ok(Self {
+			state: ControllerState {
+				bound_keys: trie.describe_bindings(),
+				worker: Some(Worker::spawn(events)),
+				..Default::default()
+			},
 			commands: trie,
-			..Default::default()
-		}
+			typing_keys: false,
+		})
+	}
+}
+
+/// Handles the keys that move around the net worth dashboard - j/k select a sheet, and
+/// [`KeyCode::Enter`] or [`KeyCode::Esc`] go back to the selected sheet. Returns whether the key
+/// was handled, as every other key does what it does outside of the dashboard
+fn handle_dashboard_key(key_event: &KeyEvent, model: &Model, view: &mut View) -> bool {
+	match key_event.code {
+		KeyCode::Char('j') | KeyCode::Down => view.next_sheet(model),
+		KeyCode::Char('k') | KeyCode::Up => view.previous_sheet(model),
+		KeyCode::Enter | KeyCode::Esc => view.dashboard = false,
+		_ => return false,
 	}
+	true
 }
@@ -1,204 +1,3391 @@
+use std::{fmt::Display, fs, path::Path, sync::Arc};
+
+use anyhow::Context;
+
+use budgeting_core::clock;
 use chrono::{Local, NaiveDate};
 
 use crate::{
+	bank::{BankBridge, BankError, SimpleFin},
 	controller::{
-		ControllerState,
+		Change, ControllerState,
 		popup::{
-			Confirm, ConfirmInner, Info, Input, InputCallback, InputInner, Popup,
-			PopupBehaviour,
+			Compare, CompareInner, Confirm, ConfirmInner, DatePicker, DatePickerInner, Diff,
+			DiffInner, Duplicates, DuplicatesInner, Forecast, ForecastInner, Form, FormInner, Info,
+			Input, InputInner, List, ListInner, Merge, MergeInner, MergeSource, Popup,
+			PopupBehaviour, Progress, ProgressInner, Report, ReportInner, Review, ReviewInner,
+			Start, StartInner, Switcher, SwitcherInner,
 		},
+		tasks::{TaskFinish, TaskProgress},
 	},
-	model::{Model, ParseTransactionMemberError, Transaction},
+	logging,
+	model::{
+		self, Access, Account, AccountType, AmountSign, AuditChange, COLUMN_NAMES, CellEdit,
+		CompoundingPeriod, DuplicateOptions, EntryDefaults, Filter, Grouping, ImportCounts,
+		ImportError, ImportPreset, ImportSettings, InterestSettings, LockOwner, Message, Model,
+		ParseTransactionMemberError, Payee, PendingImport, ReportKind, Rule, SaveError, Scripts,
+		Sort, StatementCycle, Transaction, UpdateError,
+	},
+	release_notes::{self, Release},
+	state,
+	sync::{self, SyncOutcome, SyncState},
 	view::View,
 };
 
-pub fn help(_view: &mut View, _model: &mut Model, cs: &mut ControllerState) {
-	let text = "Keymap help
+/// The error shown when the sheet a popup was opened for has since been deleted
+const SHEET_GONE: &str = "The sheet no longer exists";
+
+/// The help popup, before the list of every key that is bound
+const HELP: &str = "Keymap help
+
+In this window, <j k> scroll, <gg G> go to the top and bottom, and </> searches, with <n N>
+for the next and previous match. Every key that is bound is listed at the bottom
 
 General
     Press <q> to quit.
+    Press <C-s> to save.
+    Press <go> to switch to a recently opened file, asking to save any changes first.
+    Press <gB> to restore a backup of the file, kept from the last few saves.
+    Press <gE> to encrypt the file with a passphrase, asked for when it is opened, or change it.
+    Press <gW> to open a file alongside the current one, and <]w> <[w> to cycle between them.
     Press <?> to open this window.
+    Press <gn> to see what's new in this version.
+    Press <gM> to see the log, which is more detailed when started with --verbose.
     Press <Esc> to close any popup.
         (You can press <q> to close popups without text input, like this one)
+    Text inputs edit like a shell: <C-a C-e> go to the start and end, <A-b A-f> move a word,
+        <C-w C-u C-k> delete the word before, or to the start or end, <C-z> undoes, and <C-v>
+        pastes from the clipboard. <Up Down> go back through what was typed before, apart from
+        passphrases, which are hidden as they are typed and never remembered
 
 Navigation
+    Most commands take a (count) typed before them, e.g. 3j or 2d
+    Typing the start of a longer command, like <g> or <C-w>, lists the keys that can follow it
     (count)[j k]/[↑ ↓] for moving up and down.
-    [h l]/[← →]/[<S-Tab> <Tab>] for moving left and right.
-    [H L]/[<S-←> <S-→>] for moving between sheets.
+    The mouse wheel scrolls, and clicking a row selects it.
+    <gm> - release the mouse so text can be selected and copied, and press again to capture it
+    (count)[h l]/[← →]/[<S-Tab> <Tab>] for moving left and right.
+    (count)[zh zl] for scrolling the columns left and right, when they don't all fit.
+    [za zc zo] for folding and unfolding the group of the selected row under its heading and
+        subtotal, and [zM zR] for all of them, when the rows are grouped (with <gv>)
+    (count)[H L]/[<S-←> <S-→>] for moving between sheets.
     [<C-u> <C-d>]/[<Pgup> <Pgdn>] for scrolling.
-    [gg G]/[<Home> <End>] for moving to first and last rows
+    [gg G]/[<Home> <End>] for moving to first and last rows, or to row (count)
+    <''> goes back to the row jumped from last (with gg, G, a search or a sheet switch), and
+        (count)[<C-o> <C-i>] go back and forth through the rows jumped from in the sheet
+    <m>(letter) marks the row, and <'>(letter) goes back to it, wherever it has been sorted to
+    (count)[[m ]m] for moving to the start of the month (or the one before) and of the next month,
+        and (count)[[W ]W] for weeks, by the dates of the rows
+    [<C-w>v <C-w>s] for showing a second sheet beside or below, <C-w>w for moving between the
+        two panes, and <C-w>q for closing the other one. H and L change the sheet in the focused
+        pane, and <gd> shows the dashboard in it
+
+Filtering
+    </> - search the sheet, showing only the matching rows
+        text - labels or amounts containing the text
+        =23.50 - amounts of exactly 23.50, and =23 for amounts that round to 23
+        ~23.5 - amounts within 1 of 23.5, and ~23.5±2 for amounts within 2
+    <gl> - explore the labels of the sheet, and filter by the selected one
+    <gL> - clear the filter of the sheet
+
+Display
+    <gv> - choose how the sheet is sorted and grouped, and which columns are shown and how wide
+        These, and the filter, are saved with each sheet. The [columns] of the config file set
+        the columns of sheets that haven't chosen their own
+        Striped rows and the row height (compact or comfortable) apply to every sheet, and are
+        kept until the program is closed
+    <gV> - set the type of account the sheet is for, the balance it started from and its number
+        Credit card sheets show what is owed, and imported amounts can be inverted for banks
+        that list charges as positive
+        New rows (<o O>) can start with a category, and have their amounts made negative or
+        positive, e.g. on a sheet of expenses
+
+Summary
+    <gh> - show a summary of the file, including anything that needs attention
+        This is also shown when a file is opened
+    <gw> - review the last week's transactions that are uncategorised, pending, or unusual
+        <c> categorise, <x> mark cleared, <s> split, <n> skip, <k> go back
+    <gc> - toggle whether the selected transaction is pending
+
+Reports
+    <ga> - choose a report of the sheet over a range - its income and expenses by category and
+        by month, or the payees the most was spent on
+        The range is ytd (the default), a year like 2025, or two dates like 2025-04-01..2025-06-30
+        <j k> scroll, <r> report another range, <e> export the report to CSV
+    <gd> - show the net worth of every sheet in place of the sheet, with their recent trend
+        <j k> select a sheet, <enter> open it
+
+Forecast
+    <gt> - toggle whether the selected transaction is scheduled (it has to be dated after today)
+        Scheduled transactions are left out of the balance, and only count towards the projection
+    <gb> - switch the balance under the sheet between the actual and projected balance
+    <gf> - show the projected balance of the sheet, with upcoming transactions marked
+        <m> - (in the forecast) set the minimum balance to be warned about
+        <e> - (in the forecast) export the chart to an .svg or .png file
+
+Credit cards
+    <gs> - show the statements of the sheet, and schedule their payments
+    <gS> - set the statement cycle (closing day, due day and payment sheet) of the sheet
+
+Importing
+    <gI> - import transactions from a CSV, QIF or OFX file
+        Import rules can set the label, category and tags of imported transactions
+    <gu> - edit the import rules
+    <gU> - re-run the import rules on the shown rows of the sheet
+        Transactions that look like ones already in the sheet (same date and amount, similar label)
+        are listed for review first, where each can be skipped, kept, or merged into the existing row
+    <gR> - repeat the last import, with the newest file matching its path
+    <gN> - edit the payees, whose names replace imported labels containing any of their aliases
+        (e.g. Amazon for AMZN MKTP US*2J4), and give the shown rows of the sheet their names
+    <gF> - import the new transactions of the bank account linked to the sheet, through SimpleFIN,
+        listing the accounts to link one if it isn't linked yet. The first time, asks for a setup
+        token from the SimpleFIN bridge to connect to it
+
+Savings and loans
+    <gi> - set the interest rate and compounding period of the sheet
+        Interest is posted automatically at the start of each period, and shown in the forecast
+
+Currencies
+    <gX> - set the currency the sheet is kept in, if it isn't the base currency of the file, and
+        list the exchange rates of the currencies in use, to set one by hand, change the base
+        currency, or fetch the latest rates from the provider set up in the config
+        Sheets in other currencies are converted to the base currency for the net worth total
 
 Manipulation
-    <i> - change the value of the selected cell
-    <y> - yank/copy the current line
-    <d> - delete the current line
-        NOTE: There is currently no undo button.
-    <p> - put/paste the last yanked/deleted line below
-    <P> - put/paste the last yanked/deleted line above
-    <o> - insert new row below
-    <O> - insert new row above
+    <i> - change the value of the selected cell. Dates are picked from a calendar, where <h l>
+        move a day, <j k> a week and <H L> a month, <t> goes to today, and <i> types the date
+        Labels and categories suggest the ones already in use, chosen with <Up Down> and
+        completed with <Tab>
+        Amounts, dates and report ranges show what they will be taken as while being typed
+    <e> - edit every column of the selected row at once, with <Tab> and <S-Tab> between them
+    <y>(motion) - yank/copy the lines from the cursor to where the motion goes, e.g. y2k or yG
+    <d>(motion) - delete the lines from the cursor to where the motion goes, e.g. d3j or dgg
+        Motions are [j k], [gg G] and [<C-u> <C-d>], which all take a (count)
+    (count)<yy> - yank/copy the current line (and the lines below it)
+    (count)<dd> - delete the current line (and the lines below it)
+        NOTE: There is currently no undo button. Deleting more than one line asks first, and
+        confirm_delete in the config asks before deleting a single line too
+    <M>(motion) - move the lines to another sheet, picked from a list, keeping their order, e.g.
+        M2j, or (count)<MM> for the current line (and the lines below it)
+    <gD> - list the lines recently deleted from the sheet, and restore the selected one
+    <gJ> - merge another copy of the file into this one, picking which version of each row that
+        differs to keep. Also offered when the file is changed on disk by something else
+    <gP> - sync the file with its copy on the server set up in the config, uploading or downloading
+        whichever is newer, and merging them if both changed
+    <gC> - compare the sheet with its saved version or another sheet, row by row
+    (count)<gA> - list the changes saved to the file (of the last (count) days), newest first, and
+        go to the row of the selected one. They are kept in an audit log beside the file
+    (count)<p> - put/paste the last yanked/deleted lines below
+    (count)<P> - put/paste the last yanked/deleted lines above
+    (count)<J K> - move the current line down or up
+    <gy> - yank/copy the value of the selected cell
+    (count)<gp> - put/paste the yanked cell value into the selected cell (and the ones below it)
+    <gr> - replace text in the labels of every shown row (respects the filter)
+    <F>(motion) - fill the selected cell's value down over the lines the motion covers, e.g. F3j
+    (count)<FF> - fill the selected cell's value down over the current line and the ones below it
+        Edits of more than one cell are previewed first, and only applied once confirmed with <y>
+    <.> - repeat the last change (delete, paste, move, insert or edit)
+    <o> - insert new row below, picking its date and then typing the rest of it at once
+    <O> - insert new row above, picking its date and then typing the rest of it at once
+    <gO> - toggle between date ordering and manual ordering of the current sheet
+        In date ordering (the default), new and edited rows are moved into place by their date.
+        In manual ordering, rows stay where they are put, and can be moved with <J K>
     <C-t> - create a new sheet
     <C-r> - rename the current sheet
+    <gY> - duplicate the current sheet, as it is, with its amounts cleared, or a month later, to
+        start the next month from the rows of the last one
     <C-Del> - delete the current sheet
         NOTE: This cannot be undone, but there is a confirmation popup
+    <gz> - archive the current sheet, hiding it from the tabs and <H L> while keeping it in the
+        file, the net worth and reports, or bring it back if it is archived
+    <gZ> - list the archived sheets, to bring one back
+    <gk> - show or hide the archived sheets in the tabs
 ";
+
+/// Shows the keys and what they do, followed by every key that is bound
+pub fn help(_view: &mut View, _model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	let text = format!("{HELP}\nAll keys\n{}\n", cs.bound_keys);
 	cs.popup = Some(Info(Box::default()).with_text(text).with_title("Help"));
 }
 
-pub fn insert_action(view: &mut View, model: &mut Model, cs: &mut ControllerState) {
+/// Shows the notes of the running version
+pub fn whats_new(
+	_view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let popup = Info(Box::default()).with_title("What's new");
+	cs.popup = Some(match release_notes::current_release() {
+		Some(release) => release_notes_popup(&[release]),
+		None => popup.with_error("There are no notes for this version"),
+	});
+}
+
+/// Shows the last lines of the log, newest first. See [`logging`]
+pub fn show_log(
+	_view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	/// The amount of lines of the log shown
+	const LOG_LINES: usize = 500;
+
+	let popup = Info(Box::default()).with_title("Log - newest first");
+	let lines = logging::last_lines(LOG_LINES);
+	cs.popup = Some(if lines.is_empty() {
+		popup.with_error("Nothing has been logged yet")
+	} else {
+		let path = logging::log_path().map(|p| p.display().to_string());
+		popup
+			.with_text(lines.join("\n"))
+			.with_subtitle(path.unwrap_or_default())
+	});
+}
+
+/// Creates a popup showing the notes of the given releases, e.g. the ones since the last version
+/// that was run
+pub fn release_notes_popup(releases: &[Release]) -> Popup {
+	Info(Box::default())
+		.with_text(release_notes::describe(releases))
+		.with_title("What's new")
+}
+
+/// Creates the start screen shown when no file is opened, listing the files opened recently
+pub fn start_popup() -> Popup {
+	Start(Box::new(StartInner::new(state::recent_files())))
+		.with_title(format!("budgeting-app {}", env!("CARGO_PKG_VERSION")))
+}
+
+/// Loads the file on the worker thread, returning a popup showing that it is being loaded. Once it
+/// is, it replaces the current one and is recorded as recently opened
+pub fn open_file(path: String, cs: &mut ControllerState) -> Popup {
+	let text = format!("Loading {path}");
+	cs.start_task("Open file", move |_progress| -> TaskFinish {
+		let loaded = Model::new(Some(path.clone()));
+		Box::new(
+			move |view: &mut View, model: &mut Model, _cs| match loaded {
+				Ok(loaded) => {
+					model.replace(loaded);
+					view.reset();
+					state::add_recent_file(Path::new(&path));
+					None
+				}
+				Err(e) => opening_popup(&e, &path, false, Access::Exclusive).or_else(|| {
+					Some(
+						Info(Box::default())
+							.with_title("Open file")
+							.with_error(format!("Couldn't open {path}: {e}")),
+					)
+				}),
+			},
+		)
+	})
+	.with_text(text)
+}
+
+/// Returns the popup asking for what is needed to open the file at the given path, if it couldn't
+/// be opened for want of it: its passphrase, or whether to open it while another instance has it
+/// open. The file is then opened in place of the current one, or alongside it
+pub fn opening_popup(
+	error: &SaveError,
+	path: &str,
+	alongside: bool,
+	access: Access,
+) -> Option<Popup> {
+	match error {
+		SaveError::PassphraseNeeded => Some(unlock_file(path.to_string(), alongside, access)),
+		SaveError::Locked(owner) => Some(file_in_use(path.to_string(), alongside, owner)),
+		_ => None,
+	}
+}
+
+/// Opens the file at the given path in place of the current one, or alongside it
+fn open_now(
+	path: &str,
+	alongside: bool,
+	access: Access,
+	passphrase: Option<&str>,
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+) -> Result<(), SaveError> {
+	let loaded = Model::open(path.to_string(), passphrase, access)?;
+	if alongside {
+		cs.show_alongside(path, loaded, view, model);
+	} else {
+		model.replace(loaded);
+		view.reset();
+		state::add_recent_file(Path::new(path));
+	}
+	Ok(())
+}
+
+/// Returns the name of the file at the given path, without its directory
+fn file_name(path: &str) -> String {
+	Path::new(path).file_name().map_or_else(
+		|| path.to_string(),
+		|name| name.to_string_lossy().to_string(),
+	)
+}
+
+/// Creates an input popup asking for the passphrase of the encrypted file at the given path, which
+/// is opened in place of the current one (or alongside it) once it is right
+fn unlock_file(path: String, alongside: bool, access: Access) -> Popup {
+	let name = file_name(&path);
+	Input(Box::new(
+		InputInner::new("Passphrase", move |popup, passphrase, view, model, cs| {
+			let e = open_now(&path, alongside, access, Some(&passphrase), view, model, cs).err()?;
+			opening_popup(&e, &path, alongside, access)
+				.or_else(|| Some(popup.with_error(e.to_string())))
+		})
+		.masked(),
+	))
+	.with_subtitle(format!("({name} is encrypted)"))
+}
+
+/// Creates a list asking whether to open the file at the given path read-only, as another instance
+/// has it open, or to open it anyway, taking over its lock
+fn file_in_use(path: String, alongside: bool, owner: &LockOwner) -> Popup {
+	let name = file_name(&path);
+	List(Box::new(ListInner::new(
+		"File in use",
+		vec![
+			"Open read-only".to_string(),
+			"Open anyway (whichever saves last overwrites the other)".to_string(),
+			"Cancel".to_string(),
+		],
+		move |_popup, index, view, model, cs| {
+			let access = match index {
+				0 => Access::ReadOnly,
+				1 => Access::TakeOver,
+				_ => return None,
+			};
+			let e = open_now(&path, alongside, access, None, view, model, cs).err()?;
+			opening_popup(&e, &path, alongside, access).or_else(|| {
+				Some(
+					Info(Box::default())
+						.with_title("Open file")
+						.with_error(format!("Couldn't open {path}: {e}")),
+				)
+			})
+		},
+	)))
+	.with_text(format!("{name} is open in {owner}"))
+}
+
+/// Opens an input for a new passphrase to encrypt the file with, or none to stop encrypting it,
+/// first asking for the current one if the file is encrypted
+pub fn change_passphrase(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	if model.encryption().is_none() {
+		cs.popup = Some(new_passphrase_input());
+		return;
+	}
+	cs.popup = Some(
+		Input(Box::new(
+			InputInner::new(
+				"Current passphrase",
+				|popup, passphrase, _view, model: &mut Model, _cs| {
+					if !model.passphrase_matches(&passphrase) {
+						return Some(popup.with_error("Wrong passphrase"));
+					}
+					Some(new_passphrase_input())
+				},
+			)
+			.masked(),
+		))
+		.with_subtitle("(Of the file)"),
+	);
+}
+
+/// Creates an input for the passphrase to encrypt the file with, which is then typed again to make
+/// sure of it
+fn new_passphrase_input() -> Popup {
+	Input(Box::new(
+		InputInner::new(
+			"New passphrase",
+			|_popup, passphrase, _view, model: &mut Model, cs| {
+				if passphrase.is_empty() {
+					if model.encryption().is_some() {
+						return set_passphrase(None, model, cs);
+					}
+					return None;
+				}
+				Some(
+					Input(Box::new(
+						InputInner::new(
+							"Repeat passphrase",
+							move |popup, repeated, _view, model: &mut Model, cs| {
+								if repeated != passphrase {
+									return Some(popup.with_error("The passphrases don't match"));
+								}
+								set_passphrase(Some(&passphrase), model, cs)
+							},
+						)
+						.masked(),
+					))
+					.with_subtitle("(To make sure of it)"),
+				)
+			},
+		)
+		.masked(),
+	))
+	.with_subtitle("(Empty to stop encrypting the file)")
+}
+
+/// Encrypts the file with the passphrase when it is next saved, or stops encrypting it if there is
+/// none. Returns a popup saying why it couldn't be, if it couldn't
+fn set_passphrase(
+	passphrase: Option<&str>,
+	model: &mut Model,
+	cs: &mut ControllerState,
+) -> Option<Popup> {
+	if let Err(e) = model.set_passphrase(passphrase) {
+		return Some(
+			Info(Box::default())
+				.with_title("Passphrase")
+				.with_error(e.to_string()),
+		);
+	}
+	cs.notify(match passphrase {
+		Some(_) => "The file will be encrypted when it is saved",
+		None => "The file will no longer be encrypted when it is saved",
+	});
+	None
+}
+
+/// Opens a picker of the recently opened files (other than the current one) to switch to
+pub fn switcher(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = Some(
+		Switcher(Box::new(SwitcherInner::new(
+			other_recent_files(model),
+			false,
+		)))
+		.with_subtitle("(Type to filter, or a path to open)"),
+	);
+}
+
+/// Opens a picker of the recently opened files (other than the current one) to open alongside the
+/// current one
+pub fn open_alongside(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = Some(
+		Switcher(Box::new(SwitcherInner::new(
+			other_recent_files(model),
+			true,
+		)))
+		.with_title("Open file alongside")
+		.with_subtitle("(Type to filter, or a path to open)"),
+	);
+}
+
+/// Returns the recently opened files, other than the one that is open
+fn other_recent_files(model: &Model) -> Vec<std::path::PathBuf> {
+	let current = model
+		.filename
+		.as_ref()
+		.and_then(|filename| Path::new(filename).canonicalize().ok());
+	state::recent_files()
+		.into_iter()
+		.filter(|file| Some(file) != current.as_ref())
+		.collect()
+}
+
+/// Opens the file in place of the current one, first asking whether to save the current one if
+/// it has unsaved changes. Returns the popup to show next
+pub fn switch_to_file(path: String, model: &Model, cs: &mut ControllerState) -> Popup {
+	if model.has_unsaved_changes() {
+		return unsaved_changes_list(path, model);
+	}
+	open_file(path, cs)
+}
+
+/// Creates a list asking whether to save the current file before opening the one at the given
+/// path. A scratch session can't be saved from here, as it has no file yet
+fn unsaved_changes_list(path: String, model: &Model) -> Popup {
+	let name = Path::new(&path)
+		.file_name()
+		.map_or_else(|| path.clone(), |name| name.to_string_lossy().to_string());
+	let can_save = model.filename.is_some();
+	let mut items = vec![format!("Open {name} without saving"), "Cancel".to_string()];
+	if can_save {
+		items.insert(0, format!("Save, then open {name}"));
+	}
+	let header = match &model.filename {
+		Some(filename) => format!("{filename} has unsaved changes"),
+		None => "The scratch session has unsaved changes".to_string(),
+	};
+	List(Box::new(ListInner::new(
+		"Unsaved changes",
+		items,
+		move |popup, index, _view, model: &mut Model, cs| {
+			let index = if can_save { index } else { index + 1 };
+			if index == 0 {
+				if let Err(e) = model.save(cs.backups) {
+					return Some(popup.with_error(e.to_string()));
+				}
+				if let Some(filename) = &model.filename {
+					state::add_recent_file(Path::new(filename));
+				}
+			}
+			if index == 2 {
+				return None;
+			}
+			Some(open_file(path.clone(), cs))
+		},
+	)))
+	.with_text(header)
+}
+
+/// Creates an input popup asking for the name of a new file, which is opened once submitted. The
+/// file isn't created until it is saved. If it already exists, it is opened instead
+pub fn new_file_input() -> Popup {
+	Input(Box::new(InputInner::new(
+		"New file",
+		|popup, text: String, _view, _model, cs| {
+			let path = text.trim();
+			if path.is_empty() {
+				return Some(popup.with_error("Enter a file name"));
+			}
+			Some(open_file(path.to_string(), cs))
+		},
+	)))
+	.with_subtitle("(File name)")
+}
+
+pub fn insert_action(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) -> anyhow::Result<()> {
 	let sheet_index = view.selected_sheet;
 	let sheet = view.get_selected_sheet(model);
 
 	if let Some((row, col)) = view.get_selected_cell(sheet) {
+		let transaction = sheet
+			.transactions()
+			.get(row)
+			.context("The selected row no longer exists")?;
 		// Get current value of cell
-		let cell_contents = crate::view::get_string_of_transaction_member(
-			sheet
-				.transactions
-				.get(row)
-				.expect("Invalid row from table state"),
-			col,
-		);
+		let cell_contents = crate::view::get_string_of_transaction_member(transaction, col);
+		// Dates are picked from a calendar, which can still switch to typing them
+		if col == 0 {
+			let date = transaction.date;
+			cs.popup = Some(
+				DatePicker(Box::new(DatePickerInner::new(
+					"Insert/Update value",
+					date,
+					move |popup, date, view, model, cs| {
+						edit_cell(sheet_index, row, col, date.to_string(), view, model, cs)
+							.err()
+							.map(|message| popup.with_error(message))
+					},
+				)))
+				.into(),
+			);
+			return Ok(());
+		}
+		// Labels and categories suggest the ones already in use, to keep them consistent
+		let completions = if matches!(col, 1 | 3) {
+			model.values_of(col)
+		} else {
+			vec![]
+		};
 		// This is a popup that will return Some(self) (with some modifications) if the user's
 		// input is not valid/accepted by the model
-		cs.popup = Some(
-			Input(Box::new(InputInner::new(
-				"Insert/Update value",
-				move |popup, text, model| match model.update_transaction_member(
-					sheet_index,
-					row,
-					col,
-					text,
-				) {
-					Ok(()) => None,
-					Err(ParseTransactionMemberError { message }) => Some(popup.with_error(message)),
-				},
-			)))
-			.with_text(cell_contents),
-		);
+		let mut input = InputInner::new(
+			"Insert/Update value",
+			move |popup, text, view, model, cs| {
+				edit_cell(sheet_index, row, col, text, view, model, cs)
+					.err()
+					.map(|message| popup.with_error(message))
+			},
+		)
+		.with_completions(completions);
+		if col == 2 {
+			input = input.with_validator(preview_amount);
+		}
+		cs.popup = Some(Input(Box::new(input)).with_text(cell_contents));
+	}
+	Ok(())
+}
+
+/// Previews a typed date as the day it falls on, for [`InputInner::with_validator`]
+pub fn preview_date(text: &str) -> Result<String, String> {
+	Transaction::parse_date(text.trim())
+		.map(|date| date.format("%A %-d %B %Y").to_string())
+		.map_err(|e| e.message)
+}
+
+/// Previews a typed amount as it will be shown in the sheet, for [`InputInner::with_validator`]
+pub fn preview_amount(text: &str) -> Result<String, String> {
+	Transaction::parse_amount(text.trim())
+		.map(crate::view::format_currency)
+		.map_err(|e| e.message)
+}
+
+/// Sets the cell to the value, moving its row into place if the sheet is ordered by date.
+/// Returns the error message if the value isn't valid for the column
+fn edit_cell(
+	sheet_index: usize,
+	row: usize,
+	col: usize,
+	value: String,
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+) -> Result<(), String> {
+	model
+		.update(Message::SetMember {
+			sheet: sheet_index,
+			row,
+			col,
+			value: value.clone(),
+		})
+		.map_err(|e| e.to_string())?;
+	cs.last_change = Some(Change::Edit { col, value });
+	let message = Message::SettleRow {
+		sheet: sheet_index,
+		row,
+	};
+	if let Some(row) = cs.send(model, message).and_then(|o| o.row()) {
+		view.select_row(row, model);
+	}
+	Ok(())
+}
+
+/// Puts the cell register into the selected cell, and the cells of the same column in the
+/// `count - 1` rows below it. The value is validated by the column it is put into, so e.g. a label
+/// can't be put into the amount column
+pub fn put_cell(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let (Some((_, col)), Some(value)) = (view.get_selected_cell(sheet), cs.cell_register.clone())
+	else {
+		return;
+	};
+	let rows = view.get_selected_rows(sheet, count.unwrap_or(1));
+	match sheet.set_edits(&rows, col, &value) {
+		Ok(edits) => apply_or_preview(sheet_index, edits, model, cs),
+		Err(e) => cs.notify_error(e.message),
 	}
 }
 
-pub fn rename_sheet(view: &mut View, model: &mut Model, cs: &mut ControllerState) {
+/// Deletes the given rows into the register, so they can still be put back with `p`. Deleting
+/// more than one row is confirmed first, as is deleting a single row when the config asks for it
+pub fn delete(view: &mut View, model: &mut Model, cs: &mut ControllerState, rows: &[usize]) {
 	let sheet_index = view.selected_sheet;
+	if rows.len() == 1 && !cs.confirm_delete {
+		delete_rows(sheet_index, rows, model, cs);
+		return;
+	}
+	let prompt = match rows.len() {
+		1 => "Delete this row?".to_string(),
+		len => format!("Delete {len} rows?"),
+	};
+	let rows = rows.to_vec();
 	cs.popup = Some(
-		Input(Box::new(InputInner::new(
-			"Rename sheet",
-			move |_popup, text, model| {
-				let sheet = model
-					.get_sheet_mut(sheet_index)
-					.unwrap_or_else(|| panic!("Couldnt get sheet with index {sheet_index}"));
-				sheet.name = text;
-				None
+		Confirm(Box::new(ConfirmInner::new(
+			"Delete rows",
+			&prompt,
+			move |confirmed, _view, model, cs| {
+				if confirmed {
+					delete_rows(sheet_index, &rows, model, cs);
+				}
 			},
 		)))
-		.with_text(view.get_selected_sheet(model).name.clone()),
+		.into(),
 	);
 }
 
-pub fn delete_sheet(view: &mut View, _model: &mut Model, cs: &mut ControllerState) {
+/// Deletes the rows into the register and the trash, saying how they can be restored
+fn delete_rows(sheet_index: usize, rows: &[usize], model: &mut Model, cs: &mut ControllerState) {
+	let message = Message::DeleteRows {
+		sheet: sheet_index,
+		rows: rows.to_vec(),
+	};
+	let Some(outcome) = cs.send(model, message) else {
+		return;
+	};
+	cs.register = outcome.into_deleted();
+	cs.notify(match cs.register.len() {
+		1 => "Deleted 1 row (gD to restore)".to_string(),
+		len => format!("Deleted {len} rows (gD to restore)"),
+	});
+}
+
+/// A sheet rows can be moved to, listed by its name
+struct MoveTarget {
+	index: usize,
+	name: String,
+	archived: bool,
+}
+
+impl Display for MoveTarget {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.archived {
+			write!(f, "{} (archived)", self.name)
+		} else {
+			write!(f, "{}", self.name)
+		}
+	}
+}
+
+/// Asks which sheet to move the rows to, listing every other sheet, then moves them there
+pub fn move_to_sheet(view: &mut View, model: &mut Model, cs: &mut ControllerState, rows: &[usize]) {
 	let sheet_index = view.selected_sheet;
-	if sheet_index == 0 {
-		cs.popup = Some(Info(Box::default()).with_text("Main sheet cannot be deleted"));
+	let targets: Vec<MoveTarget> = (0..model.sheet_count())
+		.filter(|index| *index != sheet_index)
+		.filter_map(|index| {
+			let sheet = model.get_sheet(index)?;
+			Some(MoveTarget {
+				index,
+				name: sheet.name.clone(),
+				archived: sheet.archived,
+			})
+		})
+		.collect();
+	if targets.is_empty() {
+		cs.popup = Some(Info(Box::default()).with_text("There is no other sheet to move to"));
 		return;
 	}
+	let rows = rows.to_vec();
+	let title = match rows.len() {
+		1 => "Move row to".to_string(),
+		len => format!("Move {len} rows to"),
+	};
 	cs.popup = Some(
-		Confirm(Box::new(ConfirmInner::new(
-			"Delete Sheet",
-			"Are you sure you want to delete this sheet?",
-			move |confirmed, model| {
-				if !confirmed { return; }
-				model.delete_sheet(sheet_index);
+		List(Box::new(ListInner::from_items(
+			&title,
+			targets,
+			move |popup, target: &MoveTarget, _view, model: &mut Model, cs| {
+				let message = Message::MoveRows {
+					sheet: sheet_index,
+					rows: rows.clone(),
+					to: target.index,
+				};
+				if let Err(e) = model.update(message) {
+					return Some(popup.with_error(e.to_string()));
+				}
+				cs.notify(match rows.len() {
+					1 => format!("Moved 1 row to {}", target.name),
+					len => format!("Moved {len} rows to {}", target.name),
+				});
+				None
 			},
 		)))
 		.into(),
 	);
 }
 
-pub fn new_row_below(view: &mut View, model: &mut Model, cs: &mut ControllerState) {
+/// Lists the transactions recently deleted from the selected sheet, newest first, restoring
+/// whichever is selected
+pub fn trash(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) -> anyhow::Result<()> {
+	cs.popup = Some(trash_list(view.selected_sheet, model)?);
+	Ok(())
+}
+
+fn trash_list(sheet_index: usize, model: &Model) -> anyhow::Result<Popup> {
+	let sheet = model.get_sheet(sheet_index).context(SHEET_GONE)?;
+	if sheet.trash.is_empty() {
+		return Ok(Info(Box::default())
+			.with_title("Trash")
+			.with_text("Nothing has been deleted from this sheet"));
+	}
+	let items = sheet
+		.trash
+		.iter()
+		.rev()
+		.map(|trashed| {
+			let t = &trashed.transaction;
+			format!(
+				"{}  {:>12}  {}",
+				t.date,
+				crate::view::format_currency(t.amount),
+				t.label
+			)
+		})
+		.collect();
+	let len = sheet.trash.len();
+	Ok(List(Box::new(ListInner::new(
+		&format!("Trash - {}", sheet.name),
+		items,
+		move |popup, index, view, model, _cs| {
+			let message = Message::RestoreRow {
+				sheet: sheet_index,
+				index: len - 1 - index,
+			};
+			match model.update(message) {
+				Ok(outcome) => view.select_row(outcome.row().unwrap_or_default(), model),
+				Err(e) => return Some(popup.with_error(e.to_string())),
+			}
+			trash_list(sheet_index, model).ok()
+		},
+	)))
+	.with_text(format!("{:<10}  {:>12}  {}", "Date", "Amount", "Label"))
+	.with_subtitle("(Enter to restore)"))
+}
+
+/// Lists what the selected sheet can be compared with - how it was when the file was last saved,
+/// and the other sheets - showing how its transactions differ from the selected one's
+pub fn compare(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) -> anyhow::Result<()> {
 	let sheet_index = view.selected_sheet;
-	let sheet = view.get_selected_sheet(model);
-	let row = view.get_selected_row(sheet).unwrap_or(0);
+	let name = model
+		.get_sheet(sheet_index)
+		.context(SHEET_GONE)?
+		.name
+		.clone();
+	let others: Vec<(usize, String)> = (0..model.sheet_count())
+		.filter(|index| *index != sheet_index)
+		.filter_map(|index| Some((index, model.get_sheet(index)?.name.clone())))
+		.collect();
+	let items = std::iter::once("The saved version of this sheet".to_string())
+		.chain(others.iter().map(|(_, name)| name.clone()))
+		.collect();
 	cs.popup = Some(
-		Input(Box::new(InputInner::new(
-			"Insert row",
-			new_row_date(sheet_index, (row + 1).min(sheet.transactions.len())),
+		List(Box::new(ListInner::new(
+			&format!("Compare {name} with"),
+			items,
+			move |popup, index, _view, model, _cs| {
+				let sheet = model.get_sheet(sheet_index)?;
+				let (other_name, rows) = if index == 0 {
+					let saved = match model.saved_version() {
+						Ok(saved) => saved,
+						Err(e) => return Some(popup.with_error(e.to_string())),
+					};
+					let Some(before) = (0..saved.sheet_count())
+						.filter_map(|i| saved.get_sheet(i))
+						.find(|before| before.name == sheet.name)
+					else {
+						return Some(
+							popup.with_error("The saved version has no sheet by this name"),
+						);
+					};
+					("the saved version", model::compare_sheets(before, sheet))
+				} else {
+					let (other, other_name) = others.get(index - 1)?;
+					let Some(before) = model.get_sheet(*other) else {
+						return Some(popup.with_error(SHEET_GONE));
+					};
+					(other_name.as_str(), model::compare_sheets(before, sheet))
+				};
+				Some(
+					Compare(Box::new(CompareInner::new(rows)))
+						.with_title(format!("Compare {} with {other_name}", sheet.name))
+						.with_subtitle(format!("(+ only here, - only in {other_name}, ~ changed)")),
+				)
+			},
+		)))
+		.with_subtitle("(Enter to compare)"),
+	);
+	Ok(())
+}
+
+/// Lists the changes in the audit log of the file, newest first, or only those of the last
+/// (count) days, to go to the row each one was made to
+pub fn audit_log(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) -> anyhow::Result<()> {
+	model.record_changes();
+	let mut entries = model.audit_entries()?;
+	if let Some(days) = count {
+		let since = clock::now() - chrono::Days::new(days as u64);
+		entries.retain(|entry| entry.time >= since);
+	}
+	if entries.is_empty() {
+		let text = match count {
+			Some(1) => "Nothing has changed in the last day".to_string(),
+			Some(days) => format!("Nothing has changed in the last {days} days"),
+			None => "Nothing has changed yet".to_string(),
+		};
+		cs.popup = Some(Info(Box::default()).with_title("Audit log").with_text(text));
+		return Ok(());
+	}
+	entries.reverse();
+	let sheet_width = entries
+		.iter()
+		.map(|entry| entry.sheet.chars().count())
+		.max()
+		.unwrap_or(0)
+		.max("Sheet".len());
+	let items = entries
+		.iter()
+		.map(|entry| {
+			format!(
+				"{}  {:<sheet_width$}  {}",
+				entry.time.format("%Y-%m-%d %H:%M"),
+				entry.sheet,
+				entry.change
+			)
+		})
+		.collect();
+	cs.popup = Some(
+		List(Box::new(ListInner::new(
+			"Audit log",
+			items,
+			move |popup, index, view, model, _cs| {
+				let entry = &entries[index];
+				let Some((sheet_index, row)) = model.audited_row(entry) else {
+					let error = match entry.change {
+						AuditChange::Deleted { .. } => "That row was deleted",
+						AuditChange::SheetDeleted => "That sheet was deleted",
+						_ => "That row has since changed or been deleted",
+					};
+					return Some(popup.with_error(error));
+				};
+				view.go_to_sheet(sheet_index, model);
+				view.select_row(row, model);
+				None
+			},
+		)))
+		.with_text(format!("{:<16}  {:<sheet_width$}  Change", "Time", "Sheet"))
+		.with_subtitle("(Enter to go to the row)"),
+	);
+	Ok(())
+}
+
+/// Opens a list of the backups of the file, made when it is saved, to restore one from. The
+/// restored sheets replace those shown (asking first if they have unsaved changes), and replace the
+/// file once saved
+pub fn restore_backup(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) -> anyhow::Result<()> {
+	let filename = model
+		.filename
+		.clone()
+		.context("The scratch session has no backups, as it hasn't been saved")?;
+	let backups = model::backups(&filename);
+	anyhow::ensure!(!backups.is_empty(), "{filename} has no backups yet");
+	let items = backups
+		.iter()
+		.map(|(backup, modified)| {
+			let modified: chrono::DateTime<Local> = (*modified).into();
+			format!("{}  {backup}", modified.format("%Y-%m-%d %H:%M"))
+		})
+		.collect();
+	cs.popup = Some(
+		List(Box::new(ListInner::new(
+			"Restore backup",
+			items,
+			move |popup, index, view, model, _cs| {
+				let backup = backups[index].0.clone();
+				let name = Path::new(&backup)
+					.file_name()
+					.map_or_else(|| backup.clone(), |name| name.to_string_lossy().to_string());
+				if !model.has_unsaved_changes() {
+					return restore(&backup, view, model).map(|e| popup.with_error(e));
+				}
+				Some(
+					Confirm(Box::new(ConfirmInner::new(
+						"Restore backup",
+						&format!("Restore {name}, losing the changes?"),
+						move |confirmed, view, model, cs| {
+							if !confirmed {
+								return;
+							}
+							if let Some(e) = restore(&backup, view, model) {
+								cs.notify_error(e);
+							}
+						},
+					)))
+					.into(),
+				)
+			},
 		)))
-		.with_subtitle("(Date - leave blank for today)"),
+		.with_text(format!("{:<16}  Backup", "Made"))
+		.with_subtitle("(Enter to restore, then save to keep it)"),
 	);
+	Ok(())
+}
+
+/// Replaces the sheets with those in the given backup of the file. Returns why they couldn't be,
+/// if they couldn't
+fn restore(backup: &str, view: &mut View, model: &mut Model) -> Option<String> {
+	match model.restored(backup) {
+		Ok(restored) => {
+			model.replace(restored);
+			view.reset();
+			None
+		}
+		Err(e) => Some(format!("Couldn't restore {backup}: {e}")),
+	}
 }
 
-pub fn new_row_above(view: &mut View, model: &mut Model, cs: &mut ControllerState) {
+/// Fills the selected column of the given rows with the value in the selected cell, which is the
+/// row the motion started from, whichever way it went
+pub fn fill_down(view: &mut View, model: &mut Model, cs: &mut ControllerState, rows: &[usize]) {
 	let sheet_index = view.selected_sheet;
 	let sheet = view.get_selected_sheet(model);
-	let row = view.get_selected_row(sheet).unwrap_or(0);
+	if let Some((row, col)) = view.get_selected_cell(sheet) {
+		let source = cs.operator_origin.unwrap_or(row);
+		let edits = sheet.fill_down_edits(source, rows, col);
+		apply_or_preview(sheet_index, edits, model, cs);
+	}
+}
+
+/// Replaces text in the labels of every row shown in the selected sheet, after previewing the
+/// changes
+pub fn replace_labels(
+	view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
 	cs.popup = Some(
 		Input(Box::new(InputInner::new(
-			"Insert row",
-			new_row_date(sheet_index, row),
+			"Replace in labels",
+			move |_popup, from: String, _view, _model, _cs| {
+				Some(
+					Input(Box::new(InputInner::new(
+						"Replace in labels",
+						move |popup, to: String, view: &mut View, model: &mut Model, _cs| {
+							let sheet = view.get_selected_sheet(model);
+							let rows = sheet.displayed_rows();
+							let edits = sheet.replace_label_edits(&rows, &from, &to);
+							if edits.is_empty() {
+								return Some(popup.with_error("No labels would change"));
+							}
+							Some(Diff(Box::new(DiffInner::new(sheet_index, edits))).into())
+						},
+					)))
+					.with_subtitle("(Replace with)"),
+				)
+			},
 		)))
-		.with_subtitle("(Date - leave blank for today)"),
+		.with_subtitle("(Text to find)"),
 	);
 }
 
-fn new_row_date(sheet_index: usize, row: usize) -> Box<InputCallback> {
-	Box::new(move |popup: Popup, text: String, _model: &mut Model| {
-		if text.is_empty() {
-			return Some(
-				Input(Box::new(InputInner::new(
-					"Insert row",
-					new_row_label(
-						sheet_index,
-						row,
-						NaiveDate::from(Local::now().naive_local()),
-					),
-				)))
-				.with_subtitle("(Label)"),
-			);
-		}
-		match Transaction::parse_date(&text) {
-			Ok(date) => Some(
-				Input(Box::new(InputInner::new(
-					"Insert row",
-					new_row_label(sheet_index, row, date),
-				)))
-				.with_subtitle("(Label)"),
-			),
-			Err(ParseTransactionMemberError { message }) => Some(popup.with_error(&message)),
-		}
-	})
+/// Starts the weekly review, which steps through the transactions of the last week in the
+/// selected sheet that are uncategorised, pending, or have an unusual amount
+pub fn review(view: &mut View, model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	let sheet_index = view.selected_sheet;
+	let items = view.get_selected_sheet(model).review_items(clock::today());
+	let Some(first) = items.first() else {
+		cs.popup = Some(Info(Box::default()).with_title("Review").with_text(format!(
+			"Nothing to review in the last {} days",
+			model::REVIEW_DAYS
+		)));
+		return;
+	};
+	view.select_row(first.row, model);
+	cs.popup = Some(
+		Review(Box::new(ReviewInner::new(sheet_index, items))).with_title(format!(
+			"Weekly review - {}",
+			view.get_selected_sheet(model).name
+		)),
+	);
 }
 
-fn new_row_label(sheet_index: usize, row: usize, date: NaiveDate) -> Box<InputCallback> {
-	Box::new(move |_popup, text: String, _model| {
-		let label = text;
-		Some(
-			Input(Box::new(InputInner::new(
-				"Insert row",
-				new_row_amount(sheet_index, row, date, label),
-			)))
-			.with_subtitle("(Amount)"),
+/// Creates an input popup to set the category of the transaction being reviewed, which goes back
+/// to the review once it is set
+pub fn review_category(review: ReviewInner, model: &Model) -> Popup {
+	let current = review.current().transaction.category.clone();
+	let completions = model.values_of(3);
+	Input(Box::new(
+		InputInner::new(
+			"Category",
+			move |_popup, text, view, model: &mut Model, cs| {
+				let review = review.clone();
+				// Categories can be any text, so this only fails if the row is gone
+				let message = Message::SetMember {
+					sheet: review.sheet_index(),
+					row: review.current().row,
+					col: 3,
+					value: text.trim().to_string(),
+				};
+				cs.send(model, message);
+				Some(review.resolve(&model::ReviewReason::Uncategorised, view, model))
+			},
 		)
-	})
+		.with_completions(completions),
+	))
+	.with_text(current)
 }
 
-fn new_row_amount(
-	sheet_index: usize,
-	row: usize,
-	date: NaiveDate,
-	label: String,
-) -> Box<InputCallback> {
-	Box::new(move |popup: Popup, text: String, model: &mut Model| {
-		match Transaction::parse_amount(&text) {
-			Ok(amount) => {
-				let transaction = Transaction {
-					label: label.clone(),
-					date,
-					amount,
-				};
-				model.insert_row(sheet_index, row, transaction);
+/// Creates an input popup to split an amount off the transaction being reviewed into a new row,
+/// which goes back to the review once it is split
+pub fn review_split(review: ReviewInner) -> Popup {
+	Input(Box::new(InputInner::new(
+		"Split",
+		move |popup, text, _view, model: &mut Model, _cs| {
+			let mut review = review.clone();
+			let amount = match Transaction::parse_amount(text.trim()) {
+				Ok(amount) => amount,
+				Err(e) => return Some(popup.with_error(e.message)),
+			};
+			let message = Message::SplitTransaction {
+				sheet: review.sheet_index(),
+				row: review.current().row,
+				amount,
+			};
+			let new_row = match model.update(message) {
+				Ok(outcome) => outcome.row().unwrap_or_default(),
+				Err(e) => return Some(popup.with_error(e.to_string())),
+			};
+			review.split(new_row, model);
+			Some(Review(Box::new(review)).into())
+		},
+	)))
+	.with_subtitle("(Amount to split into a new row)")
+}
+
+/// Lists the import rules. Selecting a rule edits it, and selecting the last item adds a new one
+pub fn rules(_view: &mut View, model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	cs.popup = Some(rules_list(model, 0));
+}
+
+/// Opens the display settings of the selected sheet
+pub fn display_settings(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = Some(display_list(view, model, 0));
+}
+
+/// Creates the list of display settings of the selected sheet, with the setting at the given
+/// index selected. Sorting, grouping and column widths open a list of their options, and columns
+/// are shown or hidden straight away. The stripes and row height are listed last, and apply to
+/// every sheet for the rest of the session
+fn display_list(view: &View, model: &Model, selected: usize) -> Popup {
+	let display = &view.get_selected_sheet(model).display;
+	let mut items = vec![
+		format!(
+			"Sort by: {}",
+			display
+				.sort
+				.map_or_else(|| "stored order".to_string(), |sort| sort.to_string())
+		),
+		format!(
+			"Group by: {}",
+			display
+				.grouping
+				.map_or_else(|| "nothing".to_string(), |grouping| grouping.to_string())
+		),
+	];
+	items.extend(COLUMN_NAMES.iter().enumerate().map(|(col, name)| {
+		let shown = if display.is_hidden(col, &view.columns) {
+			" "
+		} else {
+			"x"
+		};
+		format!("[{shown}] {name}")
+	}));
+	items.push("Column widths".to_string());
+	items.push(format!(
+		"[{}] Striped rows (every sheet)",
+		if view.row_options.striped { "x" } else { " " }
+	));
+	items.push(format!(
+		"Row height: {} (every sheet)",
+		view.row_options.density
+	));
+	let mut popup = ListInner::new(
+		"Display",
+		items,
+		|popup, index, view: &mut View, model: &mut Model, _cs| match index {
+			0 => Some(sort_list(view, model)),
+			1 => Some(grouping_list(view, model)),
+			i if i == COLUMN_NAMES.len() + 2 => Some(width_list(view, model, 0)),
+			i if i == COLUMN_NAMES.len() + 3 => {
+				view.row_options.striped = !view.row_options.striped;
+				Some(display_list(view, model, index))
+			}
+			i if i == COLUMN_NAMES.len() + 4 => {
+				view.row_options.density = view.row_options.density.toggled();
+				Some(display_list(view, model, index))
+			}
+			_ => match model.toggle_column(view.selected_sheet, index - 2, &view.columns) {
+				Ok(true) => Some(display_list(view, model, index)),
+				Ok(false) => Some(popup.with_error("At least one column has to be shown")),
+				Err(e) => Some(popup.with_error(e.to_string())),
+			},
+		},
+	);
+	popup.list_state.select(Some(selected));
+	List(Box::new(popup)).with_subtitle(format!("({})", view.get_selected_sheet(model).name))
+}
+
+/// Creates the list of columns the selected sheet can be sorted by, in either direction
+fn sort_list(view: &View, model: &Model) -> Popup {
+	let sorts: Vec<Option<Sort>> =
+		std::iter::once(None)
+			.chain((0..COLUMN_NAMES.len()).flat_map(|column| {
+				[false, true].map(|descending| Some(Sort { column, descending }))
+			}))
+			.collect();
+	let current = view.get_selected_sheet(model).display.sort;
+	let items = sorts
+		.iter()
+		.map(|sort| sort.map_or_else(|| "Stored order".to_string(), |sort| sort.to_string()))
+		.collect();
+	let selected = sorts.iter().position(|sort| *sort == current).unwrap_or(0);
+	let mut popup = ListInner::new(
+		"Sort by",
+		items,
+		move |popup, index, view: &mut View, model: &mut Model, _cs| {
+			let row = view.get_selected_row(view.get_selected_sheet(model));
+			if let Err(e) = model.set_sort(view.selected_sheet, sorts[index]) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			if let Some(row) = row {
+				view.select_row(row, model);
+			}
+			Some(display_list(view, model, 0))
+		},
+	);
+	popup.list_state.select(Some(selected));
+	List(Box::new(popup)).into()
+}
+
+/// Creates the list of the columns of the selected sheet and their widths, with the column at the
+/// given index selected. Selecting a column edits its width
+fn width_list(view: &View, model: &Model, selected: usize) -> Popup {
+	let display = &view.get_selected_sheet(model).display;
+	let items = COLUMN_NAMES
+		.iter()
+		.enumerate()
+		.map(|(col, name)| {
+			let width = display
+				.column_width(col, &view.columns)
+				.map_or_else(|| "fit".to_string(), |width| width.to_string());
+			format!("{name}: {width}")
+		})
+		.collect();
+	let mut popup = ListInner::new(
+		"Column widths",
+		items,
+		|_popup, index, view: &mut View, model: &mut Model, _cs| {
+			let current = view
+				.get_selected_sheet(model)
+				.display
+				.column_widths
+				.get(&index)
+				.map(ToString::to_string)
+				.unwrap_or_default();
+			Some(width_input(index, current))
+		},
+	);
+	popup.list_state.select(Some(selected));
+	List(Box::new(popup)).with_subtitle(format!("({})", view.get_selected_sheet(model).name))
+}
+
+/// Creates an input popup setting the width of the column with the given index in the selected
+/// sheet, which goes back to the list of widths once it is set
+fn width_input(column: usize, current: String) -> Popup {
+	/// The widest a column can be set to
+	const MAX_WIDTH: u16 = 200;
+	Input(Box::new(InputInner::new(
+		"Column width",
+		move |popup, text, view: &mut View, model: &mut Model, _cs| {
+			let text = text.trim();
+			let width = if text.is_empty() {
 				None
+			} else {
+				match text.parse() {
+					Ok(width) if (1..=MAX_WIDTH).contains(&width) => Some(width),
+					_ => {
+						return Some(popup.with_error(format!(
+							"Invalid width \"{text}\", expected 1 to {MAX_WIDTH}"
+						)));
+					}
+				}
+			};
+			if let Err(e) = model.set_column_width(view.selected_sheet, column, width) {
+				return Some(popup.with_error(e.to_string()));
 			}
-			Err(ParseTransactionMemberError { message }) => Some(popup.with_error(message)),
-		}
-	})
+			Some(width_list(view, model, column))
+		},
+	)))
+	.with_text(current)
+	.with_subtitle(format!("({}, empty for the default)", COLUMN_NAMES[column]))
+}
+
+/// Creates the list of things the rows of the selected sheet can be grouped by
+fn grouping_list(view: &View, model: &Model) -> Popup {
+	let groupings = [None, Some(Grouping::Category), Some(Grouping::Month)];
+	let current = view.get_selected_sheet(model).display.grouping;
+	let items = groupings
+		.iter()
+		.map(|grouping| grouping.map_or_else(|| "Nothing".to_string(), |g| g.to_string()))
+		.collect();
+	let selected = groupings.iter().position(|g| *g == current).unwrap_or(0);
+	let mut popup = ListInner::new(
+		"Group by",
+		items,
+		move |popup, index, view: &mut View, model: &mut Model, _cs| {
+			let row = view.get_selected_row(view.get_selected_sheet(model));
+			if let Err(e) = model.set_grouping(view.selected_sheet, groupings[index]) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			if let Some(row) = row {
+				view.select_row(row, model);
+			}
+			Some(display_list(view, model, 1))
+		},
+	);
+	popup.list_state.select(Some(selected));
+	List(Box::new(popup)).into()
+}
+
+/// Creates the list of rules, with the rule at the given index selected
+fn rules_list(model: &Model, selected: usize) -> Popup {
+	let mut items: Vec<String> = model.rules.rules.iter().map(ToString::to_string).collect();
+	items.push("(Add a new rule)".to_string());
+	let mut popup = ListInner::new(
+		"Rules",
+		items,
+		|_popup, index, _view, model: &mut Model, _cs| {
+			Some(rule_input(index, model.rules.rules.get(index)))
+		},
+	);
+	popup.list_state.select(Some(selected));
+	let subtitle = match &model.rules.path {
+		Some(path) => format!("({})", path.display()),
+		None => "(Not saved - there is no rules file)".to_string(),
+	};
+	List(Box::new(popup))
+		.with_text("Match on contains, regex, min_amount, max_amount - set label, category, tags")
+		.with_subtitle(subtitle)
+}
+
+/// Creates an input popup editing the rule at the given index, which goes back to the list of
+/// rules once it is saved
+fn rule_input(index: usize, current: Option<&Rule>) -> Popup {
+	Input(Box::new(InputInner::new(
+		"Edit rule",
+		move |popup, text, _view, model: &mut Model, _cs| {
+			let rule = if text.trim().is_empty() {
+				None
+			} else {
+				match Rule::parse(&text) {
+					Ok(rule) => Some(rule),
+					Err(e) => return Some(popup.with_error(e.to_string())),
+				}
+			};
+			match model.set_rule(index, rule) {
+				Ok(()) => Some(rules_list(model, index)),
+				Err(e) => Some(popup.with_error(e.to_string())),
+			}
+		},
+	)))
+	.with_subtitle("(Empty to delete)")
+	.with_text(current.map_or_else(
+		|| r#"contains = "", category = """#.to_string(),
+		ToString::to_string,
+	))
+}
+
+/// Lists the payees, to add, edit or delete them, or to give the rows shown in the sheet the names
+/// of their payees
+pub fn payees(view: &mut View, model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	cs.popup = Some(payees_list(view.selected_sheet, model, 0));
+}
+
+/// Creates the list of payees, with the payee at the given index selected. Applying them to the
+/// sheet with the given index is listed last
+fn payees_list(sheet_index: usize, model: &Model, selected: usize) -> Popup {
+	let mut items: Vec<String> = model.payees.iter().map(ToString::to_string).collect();
+	items.push("(Add a new payee)".to_string());
+	items.push("(Give the shown rows of the sheet their payees' names)".to_string());
+	let apply = items.len() - 1;
+	let mut popup = ListInner::new(
+		"Payees",
+		items,
+		move |_popup, index, _view, model: &mut Model, cs| {
+			if index != apply {
+				return Some(payee_input(sheet_index, index, model.payees.get(index)));
+			}
+			let sheet = model.get_sheet(sheet_index)?;
+			let edits = sheet.payee_edits(&sheet.displayed_rows(), &model.payees);
+			if edits.is_empty() {
+				return Some(
+					Info(Box::default())
+						.with_title("Payees")
+						.with_text("No transactions would change"),
+				);
+			}
+			apply_or_preview(sheet_index, edits, model, cs);
+			// The preview of the edits, if there is one, replaces the list
+			cs.popup.take()
+		},
+	);
+	popup.list_state.select(Some(selected));
+	List(Box::new(popup))
+		.with_text("Imported labels containing an alias are given the payee's name")
+		.with_subtitle("(Name = alias, alias, ...)")
+}
+
+/// Creates an input popup editing the payee at the given index, which goes back to the list of
+/// payees once it is saved
+fn payee_input(sheet_index: usize, index: usize, current: Option<&Payee>) -> Popup {
+	Input(Box::new(InputInner::new(
+		"Edit payee",
+		move |popup, text, _view, model: &mut Model, _cs| {
+			let payee = if text.trim().is_empty() {
+				None
+			} else {
+				match Payee::parse(&text) {
+					Ok(payee) => Some(payee),
+					Err(e) => return Some(popup.with_error(e)),
+				}
+			};
+			match model.update(Message::SetPayee { index, payee }) {
+				Ok(_) => Some(payees_list(sheet_index, model, index)),
+				Err(e) => Some(popup.with_error(e.to_string())),
+			}
+		},
+	)))
+	.with_subtitle("(Empty to delete)")
+	.with_text(current.map_or_else(|| "Name = ".to_string(), ToString::to_string))
+}
+
+/// Re-runs the import rules on every row shown in the selected sheet, after previewing the changes
+pub fn rerun_rules(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let rows = sheet.displayed_rows();
+	let edits = sheet.rule_edits(&rows, &model.rules);
+	if edits.is_empty() {
+		cs.popup = Some(
+			Info(Box::default())
+				.with_title("Rules")
+				.with_text("No transactions would change"),
+		);
+		return;
+	}
+	apply_or_preview(sheet_index, edits, model, cs);
+}
+
+/// Runs the command with the given index from the user's scripts on the selected row and the
+/// `count - 1` rows below it, after previewing the changes
+///
+/// # Errors
+/// If the command fails
+pub fn run_script_command(
+	index: usize,
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	count: Option<usize>,
+) -> anyhow::Result<()> {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let rows = view.get_selected_rows(sheet, count.unwrap_or(1));
+	let edits = sheet.script_edits(&rows, &model.scripts, index)?;
+	if edits.is_empty() {
+		cs.notify("Nothing was changed");
+		return Ok(());
+	}
+	apply_or_preview(sheet_index, edits, model, cs);
+	Ok(())
+}
+
+/// Applies the edits to the sheet with the given index straight away if there is only one, or
+/// opens a preview of them that has to be confirmed if there are more
+fn apply_or_preview(
+	sheet_index: usize,
+	edits: Vec<CellEdit>,
+	model: &mut Model,
+	cs: &mut ControllerState,
+) {
+	if edits.len() > 1 {
+		cs.popup = Some(Diff(Box::new(DiffInner::new(sheet_index, edits))).into());
+	} else {
+		cs.send(
+			model,
+			Message::ApplyEdits {
+				sheet: sheet_index,
+				edits,
+			},
+		);
+	}
+}
+
+/// Saves the model to its file, first asking for a file name if it doesn't have one yet
+pub fn save(_view: &mut View, model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	if model.filename.is_some() {
+		cs.popup = save_file(model, cs);
+		return;
+	}
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Save",
+			|popup, text: String, _view, model: &mut Model, cs| {
+				if text.trim().is_empty() {
+					return Some(popup.with_error("Enter a file name"));
+				}
+				if let Err(e) = model.set_filename(text) {
+					return Some(popup.with_error(e.to_string()));
+				}
+				save_file(model, cs)
+			},
+		)))
+		.with_subtitle("(File name)"),
+	);
+}
+
+/// Saves the model, writing the file on the worker thread, and saying where to once it has been
+/// written. Returns a popup saying why it couldn't be saved, if it couldn't be
+fn save_file(model: &Model, cs: &mut ControllerState) -> Option<Popup> {
+	let popup = Info(Box::default()).with_title("Save");
+	let (filename, text) = match model.save_contents() {
+		Ok(contents) => contents,
+		Err(e) => return Some(popup.with_error(e.to_string())),
+	};
+	let sheets = match model.sheet_count() {
+		1 => "1 sheet".to_string(),
+		count => format!("{count} sheets"),
+	};
+	let backups = cs.backups;
+	let encryption = model.encryption().cloned();
+	cs.disk.saving += 1;
+	cs.run_in_background("Save", move || -> TaskFinish {
+		let result = Model::write_save(&filename, &text, backups, encryption.as_ref());
+		Box::new(move |_view, model: &mut Model, cs: &mut ControllerState| {
+			cs.disk.saving -= 1;
+			match result {
+				Ok(()) => {
+					state::add_recent_file(Path::new(&filename));
+					match model.saved_as(&filename, text) {
+						Ok(()) => cs.notify(format!("Saved {sheets} to {filename}")),
+						Err(e) => cs.notify_error(format!("Saved {sheets} to {filename}. {e}")),
+					}
+					None
+				}
+				Err(e) => Some(
+					Info(Box::default())
+						.with_title("Save")
+						.with_error(e.to_string()),
+				),
+			}
+		})
+	});
+	None
+}
+
+/// Creates a list asking what to do about the file having been changed on disk by something else,
+/// as read by [`Model::read_disk`]: reload it, keep the version being shown, or see what changed
+pub fn disk_changed(model: &Model, disk: &Model) -> Popup {
+	let changes = model.disk_changes(disk);
+	let name = model
+		.filename
+		.as_deref()
+		.map_or_else(String::new, file_name);
+	let reload = if model.has_unsaved_changes() {
+		"Reload it, discarding the unsaved changes here"
+	} else {
+		"Reload it"
+	};
+	let items = vec![
+		reload.to_string(),
+		"Keep this version, to save over it".to_string(),
+		"Merge them, picking which version of each row to keep".to_string(),
+		match changes.len() {
+			1 => "See what changed (1 difference)".to_string(),
+			count => format!("See what changed ({count} differences)"),
+		},
+	];
+	List(Box::new(ListInner::new(
+		"File changed on disk",
+		items,
+		|popup, index, view, model: &mut Model, _cs| {
+			let disk = match model.read_disk() {
+				Ok(Some(disk)) => disk,
+				Ok(None) => return None,
+				Err(e) => return Some(popup.with_error(e.to_string())),
+			};
+			match index {
+				0 => {
+					model.reload(disk);
+					view.reset();
+					None
+				}
+				1 => {
+					model.keep_over_disk(disk);
+					None
+				}
+				2 => Some(merge_popup(
+					model,
+					&disk,
+					"the file on disk",
+					MergeSource::Disk,
+				)),
+				_ => Some(disk_changes_list(model, &disk)),
+			}
+		},
+	)))
+	.with_text(format!(
+		"{name} was changed by something else since it was opened or saved"
+	))
+}
+
+/// Opens an input for the path of another copy of the file, e.g. one left by a sync service, to
+/// merge into this one row by row
+pub fn merge_file(
+	_view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Merge file",
+			|popup, text: String, _view, model, _cs| {
+				let path = text.trim();
+				if path.is_empty() {
+					return Some(popup.with_error("Enter a file name"));
+				}
+				Some(
+					load_merge_file(path.to_string(), None, model)
+						.unwrap_or_else(|e| popup.with_error(e)),
+				)
+			},
+		)))
+		.with_subtitle("(Path of the other copy)"),
+	);
+}
+
+/// Syncs the file with its copy on the server set up in the config, on the worker thread. The
+/// copy is downloaded over the file if only it changed, and merged into it if both did. See
+/// [`sync`]
+pub fn sync_file(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let popup = Info(Box::default()).with_title("Sync");
+	let Some(remote) = cs.sync.clone() else {
+		cs.popup = Some(popup.with_error("Set up syncing in the [sync] table of the config file"));
+		return;
+	};
+	let Some(filename) = model.filename.clone() else {
+		cs.popup = Some(popup.with_error("The scratch session can't be synced, as it isn't saved"));
+		return;
+	};
+	if model.has_unsaved_changes() || cs.disk.saving > 0 {
+		cs.popup = Some(popup.with_error("Save the file before syncing it"));
+		return;
+	}
+	let backups = cs.backups;
+	let text = format!("Syncing with {}", remote.url);
+	cs.popup = Some(
+		cs.start_task("Sync", move |_progress| -> TaskFinish {
+			let path = Path::new(&filename);
+			let synced = fs::read_to_string(path)
+				.map_err(|e| e.to_string())
+				.and_then(|local| {
+					remote
+						.sync(&local, state::sync_state(path).as_ref())
+						.map_err(|e| e.to_string())
+				});
+			// A newer copy is written on this thread, like saves are, and then loaded
+			let synced = match synced {
+				Ok(SyncOutcome::Pulled(copy)) => {
+					Model::write_save(&filename, &copy.text, backups, None)
+						.map(|()| SyncOutcome::Pulled(copy))
+						.map_err(|e| e.to_string())
+				}
+				synced => synced,
+			};
+			Box::new(move |view, model: &mut Model, cs| {
+				finish_sync(synced, Path::new(&filename), view, model, cs)
+			})
+		})
+		.with_text(text),
+	);
+}
+
+/// Records what syncing did, loading the copy it downloaded or merging the one it conflicted with
+fn finish_sync(
+	synced: Result<SyncOutcome, String>,
+	path: &Path,
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+) -> Option<Popup> {
+	let popup = Info(Box::default()).with_title("Sync");
+	match synced {
+		Ok(SyncOutcome::InSync(synced)) => {
+			state::set_sync_state(path, &synced);
+			cs.notify("The file is the same as its copy on the server");
+			None
+		}
+		Ok(SyncOutcome::Pushed(synced)) => {
+			state::set_sync_state(path, &synced);
+			cs.notify("Uploaded the file to the server");
+			None
+		}
+		Ok(SyncOutcome::Pulled(copy)) => {
+			let synced = SyncState {
+				hash: sync::content_hash(&copy.text),
+				etag: copy.etag,
+			};
+			state::set_sync_state(path, &synced);
+			match model.read_disk() {
+				Ok(Some(disk)) => {
+					model.reload(disk);
+					view.reset();
+				}
+				Ok(None) => {}
+				Err(e) => return Some(popup.with_error(e.to_string())),
+			}
+			cs.notify("Downloaded the newer copy from the server");
+			None
+		}
+		Ok(SyncOutcome::Conflict(copy)) => match model.read_copy(&copy.text) {
+			Ok(theirs) => Some(merge_popup(
+				model,
+				&theirs,
+				"the server",
+				MergeSource::Remote { etag: copy.etag },
+			)),
+			Err(e) => Some(popup.with_error(format!("Couldn't read the copy on the server: {e}"))),
+		},
+		Err(e) => Some(popup.with_error(e)),
+	}
+}
+
+/// Reads the other copy of the file at the given path, without locking it, and creates the popup
+/// merging it, or asking for its passphrase if it is encrypted
+fn load_merge_file(path: String, passphrase: Option<&str>, model: &Model) -> Result<Popup, String> {
+	let theirs = match Model::load(path.clone(), passphrase, Access::ReadOnly) {
+		Ok(theirs) => theirs,
+		Err(SaveError::PassphraseNeeded) => {
+			let name = file_name(&path);
+			return Ok(Input(Box::new(
+				InputInner::new("Passphrase", move |popup, passphrase, _view, model, _cs| {
+					Some(
+						load_merge_file(path.clone(), Some(&passphrase), model)
+							.unwrap_or_else(|e| popup.with_error(e)),
+					)
+				})
+				.masked(),
+			))
+			.with_subtitle(format!("({name} is encrypted)")));
+		}
+		Err(e) => return Err(format!("Couldn't read {path}: {e}")),
+	};
+	Ok(merge_popup(
+		model,
+		&theirs,
+		&file_name(&path),
+		MergeSource::File,
+	))
+}
+
+/// Creates the popup picking which versions of the rows that differ from the other copy of the
+/// file are kept
+fn merge_popup(model: &Model, theirs: &Model, name: &str, source: MergeSource) -> Popup {
+	let merges = model.merge_rows(theirs);
+	if merges.is_empty() {
+		return Info(Box::default())
+			.with_title("Merge")
+			.with_text(format!("No transactions differ from {name}"));
+	}
+	Merge(Box::new(MergeInner::new(merges, source)))
+		.with_title(format!("Merge with {name}"))
+		.with_subtitle(format!("(+ only here, - only in {name}, ~ changed)"))
+}
+
+/// Creates a list of the transactions and sheets that differ between the model and the file on
+/// disk, going back to asking what to do about them once closed
+fn disk_changes_list(model: &Model, disk: &Model) -> Popup {
+	let mut items: Vec<String> = model
+		.disk_changes(disk)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	if items.is_empty() {
+		items.push("Only the settings of the file changed".to_string());
+	}
+	List(Box::new(ListInner::new(
+		"Changes on disk",
+		items,
+		|popup, _index, _view, model: &mut Model, _cs| match model.read_disk() {
+			Ok(Some(disk)) => Some(disk_changed(model, &disk)),
+			Ok(None) => None,
+			Err(e) => Some(popup.with_error(e.to_string())),
+		},
+	)))
+	.with_subtitle("(+ only on disk, - only here. Enter to go back)")
+}
+
+pub fn label_explorer(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let summaries = view.get_selected_sheet(model).label_summary();
+	let width = summaries
+		.iter()
+		.map(|s| s.label.chars().count())
+		.max()
+		.unwrap_or(0)
+		.max("Label".len());
+	let items = summaries
+		.iter()
+		.map(|s| {
+			format!(
+				"{:<width$}  {:>5}  {:>12}",
+				s.label,
+				s.count,
+				crate::view::format_currency(s.total)
+			)
+		})
+		.collect();
+	let labels: Vec<String> = summaries.into_iter().map(|s| s.label).collect();
+	cs.popup = Some(
+		List(Box::new(ListInner::new(
+			"Labels",
+			items,
+			move |_popup, index, view, model, _cs| {
+				view.set_filter(Some(Filter::Label(labels[index].clone())), model);
+				None
+			},
+		)))
+		.with_text(format!(
+			"{:<width$}  {:>5}  {:>12}",
+			"Label", "Count", "Total"
+		))
+		.with_subtitle("(Enter to filter)"),
+	);
+}
+
+pub fn search(view: &mut View, model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	let current = match view.get_filter(model) {
+		Some(Filter::Text(text)) => text.clone(),
+		_ => String::new(),
+	};
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Search",
+			move |popup, text, view, model, _cs| {
+				if text.trim().is_empty() {
+					view.set_filter(None, model);
+					return None;
+				}
+				match Filter::parse_search(&text) {
+					Ok(filter) => {
+						view.set_filter(Some(filter), model);
+						None
+					}
+					Err(ParseTransactionMemberError { message }) => Some(popup.with_error(message)),
+				}
+			},
+		)))
+		.with_subtitle("(text, =amount, or ~amount±tolerance)")
+		.with_text(current),
+	);
+}
+
+/// Shows a summary of the whole file: how much is in it, and anything that needs attention
+pub fn health(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let health = model.health(clock::today());
+	let mut lines = vec![
+		format!(
+			"{} sheets, {} transactions",
+			health.sheets, health.transactions
+		),
+		format!("{} uncategorised transactions", health.uncategorised),
+	];
+	if health.overdue.is_empty() {
+		lines.push("No overdue statements".to_string());
+	} else {
+		lines.push(format!("{} overdue statements:", health.overdue.len()));
+		lines.extend(
+			health
+				.overdue
+				.iter()
+				.map(|(sheet, due)| format!("    {sheet} - due {due}")),
+		);
+	}
+	cs.popup = Some(
+		Info(Box::default())
+			.with_title("Summary")
+			.with_text(lines.join("\n")),
+	);
+}
+
+/// Marks the selected transaction as scheduled, or as having happened if it already is. Only
+/// transactions dated after today can be scheduled
+pub fn toggle_scheduled(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let Some(row) = view.get_selected_row(sheet) else {
+		return;
+	};
+	let transaction = &sheet.transactions()[row];
+	let today = clock::today();
+	if !transaction.scheduled && transaction.date <= today {
+		cs.notify_error("Only transactions dated after today can be scheduled");
+		return;
+	}
+	let message = Message::SetScheduled {
+		sheet: sheet_index,
+		row,
+		scheduled: !transaction.scheduled,
+	};
+	cs.send(model, message);
+}
+
+pub fn forecast(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) -> anyhow::Result<()> {
+	cs.popup = Some(forecast_popup(view.selected_sheet, model)?);
+	Ok(())
+}
+
+/// Creates a forecast popup for the sheet with the given index, including a warning if the
+/// projected balance goes below the sheet's minimum balance
+fn forecast_popup(sheet_index: usize, model: &Model) -> anyhow::Result<Popup> {
+	let sheet = model.get_sheet(sheet_index).context(SHEET_GONE)?;
+	let forecast = sheet.forecast(clock::today());
+	let warning = sheet
+		.min_balance
+		.and_then(|min| forecast.first_dip_below(min))
+		.map(|(date, balance)| {
+			format!(
+				"Projected balance dips to {} on {date}",
+				crate::view::format_currency(balance)
+			)
+		});
+	let popup = Forecast(Box::new(ForecastInner::new(
+		sheet_index,
+		forecast,
+		sheet.min_balance,
+	)))
+	.with_title(format!("Forecast - {}", sheet.name))
+	.with_subtitle("(m to set minimum balance, e to export)");
+	Ok(match warning {
+		Some(warning) => popup.with_error(warning),
+		None => popup,
+	})
+}
+
+/// Creates an input popup asking for the file to export a forecast chart to. The format is picked
+/// by the extension, which can be `.svg` or `.png`
+pub fn export_chart_input(
+	forecast: model::Forecast,
+	min_balance: Option<f64>,
+	title: String,
+) -> Popup {
+	let filename = format!(
+		"{}.svg",
+		title
+			.to_lowercase()
+			.split(|c: char| !c.is_alphanumeric())
+			.filter(|w| !w.is_empty())
+			.collect::<Vec<_>>()
+			.join("-")
+	);
+	Input(Box::new(InputInner::new(
+		"Export chart",
+		move |_popup, text, _view, _model, cs| {
+			let path = text.trim().to_string();
+			let (forecast, title) = (forecast.clone(), title.clone());
+			let popup = cs.start_task("Export chart", move |_progress| -> TaskFinish {
+				let result =
+					crate::view::export_forecast(&forecast, min_balance, &title, Path::new(&path));
+				Box::new(
+					move |_view, _model, cs: &mut ControllerState| match result {
+						Ok(()) => {
+							cs.notify(format!("Exported chart to {path}"));
+							None
+						}
+						// Asks again, so another file can be tried
+						Err(e) => Some(
+							export_chart_input(forecast, min_balance, title)
+								.with_text(path)
+								.with_error(e.to_string()),
+						),
+					},
+				)
+			});
+			Some(popup.with_text(format!("Drawing {}", text.trim())))
+		},
+	)))
+	.with_subtitle("(File - .svg or .png)")
+	.with_text(filename)
+}
+
+/// Opens the menu of reports, which asks for the range of the chosen one
+pub fn report(
+	_view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = Some(
+		List(Box::new(ListInner::from_items(
+			"Reports",
+			ReportKind::ALL.to_vec(),
+			|_popup, kind, _view, _model, _cs| Some(report_range_input(*kind)),
+		)))
+		.into(),
+	);
+}
+
+/// Creates an input popup asking for the range to report on, which shows that kind of report of
+/// the selected sheet once submitted. See [`model::Report::parse_range`]
+pub fn report_range_input(kind: ReportKind) -> Popup {
+	Input(Box::new(
+		InputInner::new(
+			&kind.to_string(),
+			move |popup, text, view: &mut View, model: &mut Model, _cs| {
+				let today = clock::today();
+				match model::Report::parse_range(&text, today) {
+					Ok((start, end)) => {
+						let sheet = view.get_selected_sheet(model);
+						Some(
+							Report(Box::new(ReportInner::new(sheet.report(start, end), kind)))
+								.with_title(format!("{kind} - {}", sheet.name))
+								.with_subtitle(format!("({start} to {end})")),
+						)
+					}
+					Err(ParseTransactionMemberError { message }) => Some(popup.with_error(message)),
+				}
+			},
+		)
+		.with_validator(|text| {
+			let today = clock::today();
+			model::Report::parse_range(text, today)
+				.map(|(start, end)| format!("{start} to {end}"))
+				.map_err(|e| e.message)
+		}),
+	))
+	.with_subtitle("(ytd, a year, or start..end)")
+	.with_text("ytd")
+}
+
+/// Creates an input popup asking for the file to export a report to, as CSV
+pub fn export_report_input(report: model::Report, title: &str) -> Popup {
+	let filename = format!(
+		"{}-{}-{}.csv",
+		title
+			.to_lowercase()
+			.split(|c: char| !c.is_alphanumeric())
+			.filter(|w| !w.is_empty())
+			.collect::<Vec<_>>()
+			.join("-"),
+		report.start,
+		report.end
+	);
+	Input(Box::new(InputInner::new(
+		"Export report",
+		move |popup, text, _view, _model, cs| {
+			let path = text.trim();
+			match crate::view::export_report_csv(&report, Path::new(path)) {
+				Ok(()) => {
+					cs.notify(format!("Exported report to {path}"));
+					None
+				}
+				Err(e) => Some(popup.with_error(e.to_string())),
+			}
+		},
+	)))
+	.with_subtitle("(File)")
+	.with_text(filename)
+}
+
+/// Creates an input popup to set the minimum balance of a sheet, which goes back to the forecast
+/// once submitted
+pub fn min_balance_input(sheet_index: usize, current: Option<f64>) -> Popup {
+	Input(Box::new(
+		InputInner::new("Minimum balance", move |popup, text, _view, model, _cs| {
+			let min_balance = if text.trim().is_empty() {
+				None
+			} else {
+				match Transaction::parse_amount(text.trim()) {
+					Ok(amount) => Some(amount),
+					Err(ParseTransactionMemberError { message }) => {
+						return Some(popup.with_error(message));
+					}
+				}
+			};
+			let message = Message::SetMinBalance {
+				sheet: sheet_index,
+				min_balance,
+			};
+			if let Err(e) = model.update(message) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			forecast_popup(sheet_index, model).ok()
+		})
+		.with_validator(preview_amount),
+	))
+	.with_subtitle("(leave blank for none)")
+	.with_text(current.map(|m| m.to_string()).unwrap_or_default())
+}
+
+pub fn statements(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	if sheet.statement_cycle.is_none() {
+		configure_statement_cycle(view, model, cs, None);
+		return;
+	}
+	let statements = sheet.statements();
+	let items = statements
+		.iter()
+		.map(|s| {
+			format!(
+				"{} - {}  {}  {:>5}  {:>12}",
+				s.start,
+				s.close,
+				s.due,
+				s.count,
+				crate::view::format_currency(s.balance)
+			)
+		})
+		.collect();
+	cs.popup = Some(
+		List(Box::new(ListInner::new(
+			&format!("Statements - {}", sheet.name),
+			items,
+			move |popup, index, _view, model, _cs| {
+				let statement = &statements[index];
+				match model.schedule_statement_payment(sheet_index, statement, clock::today()) {
+					Ok(()) => Some(Info(Box::default()).with_title("Statements").with_text(
+						format!(
+							"Scheduled a payment of {} on {}",
+							crate::view::format_currency(-statement.balance),
+							statement.due
+						),
+					)),
+					Err(e) => Some(popup.with_error(e.to_string())),
+				}
+			},
+		)))
+		.with_text(format!(
+			"{:<23}  {:<10}  {:>5}  {:>12}",
+			"Period", "Due", "Count", "Balance"
+		))
+		.with_subtitle("(Enter to schedule payment)"),
+	);
+}
+
+/// Sets up the statement cycle of the selected sheet, asking for the closing day, due day, and
+/// the sheet to pay statements from, in that order
+pub fn configure_statement_cycle(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let current = view.get_selected_sheet(model).statement_cycle.clone();
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Statement cycle",
+			move |popup, text, _view, _model, _cs| match parse_day(&text) {
+				Ok(closing_day) => Some(statement_due_day(sheet_index, closing_day)),
+				Err(message) => Some(popup.with_error(message)),
+			},
+		)))
+		.with_subtitle("(Closing day of the month)")
+		.with_text(
+			current
+				.map(|c| c.closing_day.to_string())
+				.unwrap_or_default(),
+		),
+	);
+}
+
+fn statement_due_day(sheet_index: usize, closing_day: u32) -> Popup {
+	Input(Box::new(InputInner::new(
+		"Statement cycle",
+		move |popup, text, _view, _model, _cs| match parse_day(&text) {
+			Ok(due_day) => Some(
+				Input(Box::new(InputInner::new(
+					"Statement cycle",
+					move |popup, text, _view, model: &mut Model, _cs| {
+						let payment_sheet = if text.trim().is_empty() {
+							None
+						} else if model.sheet_titles().contains(&text) {
+							Some(text)
+						} else {
+							return Some(popup.with_error(format!("No sheet named \"{text}\"")));
+						};
+						let message = Message::SetStatementCycle {
+							sheet: sheet_index,
+							cycle: Some(StatementCycle {
+								closing_day,
+								due_day,
+								payment_sheet,
+							}),
+						};
+						model
+							.update(message)
+							.err()
+							.map(|e| popup.with_error(e.to_string()))
+					},
+				)))
+				.with_subtitle("(Sheet to pay from - leave blank for none)"),
+			),
+			Err(message) => Some(popup.with_error(message)),
+		},
+	)))
+	.with_subtitle("(Due day of the month)")
+}
+
+/// Parses a day of the month, from 1 to 31
+fn parse_day(text: &str) -> Result<u32, String> {
+	match text.trim().parse::<u32>() {
+		Ok(day) if (1..=31).contains(&day) => Ok(day),
+		_ => Err("Day must be a number from 1 to 31".to_string()),
+	}
+}
+
+/// Sets up the interest of the selected sheet, asking for the annual rate and then the
+/// compounding period. Interest is accrued from today, unless it was already set up before
+pub fn configure_interest(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let current = view.get_selected_sheet(model).interest.clone();
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Interest",
+			move |popup, text, _view, model: &mut Model, _cs| {
+				if text.trim().is_empty() {
+					let message = Message::SetInterest {
+						sheet: sheet_index,
+						interest: None,
+					};
+					return model
+						.update(message)
+						.err()
+						.map(|e| popup.with_error(e.to_string()));
+				}
+				match Transaction::parse_amount(text.trim()) {
+					Ok(rate) => Some(interest_period(sheet_index, rate, model)),
+					Err(ParseTransactionMemberError { message }) => Some(popup.with_error(message)),
+				}
+			},
+		)))
+		.with_subtitle("(Annual rate in % - leave blank for none)")
+		.with_text(current.map(|c| c.rate.to_string()).unwrap_or_default()),
+	);
+}
+
+fn interest_period(sheet_index: usize, rate: f64, model: &Model) -> Popup {
+	let current = model
+		.get_sheet(sheet_index)
+		.and_then(|s| s.interest.as_ref())
+		.map(|c| c.period.to_string())
+		.unwrap_or_default();
+	Input(Box::new(InputInner::new(
+		"Interest",
+		move |popup, text, _view, model: &mut Model, _cs| match text.parse::<CompoundingPeriod>() {
+			Ok(period) => {
+				let today = clock::today();
+				let last_posted = model
+					.get_sheet(sheet_index)
+					.and_then(|s| s.interest.as_ref())
+					.and_then(|i| i.last_posted)
+					.or(Some(today));
+				let message = Message::SetInterest {
+					sheet: sheet_index,
+					interest: Some(InterestSettings {
+						rate,
+						period,
+						last_posted,
+					}),
+				};
+				if let Err(e) = model.update(message) {
+					return Some(popup.with_error(e.to_string()));
+				}
+				model.accrue_interest(today);
+				None
+			}
+			Err(message) => Some(popup.with_error(message)),
+		},
+	)))
+	.with_subtitle("(Compounding period - monthly, quarterly or yearly)")
+	.with_text(current)
+}
+
+/// Opens the settings of the account the selected sheet is for, and of the rows entered into it
+pub fn sheet_settings(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = sheet_settings_list(view.selected_sheet, model, 0);
+}
+
+/// Creates the list of account settings of the sheet, with the setting at the given index
+/// selected, followed by what is filled in for new rows. The type opens a list of types, the
+/// starting balance, account number and category of new rows are typed in, and inverting
+/// imported amounts and the sign of new amounts change straight away. Returns [`None`] if the sheet
+/// doesn't exist
+fn sheet_settings_list(sheet_index: usize, model: &Model, selected: usize) -> Option<Popup> {
+	let sheet = model.get_sheet(sheet_index)?;
+	let account = sheet.account();
+	let items = vec![
+		format!("Account type: {}", account.kind),
+		format!(
+			"Starting balance: {}",
+			crate::view::format_currency(account.starting_balance)
+		),
+		format!(
+			"Account number: {}",
+			account.number.as_deref().unwrap_or("none")
+		),
+		format!(
+			"[{}] Invert imported amounts",
+			if account.invert_imports { "x" } else { " " }
+		),
+		format!("Sign of new amounts: {}", sheet.entry_defaults.sign),
+		format!(
+			"Category of new rows: {}",
+			Some(sheet.entry_defaults.category.as_str())
+				.filter(|category| !category.is_empty())
+				.unwrap_or("none")
+		),
+	];
+	let mut popup = ListInner::new(
+		"Sheet settings",
+		items,
+		move |popup, index, _view, model: &mut Model, _cs| match index {
+			0 => Some(account_type_list(sheet_index, model)),
+			1 => Some(starting_balance_input(sheet_index, model)),
+			2 => Some(account_number_input(sheet_index, model)),
+			3 => match edit_account(sheet_index, model, |account| {
+				account.invert_imports = !account.invert_imports;
+			}) {
+				Ok(()) => sheet_settings_list(sheet_index, model, index),
+				Err(e) => Some(popup.with_error(e)),
+			},
+			4 => match edit_entry_defaults(sheet_index, model, |defaults| {
+				let next = AmountSign::ALL
+					.iter()
+					.position(|sign| *sign == defaults.sign)
+					.map_or(0, |position| (position + 1) % AmountSign::ALL.len());
+				defaults.sign = AmountSign::ALL[next];
+			}) {
+				Ok(()) => sheet_settings_list(sheet_index, model, index),
+				Err(e) => Some(popup.with_error(e)),
+			},
+			_ => Some(default_category_input(sheet_index, model)),
+		},
+	);
+	popup.list_state.select(Some(selected));
+	Some(List(Box::new(popup)).with_subtitle(format!("({})", sheet.name)))
+}
+
+/// Changes the account the sheet is for
+fn edit_account(
+	sheet_index: usize,
+	model: &mut Model,
+	edit: impl FnOnce(&mut Account),
+) -> Result<(), String> {
+	let mut account = model
+		.get_sheet(sheet_index)
+		.ok_or(SHEET_GONE)?
+		.account()
+		.clone();
+	edit(&mut account);
+	let message = Message::SetAccount {
+		sheet: sheet_index,
+		account,
+	};
+	model.update(message).map(drop).map_err(|e| e.to_string())
+}
+
+/// Changes what is filled in for the transactions entered into the sheet by hand
+fn edit_entry_defaults(
+	sheet_index: usize,
+	model: &mut Model,
+	edit: impl FnOnce(&mut EntryDefaults),
+) -> Result<(), String> {
+	let mut defaults = model
+		.get_sheet(sheet_index)
+		.ok_or(SHEET_GONE)?
+		.entry_defaults
+		.clone();
+	edit(&mut defaults);
+	let message = Message::SetEntryDefaults {
+		sheet: sheet_index,
+		defaults,
+	};
+	model.update(message).map(drop).map_err(|e| e.to_string())
+}
+
+fn default_category_input(sheet_index: usize, model: &Model) -> Popup {
+	let current = model
+		.get_sheet(sheet_index)
+		.map(|sheet| sheet.entry_defaults.category.clone())
+		.unwrap_or_default();
+	Input(Box::new(InputInner::new(
+		"Category of new rows",
+		move |popup, text, _view, model: &mut Model, _cs| {
+			let category = text.trim().to_string();
+			match edit_entry_defaults(sheet_index, model, |defaults| defaults.category = category) {
+				Ok(()) => sheet_settings_list(sheet_index, model, 5),
+				Err(e) => Some(popup.with_error(e)),
+			}
+		},
+	)))
+	.with_subtitle("(leave blank for none)")
+	.with_text(current)
+}
+
+fn account_type_list(sheet_index: usize, model: &Model) -> Popup {
+	let current = model
+		.get_sheet(sheet_index)
+		.map(|sheet| sheet.account().kind)
+		.unwrap_or_default();
+	let mut popup = ListInner::new(
+		"Account type",
+		AccountType::ALL.iter().map(ToString::to_string).collect(),
+		move |popup, index, _view, model: &mut Model, _cs| match edit_account(
+			sheet_index,
+			model,
+			|account| {
+				account.kind = AccountType::ALL[index];
+			},
+		) {
+			Ok(()) => sheet_settings_list(sheet_index, model, 0),
+			Err(e) => Some(popup.with_error(e)),
+		},
+	);
+	popup
+		.list_state
+		.select(AccountType::ALL.iter().position(|kind| *kind == current));
+	List(Box::new(popup)).into()
+}
+
+fn starting_balance_input(sheet_index: usize, model: &Model) -> Popup {
+	let current = model
+		.get_sheet(sheet_index)
+		.map_or(0.0, |sheet| sheet.account().starting_balance);
+	Input(Box::new(
+		InputInner::new("Starting balance", move |popup, text, _view, model, _cs| {
+			let starting_balance = if text.trim().is_empty() {
+				0.0
+			} else {
+				match Transaction::parse_amount(text.trim()) {
+					Ok(amount) => amount,
+					Err(ParseTransactionMemberError { message }) => {
+						return Some(popup.with_error(message));
+					}
+				}
+			};
+			match edit_account(sheet_index, model, |account| {
+				account.starting_balance = starting_balance;
+			}) {
+				Ok(()) => sheet_settings_list(sheet_index, model, 1),
+				Err(e) => Some(popup.with_error(e)),
+			}
+		})
+		.with_validator(preview_amount),
+	))
+	.with_subtitle("(Balance before the first transaction)")
+	.with_text(current.to_string())
+}
+
+fn account_number_input(sheet_index: usize, model: &Model) -> Popup {
+	let current = model
+		.get_sheet(sheet_index)
+		.and_then(|sheet| sheet.account().number.clone())
+		.unwrap_or_default();
+	Input(Box::new(InputInner::new(
+		"Account number",
+		move |popup, text, _view, model: &mut Model, _cs| {
+			let number = Some(text.trim().to_string()).filter(|number| !number.is_empty());
+			match edit_account(sheet_index, model, |account| account.number = number) {
+				Ok(()) => sheet_settings_list(sheet_index, model, 2),
+				Err(e) => Some(popup.with_error(e)),
+			}
+		},
+	)))
+	.with_subtitle("(leave blank for none)")
+	.with_text(current)
+}
+
+/// Lists the currency of the sheet, the base currency and the exchange rate of every other
+/// currency in use, to set any of them by hand, or to fetch the latest rates
+pub fn exchange_rates(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	cs.popup = Some(exchange_rates_popup(view, model));
+}
+
+/// Returns the popup listing the exchange rates. See [`exchange_rates`]
+fn exchange_rates_popup(view: &View, model: &Model) -> Popup {
+	let base = model.currencies.base.clone();
+	let mut currencies: Vec<String> = model
+		.all_sheets()
+		.filter_map(|sheet| sheet.currency.clone())
+		.chain(model.currencies.overrides.keys().cloned())
+		.filter(|currency| *currency != base)
+		.collect();
+	currencies.sort();
+	currencies.dedup();
+
+	let sheet = view.get_selected_sheet(model);
+	let sheet_index = view.selected_sheet;
+	let mut items = vec![
+		format!(
+			"Currency of {}  {}",
+			sheet.name,
+			sheet.currency.as_deref().unwrap_or(&base)
+		),
+		format!("Base currency  {base}"),
+	];
+	items.extend(currencies.iter().map(|currency| {
+		let fetched = view.rates.as_ref();
+		match (model.currencies.overrides.get(currency), fetched) {
+			(Some(rate), _) => format!("{currency}  {rate:.4} set by hand"),
+			(None, Some(fetched)) => match fetched.rate(currency, &base) {
+				Some(rate) => format!("{currency}  {rate:.4} fetched for {}", fetched.date),
+				None => format!("{currency}  no rate"),
+			},
+			(None, None) => format!("{currency}  no rate"),
+		}
+	}));
+	items.push("Fetch the latest rates".to_string());
+	let fetch = items.len() - 1;
+
+	let popup = ListInner::new(
+		"Exchange rates",
+		items,
+		move |_popup, index, _view, model: &mut Model, cs| match index {
+			0 => Some(sheet_currency_input(sheet_index, model)),
+			1 => Some(base_currency_input(&model.currencies.base)),
+			_ if index == fetch => Some(fetch_rates(cs)),
+			_ => Some(rate_input(currencies[index - 2].clone(), model)),
+		},
+	);
+	List(Box::new(popup)).with_subtitle(format!("(In {base}, worth one of each)"))
+}
+
+/// Sets the currency the sheet is kept in, or clears it so the sheet is in the base currency
+fn sheet_currency_input(sheet_index: usize, model: &Model) -> Popup {
+	let current = model
+		.get_sheet(sheet_index)
+		.and_then(|sheet| sheet.currency.clone())
+		.unwrap_or_default();
+	let subtitle = format!(
+		"(Code like EUR - leave blank for the base currency, {})",
+		model.currencies.base
+	);
+	Input(Box::new(InputInner::new(
+		"Currency of the sheet",
+		move |popup, text, view, model: &mut Model, _cs| {
+			let currency = if text.trim().is_empty() {
+				None
+			} else {
+				match model::currency_code(&text) {
+					Some(code) => Some(code),
+					None => return Some(popup.with_error("Not a currency code")),
+				}
+			};
+			let message = Message::SetCurrency {
+				sheet: sheet_index,
+				currency,
+			};
+			if let Err(e) = model.update(message) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			Some(exchange_rates_popup(view, model))
+		},
+	)))
+	.with_subtitle(subtitle)
+	.with_text(current)
+}
+
+fn base_currency_input(current: &str) -> Popup {
+	Input(Box::new(InputInner::new(
+		"Base currency",
+		move |popup, text, view, model: &mut Model, _cs| {
+			let Some(currency) = model::currency_code(&text) else {
+				return Some(popup.with_error("Not a currency code"));
+			};
+			if let Err(e) = model.update(Message::SetBaseCurrency { currency }) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			Some(exchange_rates_popup(view, model))
+		},
+	)))
+	.with_subtitle("(Code like USD, which totals are shown in)")
+	.with_text(current)
+}
+
+fn rate_input(currency: String, model: &Model) -> Popup {
+	let current = model
+		.currencies
+		.overrides
+		.get(&currency)
+		.map(ToString::to_string)
+		.unwrap_or_default();
+	let subtitle = format!(
+		"(What 1 {currency} is worth in {} - leave blank to use the fetched rate)",
+		model.currencies.base
+	);
+	let title = format!("Rate of {currency}");
+	Input(Box::new(InputInner::new(
+		&title,
+		move |popup, text, view, model: &mut Model, _cs| {
+			let rate = if text.trim().is_empty() {
+				None
+			} else {
+				match text.trim().parse::<f64>() {
+					Ok(rate) if rate > 0.0 => Some(rate),
+					_ => return Some(popup.with_error("The rate must be a positive number")),
+				}
+			};
+			let message = Message::SetRate {
+				currency: currency.clone(),
+				rate,
+			};
+			if let Err(e) = model.update(message) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			Some(exchange_rates_popup(view, model))
+		},
+	)))
+	.with_subtitle(subtitle)
+	.with_text(current)
+}
+
+/// Fetches the latest exchange rates on the worker thread, from the provider set up in the
+/// config, and lists them once they are fetched
+fn fetch_rates(cs: &mut ControllerState) -> Popup {
+	let Some(source) = cs.rates.clone() else {
+		return Info(Box::default())
+			.with_title("Exchange rates")
+			.with_error("Set up a rate provider in the [rates] table of the config file");
+	};
+	cs.start_task("Exchange rates", move |_progress| -> TaskFinish {
+		let fetched = source.fetch();
+		if let Ok(rates) = &fetched {
+			state::set_exchange_rates(rates);
+		}
+		Box::new(move |view, model: &mut Model, _cs| match fetched {
+			Ok(rates) => {
+				view.rates = Some(rates);
+				Some(exchange_rates_popup(view, model))
+			}
+			Err(e) => Some(
+				Info(Box::default())
+					.with_title("Exchange rates")
+					.with_error(e.to_string()),
+			),
+		})
+	})
+	.with_text("Fetching the latest rates")
+}
+
+/// Fetches the latest exchange rates in the background, if a rate provider is set up and those
+/// last fetched are old, for the net worth to convert sheets in other currencies with
+pub fn refresh_rates(cs: &mut ControllerState) {
+	let Some(source) = cs.rates.clone() else {
+		return;
+	};
+	if !state::exchange_rates_stale() {
+		return;
+	}
+	cs.run_in_background("Exchange rates", move || -> TaskFinish {
+		let fetched = source.fetch();
+		if let Ok(rates) = &fetched {
+			state::set_exchange_rates(rates);
+		}
+		Box::new(move |view, _model, _cs| {
+			match fetched {
+				Ok(rates) => view.rates = Some(rates),
+				Err(e) => tracing::warn!("Couldn't fetch the exchange rates: {e}"),
+			}
+			None
+		})
+	});
+}
+
+/// Imports transactions from a file, asking for the path (which can contain `*` wildcards),
+/// the import preset, and the sheet to import into
+pub fn import(view: &mut View, model: &mut Model, cs: &mut ControllerState, _count: Option<usize>) {
+	let sheet = view.get_selected_sheet(model).name.clone();
+	let current = model
+		.last_import
+		.as_ref()
+		.map(|i| i.pattern.clone())
+		.unwrap_or_default();
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Import",
+			move |popup, text, _view, model: &mut Model, _cs| {
+				if text.trim().is_empty() {
+					return Some(popup.with_error("A file must be given"));
+				}
+				Some(import_preset(
+					text.trim().to_string(),
+					sheet.clone(),
+					&model.scripts,
+				))
+			},
+		)))
+		.with_subtitle("(File - use * to pick the newest matching file)")
+		.with_text(current),
+	);
+}
+
+/// Asks for the preset to read the file with, starting on the first one for the file's extension
+fn import_preset(pattern: String, sheet: String, scripts: &Scripts) -> Popup {
+	let builtin = ImportPreset::all(scripts);
+	let extension = Path::new(&pattern)
+		.extension()
+		.and_then(|e| e.to_str())
+		.map(str::to_ascii_lowercase);
+	let selected = builtin
+		.iter()
+		.position(|p| extension.as_deref() == Some(p.format.extension()))
+		.unwrap_or(0);
+	let presets: Vec<String> = builtin.into_iter().map(|p| p.name).collect();
+	let mut popup = ListInner::from_items(
+		"Import preset",
+		presets,
+		move |_popup, preset, _view, model: &mut Model, _cs| {
+			let titles = model.sheet_titles();
+			let selected = titles.iter().position(|t| *t == sheet).unwrap_or(0);
+			let pattern = pattern.clone();
+			let preset = preset.clone();
+			let mut popup = ListInner::from_items(
+				"Import into sheet",
+				titles,
+				move |_popup, sheet: &String, _view, model: &mut Model, cs| {
+					Some(run_import(
+						cs,
+						ImportSettings {
+							pattern: pattern.clone(),
+							preset: preset.clone(),
+							sheet: sheet.clone(),
+						},
+						model.scripts.clone(),
+					))
+				},
+			);
+			popup.list_state.select(Some(selected));
+			Some(List(Box::new(popup)).into())
+		},
+	);
+	popup.list_state.select(Some(selected));
+	List(Box::new(popup)).into()
+}
+
+/// Repeats the last import, picking the newest file matching its pattern
+pub fn repeat_import(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let popup = match model.last_import.clone() {
+		Some(settings) => run_import(cs, settings, model.scripts.clone()),
+		None => Info(Box::default())
+			.with_title("Import")
+			.with_error("Nothing has been imported yet"),
+	};
+	cs.popup = Some(popup);
+}
+
+/// Returns a popup showing the progress of a task. See [`ControllerState::start_task`]
+pub fn progress_popup(title: &str, progress: Arc<TaskProgress>) -> Popup {
+	Progress(Box::new(ProgressInner::new(title, progress))).with_subtitle("(Esc to cancel)")
+}
+
+/// Reads the import on another thread, returning a popup showing how much of the file has been
+/// read. Once it has, if any of the imported transactions look like ones already in the sheet, a
+/// popup to review them is shown, and otherwise the import is finished straight away. The scripts
+/// are given for the presets they add
+fn run_import(cs: &mut ControllerState, settings: ImportSettings, scripts: Scripts) -> Popup {
+	let text = format!("Reading {}", settings.pattern);
+	cs.start_task("Import", move |progress| -> TaskFinish {
+		let transactions = settings.read(&scripts, |done, total| progress.report(done, total));
+		Box::new(move |_view, model: &mut Model, _cs| {
+			let pending = transactions.and_then(|t| model.pending_import(settings, t));
+			Some(review_import(pending, model))
+		})
+	})
+	.with_text(text)
+}
+
+/// Finishes the import straight away, or shows the popup to review the imported transactions that
+/// look like ones already in the sheet if there are any
+fn review_import(pending: Result<PendingImport, ImportError>, model: &mut Model) -> Popup {
+	match pending {
+		Ok(pending) if pending.conflicts.is_empty() => {
+			let sheet = pending.source.sheet().to_string();
+			imported(model.finish_import(pending), &sheet)
+		}
+		Ok(pending) => {
+			let subtitle = format!(
+				"{} of {} look like transactions already in {}",
+				pending.conflicts.len(),
+				pending.conflicts.len() + pending.transactions.len(),
+				pending.source.sheet(),
+			);
+			Duplicates(Box::new(DuplicatesInner::new(pending))).with_subtitle(subtitle)
+		}
+		Err(e) => Info(Box::default())
+			.with_title("Import")
+			.with_error(e.to_string()),
+	}
+}
+
+/// Imports the transactions of the bank account linked to the sheet, fetched through `SimpleFIN`
+/// on the worker thread. If the sheet isn't linked to an account yet, the accounts are listed to
+/// link one. The bridge is connected to first if it hasn't been. See [`bank`]
+pub fn bank_import(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet = view.get_selected_sheet(model).name.clone();
+	cs.popup = Some(match state::simplefin() {
+		Some(bridge) => fetch_bank(cs, model, bridge, sheet),
+		None => connect_bank(sheet),
+	});
+}
+
+/// Asks for a `SimpleFIN` setup token, and claims it on the worker thread before fetching the
+/// transactions for the sheet
+fn connect_bank(sheet: String) -> Popup {
+	Input(Box::new(
+		InputInner::new(
+			"Connect to SimpleFIN",
+			move |popup, token, _view, _model, cs| {
+				if token.trim().is_empty() {
+					return Some(popup.with_error("A setup token must be given"));
+				}
+				let sheet = sheet.clone();
+				Some(
+					cs.start_task("Connect to SimpleFIN", move |_progress| -> TaskFinish {
+						let claimed = SimpleFin::claim(&token);
+						Box::new(move |_view, model: &mut Model, cs| match claimed {
+							Ok(bridge) => {
+								state::set_simplefin(Some(&bridge));
+								Some(fetch_bank(cs, model, bridge, sheet))
+							}
+							Err(e) => Some(connect_bank(sheet).with_error(e.to_string())),
+						})
+					}),
+				)
+			},
+		)
+		.masked(),
+	))
+	.with_subtitle("(Setup token from the SimpleFIN bridge, which can only be used once)")
+}
+
+/// Fetches the transactions of every account on the worker thread, from a little before the
+/// newest one imported into the sheet
+fn fetch_bank(cs: &mut ControllerState, model: &Model, bridge: SimpleFin, sheet: String) -> Popup {
+	let link = model.bank_link(&sheet).cloned();
+	let since = link.as_ref().map_or_else(
+		|| clock::today() - chrono::Days::new(30),
+		model::BankLink::fetch_since,
+	);
+	let text = format!("Fetching transactions since {since} from {}", bridge.name());
+	cs.start_task("Bank import", move |_progress| -> TaskFinish {
+		let fetched = bridge.fetch(since);
+		Box::new(move |_view, model: &mut Model, _cs| {
+			let popup = Info(Box::default()).with_title("Bank import");
+			let accounts = match fetched {
+				Ok(accounts) => accounts,
+				Err(BankError::Unauthorized) => {
+					state::set_simplefin(None);
+					return Some(
+						connect_bank(sheet).with_error(BankError::Unauthorized.to_string()),
+					);
+				}
+				Err(e) => return Some(popup.with_error(e.to_string())),
+			};
+			if let Some(link) = link
+				&& let Some(account) = accounts.iter().find(|a| a.id == link.account)
+			{
+				return Some(review_import(
+					model.pending_bank_import(&sheet, account.clone()),
+					model,
+				));
+			}
+			if accounts.is_empty() {
+				return Some(popup.with_error("The bridge has no accounts"));
+			}
+			let names = accounts.iter().map(|a| a.name.clone()).collect();
+			let title = format!("Link {sheet} to account");
+			Some(
+				List(Box::new(ListInner::new(
+					&title,
+					names,
+					move |_popup, index, _view, model: &mut Model, _cs| {
+						let account = accounts[index].clone();
+						Some(review_import(
+							model.pending_bank_import(&sheet, account),
+							model,
+						))
+					},
+				)))
+				.into(),
+			)
+		})
+	})
+	.with_text(text)
+}
+
+/// Returns an info popup saying what an import did to the sheet, or why it couldn't be finished
+pub fn imported(counts: Result<ImportCounts, UpdateError>, sheet: &str) -> Popup {
+	let counts = match counts {
+		Ok(counts) => counts,
+		Err(e) => {
+			return Info(Box::default())
+				.with_title("Import")
+				.with_error(e.to_string());
+		}
+	};
+	let mut parts = vec![format!(
+		"Imported {} transactions into {sheet}",
+		counts.added
+	)];
+	if counts.merged > 0 {
+		parts.push(format!("merged {}", counts.merged));
+	}
+	if counts.skipped > 0 {
+		parts.push(format!("skipped {} duplicates", counts.skipped));
+	}
+	let text = parts.join(", ");
+	Info(Box::default()).with_title("Import").with_text(text)
+}
+
+pub fn rename_sheet(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	cs.popup = Some(
+		Input(Box::new(InputInner::new(
+			"Rename sheet",
+			move |popup, text, _view, model, _cs| {
+				let message = Message::RenameSheet {
+					sheet: sheet_index,
+					name: text,
+				};
+				model
+					.update(message)
+					.err()
+					.map(|e| popup.with_error(e.to_string()))
+			},
+		)))
+		.with_text(view.get_selected_sheet(model).name.clone()),
+	);
+}
+
+/// Asks how to duplicate the selected sheet - as it is, with its amounts cleared, or a month
+/// later - and then for the name of the copy, which is moved to once it is added
+pub fn duplicate_sheet(
+	view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let choices = [
+		("As it is", DuplicateOptions::default()),
+		(
+			"With the amounts cleared",
+			DuplicateOptions {
+				clear_amounts: true,
+				next_month: false,
+			},
+		),
+		(
+			"A month later",
+			DuplicateOptions {
+				clear_amounts: false,
+				next_month: true,
+			},
+		),
+		(
+			"A month later, with the amounts cleared",
+			DuplicateOptions {
+				clear_amounts: true,
+				next_month: true,
+			},
+		),
+	];
+	cs.popup = Some(
+		List(Box::new(ListInner::new(
+			"Duplicate sheet",
+			choices
+				.iter()
+				.map(|(name, _)| (*name).to_string())
+				.collect(),
+			move |_popup, index, _view, model: &mut Model, _cs| {
+				let name = model
+					.get_sheet(sheet_index)
+					.map(|sheet| format!("{} copy", sheet.name))
+					.unwrap_or_default();
+				Some(duplicate_name_input(sheet_index, choices[index].1).with_text(name))
+			},
+		)))
+		.into(),
+	);
+}
+
+fn duplicate_name_input(sheet_index: usize, options: DuplicateOptions) -> Popup {
+	Input(Box::new(InputInner::new(
+		"Name of the copy",
+		move |popup, text, view, model: &mut Model, _cs| {
+			let message = Message::DuplicateSheet {
+				sheet: sheet_index,
+				name: text.trim().to_string(),
+				options,
+			};
+			if let Err(e) = model.update(message) {
+				return Some(popup.with_error(e.to_string()));
+			}
+			view.go_to_sheet(model.sheet_count() - 1, model);
+			None
+		},
+	)))
+	.into()
+}
+
+pub fn delete_sheet(
+	view: &mut View,
+	_model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	if sheet_index == 0 {
+		cs.popup = Some(Info(Box::default()).with_text("Main sheet cannot be deleted"));
+		return;
+	}
+	cs.popup = Some(
+		Confirm(Box::new(ConfirmInner::new(
+			"Delete Sheet",
+			"Are you sure you want to delete this sheet?",
+			move |confirmed, _view, model, cs| {
+				if !confirmed {
+					return;
+				}
+				cs.send(model, Message::DeleteSheet { sheet: sheet_index });
+			},
+		)))
+		.into(),
+	);
+}
+
+/// Archives the selected sheet, or brings it back if it is already archived. Once archived, the
+/// previous sheet is selected, unless archived sheets are shown
+pub fn archive_sheet(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let archived = !view.get_selected_sheet(model).archived;
+	if cs
+		.send(
+			model,
+			Message::ArchiveSheet {
+				sheet: sheet_index,
+				archived,
+			},
+		)
+		.is_none()
+	{
+		return;
+	}
+	if !view.is_sheet_shown(sheet_index, model) {
+		view.previous_sheet(model);
+	}
+	cs.notify(if archived {
+		"Archived the sheet"
+	} else {
+		"Brought the sheet back from the archive"
+	});
+}
+
+/// Lists the archived sheets, to bring one back from the archive and move to it
+pub fn unarchive_sheet(
+	_view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let archived: Vec<usize> = model
+		.all_sheets()
+		.enumerate()
+		.filter(|(_, sheet)| sheet.archived)
+		.map(|(index, _)| index)
+		.collect();
+	if archived.is_empty() {
+		cs.popup = Some(Info(Box::default()).with_text("No sheet is archived"));
+		return;
+	}
+	let items = archived
+		.iter()
+		.filter_map(|index| model.get_sheet(*index))
+		.map(|sheet| sheet.name.clone())
+		.collect();
+	cs.popup = Some(
+		List(Box::new(ListInner::new(
+			"Archived sheets",
+			items,
+			move |popup, index, view: &mut View, model: &mut Model, _cs| {
+				let message = Message::ArchiveSheet {
+					sheet: archived[index],
+					archived: false,
+				};
+				if let Err(e) = model.update(message) {
+					return Some(popup.with_error(e.to_string()));
+				}
+				view.go_to_sheet(archived[index], model);
+				None
+			},
+		)))
+		.with_subtitle("(Select one to bring it back)"),
+	);
+}
+
+/// Inserts a new row below the selected one, asking for all of its columns at once
+pub fn new_row_below(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let row = view.get_selected_row(sheet).unwrap_or(0);
+	cs.popup = Some(new_row_date(
+		sheet_index,
+		(row + 1).min(sheet.transactions().len()),
+		true,
+	));
+}
+
+/// Inserts a new row above the selected one, asking for all of its columns at once
+pub fn new_row_above(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let row = view.get_selected_row(sheet).unwrap_or(0);
+	cs.popup = Some(new_row_date(sheet_index, row, false));
+}
+
+/// Picks the date of a new row from a calendar, starting from today, before asking for the rest
+/// of it
+fn new_row_date(sheet_index: usize, row: usize, below: bool) -> Popup {
+	DatePicker(Box::new(DatePickerInner::new(
+		"Insert row",
+		clock::today(),
+		move |_popup, date, _view, model: &mut Model, _cs| {
+			let defaults = model
+				.get_sheet(sheet_index)
+				.map(|sheet| sheet.entry_defaults.clone())
+				.unwrap_or_default();
+			Some(new_row_form(sheet_index, row, below, date, defaults))
+		},
+	)))
+	.into()
+}
+
+/// A form for a new transaction on the given date, which is inserted at the given row (or by its
+/// date). It starts with the sheet's default category, and its amount is given the sheet's sign
+fn new_row_form(
+	sheet_index: usize,
+	row: usize,
+	below: bool,
+	date: NaiveDate,
+	defaults: EntryDefaults,
+) -> Popup {
+	let transaction = Transaction {
+		date,
+		category: defaults.category,
+		pending: true,
+		..Transaction::default()
+	};
+	let mut fields = transaction_fields(&transaction);
+	// The amount has to be typed, rather than left as 0
+	fields[2].1 = String::new();
+	let mut form = FormInner::new(
+		"Insert row",
+		fields,
+		move |form, values, view, model, cs| {
+			let mut transaction = match form_transaction(form, values, transaction.clone()) {
+				Ok(transaction) => transaction,
+				Err(popup) => return Some(popup),
+			};
+			transaction.amount = defaults.sign.apply(transaction.amount);
+			cs.last_change = Some(Change::Insert {
+				transaction: transaction.clone(),
+				below,
+			});
+			let message = Message::InsertRow {
+				sheet: sheet_index,
+				row,
+				transaction,
+			};
+			if let Some(row) = cs.send(model, message).and_then(|o| o.row()) {
+				view.select_row(row, model);
+			}
+			None
+		},
+	);
+	// The date has already been picked
+	form.focus(1);
+	let subtitle = match defaults.sign {
+		AmountSign::AsTyped => "(Tab for the next field)".to_string(),
+		sign => format!("(Tab for the next field - amounts are made {sign})"),
+	};
+	Form(Box::new(form)).with_subtitle(subtitle)
+}
+
+/// Edits every column of the selected row at once
+pub fn edit_row(
+	view: &mut View,
+	model: &mut Model,
+	cs: &mut ControllerState,
+	_count: Option<usize>,
+) {
+	let sheet_index = view.selected_sheet;
+	let sheet = view.get_selected_sheet(model);
+	let Some(row) = view.get_selected_row(sheet) else {
+		return;
+	};
+	let transaction = sheet.transactions()[row].clone();
+	cs.popup = Some(
+		Form(Box::new(FormInner::new(
+			"Edit row",
+			transaction_fields(&transaction),
+			move |form, values, view, model, cs| match form_transaction(
+				form,
+				values,
+				transaction.clone(),
+			) {
+				Ok(transaction) => {
+					let message = Message::ReplaceTransaction {
+						sheet: sheet_index,
+						row,
+						transaction,
+					};
+					if let Some(row) = cs.send(model, message).and_then(|o| o.row()) {
+						view.select_row(row, model);
+					}
+					None
+				}
+				Err(popup) => Some(popup),
+			},
+		)))
+		.with_subtitle("(Tab for the next field)"),
+	);
+}
+
+/// Returns the fields of a form for the transaction, one for each column
+fn transaction_fields(transaction: &Transaction) -> Vec<(&'static str, String)> {
+	COLUMN_NAMES
+		.iter()
+		.enumerate()
+		.map(|(col, name)| (*name, transaction.member_string(col)))
+		.collect()
+}
+
+/// Sets the columns of the transaction from the values of its form (see [`transaction_fields`]).
+/// If any is invalid, the form is returned with that field focused and the error shown
+fn form_transaction(
+	mut form: Form,
+	values: Vec<String>,
+	mut transaction: Transaction,
+) -> Result<Transaction, Popup> {
+	for (col, value) in values.into_iter().enumerate() {
+		if let Err(ParseTransactionMemberError { message }) = transaction.set_member(col, value) {
+			form.focus(col);
+			return Err(form.with_error(format!("{}: {message}", COLUMN_NAMES[col])));
+		}
+	}
+	Ok(transaction)
 }
@@ -1,27 +1,79 @@
 use std::{
-	fmt::Debug,
+	fmt::{Debug, Display},
 	ops::{Deref, DerefMut},
+	path::{Path, PathBuf},
 	rc::Rc,
+	sync::Arc,
+	time::{Duration, Instant},
 };
 
+use budgeting_core::clock;
+use chrono::{Days, Months, NaiveDate};
 use enum_dispatch::enum_dispatch;
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+	crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+	widgets::ListState,
+};
 use tui_textarea::TextArea;
 
-use crate::model::Model;
+use crate::{
+	clipboard,
+	controller::{ControllerState, tasks::TaskProgress},
+	model::{self, Access, Message, Model},
+	state, sync,
+	view::View,
+};
 
 pub mod defaults;
 
-pub trait InputCallbackFn: Fn(Popup, String, &mut Model) -> Option<Popup> {}
-impl<T> InputCallbackFn for T where T: Fn(Popup, String, &mut Model) -> Option<Popup> {}
+pub trait InputCallbackFn:
+	Fn(Popup, String, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+impl<T> InputCallbackFn for T where
+	T: Fn(Popup, String, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
 
 pub type InputCallback = dyn InputCallbackFn;
 
+/// Checks the text of an input as it is typed, returning a preview of what it means (e.g. the
+/// date it is parsed as) or why it isn't valid
+pub type Validator = dyn Fn(&str) -> Result<String, String>;
+
+pub trait FormCallbackFn:
+	Fn(Form, Vec<String>, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+impl<T> FormCallbackFn for T where
+	T: Fn(Form, Vec<String>, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+
+pub type FormCallback = dyn FormCallbackFn;
+
+pub trait DateCallbackFn:
+	Fn(Popup, NaiveDate, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+impl<T> DateCallbackFn for T where
+	T: Fn(Popup, NaiveDate, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+
+pub type DateCallback = dyn DateCallbackFn;
+
 #[enum_dispatch(Popup)]
 pub trait PopupBehaviour {
 	/// Handles the given key events. This is necessary since the popups hijack the controls while
 	/// visible
-	fn handle_key_event(self, key_event: &KeyEvent, model: &mut Model) -> Option<Popup>;
+	fn handle_key_event(
+		self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup>;
 	/// Adds some text to the popup
 	fn with_text<S: Into<String>>(self, text: S) -> Popup;
 	/// Adds a title to the popup
@@ -35,8 +87,21 @@ pub trait PopupBehaviour {
 #[enum_dispatch]
 pub enum Popup {
 	Input,
+	Form,
+	DatePicker,
 	Info,
 	Confirm,
+	List,
+	Forecast,
+	Diff,
+	Compare,
+	Merge,
+	Duplicates,
+	Review,
+	Report,
+	Start,
+	Switcher,
+	Progress,
 }
 
 pub struct Info(Box<InfoInner>);
@@ -61,6 +126,14 @@ pub struct InfoInner {
 	title: String,
 	subtitle: Option<String>,
 	error: Option<String>,
+	/// The line of the text shown at the top, as the text can be scrolled
+	scroll: usize,
+	/// Whether `g` was just pressed, so a second `g` goes to the top
+	pending_g: bool,
+	/// The search being typed after `/`, if one is
+	search: Option<String>,
+	/// The last search, which `n` and `N` go to the next and previous match of
+	query: Option<String>,
 }
 
 impl InfoInner {
@@ -68,6 +141,81 @@ impl InfoInner {
 		&self.text
 	}
 
+	pub fn scroll(&self) -> usize {
+		self.scroll
+	}
+
+	pub fn search(&self) -> Option<&String> {
+		self.search.as_ref()
+	}
+
+	pub fn query(&self) -> Option<&String> {
+		self.query.as_ref()
+	}
+
+	/// Returns whether the line matches the last search, ignoring case
+	pub fn matches(&self, line: &str) -> bool {
+		self.query
+			.as_ref()
+			.is_some_and(|query| line.to_lowercase().contains(&query.to_lowercase()))
+	}
+
+	/// Scrolls to the next line matching the last search, after the top one (or from it, if
+	/// `include_top`), or to the previous one if `forward` is false. The search wraps around the
+	/// ends of the text
+	fn find(&mut self, forward: bool, include_top: bool) {
+		let lines: Vec<&str> = self.text.lines().collect();
+		let len = lines.len();
+		if len == 0 {
+			return;
+		}
+		let start = usize::from(!include_top);
+		let found = (start..len + start)
+			.map(|offset| {
+				if forward {
+					(self.scroll + offset) % len
+				} else {
+					(self.scroll + len - offset % len) % len
+				}
+			})
+			.find(|line| self.matches(lines[*line]));
+		match found {
+			Some(line) => {
+				self.scroll = line;
+				self.error = None;
+			}
+			None => {
+				self.error = self
+					.query
+					.as_ref()
+					.map(|query| format!("No match for \"{query}\""));
+			}
+		}
+	}
+
+	/// Handles a key while a search is being typed, running it on [`KeyCode::Enter`]
+	fn type_search(&mut self, key_event: &KeyEvent) {
+		let Some(search) = &mut self.search else {
+			return;
+		};
+		match key_event.code {
+			KeyCode::Esc => self.search = None,
+			KeyCode::Enter => {
+				let search = self.search.take().unwrap_or_default();
+				if !search.is_empty() {
+					self.query = Some(search);
+					self.find(true, true);
+				}
+			}
+			KeyCode::Backspace if search.is_empty() => self.search = None,
+			KeyCode::Backspace => {
+				search.pop();
+			}
+			KeyCode::Char(c) => search.push(c),
+			_ => {}
+		}
+	}
+
 	pub fn title(&self) -> &String {
 		&self.title
 	}
@@ -82,11 +230,36 @@ impl InfoInner {
 }
 
 impl PopupBehaviour for Info {
-	fn handle_key_event(self, key_event: &KeyEvent, _model: &mut Model) -> Option<Popup> {
+	/// Scrolls with j/k and gg/G, searches with `/` (and n/N), and closes on [`KeyCode::Esc`] or
+	/// `q`
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		_model: &mut Model,
+		_cs: &mut ControllerState,
+	) -> Option<Popup> {
+		if self.search.is_some() {
+			self.type_search(key_event);
+			return Some(self.into());
+		}
+		let pending_g = std::mem::take(&mut self.pending_g);
+		let last_line = self.text.lines().count().saturating_sub(1);
 		match key_event.code {
-			KeyCode::Esc | KeyCode::Char('q') => None,
-			_ => Some(self.into()),
+			KeyCode::Esc | KeyCode::Char('q') => return None,
+			KeyCode::Char('j') | KeyCode::Down => {
+				self.scroll = (self.scroll + 1).min(last_line);
+			}
+			KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+			KeyCode::Char('g') if pending_g => self.scroll = 0,
+			KeyCode::Char('g') => self.pending_g = true,
+			KeyCode::Char('G') => self.scroll = last_line,
+			KeyCode::Char('/') => self.search = Some(String::new()),
+			KeyCode::Char('n') => self.find(true, false),
+			KeyCode::Char('N') => self.find(false, false),
+			_ => {}
 		}
+		Some(self.into())
 	}
 
 	fn with_text<S: Into<String>>(mut self, text: S) -> Popup {
@@ -132,16 +305,43 @@ pub struct InputInner {
 	title: String,
 	subtitle: Option<String>,
 	error: Option<String>,
+	/// The values suggested as the text is typed, most likely first. See
+	/// [`InputInner::with_completions`]
+	completions: Vec<String>,
+	/// The index of the highlighted suggestion, if one is
+	suggestion: Option<usize>,
+	/// Checks the text on every keystroke. See [`InputInner::with_validator`]
+	validator: Option<Rc<Validator>>,
+	/// The name the text submitted is remembered under, to be gone back to with the arrow keys.
+	/// See [`InputInner::with_history`]
+	history: String,
+	/// How many inputs back through the history is being shown, if it is, with the text typed
+	/// before going back
+	browsing: Option<(usize, String)>,
+	/// Whether the text is a secret, like a passphrase. See [`InputInner::masked`]
+	masked: bool,
 }
 
 impl Debug for InputInner {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Popup")
-			.field("text_area", &self.text_area)
+			.field(
+				"text_area",
+				if self.masked {
+					&"<masked>"
+				} else {
+					&self.text_area
+				},
+			)
 			.field("on_submit", &"<closure>")
 			.field("title", &self.title)
 			.field("subtitle", &self.subtitle)
 			.field("error", &self.error)
+			.field("completions", &self.completions)
+			.field("suggestion", &self.suggestion)
+			.field("validator", &self.validator.as_ref().map(|_| "<closure>"))
+			.field("history", &self.history)
+			.field("browsing", &self.browsing)
 			.finish()
 	}
 }
@@ -158,9 +358,184 @@ impl InputInner {
 			title: title.to_string(),
 			subtitle: None,
 			error: None,
+			completions: vec![],
+			suggestion: None,
+			validator: None,
+			history: title.to_string(),
+			browsing: None,
+			masked: false,
+		}
+	}
+
+	/// Shows the text as asterisks, for passphrases and other secrets. The text submitted isn't
+	/// remembered, so it can't be gone back to with the arrow keys
+	#[must_use]
+	pub fn masked(mut self) -> Self {
+		self.text_area.set_mask_char('*');
+		self.masked = true;
+		self
+	}
+
+	/// Remembers the text submitted under the given name, rather than the title, e.g. for inputs
+	/// with the same title that take different kinds of value
+	#[must_use]
+	pub fn with_history<S: Into<String>>(mut self, name: S) -> Self {
+		self.history = name.into();
+		self
+	}
+
+	/// Checks the text as it is typed, showing what it will be taken as, or why it isn't valid,
+	/// before it is submitted
+	#[must_use]
+	pub fn with_validator<F>(mut self, validator: F) -> Self
+	where
+		F: Fn(&str) -> Result<String, String> + 'static,
+	{
+		self.validator = Some(Rc::new(validator));
+		self
+	}
+
+	/// Returns the preview of the text typed so far, or why it isn't valid, if the input has a
+	/// validator. Nothing is previewed while the input is empty
+	pub fn preview(&self) -> Option<Result<String, String>> {
+		let text = self.text();
+		if text.trim().is_empty() {
+			return None;
+		}
+		self.validator.as_ref().map(|validator| validator(&text))
+	}
+
+	/// The most suggestions shown at once
+	const MAX_SUGGESTIONS: usize = 5;
+
+	/// Suggests the given values while typing, e.g. the labels already in use, so the same thing
+	/// is named the same way every time. The values are expected most likely first
+	#[must_use]
+	pub fn with_completions(mut self, completions: Vec<String>) -> Self {
+		self.completions = completions;
+		self
+	}
+
+	/// Returns the values that complete the text typed so far, ignoring case. Values starting
+	/// with the text come before those that only contain it, and the text itself isn't suggested
+	pub fn suggestions(&self) -> Vec<&String> {
+		let text = self.text().trim().to_lowercase();
+		if text.is_empty() {
+			return vec![];
+		}
+		let (mut starting, containing): (Vec<&String>, Vec<&String>) = self
+			.completions
+			.iter()
+			.filter(|value| {
+				let value = value.to_lowercase();
+				value.contains(&text) && value != text
+			})
+			.partition(|value| value.to_lowercase().starts_with(&text));
+		starting.extend(containing);
+		starting.truncate(Self::MAX_SUGGESTIONS);
+		starting
+	}
+
+	pub fn suggestion(&self) -> Option<usize> {
+		self.suggestion
+	}
+
+	/// Returns the text typed so far
+	fn text(&self) -> String {
+		let mut text = self.text_area.lines().join(" ");
+		text.retain(|c| c != '\n' && c != '\r');
+		text
+	}
+
+	/// Replaces the text with the given text, with the cursor at its end
+	fn set_text(&mut self, text: &str) {
+		let mut text_area = TextArea::default();
+		text_area.insert_str(text);
+		self.text_area = text_area;
+	}
+
+	/// Types the text at the cursor, with any line breaks as spaces, as the input is one line
+	pub fn paste(&mut self, text: &str) {
+		let text = text
+			.trim_end_matches(['\n', '\r'])
+			.replace(['\n', '\r'], " ");
+		if self.text_area.insert_str(text) {
+			self.error = None;
+			self.suggestion = None;
+			self.browsing = None;
 		}
 	}
 
+	/// Replaces the text with the highlighted suggestion, or the first one if none is
+	fn complete(&mut self) {
+		let suggestions = self.suggestions();
+		if let Some(value) = suggestions.get(self.suggestion.unwrap_or(0)) {
+			let value = (*value).clone();
+			self.set_text(&value);
+		}
+		self.suggestion = None;
+	}
+
+	/// Shows the text submitted before the one shown (or after it, if not `older`), from the
+	/// given history, oldest first. Going forward past the last one shows the text that was typed
+	/// before going back
+	fn step_history(&mut self, older: bool, history: &[String]) {
+		let (back, typed) = match self.browsing.take() {
+			Some(browsing) => browsing,
+			None if older => (0, self.text()),
+			None => return,
+		};
+		let back = if older {
+			(back + 1).min(history.len())
+		} else {
+			back - 1
+		};
+		if back == 0 {
+			self.set_text(&typed);
+		} else {
+			self.set_text(&history[history.len() - back]);
+			self.browsing = Some((back, typed));
+		}
+		self.suggestion = None;
+	}
+
+	/// Applies the key to the text. The keys are mostly the text area's own, which are like
+	/// readline's (e.g. <C-w>, <C-a>, <C-e>, <A-b> and <A-f>), except for <C-u>, which deletes to
+	/// the start of the text rather than undoing, which <C-z> and <C-_> do instead. <C-v> pastes
+	/// from the system clipboard. Returns whether the text changed
+	fn edit(&mut self, key_event: &KeyEvent) -> bool {
+		if !key_event.modifiers.contains(KeyModifiers::CONTROL) {
+			return self.text_area.input(*key_event);
+		}
+		match key_event.code {
+			KeyCode::Char('u') => self.text_area.delete_line_by_head(),
+			KeyCode::Char('z' | '_') => self.text_area.undo(),
+			KeyCode::Char('v') => {
+				match clipboard::paste() {
+					Ok(text) => self.paste(&text),
+					Err(e) => self.error = Some(e.to_string()),
+				}
+				// Pasting has already done what changing the text does
+				false
+			}
+			_ => self.text_area.input(*key_event),
+		}
+	}
+
+	/// Moves the highlight to the next or previous suggestion, wrapping around
+	fn move_suggestion(&mut self, forward: bool) {
+		let len = self.suggestions().len();
+		if len == 0 {
+			return;
+		}
+		self.suggestion = Some(match (self.suggestion, forward) {
+			(None, true) => 0,
+			(None, false) => len - 1,
+			(Some(i), true) => (i + 1) % len,
+			(Some(i), false) => (i + len - 1) % len,
+		});
+	}
+
 	pub fn title(&self) -> &String {
 		&self.title
 	}
@@ -173,19 +548,55 @@ impl InputInner {
 }
 impl PopupBehaviour for Input {
 	/// Handles the [`KeyEvent`] given.
-	/// Calls [`Self::on_submit`] on [`KeyCode::Enter`], returning [`None`]
+	/// Calls [`Self::on_submit`] on [`KeyCode::Enter`], with the highlighted suggestion if there
+	/// is one
 	/// Returns [`None`] on [`KeyCode::Esc`], discarding the input
-	/// Otherwise, returns [`Some<Self>`] with the key event applied to [`Self::text_area`]
-	fn handle_key_event(mut self, key_event: &KeyEvent, model: &mut Model) -> Option<Popup> {
+	/// Moves the highlight between the suggestions with the arrow keys, and completes the text
+	/// with [`KeyCode::Tab`]. While there are no suggestions, the arrow keys go back through the
+	/// text submitted before instead
+	/// Otherwise, returns [`Some<Self>`] with the key event applied to [`Self::text_area`]. See
+	/// [`InputInner::edit`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
 		match key_event.code {
 			KeyCode::Enter => {
-				let mut text = self.text_area.lines().join(" ");
-				text.retain(|c| c != '\n' && c != '\r');
-				(self.on_submit.clone())(self.into(), text, model)
+				if self.suggestion.is_some() {
+					self.complete();
+				}
+				let text = self.text();
+				if !self.masked {
+					cs.remember_input(&self.history, &text);
+				}
+				(self.on_submit.clone())(self.into(), text, view, model, cs)
 			}
 			KeyCode::Esc => None,
+			KeyCode::Tab => {
+				self.complete();
+				Some(self.into())
+			}
+			KeyCode::Down | KeyCode::Up if self.masked => Some(self.into()),
+			KeyCode::Down | KeyCode::Up => {
+				let up = key_event.code == KeyCode::Up;
+				if self.browsing.is_none() && !self.suggestions().is_empty() {
+					self.move_suggestion(!up);
+				} else {
+					let history = cs.input_history(&self.history);
+					self.step_history(up, history);
+				}
+				Some(self.into())
+			}
 			_ => {
-				self.text_area.input(*key_event);
+				// The error was for the text that was submitted, so it goes once that changes
+				if self.edit(key_event) {
+					self.error = None;
+					self.browsing = None;
+				}
+				self.suggestion = None;
 				Some(self.into())
 			}
 		}
@@ -212,6 +623,283 @@ impl PopupBehaviour for Input {
 	}
 }
 
+pub struct Form(Box<FormInner>);
+
+impl Deref for Form {
+	type Target = FormInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Form {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+pub struct FormInner {
+	/// The name of each field, and the text typed into it
+	fields: Vec<(String, TextArea<'static>)>,
+	/// The index of the field being typed into
+	focused: usize,
+	on_submit: Rc<FormCallback>,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl FormInner {
+	/// Creates a new popup with a text input for each of the given fields, filled with its
+	/// initial value. The [`FormCallback`] is given the text of every field, in the same order
+	pub fn new<F>(title: &str, fields: Vec<(&str, String)>, f: F) -> Self
+	where
+		F: FormCallbackFn + 'static,
+	{
+		Self {
+			fields: fields
+				.into_iter()
+				.map(|(name, value)| {
+					let mut text_area = TextArea::default();
+					text_area.insert_str(value);
+					(name.to_string(), text_area)
+				})
+				.collect(),
+			focused: 0,
+			on_submit: Rc::new(f),
+			title: title.to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn fields(&self) -> &Vec<(String, TextArea<'static>)> {
+		&self.fields
+	}
+	pub fn focused(&self) -> usize {
+		self.focused
+	}
+	/// Moves the cursor to the field with the given index, e.g. the one that has an error
+	pub fn focus(&mut self, index: usize) {
+		self.focused = index.min(self.fields.len().saturating_sub(1));
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Form {
+	/// Moves between the fields with Tab and Shift-Tab (or the up and down arrows), calling
+	/// [`FormInner::on_submit`] with the text of every field on [`KeyCode::Enter`]. Returns
+	/// [`None`] on [`KeyCode::Esc`], discarding the input
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		let len = self.fields.len();
+		match key_event.code {
+			KeyCode::Enter => {
+				let values = self
+					.fields
+					.iter()
+					.map(|(_, text_area)| text_area.lines().join(" "))
+					.collect();
+				(self.on_submit.clone())(self, values, view, model, cs)
+			}
+			KeyCode::Esc => None,
+			KeyCode::Tab | KeyCode::Down => {
+				self.focused = (self.focused + 1) % len;
+				Some(self.into())
+			}
+			KeyCode::BackTab | KeyCode::Up => {
+				self.focused = (self.focused + len - 1) % len;
+				Some(self.into())
+			}
+			_ => {
+				let focused = self.focused;
+				self.fields[focused].1.input(*key_event);
+				Some(self.into())
+			}
+		}
+	}
+
+	/// Replaces the text of the focused field
+	fn with_text<S: Into<String>>(mut self, text: S) -> Popup {
+		let focused = self.focused;
+		let mut text_area = TextArea::default();
+		text_area.insert_str(text.into());
+		self.fields[focused].1 = text_area;
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct DatePicker(Box<DatePickerInner>);
+
+impl Deref for DatePicker {
+	type Target = DatePickerInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for DatePicker {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+pub struct DatePickerInner {
+	/// The date that is selected in the calendar
+	date: NaiveDate,
+	on_submit: Rc<DateCallback>,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl DatePickerInner {
+	/// Creates a new popup showing the month of the given date as a calendar, with the date
+	/// selected. The [`DateCallback`] is given the date the user picked
+	pub fn new<F>(title: &str, date: NaiveDate, f: F) -> Self
+	where
+		F: DateCallbackFn + 'static,
+	{
+		Self {
+			date,
+			on_submit: Rc::new(f),
+			title: title.to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn date(&self) -> NaiveDate {
+		self.date
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+
+	/// Moves the selection by the given amount of days, keeping it where it is if that would
+	/// leave the range of dates
+	fn move_days(&mut self, days: i64) {
+		let moved = if days < 0 {
+			self.date.checked_sub_days(Days::new(days.unsigned_abs()))
+		} else {
+			self.date.checked_add_days(Days::new(days.unsigned_abs()))
+		};
+		self.date = moved.unwrap_or(self.date);
+	}
+
+	/// Moves the selection to the same day of the next or previous month, or the last day of that
+	/// month if it is shorter
+	fn move_month(&mut self, forward: bool) {
+		let moved = if forward {
+			self.date.checked_add_months(Months::new(1))
+		} else {
+			self.date.checked_sub_months(Months::new(1))
+		};
+		self.date = moved.unwrap_or(self.date);
+	}
+}
+
+impl PopupBehaviour for DatePicker {
+	/// Moves the selection by a day with h/l and a week with j/k (or the arrow keys), and by a
+	/// month with H/L. t goes to today, and i switches to typing the date. Calls
+	/// [`DatePickerInner::on_submit`] with the selected date on [`KeyCode::Enter`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Enter => {
+				let date = self.date;
+				return (self.on_submit.clone())(self.into(), date, view, model, cs);
+			}
+			KeyCode::Char('h') | KeyCode::Left => self.move_days(-1),
+			KeyCode::Char('l') | KeyCode::Right => self.move_days(1),
+			KeyCode::Char('k') | KeyCode::Up => self.move_days(-7),
+			KeyCode::Char('j') | KeyCode::Down => self.move_days(7),
+			KeyCode::Char('H') | KeyCode::PageUp => self.move_month(false),
+			KeyCode::Char('L') | KeyCode::PageDown => self.move_month(true),
+			KeyCode::Char('t') => self.date = clock::today(),
+			KeyCode::Char('i') => {
+				let on_submit = self.on_submit.clone();
+				return Some(
+					Input(Box::new(
+						InputInner::new(&self.title, move |popup, text, view, model, cs| {
+							match model::Transaction::parse_date(text.trim()) {
+								Ok(date) => on_submit(popup, date, view, model, cs),
+								Err(e) => Some(popup.with_error(e.message)),
+							}
+						})
+						.with_validator(defaults::preview_date),
+					))
+					.with_subtitle("(Date)")
+					.with_text(self.date.to_string()),
+				);
+			}
+			KeyCode::Char('q') | KeyCode::Esc => return None,
+			_ => {}
+		}
+		Some(self.into())
+	}
+
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
 pub struct Confirm(Box<ConfirmInner>);
 
 impl Deref for Confirm {
@@ -228,8 +916,8 @@ impl DerefMut for Confirm {
 	}
 }
 
-pub trait ConfirmCallbackFn: Fn(bool, &mut Model) {}
-impl<T> ConfirmCallbackFn for T where T: Fn(bool, &mut Model) {}
+pub trait ConfirmCallbackFn: Fn(bool, &mut View, &mut Model, &mut ControllerState) {}
+impl<T> ConfirmCallbackFn for T where T: Fn(bool, &mut View, &mut Model, &mut ControllerState) {}
 
 pub type ConfirmCallback = dyn ConfirmCallbackFn;
 
@@ -271,14 +959,20 @@ impl ConfirmInner {
 impl PopupBehaviour for Confirm {
 	/// Handles the given key events. This is necessary since the popups hijack the controls while
 	/// visible
-	fn handle_key_event(self, key_event: &KeyEvent, model: &mut Model) -> Option<Popup> {
+	fn handle_key_event(
+		self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
 		match key_event.code {
 			KeyCode::Char('y') | KeyCode::Enter => {
-				(self.on_submit)(true, model);
+				(self.on_submit)(true, view, model, cs);
 				None
 			}
 			KeyCode::Char('n') => {
-				(self.on_submit)(false, model);
+				(self.on_submit)(false, view, model, cs);
 				None
 			}
 			KeyCode::Char('q') | KeyCode::Esc => None,
@@ -306,3 +1000,1472 @@ impl PopupBehaviour for Confirm {
 		self.into()
 	}
 }
+
+pub struct Progress(Box<ProgressInner>);
+
+impl Deref for Progress {
+	type Target = ProgressInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Progress {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+/// Shows how far along the running task is. See [`ControllerState::start_task`]
+pub struct ProgressInner {
+	progress: Arc<TaskProgress>,
+	/// When the task was started
+	started: Instant,
+	/// What the task is doing, like the file being read
+	text: String,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl ProgressInner {
+	pub fn new(title: &str, progress: Arc<TaskProgress>) -> Self {
+		Self {
+			progress,
+			started: Instant::now(),
+			text: String::new(),
+			title: title.to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+	/// Returns the fraction of the task that is done, or [`None`] if that isn't known
+	pub fn ratio(&self) -> Option<f64> {
+		self.progress.ratio()
+	}
+	/// Returns how long the task has been running
+	pub fn elapsed(&self) -> Duration {
+		self.started.elapsed()
+	}
+	pub fn text(&self) -> &String {
+		&self.text
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Progress {
+	/// Handles the given key events. This is necessary since the popups hijack the controls while
+	/// visible
+	fn handle_key_event(
+		self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		_model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Char('q') | KeyCode::Esc => {
+				cs.cancel_task();
+				None
+			}
+			_ => Some(self.into()),
+		}
+	}
+	/// Adds some text to the popup
+	fn with_text<S: Into<String>>(mut self, text: S) -> Popup {
+		self.text = text.into();
+		self.into()
+	}
+	/// Adds a title to the popup
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+	/// Adds a subtitle to the popup
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+	/// Adds an error message to the popup
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct List(Box<ListInner>);
+
+impl Deref for List {
+	type Target = ListInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for List {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+pub trait ListCallbackFn:
+	Fn(Popup, usize, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+impl<T> ListCallbackFn for T where
+	T: Fn(Popup, usize, &mut View, &mut Model, &mut ControllerState) -> Option<Popup>
+{
+}
+
+pub type ListCallback = dyn ListCallbackFn;
+
+pub struct ListInner {
+	items: Vec<String>,
+	pub list_state: ListState,
+	on_select: Rc<ListCallback>,
+	header: Option<String>,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl ListInner {
+	/// Creates a new list popup showing the given items, with the first one selected. The
+	/// [`ListCallback`] is given the index of the item the user selected
+	pub fn new<F>(title: &str, items: Vec<String>, f: F) -> Self
+	where
+		F: ListCallbackFn + 'static,
+	{
+		Self {
+			items,
+			list_state: ListState::default().with_selected(Some(0)),
+			on_select: Rc::new(f),
+			header: None,
+			title: title.to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	/// Creates a new list popup showing each of the given items as its [`Display`], with the
+	/// first one selected. The callback is given the item the user selected, rather than its
+	/// index, so it doesn't need to keep a copy of the items to look it up in
+	pub fn from_items<T, F>(title: &str, items: Vec<T>, f: F) -> Self
+	where
+		T: Display + 'static,
+		F: Fn(Popup, &T, &mut View, &mut Model, &mut ControllerState) -> Option<Popup> + 'static,
+	{
+		let names = items.iter().map(ToString::to_string).collect();
+		Self::new(title, names, move |popup, index, view, model, cs| {
+			f(popup, &items[index], view, model, cs)
+		})
+	}
+
+	pub fn items(&self) -> &Vec<String> {
+		&self.items
+	}
+	pub fn header(&self) -> Option<&String> {
+		self.header.as_ref()
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for List {
+	/// Moves the selection with j/k, gg/G (or the arrow keys/home/end), and calls
+	/// [`ListInner::on_select`] with the selected index on [`KeyCode::Enter`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Enter => match self.list_state.selected() {
+				Some(index) if index < self.items.len() => {
+					(self.on_select.clone())(self.into(), index, view, model, cs)
+				}
+				_ => Some(self.into()),
+			},
+			KeyCode::Char('j') | KeyCode::Down => {
+				let last = self.items.len().saturating_sub(1);
+				let next = self.list_state.selected().map_or(0, |i| (i + 1).min(last));
+				self.list_state.select(Some(next));
+				Some(self.into())
+			}
+			KeyCode::Char('k') | KeyCode::Up => {
+				self.list_state.select_previous();
+				Some(self.into())
+			}
+			KeyCode::Char('g') | KeyCode::Home => {
+				self.list_state.select(Some(0));
+				Some(self.into())
+			}
+			KeyCode::Char('G') | KeyCode::End => {
+				let last = self.items.len().saturating_sub(1);
+				self.list_state.select(Some(last));
+				Some(self.into())
+			}
+			KeyCode::Char('q') | KeyCode::Esc => None,
+			_ => Some(self.into()),
+		}
+	}
+
+	/// Adds a header line above the items, e.g. for column headings
+	fn with_text<S: Into<String>>(mut self, text: S) -> Popup {
+		self.header = Some(text.into());
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Forecast(Box<ForecastInner>);
+
+impl Deref for Forecast {
+	type Target = ForecastInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Forecast {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug)]
+pub struct ForecastInner {
+	sheet_index: usize,
+	forecast: model::Forecast,
+	min_balance: Option<f64>,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl ForecastInner {
+	/// Creates a new popup showing the forecast of the sheet with the given index
+	pub fn new(sheet_index: usize, forecast: model::Forecast, min_balance: Option<f64>) -> Self {
+		Self {
+			sheet_index,
+			forecast,
+			min_balance,
+			title: "Forecast".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn forecast(&self) -> &model::Forecast {
+		&self.forecast
+	}
+	pub fn min_balance(&self) -> Option<f64> {
+		self.min_balance
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Forecast {
+	/// Closes on [`KeyCode::Esc`] or `q`, opens an input popup to set the minimum balance of the
+	/// sheet on `m`, and one to export the chart to an image on `e`
+	fn handle_key_event(
+		self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		_model: &mut Model,
+		_cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Esc | KeyCode::Char('q') => None,
+			KeyCode::Char('m') => Some(defaults::min_balance_input(
+				self.sheet_index,
+				self.min_balance,
+			)),
+			KeyCode::Char('e') => Some(defaults::export_chart_input(
+				self.forecast.clone(),
+				self.min_balance,
+				self.title.clone(),
+			)),
+			_ => Some(self.into()),
+		}
+	}
+
+	/// Forecasts have no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Diff(Box<DiffInner>);
+
+impl Deref for Diff {
+	type Target = DiffInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Diff {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug)]
+pub struct DiffInner {
+	sheet_index: usize,
+	edits: Vec<model::CellEdit>,
+	offset: usize,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl DiffInner {
+	/// Creates a new popup previewing the given edits to the sheet with the given index, which
+	/// are only applied once the user confirms them
+	pub fn new(sheet_index: usize, edits: Vec<model::CellEdit>) -> Self {
+		Self {
+			sheet_index,
+			edits,
+			offset: 0,
+			title: "Preview changes".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn edits(&self) -> &Vec<model::CellEdit> {
+		&self.edits
+	}
+	/// The index of the first edit shown, as the preview can be scrolled
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Diff {
+	/// Applies the edits on `y` or [`KeyCode::Enter`], discards them on `n`, `q` or
+	/// [`KeyCode::Esc`], and scrolls the preview with j/k
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		model: &mut Model,
+		_cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Char('y') | KeyCode::Enter => {
+				let message = Message::ApplyEdits {
+					sheet: self.sheet_index,
+					edits: self.edits.clone(),
+				};
+				match model.update(message) {
+					Ok(_) => None,
+					Err(e) => Some(self.with_error(e.to_string())),
+				}
+			}
+			KeyCode::Char('n' | 'q') | KeyCode::Esc => None,
+			KeyCode::Char('j') | KeyCode::Down => {
+				self.offset = (self.offset + 1).min(self.edits.len().saturating_sub(1));
+				Some(self.into())
+			}
+			KeyCode::Char('k') | KeyCode::Up => {
+				self.offset = self.offset.saturating_sub(1);
+				Some(self.into())
+			}
+			_ => Some(self.into()),
+		}
+	}
+
+	/// Diffs have no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Compare(Box<CompareInner>);
+
+impl Deref for Compare {
+	type Target = CompareInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Compare {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug)]
+pub struct CompareInner {
+	rows: Vec<model::RowDiff>,
+	/// Whether the transactions that are the same in both versions are shown, around the ones
+	/// that differ
+	show_same: bool,
+	offset: usize,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl CompareInner {
+	/// Creates a new popup showing how each transaction differs between two versions of a sheet.
+	/// See [`model::compare_sheets`]
+	pub fn new(rows: Vec<model::RowDiff>) -> Self {
+		Self {
+			rows,
+			show_same: false,
+			offset: 0,
+			title: "Compare".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	/// Returns the rows shown, which are only those that differ unless the unchanged ones are
+	/// shown too
+	pub fn shown_rows(&self) -> impl Iterator<Item = &model::RowDiff> {
+		self.rows
+			.iter()
+			.filter(|row| self.show_same || !matches!(row, model::RowDiff::Same(_)))
+	}
+	/// Returns how many transactions were added, removed and changed
+	pub fn counts(&self) -> (usize, usize, usize) {
+		self.rows
+			.iter()
+			.fold((0, 0, 0), |(added, removed, changed), row| match row {
+				model::RowDiff::Added(_) => (added + 1, removed, changed),
+				model::RowDiff::Removed(_) => (added, removed + 1, changed),
+				model::RowDiff::Changed { .. } => (added, removed, changed + 1),
+				model::RowDiff::Same(_) => (added, removed, changed),
+			})
+	}
+	pub fn show_same(&self) -> bool {
+		self.show_same
+	}
+	/// The index of the first shown row that is drawn, as the rows can be scrolled
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Compare {
+	/// Scrolls with j/k, shows or hides the unchanged rows with `a`, and closes on `q` or
+	/// [`KeyCode::Esc`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		_model: &mut Model,
+		_cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Char('q') | KeyCode::Esc => return None,
+			KeyCode::Char('j') | KeyCode::Down => {
+				self.offset = (self.offset + 1).min(self.shown_rows().count().saturating_sub(1));
+			}
+			KeyCode::Char('k') | KeyCode::Up => self.offset = self.offset.saturating_sub(1),
+			KeyCode::Char('a') => {
+				self.show_same = !self.show_same;
+				self.offset = 0;
+			}
+			_ => {}
+		}
+		Some(self.into())
+	}
+
+	/// Comparisons have no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Merge(Box<MergeInner>);
+
+impl Deref for Merge {
+	type Target = MergeInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Merge {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+/// The other copy of the file being merged, which decides what is done once it has been
+#[derive(Debug, Clone)]
+pub enum MergeSource {
+	/// A copy the user picked, which is left as it is
+	File,
+	/// The file on disk, which the merged model is then saved over
+	Disk,
+	/// The copy on the server with the `ETag`, which the merged model is then uploaded over. See
+	/// [`sync`](crate::sync)
+	Remote { etag: String },
+}
+
+#[derive(Debug)]
+pub struct MergeInner {
+	merges: Vec<model::SheetMerge>,
+	/// The index of the selected row, counting the rows of every sheet in turn
+	selected: usize,
+	source: MergeSource,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl MergeInner {
+	/// Creates a new popup to pick which versions of the transactions that differ from another
+	/// copy of the file are kept. See [`Model::merge_rows`]
+	pub fn new(merges: Vec<model::SheetMerge>, source: MergeSource) -> Self {
+		Self {
+			merges,
+			selected: 0,
+			source,
+			title: "Merge".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn merges(&self) -> &Vec<model::SheetMerge> {
+		&self.merges
+	}
+	pub fn selected(&self) -> usize {
+		self.selected
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+
+	fn rows_mut(&mut self) -> impl Iterator<Item = &mut model::MergeRow> {
+		self.merges.iter_mut().flat_map(|merge| &mut merge.rows)
+	}
+
+	/// Sets what is kept of the selected row, or of every row
+	fn pick(&mut self, pick: Option<model::Pick>, all: bool) {
+		let selected = self.selected;
+		for (index, row) in self.rows_mut().enumerate() {
+			if all || index == selected {
+				row.pick = pick.unwrap_or(row.pick.next());
+			}
+		}
+	}
+
+	/// Keeps the picked versions, then saves over the file on disk if it was merged with, or
+	/// records that the copy on the server was merged, so the next sync uploads over it
+	fn apply(self, model: &mut Model, cs: &mut ControllerState) {
+		let count = self
+			.merges
+			.iter()
+			.map(|merge| merge.rows.len())
+			.sum::<usize>();
+		let message = Message::Merge {
+			merges: self.merges,
+		};
+		if cs.send(model, message).is_none() {
+			return;
+		}
+		let merged = match count {
+			1 => "Merged 1 row".to_string(),
+			count => format!("Merged {count} rows"),
+		};
+		match self.source {
+			MergeSource::File => cs.notify(merged),
+			MergeSource::Disk => {
+				if let Ok(Some(disk)) = model.read_disk() {
+					model.keep_over_disk(disk);
+				}
+				cs.notify(merged);
+			}
+			MergeSource::Remote { etag } => {
+				if let Some(filename) = &model.filename {
+					// The file has changed since, as far as syncing knows, so it is uploaded
+					let synced = sync::SyncState {
+						etag,
+						hash: String::new(),
+					};
+					state::set_sync_state(Path::new(filename), &synced);
+				}
+				cs.notify(format!("{merged}. Save and sync again (gP) to upload them"));
+			}
+		}
+	}
+}
+
+impl PopupBehaviour for Merge {
+	/// Moves between rows with j/k, keeps the selected row's version here on `m`, the other copy's
+	/// on `t`, both on `b`, cycles through them on [`KeyCode::Char(' ')`] (capital for every row),
+	/// merges on `y` and cancels on `q` or [`KeyCode::Esc`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		let count = self.rows_mut().count();
+		let all = matches!(key_event.code, KeyCode::Char(c) if c.is_uppercase());
+		match key_event.code {
+			KeyCode::Char('q') | KeyCode::Esc => return None,
+			KeyCode::Char('y') => {
+				self.0.apply(model, cs);
+				return None;
+			}
+			KeyCode::Char('j') | KeyCode::Down => {
+				self.selected = (self.selected + 1).min(count.saturating_sub(1));
+			}
+			KeyCode::Char('k') | KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+			KeyCode::Char('m' | 'M') => self.pick(Some(model::Pick::Mine), all),
+			KeyCode::Char('t' | 'T') => self.pick(Some(model::Pick::Theirs), all),
+			KeyCode::Char('b' | 'B') => self.pick(Some(model::Pick::Both), all),
+			KeyCode::Char(' ') => self.pick(None, false),
+			_ => {}
+		}
+		Some(self.into())
+	}
+
+	/// Merges have no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Duplicates(Box<DuplicatesInner>);
+
+impl Deref for Duplicates {
+	type Target = DuplicatesInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Duplicates {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug)]
+pub struct DuplicatesInner {
+	pending: model::PendingImport,
+	list_state: ListState,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl DuplicatesInner {
+	/// Creates a new popup for reviewing the conflicts of the import, which is only finished once
+	/// the user confirms it
+	pub fn new(pending: model::PendingImport) -> Self {
+		Self {
+			pending,
+			list_state: ListState::default().with_selected(Some(0)),
+			title: "Possible duplicates".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn pending(&self) -> &model::PendingImport {
+		&self.pending
+	}
+	pub fn list_state(&self) -> &ListState {
+		&self.list_state
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+
+	/// Sets the resolution of the selected conflict, and moves on to the next one
+	fn resolve(&mut self, resolution: model::Resolution) {
+		if let Some(conflict) = self
+			.list_state
+			.selected()
+			.and_then(|i| self.pending.conflicts.get_mut(i))
+		{
+			conflict.resolution = resolution;
+			self.list_state.select_next();
+		}
+	}
+}
+
+impl PopupBehaviour for Duplicates {
+	/// Resolves the selected conflict with `s` (skip), `a` (keep both) or `m` (merge), or cycles
+	/// through them with [`KeyCode::Tab`]. `S`, `A` and `M` resolve every conflict at once. The
+	/// import is finished on `y` or [`KeyCode::Enter`], and cancelled on `q` or [`KeyCode::Esc`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		model: &mut Model,
+		_cs: &mut ControllerState,
+	) -> Option<Popup> {
+		let all = |popup: &mut Self, resolution| {
+			for conflict in &mut popup.pending.conflicts {
+				conflict.resolution = resolution;
+			}
+		};
+		match key_event.code {
+			KeyCode::Char('y') | KeyCode::Enter => {
+				let sheet = self.pending.source.sheet().to_string();
+				let counts = model.finish_import(self.0.pending);
+				return Some(defaults::imported(counts, &sheet));
+			}
+			KeyCode::Char('q') | KeyCode::Esc => return None,
+			KeyCode::Char('j') | KeyCode::Down => self.list_state.select_next(),
+			KeyCode::Char('k') | KeyCode::Up => self.list_state.select_previous(),
+			KeyCode::Char('s') => self.resolve(model::Resolution::Skip),
+			KeyCode::Char('a') => self.resolve(model::Resolution::Keep),
+			KeyCode::Char('m') => self.resolve(model::Resolution::Merge),
+			KeyCode::Char('S') => all(&mut self, model::Resolution::Skip),
+			KeyCode::Char('A') => all(&mut self, model::Resolution::Keep),
+			KeyCode::Char('M') => all(&mut self, model::Resolution::Merge),
+			KeyCode::Tab => {
+				if let Some(conflict) = self
+					.list_state
+					.selected()
+					.and_then(|i| self.pending.conflicts.get_mut(i))
+				{
+					conflict.resolution = conflict.resolution.next();
+				}
+			}
+			_ => {}
+		}
+		// Selecting past the end is clamped when rendering, so it's clamped here too, so that the
+		// selection always points at a conflict
+		let last = self.pending.conflicts.len().saturating_sub(1);
+		if self.list_state.selected().is_some_and(|i| i > last) {
+			self.list_state.select(Some(last));
+		}
+		Some(self.into())
+	}
+
+	/// The conflicts are the content, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Review(Box<ReviewInner>);
+
+impl Deref for Review {
+	type Target = ReviewInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Review {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ReviewInner {
+	sheet_index: usize,
+	items: Vec<model::ReviewItem>,
+	/// The index of the item being reviewed
+	position: usize,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl ReviewInner {
+	/// Creates a new popup stepping through the given transactions of the sheet with the given
+	/// index
+	pub fn new(sheet_index: usize, items: Vec<model::ReviewItem>) -> Self {
+		Self {
+			sheet_index,
+			items,
+			position: 0,
+			title: "Review".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn items(&self) -> &Vec<model::ReviewItem> {
+		&self.items
+	}
+	/// The index of the item being reviewed
+	pub fn position(&self) -> usize {
+		self.position
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+
+	pub fn sheet_index(&self) -> usize {
+		self.sheet_index
+	}
+
+	/// The item being reviewed
+	pub fn current(&self) -> &model::ReviewItem {
+		&self.items[self.position]
+	}
+
+	/// Marks the reason as dealt with for the item being reviewed, and updates its transaction
+	/// from the model. Moves on to the next item if that was the last reason
+	pub fn resolve(
+		mut self,
+		reason: &model::ReviewReason,
+		view: &mut View,
+		model: &Model,
+	) -> Popup {
+		let sheet_index = self.sheet_index;
+		let item = &mut self.items[self.position];
+		item.reasons.retain(|r| r != reason);
+		if let Some(t) = model
+			.get_sheet(sheet_index)
+			.and_then(|s| s.transactions().get(item.row))
+		{
+			item.transaction = t.clone();
+		}
+		if item.reasons.is_empty() {
+			self.step(1, view, model)
+		} else {
+			Review(Box::new(self)).into()
+		}
+	}
+
+	/// Records that a transaction was split into a new row, so the rows after it move down, and
+	/// the new row is reviewed next
+	pub fn split(&mut self, new_row: usize, model: &Model) {
+		for item in &mut self.items {
+			if item.row >= new_row {
+				item.row += 1;
+			}
+		}
+		let sheet = model.get_sheet(self.sheet_index).unwrap();
+		self.items[self.position].transaction = sheet.transactions()[new_row - 1].clone();
+		let transaction = sheet.transactions()[new_row].clone();
+		let mut reasons = vec![model::ReviewReason::Uncategorised];
+		if transaction.pending {
+			reasons.push(model::ReviewReason::Pending);
+		}
+		self.items.insert(
+			self.position + 1,
+			model::ReviewItem {
+				row: new_row,
+				transaction,
+				reasons,
+			},
+		);
+	}
+
+	/// Moves forward (or back) through the items, selecting the row of the new one in the view.
+	/// Finishes the review when moving past the last item
+	pub fn step(mut self, by: isize, view: &mut View, model: &Model) -> Popup {
+		match self.position.checked_add_signed(by) {
+			Some(position) if position < self.items.len() => {
+				self.position = position;
+				view.select_row(self.current().row, model);
+				Review(Box::new(self)).into()
+			}
+			Some(_) => Info(Box::default())
+				.with_title("Review")
+				.with_text(format!("Reviewed {} transactions", self.items.len())),
+			None => Review(Box::new(self)).into(),
+		}
+	}
+}
+
+impl PopupBehaviour for Review {
+	/// Opens an input popup to categorise the transaction on `c`, marks it cleared on `x`, opens
+	/// an input popup to split it on `s`, moves to the next transaction on `n`, `j` or
+	/// [`KeyCode::Char(' ')`], back on `k`, and closes on `q` or [`KeyCode::Esc`]
+	fn handle_key_event(
+		self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		let row = self.current().row;
+		match key_event.code {
+			KeyCode::Char('q') | KeyCode::Esc => None,
+			KeyCode::Char('c') => Some(defaults::review_category(*self.0, model)),
+			KeyCode::Char('s') => Some(defaults::review_split(*self.0)),
+			KeyCode::Char('x') => {
+				let message = Message::SetPending {
+					sheet: self.sheet_index,
+					row,
+					pending: false,
+				};
+				cs.send(model, message);
+				Some(self.0.resolve(&model::ReviewReason::Pending, view, model))
+			}
+			KeyCode::Char('n' | 'j' | ' ') => Some(self.0.step(1, view, model)),
+			KeyCode::Char('k') => Some(self.0.step(-1, view, model)),
+			_ => Some(self.into()),
+		}
+	}
+
+	/// Reviews have no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Report(Box<ReportInner>);
+
+impl Deref for Report {
+	type Target = ReportInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Report {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug)]
+pub struct ReportInner {
+	report: model::Report,
+	/// How the report is shown
+	kind: model::ReportKind,
+	/// How many lines the report is scrolled down by
+	scroll: u16,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl ReportInner {
+	/// Creates a new popup showing the report
+	pub fn new(report: model::Report, kind: model::ReportKind) -> Self {
+		Self {
+			report,
+			kind,
+			scroll: 0,
+			title: "Report".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn report(&self) -> &model::Report {
+		&self.report
+	}
+	pub fn kind(&self) -> model::ReportKind {
+		self.kind
+	}
+	pub fn scroll(&self) -> u16 {
+		self.scroll
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Report {
+	/// Scrolls with j/k, asks for another range on `r`, opens an input popup to export the report
+	/// to CSV on `e`, and closes on [`KeyCode::Esc`] or `q`
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		_model: &mut Model,
+		_cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Esc | KeyCode::Char('q') => None,
+			KeyCode::Char('j') | KeyCode::Down => {
+				self.scroll = self.scroll.saturating_add(1);
+				Some(self.into())
+			}
+			KeyCode::Char('k') | KeyCode::Up => {
+				self.scroll = self.scroll.saturating_sub(1);
+				Some(self.into())
+			}
+			KeyCode::Char('r') => Some(defaults::report_range_input(self.kind)),
+			KeyCode::Char('e') => Some(defaults::export_report_input(
+				self.report.clone(),
+				&self.title,
+			)),
+			_ => Some(self.into()),
+		}
+	}
+
+	/// Reports have no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Start(Box<StartInner>);
+
+impl Deref for Start {
+	type Target = StartInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Start {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct StartInner {
+	/// The files opened recently, most recent first
+	files: Vec<PathBuf>,
+	pub list_state: ListState,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl StartInner {
+	/// Creates a new start screen listing the given files, with the first one selected
+	pub fn new(files: Vec<PathBuf>) -> Self {
+		Self {
+			files,
+			list_state: ListState::default().with_selected(Some(0)),
+			title: "Start".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	pub fn files(&self) -> &Vec<PathBuf> {
+		&self.files
+	}
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Start {
+	/// Moves the selection with j/k, opens the selected file on [`KeyCode::Enter`], asks for the
+	/// name of a new file on `n`, starts a scratch session on `s` or [`KeyCode::Esc`], and quits
+	/// on `q`
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		_view: &mut View,
+		_model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		match key_event.code {
+			KeyCode::Enter => {
+				let Some(path) = self.list_state.selected().and_then(|i| self.files.get(i)) else {
+					return Some(self.into());
+				};
+				Some(defaults::open_file(path.display().to_string(), cs))
+			}
+			KeyCode::Char('j') | KeyCode::Down => {
+				let last = self.files.len().saturating_sub(1);
+				let next = self.list_state.selected().map_or(0, |i| (i + 1).min(last));
+				self.list_state.select(Some(next));
+				Some(self.into())
+			}
+			KeyCode::Char('k') | KeyCode::Up => {
+				self.list_state.select_previous();
+				Some(self.into())
+			}
+			KeyCode::Char('n') => Some(defaults::new_file_input()),
+			KeyCode::Char('s') | KeyCode::Esc => None,
+			KeyCode::Char('q') => {
+				cs.exit = true;
+				None
+			}
+			_ => Some(self.into()),
+		}
+	}
+
+	/// The start screen has no text, so this does nothing
+	fn with_text<S: Into<String>>(self, _text: S) -> Popup {
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+pub struct Switcher(Box<SwitcherInner>);
+
+impl Deref for Switcher {
+	type Target = SwitcherInner;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Switcher {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SwitcherInner {
+	/// The text the files are filtered by
+	pub text_area: TextArea<'static>,
+	/// The files opened recently, most recent first
+	files: Vec<PathBuf>,
+	/// The selected file, as an index into [`SwitcherInner::matches`]
+	pub list_state: ListState,
+	/// Whether the file is opened alongside the current one, rather than in its place
+	alongside: bool,
+	title: String,
+	subtitle: Option<String>,
+	error: Option<String>,
+}
+
+impl SwitcherInner {
+	/// Creates a new switcher between the given files, with the first one selected. The chosen
+	/// file is opened alongside the current one if `alongside`, or in its place otherwise
+	pub fn new(files: Vec<PathBuf>, alongside: bool) -> Self {
+		Self {
+			text_area: TextArea::default(),
+			files,
+			list_state: ListState::default().with_selected(Some(0)),
+			alongside,
+			title: "Open file".to_string(),
+			subtitle: None,
+			error: None,
+		}
+	}
+
+	/// The text typed to filter the files
+	pub fn query(&self) -> String {
+		self.text_area.lines().join("")
+	}
+
+	/// Returns the files matching the query, in the order they were opened. A file matches if
+	/// every character of the query appears in its path in order, ignoring case
+	pub fn matches(&self) -> Vec<&PathBuf> {
+		let query = self.query().to_lowercase();
+		self.files
+			.iter()
+			.filter(|file| {
+				let path = file.display().to_string().to_lowercase();
+				let mut chars = path.chars();
+				query.chars().all(|q| chars.any(|c| c == q))
+			})
+			.collect()
+	}
+
+	pub fn title(&self) -> &String {
+		&self.title
+	}
+	pub fn subtitle(&self) -> Option<&String> {
+		self.subtitle.as_ref()
+	}
+	pub fn error(&self) -> Option<&String> {
+		self.error.as_ref()
+	}
+}
+
+impl PopupBehaviour for Switcher {
+	/// Filters the files by what is typed, moves the selection with the arrow keys (or
+	/// ctrl+n/ctrl+p), and opens the selected file on [`KeyCode::Enter`] - or the typed path, if
+	/// no file matches it. Closes on [`KeyCode::Esc`]
+	fn handle_key_event(
+		mut self,
+		key_event: &KeyEvent,
+		view: &mut View,
+		model: &mut Model,
+		cs: &mut ControllerState,
+	) -> Option<Popup> {
+		let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+		match key_event.code {
+			KeyCode::Enter => {
+				let matches = self.matches();
+				let path = match self.list_state.selected().and_then(|i| matches.get(i)) {
+					Some(path) => path.display().to_string(),
+					None => self.query().trim().to_string(),
+				};
+				if path.is_empty() {
+					return Some(self.with_error("Type the path of a file to open"));
+				}
+				if !self.alongside {
+					return Some(defaults::switch_to_file(path, model, cs));
+				}
+				match cs.open_alongside(&path, view, model) {
+					Ok(()) => None,
+					Err(e) => defaults::opening_popup(&e, &path, true, Access::Exclusive)
+						.or_else(|| Some(self.with_error(format!("Couldn't open {path}: {e}")))),
+				}
+			}
+			KeyCode::Esc => None,
+			KeyCode::Down => Some(self.step(1)),
+			KeyCode::Char('n') if control => Some(self.step(1)),
+			KeyCode::Up => Some(self.step(-1)),
+			KeyCode::Char('p') if control => Some(self.step(-1)),
+			_ => {
+				self.text_area.input(*key_event);
+				self.list_state.select(Some(0));
+				Some(self.into())
+			}
+		}
+	}
+
+	/// Types the text into the filter
+	fn with_text<S: Into<String>>(mut self, text: S) -> Popup {
+		self.text_area.insert_str(text.into());
+		self.into()
+	}
+
+	fn with_title<S: Into<String>>(mut self, title: S) -> Popup {
+		self.title = title.into();
+		self.into()
+	}
+
+	fn with_subtitle<S: Into<String>>(mut self, subtitle: S) -> Popup {
+		self.subtitle = Some(subtitle.into());
+		self.into()
+	}
+
+	fn with_error<S: Into<String>>(mut self, error: S) -> Popup {
+		self.error = Some(error.into());
+		self.into()
+	}
+}
+
+impl Switcher {
+	/// Moves the selection down (or up) through the matching files
+	fn step(mut self, by: isize) -> Popup {
+		let last = self.matches().len().saturating_sub(1);
+		let selected = self
+			.list_state
+			.selected()
+			.unwrap_or(0)
+			.saturating_add_signed(by)
+			.min(last);
+		self.list_state.select(Some(selected));
+		self.into()
+	}
+}
@@ -0,0 +1,1195 @@
+//! The actions the program can run, each registered with an id and the keys it is bound to by
+//! default, which the controller's key bindings are made from. Forks and plugins can add actions
+//! of their own to the registry, or rebind or remove the builtin ones, and give it to
+//! [`Controller::with_registry`](crate::controller::Controller::with_registry), without changing
+//! the controller
+
+use anyhow::{Context, Result, bail, ensure};
+use ratatui::layout::Direction;
+
+use crate::{
+	controller::{
+		actions,
+		commands::{
+			ActionFn, ActionResult, Binding, Command, CommandTrie, OperatorFn, boxed_action,
+			boxed_operator,
+		},
+		keys::KeySequence,
+		popup,
+	},
+	model::Message,
+	view::DatePeriod,
+};
+
+/// The names of the groups of actions that start with the same keys, shown in the hints while
+/// a key sequence is being typed
+const GROUP_NAMES: &[(&str, &str)] = &[
+	("g", "go to, views and features"),
+	("z", "scroll columns and folds"),
+	("]", "next"),
+	("[", "previous"),
+	("<C-w>", "panes"),
+	("m", "marks"),
+	("'", "jumps and marks"),
+];
+
+/// An action in the registry, and the keys it is bound to
+#[derive(Debug)]
+pub struct RegisteredAction {
+	/// The id the action is looked up by, which stays the same when it is rebound, like `save`
+	pub id: String,
+	/// The keys the action is bound to, in the notation of [`KeySequence`]
+	pub keys: String,
+	/// A few words naming the action. See [`Binding::name`]
+	pub name: Option<String>,
+	/// What the action does. See [`Binding::description`]
+	pub description: Option<String>,
+	command: Command,
+}
+
+/// Every action the controller can run, by id, in the order they were registered
+#[derive(Debug, Default)]
+pub struct ActionRegistry {
+	actions: Vec<RegisteredAction>,
+	/// The names of the groups of actions, by the keys they start with. See
+	/// [`ActionRegistry::name_group`]
+	groups: Vec<(String, String)>,
+}
+
+impl ActionRegistry {
+	/// Returns the program's own actions, bound to their default keys
+	pub fn builtin() -> Self {
+		let groups: [fn(ActionRegistry) -> ActionRegistry; 13] = [
+			movement_actions,
+			jump_actions,
+			period_actions,
+			editing_actions,
+			register_actions,
+			display_actions,
+			fold_actions,
+			feature_actions,
+			import_actions,
+			file_actions,
+			sheet_actions,
+			pane_actions,
+			general_actions,
+		];
+		let registry = groups
+			.into_iter()
+			.fold(Self::default(), |registry, add| add(registry));
+		GROUP_NAMES.iter().fold(registry, |registry, (keys, name)| {
+			registry.name_group(keys, name)
+		})
+	}
+
+	/// Registers an action with the given id
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// If an action with the id is already registered
+	///
+	/// # Examples
+	/// ```
+	/// let registry = ActionRegistry::builtin()
+	///     .add("top", ("<C-g>", "top", "go to the first row"), |view, model, _, _| {
+	///         view.first_row(model)
+	///     });
+	/// ```
+	pub fn add<'a, F, R>(self, id: &str, binding: impl Into<Binding<'a>>, action: F) -> Self
+	where
+		F: ActionFn<R> + 'static,
+		R: ActionResult,
+	{
+		self.register(id, binding.into(), Command::Action(boxed_action(action)))
+	}
+
+	/// Registers an action that changes the model, and so can be repeated with `.`
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// See [`ActionRegistry::add`]
+	pub fn add_repeatable<'a, F, R>(
+		self,
+		id: &str,
+		binding: impl Into<Binding<'a>>,
+		action: F,
+	) -> Self
+	where
+		F: ActionFn<R> + 'static,
+		R: ActionResult,
+	{
+		self.register(
+			id,
+			binding.into(),
+			Command::Repeatable(boxed_action(action)),
+		)
+	}
+
+	/// Registers an action that moves the cursor, and so can be used after an operator
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// See [`ActionRegistry::add`]
+	pub fn add_motion<'a, F, R>(self, id: &str, binding: impl Into<Binding<'a>>, action: F) -> Self
+	where
+		F: ActionFn<R> + 'static,
+		R: ActionResult,
+	{
+		self.register(id, binding.into(), Command::Motion(boxed_action(action)))
+	}
+
+	/// Registers an operator, which is applied over the following motion, or to `count` rows if
+	/// the operator is typed twice (e.g. `yy`)
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// See [`ActionRegistry::add`]
+	pub fn add_operator<'a, F, R>(
+		self,
+		id: &str,
+		binding: impl Into<Binding<'a>>,
+		operator: F,
+	) -> Self
+	where
+		F: OperatorFn<R> + 'static,
+		R: ActionResult,
+	{
+		let command = Command::Operator {
+			operator: boxed_operator(operator),
+			repeatable: false,
+		};
+		self.register(id, binding.into(), command)
+	}
+
+	/// Registers an operator that changes the model, and so can be repeated with `.`
+	/// This is a fluent setter
+	///
+	/// # Panics
+	/// See [`ActionRegistry::add`]
+	pub fn add_repeatable_operator<'a, F, R>(
+		self,
+		id: &str,
+		binding: impl Into<Binding<'a>>,
+		operator: F,
+	) -> Self
+	where
+		F: OperatorFn<R> + 'static,
+		R: ActionResult,
+	{
+		let command = Command::Operator {
+			operator: boxed_operator(operator),
+			repeatable: true,
+		};
+		self.register(id, binding.into(), command)
+	}
+
+	/// Names the group of actions whose keys start with the given keys, for the hints shown while
+	/// a key sequence is being typed
+	/// This is a fluent setter
+	pub fn name_group(mut self, keys: &str, name: &str) -> Self {
+		self.groups.push((keys.to_string(), name.to_string()));
+		self
+	}
+
+	/// Returns the action with the given id
+	pub fn get(&self, id: &str) -> Option<&RegisteredAction> {
+		self.actions.iter().find(|action| action.id == id)
+	}
+
+	/// Returns every action, in the order they were registered
+	pub fn actions(&self) -> &[RegisteredAction] {
+		&self.actions
+	}
+
+	/// Binds the action with the given id to other keys
+	///
+	/// # Errors
+	/// If there is no action with the id, the keys aren't a key sequence, or another action is
+	/// bound to them
+	pub fn rebind(&mut self, id: &str, keys: &str) -> Result<()> {
+		let sequence: KeySequence = keys
+			.parse()
+			.with_context(|| format!("Invalid keys {keys}"))?;
+		if let Some(other) = self.actions.iter().find(|action| {
+			action.id != id
+				&& action
+					.keys
+					.parse::<KeySequence>()
+					.is_ok_and(|k| k == sequence)
+		}) {
+			bail!("{keys} is already bound to {}", other.id);
+		}
+		let action = self
+			.actions
+			.iter_mut()
+			.find(|action| action.id == id)
+			.with_context(|| format!("No action {id}"))?;
+		action.keys = keys.to_string();
+		Ok(())
+	}
+
+	/// Removes the action with the given id, returning whether there was one
+	pub fn remove(&mut self, id: &str) -> bool {
+		let len = self.actions.len();
+		self.actions.retain(|action| action.id != id);
+		self.actions.len() < len
+	}
+
+	/// Makes the trie the controller looks up the keys typed in. The names of groups that no
+	/// action starts with (once they have been removed or rebound) are left out
+	///
+	/// # Errors
+	/// If two actions are bound to the same keys
+	pub(super) fn into_trie(self) -> Result<CommandTrie> {
+		let mut trie = CommandTrie::default();
+		for action in self.actions {
+			let sequence: KeySequence = action.keys.parse()?;
+			ensure!(
+				trie.traverse(sequence.keys())
+					.and_then(CommandTrie::command)
+					.is_none(),
+				"{} is bound to more than one action",
+				action.keys
+			);
+			let binding = Binding {
+				keys: &action.keys,
+				name: action.name.as_deref(),
+				description: action.description.as_deref(),
+			};
+			trie.add_command(binding, action.command);
+		}
+		for (keys, name) in self.groups {
+			if keys
+				.parse::<KeySequence>()
+				.is_ok_and(|sequence| trie.traverse(sequence.keys()).is_some())
+			{
+				trie = trie.name_group(&keys, &name);
+			}
+		}
+		Ok(trie)
+	}
+
+	/// Adds the action to the registry
+	fn register(mut self, id: &str, binding: Binding<'_>, command: Command) -> Self {
+		assert!(self.get(id).is_none(), "Duplicate action {id}");
+		self.actions.push(RegisteredAction {
+			id: id.to_string(),
+			keys: binding.keys.to_string(),
+			name: binding.name.map(str::to_string),
+			description: binding.description.map(str::to_string),
+			command,
+		});
+		self
+	}
+}
+
+/// Adds the actions that go to the start of each month or week, by the dates of the rows
+fn period_actions(registry: ActionRegistry) -> ActionRegistry {
+	[(DatePeriod::Month, 'm'), (DatePeriod::Week, 'W')]
+		.into_iter()
+		.fold(registry, |registry, (period, key)| {
+			registry
+				.add(
+					format!("next-{period}").as_str(),
+					(
+						format!("]{key}").as_str(),
+						format!("next {period}").as_str(),
+						format!("go to the first row of the next (count) {period}s").as_str(),
+					),
+					move |view, model, _cs, count| {
+						for _ in 0..count.unwrap_or(1) {
+							ensure!(
+								view.go_to_period_start(period, true, model),
+								"No later {period}"
+							);
+						}
+						Ok(())
+					},
+				)
+				.add(
+					format!("{period}-start").as_str(),
+					(
+						format!("[{key}").as_str(),
+						format!("{period} start").as_str(),
+						format!(
+							"go to the first row of the {period}, or of the (count) {period}s before"
+						)
+						.as_str(),
+					),
+					move |view, model, _cs, count| {
+						for _ in 0..count.unwrap_or(1) {
+							ensure!(
+								view.go_to_period_start(period, false, model),
+								"No earlier {period}"
+							);
+						}
+						Ok(())
+					},
+				)
+		})
+}
+
+/// Adds the actions that go back and forth through the rows jumped away from, and to marked rows
+fn jump_actions(registry: ActionRegistry) -> ActionRegistry {
+	let registry = ('a'..='z').fold(registry, |registry, letter| {
+		registry
+			.add(
+				format!("set-mark-{letter}").as_str(),
+				(
+					format!("m{letter}").as_str(),
+					"set mark",
+					"mark the row with the letter, which stays on it when rows are sorted or added",
+				),
+				move |view, model, _cs, _count| {
+					ensure!(view.set_mark(letter, model), "No row to mark");
+					Ok(())
+				},
+			)
+			.add_motion(
+				format!("go-to-mark-{letter}").as_str(),
+				(
+					format!("'{letter}").as_str(),
+					"go to mark",
+					"go to the row marked with the letter",
+				),
+				move |view, model, _cs, _count| {
+					ensure!(
+						view.jump_to_mark(letter, model),
+						"Mark {letter} isn't set, or its row was deleted"
+					);
+					Ok(())
+				},
+			)
+	});
+	registry
+		.add_motion(
+			"last-jump",
+			(
+				"''",
+				"last jump",
+				"go back to the row jumped from last, with gg, G, a search or a sheet switch",
+			),
+			|view, model, _cs, _count| {
+				ensure!(view.jump_to_last(model), "No jump to go back to");
+				Ok(())
+			},
+		)
+		.add(
+			"jump-back",
+			(
+				"<C-o>",
+				"jump back",
+				"go back (count) jumps through the jump list",
+			),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					ensure!(view.jump_back(model), "No earlier jump");
+				}
+				Ok(())
+			},
+		)
+		.add(
+			"jump-forward",
+			(
+				"<C-i>",
+				"jump forward",
+				"go forward (count) jumps through the jump list, after going back",
+			),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					ensure!(view.jump_forward(model), "No later jump");
+				}
+				Ok(())
+			},
+		)
+}
+
+/// Adds the actions that move around the sheet
+fn movement_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add_motion(
+			"down",
+			("j", "down", "move down (count) rows"),
+			|view, model, _cs, count| {
+				view.down_by(count.unwrap_or(1), model);
+			},
+		)
+		.add_motion(
+			"up",
+			("k", "up", "move up (count) rows"),
+			|view, model, _cs, count| {
+				view.up_by(count.unwrap_or(1), model);
+			},
+		)
+		.add(
+			"left",
+			("h", "left", "move left (count) columns"),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					view.previous_column(model);
+				}
+			},
+		)
+		.add(
+			"right",
+			("l", "right", "move right (count) columns"),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					view.next_column(model);
+				}
+			},
+		)
+		.add(
+			"scroll-columns-left",
+			(
+				"zh",
+				"scroll columns left",
+				"scroll the columns left, when they don't all fit",
+			),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					view.scroll_columns(false, model);
+				}
+			},
+		)
+		.add(
+			"scroll-columns-right",
+			(
+				"zl",
+				"scroll columns right",
+				"scroll the columns right, when they don't all fit",
+			),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					view.scroll_columns(true, model);
+				}
+			},
+		)
+		.add_motion(
+			"first-row",
+			(
+				"gg",
+				"first row, or row (count)",
+				"go to the first row, or to row (count)",
+			),
+			|view, model, _cs, count| match count {
+				Some(row) => view.jump_to_row(row, model),
+				None => view.first_row(model),
+			},
+		)
+		.add_motion(
+			"last-row",
+			(
+				"G",
+				"last row, or row (count)",
+				"go to the last row, or to row (count)",
+			),
+			|view, model, _cs, count| match count {
+				Some(row) => view.jump_to_row(row, model),
+				None => view.last_row(model),
+			},
+		)
+		.add_motion(
+			"half-page-down",
+			(
+				"<C-d>",
+				"half a screen down",
+				"scroll down by half the screen",
+			),
+			|view, model, _cs, _count| view.half_down(model),
+		)
+		.add_motion(
+			"half-page-up",
+			("<C-u>", "half a screen up", "scroll up by half the screen"),
+			|view, model, _cs, _count| view.half_up(model),
+		)
+}
+
+/// Adds the actions that change which rows of the sheet are shown, and how
+fn display_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"search",
+			(
+				"/",
+				"search",
+				"search the sheet, showing only the matching rows",
+			),
+			popup::defaults::search,
+		)
+		.add(
+			"explore-labels",
+			(
+				"gl",
+				"explore labels",
+				"explore the labels of the sheet, and filter by the selected one",
+			),
+			popup::defaults::label_explorer,
+		)
+		.add(
+			"clear-filter",
+			("gL", "clear filter", "clear the filter of the sheet"),
+			|view, model, _cs, _count| {
+				view.set_filter(None, model);
+			},
+		)
+		.add(
+			"display-settings",
+			(
+				"gv",
+				"display settings",
+				"choose how the sheet is sorted and grouped, and which columns are shown",
+			),
+			popup::defaults::display_settings,
+		)
+		.add(
+			"sheet-settings",
+			(
+				"gV",
+				"sheet settings",
+				"set the account type, starting balance and account number of the sheet, and the sign and category of new rows",
+			),
+			popup::defaults::sheet_settings,
+		)
+}
+
+/// Adds the actions that fold the groups of rows under their headings, when the rows are
+/// grouped
+fn fold_actions(registry: ActionRegistry) -> ActionRegistry {
+	/// Shown when there are no groups to fold
+	const NOT_GROUPED: &str = "Group the rows (with gv) to fold them";
+
+	registry
+		.add(
+			"toggle-fold",
+			(
+				"za",
+				"toggle fold",
+				"fold or unfold the group of the selected row",
+			),
+			|view, model, _cs, _count| {
+				ensure!(view.fold(None, model), NOT_GROUPED);
+				Ok(())
+			},
+		)
+		.add(
+			"fold",
+			(
+				"zc",
+				"fold",
+				"fold the group of the selected row under its heading",
+			),
+			|view, model, _cs, _count| {
+				ensure!(view.fold(Some(true), model), NOT_GROUPED);
+				Ok(())
+			},
+		)
+		.add(
+			"unfold",
+			("zo", "unfold", "unfold the group of the selected row"),
+			|view, model, _cs, _count| {
+				ensure!(view.fold(Some(false), model), NOT_GROUPED);
+				Ok(())
+			},
+		)
+		.add(
+			"fold-all",
+			(
+				"zM",
+				"fold all",
+				"fold every group, so only their headings are shown",
+			),
+			|view, model, _cs, _count| {
+				ensure!(view.fold_all(true, model), NOT_GROUPED);
+				Ok(())
+			},
+		)
+		.add(
+			"unfold-all",
+			("zR", "unfold all", "unfold every group"),
+			|view, model, _cs, _count| {
+				ensure!(view.fold_all(false, model), NOT_GROUPED);
+				Ok(())
+			},
+		)
+}
+
+/// Adds the actions that change the rows and cells of the sheet
+fn editing_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"edit-cell",
+			("i", "edit cell", "change the value of the selected cell"),
+			popup::defaults::insert_action,
+		)
+		.add(
+			"edit-row",
+			(
+				"e",
+				"edit row",
+				"edit every column of the selected row at once",
+			),
+			popup::defaults::edit_row,
+		)
+		.add_repeatable(
+			"move-row-down",
+			("J", "move row down", "move the current row down"),
+			actions::move_row_down,
+		)
+		.add_repeatable(
+			"move-row-up",
+			("K", "move row up", "move the current row up"),
+			actions::move_row_up,
+		)
+		.add_repeatable_operator(
+			"fill-cell-down",
+			(
+				"F",
+				"fill cell down",
+				"fill the selected cell's value down over the rows the motion covers",
+			),
+			popup::defaults::fill_down,
+		)
+		.add(
+			"yank-cell",
+			(
+				"gy",
+				"yank cell",
+				"yank (copy) the value of the selected cell",
+			),
+			actions::yank_cell,
+		)
+		.add_repeatable(
+			"put-cell",
+			(
+				"gp",
+				"put cell",
+				"put (paste) the yanked cell value into the selected cell",
+			),
+			popup::defaults::put_cell,
+		)
+		.add(
+			"replace-in-labels",
+			(
+				"gr",
+				"replace in labels",
+				"replace text in the labels of every shown row",
+			),
+			popup::defaults::replace_labels,
+		)
+		.add(
+			"new-row-below",
+			("o", "new row below", "insert a new row below"),
+			popup::defaults::new_row_below,
+		)
+		.add(
+			"new-row-above",
+			("O", "new row above", "insert a new row above"),
+			popup::defaults::new_row_above,
+		)
+		.add(
+			"toggle-date-ordering",
+			(
+				"gO",
+				"toggle date ordering",
+				"toggle between date ordering and manual ordering of the sheet",
+			),
+			|view, model, cs, _count| {
+				let message = Message::ToggleOrder {
+					sheet: view.selected_sheet,
+				};
+				cs.send(model, message);
+			},
+		)
+}
+
+/// Adds the actions that yank, delete and put whole rows, through the register
+fn register_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add_operator(
+			"yank-rows",
+			("y", "yank rows", "yank (copy) the rows the motion covers"),
+			actions::yank,
+		)
+		.add_repeatable_operator(
+			"delete-rows",
+			("d", "delete rows", "delete the rows the motion covers"),
+			popup::defaults::delete,
+		)
+		.add(
+			"trash",
+			(
+				"gD",
+				"trash",
+				"list the rows recently deleted from the sheet, and restore them",
+			),
+			popup::defaults::trash,
+		)
+		.add(
+			"compare-sheets",
+			(
+				"gC",
+				"compare sheets",
+				"compare the sheet with its saved version or another sheet, row by row",
+			),
+			popup::defaults::compare,
+		)
+		.add(
+			"merge-file",
+			(
+				"gJ",
+				"merge file",
+				"merge another copy of the file into this one, picking which version of each row to keep",
+			),
+			popup::defaults::merge_file,
+		)
+		.add(
+			"sync",
+			(
+				"gP",
+				"sync",
+				"sync the file with its copy on the server, merging them if both changed",
+			),
+			popup::defaults::sync_file,
+		)
+		.add(
+			"audit-log",
+			(
+				"gA",
+				"audit log",
+				"list every change saved to the file, or those of the last (count) days, and go to their rows",
+			),
+			popup::defaults::audit_log,
+		)
+		.add(
+			"restore-backup",
+			(
+				"gB",
+				"restore backup",
+				"list the backups of the file, made when it is saved, and restore one",
+			),
+			popup::defaults::restore_backup,
+		)
+		.add(
+			"passphrase",
+			(
+				"gE",
+				"passphrase",
+				"encrypt the file with a passphrase, or change or remove its passphrase",
+			),
+			popup::defaults::change_passphrase,
+		)
+		.add_repeatable(
+			"put-below",
+			(
+				"p",
+				"put below",
+				"put (paste) the last yanked or deleted rows below",
+			),
+			actions::put_below,
+		)
+		.add_repeatable(
+			"put-above",
+			(
+				"P",
+				"put above",
+				"put (paste) the last yanked or deleted rows above",
+			),
+			actions::put_above,
+		)
+}
+
+/// Adds the actions of the features beyond editing the sheet - summaries, reports and
+/// statements
+fn feature_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"summary",
+			(
+				"gh",
+				"summary",
+				"show a summary of the file, including anything that needs attention",
+			),
+			popup::defaults::health,
+		)
+		.add(
+			"weekly-review",
+			(
+				"gw",
+				"weekly review",
+				"review the last week's transactions that are uncategorised, pending, or unusual",
+			),
+			popup::defaults::review,
+		)
+		.add(
+			"toggle-pending",
+			(
+				"gc",
+				"toggle pending",
+				"toggle whether the selected transaction is pending",
+			),
+			|view, model, cs, _count| {
+				let sheet = view.get_selected_sheet(model);
+				if let Some(row) = view.get_selected_row(sheet) {
+					let pending = !sheet.transactions()[row].pending;
+					let sheet = view.selected_sheet;
+					cs.send(model, Message::SetPending { sheet, row, pending });
+				}
+			},
+		)
+		.add(
+			"toggle-scheduled",
+			(
+				"gt",
+				"toggle scheduled",
+				"toggle whether the selected transaction is scheduled",
+			),
+			popup::defaults::toggle_scheduled,
+		)
+		.add(
+			"report",
+			(
+				"ga",
+				"report",
+				"report on a range of dates - income and expenses, or the top payees",
+			),
+			popup::defaults::report,
+		)
+		.add(
+			"forecast",
+			(
+				"gf",
+				"forecast",
+				"show the projected balance of the sheet, with upcoming transactions marked",
+			),
+			popup::defaults::forecast,
+		)
+		.add(
+			"statements",
+			(
+				"gs",
+				"statements",
+				"show the statements of the sheet, and schedule their payments",
+			),
+			popup::defaults::statements,
+		)
+		.add(
+			"statement-cycle",
+			(
+				"gS",
+				"statement cycle",
+				"set the statement cycle of the sheet",
+			),
+			popup::defaults::configure_statement_cycle,
+		)
+		.add(
+			"interest",
+			(
+				"gi",
+				"interest",
+				"set the interest rate and compounding period of the sheet",
+			),
+			popup::defaults::configure_interest,
+		)
+		.add(
+			"exchange-rates",
+			(
+				"gX",
+				"currencies",
+				"set the currency of the sheet, and list the exchange rates of the currencies in use to set them by hand or fetch the latest",
+			),
+			popup::defaults::exchange_rates,
+		)
+}
+
+/// Adds the actions that switch between files and sheets
+fn file_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"switch-file",
+			("go", "switch file", "switch to a recently opened file"),
+			popup::defaults::switcher,
+		)
+		.add(
+			"open-file-alongside",
+			(
+				"gW",
+				"open file alongside",
+				"open a file alongside the current one",
+			),
+			popup::defaults::open_alongside,
+		)
+		.add(
+			"next-file",
+			("]w", "next file", "show the next open file"),
+			|view, model, cs, _count| {
+				cs.cycle_workspace(true, view, model);
+			},
+		)
+		.add(
+			"previous-file",
+			("[w", "previous file", "show the previous open file"),
+			|view, model, cs, _count| {
+				cs.cycle_workspace(false, view, model);
+			},
+		)
+		.add(
+			"previous-sheet",
+			("H", "previous sheet", "move to the previous sheet"),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					view.previous_sheet(model);
+				}
+			},
+		)
+		.add(
+			"next-sheet",
+			("L", "next sheet", "move to the next sheet"),
+			|view, model, _cs, count| {
+				for _ in 0..count.unwrap_or(1) {
+					view.next_sheet(model);
+				}
+			},
+		)
+}
+
+/// Adds the actions that create, rename, duplicate, archive and delete sheets, and move rows
+/// between them
+fn sheet_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"new-sheet",
+			("<C-t>", "new sheet", "create a new sheet"),
+			|_view, model, cs, _count| {
+				cs.send(model, Message::CreateSheet);
+			},
+		)
+		.add(
+			"rename-sheet",
+			("<C-r>", "rename sheet", "rename the current sheet"),
+			popup::defaults::rename_sheet,
+		)
+		.add(
+			"duplicate-sheet",
+			(
+				"gY",
+				"duplicate sheet",
+				"copy the current sheet, optionally clearing its amounts or moving it a month later",
+			),
+			popup::defaults::duplicate_sheet,
+		)
+		.add(
+			"delete-sheet",
+			("<C-Del>", "delete sheet", "delete the current sheet"),
+			popup::defaults::delete_sheet,
+		)
+		.add_operator(
+			"move-rows",
+			(
+				"M",
+				"move rows",
+				"move the rows the motion covers to another sheet, picked from a list",
+			),
+			popup::defaults::move_to_sheet,
+		)
+		.add(
+			"archive-sheet",
+			(
+				"gz",
+				"archive sheet",
+				"hide the current sheet from the tabs, keeping it in the file and the net worth, or bring it back if it is archived",
+			),
+			popup::defaults::archive_sheet,
+		)
+		.add(
+			"unarchive-sheet",
+			(
+				"gZ",
+				"unarchive sheet",
+				"list the archived sheets, to bring one back",
+			),
+			popup::defaults::unarchive_sheet,
+		)
+		.add(
+			"toggle-archived",
+			(
+				"gk",
+				"show archived",
+				"show or hide the archived sheets in the tabs",
+			),
+			|view, model, _cs, _count| {
+				view.shown_sheets = view.shown_sheets.toggled();
+				if !view.is_sheet_shown(view.selected_sheet, model) {
+					view.previous_sheet(model);
+				}
+			},
+		)
+}
+
+/// Adds the actions that import transactions, and edit and re-run the import rules
+fn import_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"import",
+			(
+				"gI",
+				"import",
+				"import transactions from a CSV, QIF or OFX file",
+			),
+			popup::defaults::import,
+		)
+		.add(
+			"repeat-import",
+			(
+				"gR",
+				"repeat import",
+				"repeat the last import, with the newest file matching its path",
+			),
+			popup::defaults::repeat_import,
+		)
+		.add(
+			"bank-import",
+			(
+				"gF",
+				"bank import",
+				"import the new transactions of the bank account linked to the sheet, through SimpleFIN",
+			),
+			popup::defaults::bank_import,
+		)
+		.add(
+			"import-rules",
+			("gu", "import rules", "edit the import rules"),
+			popup::defaults::rules,
+		)
+		.add(
+			"payees",
+			(
+				"gN",
+				"payees",
+				"edit the payees, whose names replace imported labels containing their aliases",
+			),
+			popup::defaults::payees,
+		)
+		.add(
+			"rerun-import-rules",
+			(
+				"gU",
+				"re-run import rules",
+				"re-run the import rules on the shown rows of the sheet",
+			),
+			popup::defaults::rerun_rules,
+		)
+}
+
+/// Adds the actions that split the view into panes, and switch to the dashboard
+fn pane_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"split-side-by-side",
+			(
+				"<C-w>v",
+				"split side by side",
+				"split the view to show two sheets side by side",
+			),
+			|view, model, _cs, _count| {
+				view.split(Direction::Horizontal, model);
+			},
+		)
+		.add(
+			"split-one-above-the-other",
+			(
+				"<C-w>s",
+				"split one above the other",
+				"split the view to show two sheets one above the other",
+			),
+			|view, model, _cs, _count| {
+				view.split(Direction::Vertical, model);
+			},
+		)
+		.add(
+			"other-pane",
+			(
+				"<C-w>w",
+				"other pane",
+				"move to the other pane of the split view",
+			),
+			|view, model, _cs, _count| view.switch_pane(model),
+		)
+		.add(
+			"close-other-pane",
+			(
+				"<C-w>q",
+				"close other pane",
+				"close the other pane of the split view",
+			),
+			|view, _model, _cs, _count| view.close_split(),
+		)
+		.add(
+			"dashboard",
+			(
+				"gd",
+				"dashboard",
+				"show the net worth of every sheet in place of the sheet",
+			),
+			|view, _model, _cs, _count| {
+				view.dashboard = !view.dashboard;
+			},
+		)
+		.add(
+			"actual-projected-balance",
+			(
+				"gb",
+				"actual/projected balance",
+				"switch between the actual and projected balance",
+			),
+			|view, _model, _cs, _count| {
+				view.projected = !view.projected;
+			},
+		)
+}
+
+/// Adds the actions that quit, save, manage sheets and show help
+fn general_actions(registry: ActionRegistry) -> ActionRegistry {
+	registry
+		.add(
+			"quit",
+			("q", "quit", "quit the program"),
+			|_view, _model, cs, _count| cs.exit = true,
+		)
+		.add(
+			"quit-ctrl-c",
+			("<C-c>", "quit", "quit the program"),
+			|_view, _model, cs, _count| cs.exit = true,
+		)
+		.add(
+			"toggle-mouse-capture",
+			(
+				"gm",
+				"toggle mouse capture",
+				"release the mouse so text can be selected, or capture it again",
+			),
+			|_view, _model, cs, _count| {
+				cs.mouse_capture = !cs.mouse_capture;
+			},
+		)
+		.add(
+			"save",
+			("<C-s>", "save", "save the file"),
+			popup::defaults::save,
+		)
+		.add(
+			"help",
+			("?", "help", "show the keys and what they do"),
+			popup::defaults::help,
+		)
+		.add(
+			"whats-new",
+			("gn", "what's new", "show what's new in this version"),
+			popup::defaults::whats_new,
+		)
+		.add(
+			"show-log",
+			("gM", "log", "show the last lines of the log"),
+			popup::defaults::show_log,
+		)
+}
@@ -0,0 +1,199 @@
+//! Work that can take a while or touches the disk, like reading a large import or saving, done on
+//! the worker thread so the program keeps drawing while it runs. A task's progress is shown in a
+//! [`Progress`](crate::controller::popup::Progress) popup, where it can be cancelled with Esc,
+//! while background jobs (like saving) run without one
+
+use std::{
+	panic::{self, AssertUnwindSafe},
+	sync::{
+		Arc,
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		mpsc::{self, Sender},
+	},
+	thread,
+};
+
+use crate::{
+	controller::{
+		ControllerState,
+		popup::{Popup, defaults},
+	},
+	events::AppEvent,
+	model::Model,
+	view::View,
+};
+
+/// What to do with the result of a job once it is done. This is run on the main thread, as it
+/// needs the model, and returns the popup to show next, if any
+pub type TaskFinish =
+	Box<dyn FnOnce(&mut View, &mut Model, &mut ControllerState) -> Option<Popup> + Send>;
+
+/// Work sent to the worker thread
+struct Job {
+	/// The id of the task the job is for, or [`None`] for a background job
+	task: Option<u64>,
+	/// What the job is doing, like "Import", to say what failed if it panics
+	title: String,
+	work: Box<dyn FnOnce() -> TaskFinish + Send>,
+}
+
+/// The thread doing the jobs, one at a time in the order they were started. Each result is sent
+/// to the main loop as an [`AppEvent::Finished`]
+pub struct Worker {
+	jobs: Sender<Job>,
+	/// The id given to the next task
+	next_id: u64,
+}
+
+impl Worker {
+	/// Starts the worker thread, which sends the results of its jobs to `events`
+	pub fn spawn(events: Sender<AppEvent>) -> Self {
+		let (jobs, receiver) = mpsc::channel::<Job>();
+		thread::spawn(move || {
+			for job in receiver {
+				let title = job.title;
+				// A job that panics is reported like any other failure, and the worker carries on
+				let finish = panic::catch_unwind(AssertUnwindSafe(job.work)).unwrap_or_else(|_| {
+					Box::new(move |_view, _model, cs: &mut ControllerState| {
+						cs.notify_error(format!("{title} failed"));
+						None
+					})
+				});
+				let event = AppEvent::Finished {
+					task: job.task,
+					finish,
+				};
+				if events.send(event).is_err() {
+					return;
+				}
+			}
+		});
+		Self { jobs, next_id: 0 }
+	}
+
+	/// Sends the work to the worker thread
+	fn send(&self, task: Option<u64>, title: &str, work: Box<dyn FnOnce() -> TaskFinish + Send>) {
+		// The worker only stops once the main loop has, so there is nothing to tell if this fails
+		let _ = self.jobs.send(Job {
+			task,
+			title: title.to_string(),
+			work,
+		});
+	}
+}
+
+/// A task being done by the worker, whose progress is shown in a popup
+pub struct Task {
+	id: u64,
+	/// What the task is doing, like "Import"
+	title: String,
+	progress: Arc<TaskProgress>,
+}
+
+/// How far along a task is, shared between the worker doing it and the popup showing it
+#[derive(Debug, Default)]
+pub struct TaskProgress {
+	done: AtomicU64,
+	/// The amount of work there is to do, or 0 if it isn't known
+	total: AtomicU64,
+	cancelled: AtomicBool,
+}
+
+impl TaskProgress {
+	/// Records that `done` out of `total` units of work (like bytes read) are done, returning
+	/// whether to carry on, which is false once the task has been cancelled
+	pub fn report(&self, done: u64, total: u64) -> bool {
+		self.done.store(done, Ordering::Relaxed);
+		self.total.store(total, Ordering::Relaxed);
+		!self.cancelled.load(Ordering::Relaxed)
+	}
+
+	/// Returns the fraction of the work that is done, or [`None`] if the amount of work isn't
+	/// known. Once all of it is done, whatever the task does next (like parsing the file it read)
+	/// isn't measured, so that isn't known either
+	pub fn ratio(&self) -> Option<f64> {
+		let (done, total) = (
+			self.done.load(Ordering::Relaxed),
+			self.total.load(Ordering::Relaxed),
+		);
+		#[allow(clippy::cast_precision_loss)]
+		(done < total).then(|| done as f64 / total as f64)
+	}
+}
+
+impl ControllerState {
+	/// Starts doing `work` on the worker thread, returning a popup showing its progress. The work
+	/// reports to the given [`TaskProgress`], and returns what to do with its result, which is
+	/// done by [`ControllerState::finish_job`] once the main loop hears that it is finished
+	pub fn start_task<W>(&mut self, title: &str, work: W) -> Popup
+	where
+		W: FnOnce(&TaskProgress) -> TaskFinish + Send + 'static,
+	{
+		let worker = self
+			.worker
+			.as_mut()
+			.expect("The worker is started with the controller");
+		let id = worker.next_id;
+		worker.next_id += 1;
+		let progress = Arc::new(TaskProgress::default());
+		let shared = Arc::clone(&progress);
+		worker.send(Some(id), title, Box::new(move || work(&shared)));
+		self.task = Some(Task {
+			id,
+			title: title.to_string(),
+			progress: Arc::clone(&progress),
+		});
+		defaults::progress_popup(title, progress)
+	}
+
+	/// Starts doing `work` on the worker thread without showing its progress, for work that is
+	/// usually quick but shouldn't hold up drawing, like writing the file being saved
+	pub fn run_in_background<W>(&mut self, title: &str, work: W)
+	where
+		W: FnOnce() -> TaskFinish + Send + 'static,
+	{
+		self.worker
+			.as_ref()
+			.expect("The worker is started with the controller")
+			.send(None, title, Box::new(work));
+	}
+
+	/// Returns whether a task is being done, so its progress is being shown
+	pub fn has_running_task(&self) -> bool {
+		self.task.is_some()
+	}
+
+	/// Does what a finished job returned to do with its result. A task's popup replaces its
+	/// progress, unless it was cancelled, in which case its result is thrown away. A background
+	/// job's popup is only shown if no other popup is
+	pub fn finish_job(
+		&mut self,
+		task: Option<u64>,
+		finish: TaskFinish,
+		view: &mut View,
+		model: &mut Model,
+	) {
+		match task {
+			Some(id) => {
+				if self.task.as_ref().is_some_and(|task| task.id == id) {
+					self.task = None;
+					self.popup = finish(view, model, self);
+				}
+			}
+			None => {
+				if let Some(popup) = finish(view, model, self) {
+					self.popup.get_or_insert(popup);
+				}
+			}
+		}
+	}
+
+	/// Cancels the running task, so it stops as soon as it next reports its progress, and its
+	/// result is thrown away
+	pub fn cancel_task(&mut self) {
+		if let Some(task) = self.task.take() {
+			task.progress.cancelled.store(true, Ordering::Relaxed);
+			self.notify(format!("{} cancelled", task.title));
+		}
+	}
+}
@@ -0,0 +1,130 @@
+//! Files that are open alongside the one being shown, so more than one budget can be worked on in
+//! a session. Each keeps its own sheets and view, and they are cycled through one at a time
+
+use std::path::Path;
+
+use crate::{
+	controller::ControllerState,
+	model::{Model, SaveError},
+	state,
+	view::View,
+};
+
+/// A file that is open but not being shown, with the view it was left in
+pub struct Workspace {
+	model: Model,
+	view: View,
+}
+
+impl ControllerState {
+	/// Returns the amount of files open, including the one being shown
+	pub fn open_file_count(&self) -> usize {
+		self.workspaces.len() + 1
+	}
+
+	/// Returns the index of the workspace with the given file, if it is open
+	fn workspace_index(&self, path: &Path) -> Option<usize> {
+		self.workspaces
+			.iter()
+			.position(|workspace| is_file(&workspace.model, path))
+	}
+
+	/// Shows the file at the given path if it is already open, returning whether it is. A file
+	/// can be named by more than one path, so they are compared once they are canonical
+	fn show_if_open(&mut self, path: &Path, view: &mut View, model: &mut Model) -> bool {
+		if is_file(model, path) {
+			self.notify(format!("{} is already open", path.display()));
+			return true;
+		}
+		let Some(index) = self.workspace_index(path) else {
+			return false;
+		};
+		let workspace = self.workspaces.remove(index);
+		let shown = Self::show(workspace, view, model);
+		self.workspaces.push(shown);
+		true
+	}
+
+	/// Opens the file alongside the current one and shows it, keeping the current one open. If
+	/// the file is already open, it is shown instead
+	///
+	/// # Errors
+	/// If the file exists but can't be loaded, in which case nothing changes
+	pub fn open_alongside(
+		&mut self,
+		path: &str,
+		view: &mut View,
+		model: &mut Model,
+	) -> Result<(), SaveError> {
+		if self.show_if_open(Path::new(path), view, model) {
+			return Ok(());
+		}
+		let loaded = Model::new(Some(path.to_string()))?;
+		self.show_alongside(path, loaded, view, model);
+		Ok(())
+	}
+
+	/// Shows the model loaded from the file at the given path, keeping the current file open. If
+	/// the file is already open, it is shown instead, and the model loaded from it is dropped
+	pub fn show_alongside(
+		&mut self,
+		path: &str,
+		loaded: Model,
+		view: &mut View,
+		model: &mut Model,
+	) {
+		if self.show_if_open(Path::new(path), view, model) {
+			return;
+		}
+		let mut new_view = View::new();
+		new_view.safe_mode = view.safe_mode;
+		new_view.columns = view.columns.clone();
+		new_view.theme = view.theme;
+		new_view.highlights.clone_from(&view.highlights);
+		new_view.row_options = view.row_options;
+		let workspace = Workspace {
+			model: loaded,
+			view: new_view,
+		};
+		let shown = Self::show(workspace, view, model);
+		self.workspaces.push(shown);
+		state::add_recent_file(Path::new(path));
+	}
+
+	/// Shows the next open file, or the previous one if `forward` is false
+	pub fn cycle_workspace(&mut self, forward: bool, view: &mut View, model: &mut Model) {
+		if self.workspaces.is_empty() {
+			return;
+		}
+		if forward {
+			let next = self.workspaces.remove(0);
+			let shown = Self::show(next, view, model);
+			self.workspaces.push(shown);
+		} else {
+			let previous = self.workspaces.pop().expect("there is another workspace");
+			let shown = Self::show(previous, view, model);
+			self.workspaces.insert(0, shown);
+		}
+	}
+
+	/// Shows the workspace in place of the current file, returning the current file as a
+	/// workspace. The import rules follow the file being shown, as they aren't part of any file
+	fn show(mut workspace: Workspace, view: &mut View, model: &mut Model) -> Workspace {
+		workspace.model.rules = std::mem::take(&mut model.rules);
+		std::mem::swap(&mut workspace.model, model);
+		std::mem::swap(&mut workspace.view, view);
+		workspace
+	}
+}
+
+/// Returns whether the model was loaded from the file at the given path
+fn is_file(model: &Model, path: &Path) -> bool {
+	let Ok(path) = path.canonicalize() else {
+		return false;
+	};
+	model
+		.filename
+		.as_ref()
+		.and_then(|filename| Path::new(filename).canonicalize().ok())
+		.is_some_and(|filename| filename == path)
+}
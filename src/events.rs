@@ -0,0 +1,47 @@
+//! The events the main loop waits on. Input from the terminal is read on a thread of its own, and
+//! the results of work done on the worker thread (see [`Worker`](crate::controller::Worker)) are
+//! sent down the same channel, so the main loop only wakes up when there is something to handle,
+//! or when it is time to redraw
+
+use std::{io, sync::mpsc::Sender, thread};
+
+use ratatui::crossterm::event::{self, Event};
+
+use crate::controller::TaskFinish;
+
+/// Something for the main loop to handle
+pub enum AppEvent {
+	/// An event from the terminal, like a key being pressed
+	Terminal(Event),
+	/// A job on the worker thread is done. See [`ControllerState::finish_job`]
+	///
+	/// [`ControllerState::finish_job`]: crate::controller::ControllerState::finish_job
+	Finished {
+		/// The id of the task the job was for, or [`None`] for a background job
+		task: Option<u64>,
+		finish: TaskFinish,
+	},
+	/// The terminal couldn't be read from, so no more input will come
+	InputError(io::Error),
+	/// The file being shown was touched on disk. See [`FileWatcher`](crate::watch::FileWatcher)
+	FileChanged,
+}
+
+/// Starts the thread that reads events from the terminal, sending each to `events`
+pub fn spawn_input(events: Sender<AppEvent>) {
+	thread::spawn(move || {
+		loop {
+			let event = match event::read() {
+				Ok(event) => AppEvent::Terminal(event),
+				Err(e) => {
+					let _ = events.send(AppEvent::InputError(e));
+					return;
+				}
+			};
+			// The main loop has stopped, so nothing is listening
+			if events.send(event).is_err() {
+				return;
+			}
+		}
+	});
+}
@@ -0,0 +1,65 @@
+//! Logging what the program does to a file in the state directory (see [`state::state_dir`]), to
+//! look back at when something behaves oddly. Saving, loading, imports and errors are always
+//! logged, and with `--verbose` also every key typed outside of popups, every command run and
+//! every change made to the file. Keys typed into popups are never logged, so passphrases don't
+//! end up in the log. Failing to open the log is ignored, like the rest of the state
+
+use std::{
+	fs::{self, File, OpenOptions},
+	path::PathBuf,
+	sync::Mutex,
+};
+
+use tracing::Level;
+
+use crate::state;
+
+/// The name of the log file in the state directory
+const LOG_FILE: &str = "budgeting-app.log";
+
+/// How big the log can get before it is moved aside to start a new one, in bytes. Only the
+/// previous log is kept
+const MAX_SIZE: u64 = 1024 * 1024;
+
+/// Returns where the log is written
+pub fn log_path() -> Option<PathBuf> {
+	Some(state::state_dir()?.join(LOG_FILE))
+}
+
+/// Starts logging to the log file, with everything if `verbose` and only the important things if
+/// not
+pub fn init(verbose: bool) {
+	let Some(file) = open_log() else {
+		return;
+	};
+	let level = if verbose { Level::TRACE } else { Level::INFO };
+	let _ = tracing_subscriber::fmt()
+		.with_writer(Mutex::new(file))
+		.with_ansi(false)
+		.with_target(false)
+		.with_max_level(level)
+		.try_init();
+	tracing::info!("Started budgeting-app {}", env!("CARGO_PKG_VERSION"));
+}
+
+/// Opens the log file to append to, first moving it aside if it has got too big
+fn open_log() -> Option<File> {
+	let path = log_path()?;
+	fs::create_dir_all(path.parent()?).ok()?;
+	if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_SIZE) {
+		let _ = fs::rename(&path, path.with_extension("log.old"));
+	}
+	OpenOptions::new().create(true).append(true).open(path).ok()
+}
+
+/// Returns the last `count` lines of the log, newest first
+pub fn last_lines(count: usize) -> Vec<String> {
+	log_path()
+		.and_then(|path| fs::read_to_string(path).ok())
+		.unwrap_or_default()
+		.lines()
+		.rev()
+		.take(count)
+		.map(str::to_string)
+		.collect()
+}
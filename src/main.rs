@@ -5,51 +5,483 @@
 	dead_code
 )]
 
-use std::time::Duration;
+use std::{
+	io::{self, IsTerminal, stdout},
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::mpsc,
+};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+use budgeting_core::{bank, clock, model, rates, sync};
+use chrono::NaiveDate;
 use clap::Parser;
-use ratatui::{Terminal, crossterm::event, prelude::Backend};
+use ratatui::{
+	Terminal,
+	crossterm::{
+		event::{
+			DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+			EnableFocusChange, EnableMouseCapture, Event,
+		},
+		execute,
+	},
+	prelude::Backend,
+};
 
-use crate::{controller::Controller, model::Model, view::View};
+use crate::{
+	config::Config,
+	controller::{Controller, popup::Popup},
+	events::AppEvent,
+	model::{Access, ImportPreset, Model, RuleSet, SaveError, Scripts, Transaction},
+	view::View,
+	watch::FileWatcher,
+};
 
+mod batch;
+mod cli;
+mod clipboard;
+mod config;
 mod controller;
-mod model;
+mod events;
+mod logging;
+mod release_notes;
+mod state;
+#[cfg(test)]
+mod tests;
 mod view;
+mod watch;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
 	/// File to open
 	filename: Option<String>,
+	/// Config file to use, instead of the one in the default location
+	#[arg(long)]
+	config: Option<PathBuf>,
+	/// Start with the default config and without the import rules and scripts, to tell whether a
+	/// problem comes from them. Files can still be opened, edited and saved
+	#[arg(long, conflicts_with = "config")]
+	safe_mode: bool,
+	/// Open a scratch sheet of the transactions piped in, without a file, as read by the import
+	/// preset with the given name, or the first one for the kind of file (csv, qif or ofx)
+	#[arg(long, conflicts_with = "filename")]
+	stdin_format: Option<String>,
+	/// Type the keys into the file instead of opening the program, written like the keys of the
+	/// config's mappings (e.g. "gU<CR><C-s>" to apply the import rules and save). Can be given more
+	/// than once, to type each in turn
+	#[arg(short, long, value_name = "KEYS")]
+	execute: Vec<String>,
+	/// Type the keys on each line of the file, like --execute. Blank lines and lines starting with
+	/// # are skipped
+	#[arg(long, value_name = "FILE", conflicts_with = "execute")]
+	script: Option<PathBuf>,
+	/// Sheet to start on, by name
+	#[arg(long, requires = "filename")]
+	sheet: Option<String>,
+	/// Row to start at, by its number or a date (starting at the first transaction on or after it)
+	#[arg(long, requires = "filename")]
+	goto: Option<GoTo>,
+	/// Act as if today were the given date (like 2024-07-01), e.g. for demos, or to see what the
+	/// program showed on a given day. Dates typed relative to today, scheduled transactions,
+	/// reports and forecasts all go by it
+	#[arg(long, global = true, value_name = "DATE", value_parser = Transaction::parse_date)]
+	today: Option<NaiveDate>,
+	/// Log every key typed (outside of popups), command run and change made to the file, as well as
+	/// what is always logged: saving, loading, imports and errors. The log can be seen in the
+	/// program with `gM`
+	#[arg(short, long, global = true)]
+	verbose: bool,
+	#[command(subcommand)]
+	command: Option<cli::Command>,
+}
+
+/// A row to start at, given with `--goto`
+#[derive(Debug, Clone, Copy)]
+enum GoTo {
+	Row(usize),
+	Date(NaiveDate),
+}
+
+impl FromStr for GoTo {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(row) = s.parse() {
+			return Ok(GoTo::Row(row));
+		}
+		Transaction::parse_date(s)
+			.map(GoTo::Date)
+			.map_err(|_| format!("\"{s}\" isn't a row number or a date, like 2024-07-01"))
+	}
+}
+
+/// Where in the file to start, given on the command line
+#[derive(Debug, Default)]
+struct StartAt {
+	sheet: usize,
+	goto: Option<GoTo>,
+}
+
+impl StartAt {
+	/// Finds the sheet with the given name in the model
+	///
+	/// # Errors
+	/// If the model has no sheet with that name
+	fn new(model: &Model, sheet: Option<&str>, goto: Option<GoTo>) -> Result<Self, String> {
+		let sheet = match sheet {
+			Some(name) => model
+				.sheet_titles()
+				.iter()
+				.position(|title| title == name)
+				.ok_or_else(|| format!("No sheet named \"{name}\""))?,
+			None => 0,
+		};
+		Ok(Self { sheet, goto })
+	}
+
+	/// Selects the sheet and row in the view
+	fn apply(&self, view: &mut View, model: &Model) {
+		view.selected_sheet = self.sheet;
+		match self.goto {
+			Some(GoTo::Row(row)) => view.jump_to_row(row, model),
+			Some(GoTo::Date(date)) => {
+				view.go_to_date(date, model);
+			}
+			None => {}
+		}
+	}
 }
 
 fn main() {
 	let args = Args::parse();
+	clock::set_today(args.today);
+	logging::init(args.verbose);
+
+	// Loaded before the terminal is taken over, so any error is printed normally
+	let config = if args.safe_mode {
+		Config::default()
+	} else {
+		match Config::load(args.config) {
+			Ok(config) => config,
+			Err(e) => {
+				eprintln!("{e}");
+				return;
+			}
+		}
+	};
+	if let Some(command) = args.command {
+		// Scripts can tell the command failed from the exit code
+		if let Err(e) = cli::run(command, &config, args.safe_mode) {
+			eprintln!("{e}");
+			std::process::exit(1);
+		}
+		return;
+	}
+	let opened_file = args.filename.is_some() || args.stdin_format.is_some();
+	// A file that needs a passphrase, or is open in another instance, is asked about in the
+	// program, and opened once it has been
+	let mut pending = None;
+	let mut model = match Model::new(args.filename.clone()) {
+		Ok(model) => model,
+		Err(e @ (SaveError::PassphraseNeeded | SaveError::Locked(_))) => {
+			pending = args.filename.map(|filename| (filename, e));
+			Model::new(None).expect("a new model can always be made")
+		}
+		Err(e) => {
+			eprintln!("Couldn't open file: {e}");
+			return;
+		}
+	};
+	if let Some(filename) = &model.filename {
+		state::add_recent_file(Path::new(filename));
+	}
+	// A file that isn't open yet (as it is asked about first) starts at the top, as its sheets
+	// aren't known
+	let start_at = if pending.is_some() {
+		StartAt::default()
+	} else {
+		match StartAt::new(&model, args.sheet.as_deref(), args.goto) {
+			Ok(start_at) => start_at,
+			Err(e) => {
+				eprintln!("{e}");
+				return;
+			}
+		}
+	};
+	// In safe mode the rules and scripts are left empty, and the rules aren't saved over the user's
+	if !args.safe_mode
+		&& let Err(e) = load_rules_and_scripts(&mut model, &config)
+	{
+		eprintln!("{e}");
+		return;
+	}
+	if let Some(format) = &args.stdin_format {
+		match read_stdin(format, &model.rules, &model.scripts) {
+			Ok(transactions) => model.replace(Model::scratch_with(transactions)),
+			Err(e) => {
+				eprintln!("{e}");
+				return;
+			}
+		}
+	}
+
+	if !args.execute.is_empty() || args.script.is_some() {
+		let steps = batch::read_script(&args.execute, args.script.as_deref());
+		// The model is dropped before exiting, so its lock is removed
+		if let Err(e) = run_script(steps, model, pending, &config, args.safe_mode) {
+			eprintln!("{e:#}");
+			std::process::exit(1);
+		}
+		return;
+	}
 
 	let terminal = ratatui::init();
-	let res = run_program(terminal, args);
+	// Focus changes are only used to save power, so it doesn't matter if they aren't supported
+	let _ = execute!(stdout(), EnableFocusChange);
+	// Pasted text comes as one event, so its line breaks don't submit inputs
+	let _ = execute!(stdout(), EnableBracketedPaste);
+	let res = run_program(
+		terminal,
+		model,
+		&config,
+		opened_file,
+		pending,
+		&start_at,
+		args.safe_mode,
+	);
+	let _ = execute!(
+		stdout(),
+		DisableFocusChange,
+		DisableMouseCapture,
+		DisableBracketedPaste
+	);
 	ratatui::restore();
 	if let Err(e) = res {
 		println!("{e:?}");
 	}
 }
 
-/// Runs the program
-fn run_program<B: Backend>(mut terminal: Terminal<B>, args: Args) -> Result<()> {
-	let mut model = Model::new(args.filename);
+/// Loads the import rules and the user's scripts, from where the config says, into the model
+fn load_rules_and_scripts(model: &mut Model, config: &Config) -> Result<()> {
+	if let Some(path) = config.rules_path() {
+		model.rules = RuleSet::load(path)?;
+	}
+	if let Some(path) = config.scripts_path() {
+		model.scripts = Scripts::load(&path)?;
+	}
+	Ok(())
+}
+
+/// Reads the transactions piped in, in the given format (see [`Args::stdin_format`]), applying the
+/// import rules to them
+fn read_stdin(
+	format: &str,
+	rules: &RuleSet,
+	scripts: &Scripts,
+) -> Result<Vec<Transaction>, String> {
+	if io::stdin().is_terminal() {
+		return Err("Nothing was piped in for --stdin-format to read".to_string());
+	}
+	let preset = ImportPreset::find(format, scripts)
+		.or_else(|| ImportPreset::for_extension(format, scripts))
+		.ok_or_else(|| {
+			format!("No import preset named \"{format}\", or reading .{format} files")
+		})?;
+	let text = io::read_to_string(io::stdin())
+		.map_err(|e| format!("Couldn't read what was piped in: {e}"))?;
+	let mut transactions = preset.parse(&text).map_err(|e| e.to_string())?;
+	if transactions.is_empty() {
+		return Err("No transactions were piped in".to_string());
+	}
+	for transaction in &mut transactions {
+		rules.apply(transaction);
+	}
+	Ok(transactions)
+}
+
+/// The most events handled in one go before the screen is drawn again, so a flood of input can't
+/// keep it from being drawn
+const MAX_EVENTS_PER_FRAME: usize = 256;
+
+/// Types the keys given with `--execute` or `--script` into the model instead of opening the
+/// program
+///
+/// # Errors
+/// If the keys can't be read, the file couldn't be opened, or any of the keys went wrong
+fn run_script(
+	steps: Result<Vec<batch::Step>>,
+	model: Model,
+	pending: Option<(String, SaveError)>,
+	config: &Config,
+	safe_mode: bool,
+) -> Result<()> {
+	if let Some((_, e)) = pending {
+		bail!("Couldn't open file: {e}");
+	}
+	batch::run(model, config, safe_mode, &steps?)
+}
+
+/// Creates the view, set up as the config says
+fn new_view(config: &Config, safe_mode: bool) -> View {
 	let mut view = View::new();
-	let mut controller = Controller::new();
+	view.safe_mode = safe_mode;
+	view.columns = config.columns.clone();
+	view.theme = config.theme();
+	view.highlights.clone_from(&config.highlights);
+	view
+}
+
+/// Creates the controller, set up as the config says, with the commands added by the scripts,
+/// which sends the results of its jobs to `events`
+fn new_controller(
+	config: &Config,
+	scripts: &Scripts,
+	events: mpsc::Sender<AppEvent>,
+) -> Controller {
+	let mut controller = Controller::new(events);
+	controller.state.mouse_capture = config.mouse;
+	controller.state.confirm_delete = config.confirm_delete;
+	controller.state.backups = config.backups;
+	controller.state.sync.clone_from(&config.sync);
+	controller.state.rates.clone_from(&config.rates);
+	controller.state.key_timeout = config.key_timeout();
+	if let Err(e) = controller.add_mappings(&config.leader, &config.mappings, scripts.commands()) {
+		controller.state.notify_error(format!("{e:#}"));
+	}
+	controller
+}
+
+/// Runs the program
+fn run_program<B: Backend>(
+	mut terminal: Terminal<B>,
+	mut model: Model,
+	config: &Config,
+	opened_file: bool,
+	pending: Option<(String, SaveError)>,
+	start_at: &StartAt,
+	safe_mode: bool,
+) -> Result<()> {
+	let mut view = new_view(config, safe_mode);
+	start_at.apply(&mut view, &model);
+	let (sender, events) = mpsc::channel();
+	events::spawn_input(sender.clone());
+	let mut watcher = FileWatcher::new(sender.clone());
+	let mut controller = new_controller(config, &model.scripts, sender);
+	view.rates = state::exchange_rates();
+	controller::popup::defaults::refresh_rates(&mut controller.state);
+	// After an upgrade, what's new is shown instead of the summary, which is one key (gh) away
+	let releases = release_notes::check_for_upgrade();
+	if let Some((path, e)) = pending {
+		controller.state.popup =
+			controller::popup::defaults::opening_popup(&e, &path, false, Access::Exclusive);
+	} else if !releases.is_empty() {
+		controller.state.popup = Some(controller::popup::defaults::release_notes_popup(&releases));
+	} else if opened_file {
+		controller::popup::defaults::health(&mut view, &mut model, &mut controller.state, None);
+	} else {
+		controller.state.popup = Some(controller::popup::defaults::start_popup());
+	}
 
+	let mut focused = true;
+	let mut mouse_captured = false;
+	// Whether anything shown has changed since the screen was last drawn
+	let mut dirty = true;
 	loop {
-		terminal.draw(|frame| view.render(frame, &model, &controller.state))?;
+		if controller.state.mouse_capture != mouse_captured {
+			mouse_captured = controller.state.mouse_capture;
+			if mouse_captured {
+				execute!(stdout(), EnableMouseCapture)?;
+			} else {
+				execute!(stdout(), DisableMouseCapture)?;
+			}
+		}
 
-		if event::poll(Duration::from_millis(10))? {
-			controller.handle_events(&event::read()?, &mut model, &mut view);
+		// A task's progress moves, the time left to finish a key sequence counts down, and messages
+		// and key sequences expire, without any event
+		dirty |= controller.state.expire_notifications()
+			|| controller.state.has_running_task()
+			|| controller.state.key_time_left().is_some();
+		dirty |= controller.expire_pending_keys(&mut model, &mut view);
+		watcher.follow(model.filename.as_deref());
+		dirty |= controller.state.check_disk(&model);
+		if dirty {
+			model.record_changes();
+			terminal.draw(|frame| view.render(frame, &model, &controller.state))?;
+			dirty = false;
 		}
 
-		if controller.state.exit {
-			return Ok(());
+		// Block until the next event rather than waking up to redraw, unless what is shown changes
+		// on its own (and the terminal has focus)
+		let suspended = !focused && config.suspend_when_unfocused;
+		let event = if suspended || !controller.state.changes_over_time() {
+			events.recv().ok()
+		} else {
+			events.recv_timeout(config.poll_interval()).ok()
+		};
+		// Every event that queued up while the screen was being drawn is handled before it is drawn
+		// again. Held keys repeat faster than a large sheet can be drawn, so handling one per frame
+		// would keep the cursor moving long after the key is let go. If nothing happened in time,
+		// the screen is just redrawn
+		let queued = events.try_iter().take(MAX_EVENTS_PER_FRAME - 1);
+		for event in event.into_iter().chain(queued) {
+			match event {
+				AppEvent::Terminal(Event::FocusLost) => focused = false,
+				AppEvent::Terminal(Event::FocusGained) => focused = true,
+				AppEvent::Terminal(event) => {
+					dirty |= controller.handle_events(&event, &mut model, &mut view);
+				}
+				AppEvent::Finished { task, finish } => {
+					controller
+						.state
+						.finish_job(task, finish, &mut view, &mut model);
+					dirty = true;
+				}
+				AppEvent::InputError(e) => return Err(e.into()),
+				AppEvent::FileChanged => controller.state.file_touched(),
+			}
+			if controller.state.exit {
+				return finish_jobs(&mut controller, &mut model, &mut view, &events);
+			}
 		}
 	}
 }
+
+/// Waits for whatever the worker is still doing (like saving) to finish before the program quits,
+/// so a save started just before quitting isn't lost
+///
+/// # Errors
+/// If any of the jobs went wrong, like the file not being saved
+fn finish_jobs(
+	controller: &mut Controller,
+	model: &mut Model,
+	view: &mut View,
+	events: &mpsc::Receiver<AppEvent>,
+) -> Result<()> {
+	let cs = &mut controller.state;
+	// Only what the jobs show is reported, not what was being shown when the program quit
+	cs.popup = None;
+	cs.notifications.clear();
+	while cs.is_busy() {
+		match events.recv() {
+			Ok(AppEvent::Finished { task, finish }) => cs.finish_job(task, finish, view, model),
+			Ok(_) => {}
+			Err(_) => break,
+		}
+	}
+	let mut errors: Vec<String> = cs
+		.notifications
+		.drain(..)
+		.filter(|notification| notification.error)
+		.map(|notification| notification.message)
+		.collect();
+	if let Some(Popup::Info(info)) = &cs.popup
+		&& let Some(error) = info.error()
+	{
+		errors.push(format!("{}: {error}", info.title()));
+	}
+	if !errors.is_empty() {
+		bail!("{}", errors.join("\n"));
+	}
+	Ok(())
+}
@@ -0,0 +1,140 @@
+//! The notes of each release, bundled into the program so the user can be told what's new after
+//! upgrading. The version that last ran is kept in the state directory, to tell when that happens
+
+use serde::Deserialize;
+
+use crate::state;
+
+/// The release notes, newest release first. See `release_notes.toml`
+const RELEASE_NOTES: &str = include_str!("../release_notes.toml");
+/// The version of the program that is running
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The notes of a single release
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Release {
+	pub version: String,
+	/// What the release added, in a sentence each
+	#[serde(default)]
+	pub features: Vec<String>,
+	/// The keys the release added or changed
+	#[serde(default)]
+	pub keys: Vec<KeyNote>,
+}
+
+/// A key added or changed by a release
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyNote {
+	pub keys: String,
+	/// What pressing the keys does
+	pub action: String,
+}
+
+#[derive(Deserialize)]
+struct ReleaseNotes {
+	#[serde(rename = "release")]
+	releases: Vec<Release>,
+}
+
+/// Returns the notes of every release, newest first
+///
+/// # Panics
+/// If the bundled notes aren't valid, which is caught as soon as the program is run
+fn releases() -> Vec<Release> {
+	toml::from_str::<ReleaseNotes>(RELEASE_NOTES)
+		.expect("the bundled release notes are valid")
+		.releases
+}
+
+/// Parses a version like `1.2.3` so versions can be compared. Missing parts are 0, so trailing
+/// zeros are dropped, and `1.2` is the same version as `1.2.0`
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+	let mut parts: Vec<u64> = version
+		.trim()
+		.split('.')
+		.map(|part| part.parse().ok())
+		.collect::<Option<_>>()?;
+	while parts.last() == Some(&0) {
+		parts.pop();
+	}
+	Some(parts)
+}
+
+/// Returns the notes of the running version, if there are any
+pub fn current_release() -> Option<Release> {
+	releases()
+		.into_iter()
+		.find(|release| release.version == CURRENT_VERSION)
+}
+
+/// Returns the notes of the releases after `last` up to the running version, newest first. If
+/// `last` can't be understood, only the running version's notes are returned
+fn releases_since(last: &str) -> Vec<Release> {
+	let current = parse_version(CURRENT_VERSION);
+	let Some(last) = parse_version(last) else {
+		return current_release().into_iter().collect();
+	};
+	releases()
+		.into_iter()
+		.filter(|release| {
+			let version = parse_version(&release.version);
+			version.as_ref() > Some(&last) && version <= current
+		})
+		.collect()
+}
+
+/// Records the running version as the one that last ran, and returns the notes of the releases
+/// since the version that ran before, if it was older. Nothing is returned the first time the
+/// program runs, as everything is new. Failing to read or write the state is ignored, as the
+/// notes are only a courtesy
+pub fn check_for_upgrade() -> Vec<Release> {
+	if state::state_dir().is_none() {
+		return vec![];
+	}
+	let last = state::read("last_version");
+	if last.as_deref().map(str::trim) != Some(CURRENT_VERSION) {
+		state::write("last_version", CURRENT_VERSION);
+	}
+	last.map(|last| releases_since(&last)).unwrap_or_default()
+}
+
+/// Describes the releases, for showing in a popup
+pub fn describe(releases: &[Release]) -> String {
+	let sections: Vec<String> = releases
+		.iter()
+		.map(|release| {
+			let mut lines = vec![format!("Version {}", release.version)];
+			if !release.features.is_empty() {
+				lines.push(String::new());
+				lines.extend(release.features.iter().map(|f| format!("    - {f}")));
+			}
+			if !release.keys.is_empty() {
+				lines.push(String::new());
+				lines.push("New keys".to_string());
+				lines.extend(
+					release
+						.keys
+						.iter()
+						.map(|k| format!("    <{}> - {}", k.keys, k.action)),
+				);
+			}
+			lines.join("\n")
+		})
+		.collect();
+	sections.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compares_versions_with_missing_parts_as_zero() {
+		assert_eq!(parse_version("1.2"), parse_version("1.2.0"));
+		assert!(parse_version("1.2") < parse_version("1.2.1"));
+		assert!(parse_version("1.10.0") > parse_version("1.9"));
+		assert_eq!(parse_version("1.x"), None);
+	}
+}
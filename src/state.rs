@@ -0,0 +1,161 @@
+//! Small bits of state kept between runs, like the files opened recently. Failing to read or
+//! write them is ignored, as the program works the same without them
+
+use std::{
+	env, fs,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use crate::{bank::SimpleFin, rates::ExchangeRates, sync::SyncState};
+
+/// The amount of recently opened files that are remembered
+const RECENT_FILES: usize = 10;
+
+/// How long fetched exchange rates are used before they are fetched again on startup. Providers
+/// publish new rates once a day
+const RATES_MAX_AGE: Duration = Duration::from_hours(12);
+
+/// Returns the directory state is kept in, which is `budgeting-app` in `$XDG_STATE_HOME` (or
+/// `~/.local/state` if that isn't set)
+pub fn state_dir() -> Option<PathBuf> {
+	env::var_os("XDG_STATE_HOME")
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+		.map(|dir| dir.join("budgeting-app"))
+}
+
+/// Writes the contents to the file with the given name in the state directory, creating the
+/// directory if needed
+pub fn write(name: &str, contents: &str) {
+	let Some(dir) = state_dir() else {
+		return;
+	};
+	let _ = fs::create_dir_all(&dir);
+	let _ = fs::write(dir.join(name), contents);
+}
+
+/// Reads the file with the given name from the state directory, if it is there
+pub fn read(name: &str) -> Option<String> {
+	fs::read_to_string(state_dir()?.join(name)).ok()
+}
+
+/// Returns the files opened recently that still exist, most recent first
+pub fn recent_files() -> Vec<PathBuf> {
+	read("recent_files")
+		.unwrap_or_default()
+		.lines()
+		.map(PathBuf::from)
+		.filter(|path| path.is_file())
+		.collect()
+}
+
+/// Records that the file was opened (or saved), moving it to the top of the recent files
+pub fn add_recent_file(path: &Path) {
+	let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+	let mut files = recent_files();
+	files.retain(|file| *file != path);
+	files.insert(0, path);
+	files.truncate(RECENT_FILES);
+	let lines: Vec<String> = files
+		.iter()
+		.map(|file| file.display().to_string())
+		.collect();
+	write("recent_files", &lines.join("\n"));
+}
+
+/// Returns what the file and its copy on the server were when they were last synced, if they
+/// have been. See [`sync`](crate::sync)
+pub fn sync_state(path: &Path) -> Option<SyncState> {
+	let path = path.canonicalize().ok()?.display().to_string();
+	read("sync")?.lines().find_map(|line| {
+		let mut fields = line.split('\t');
+		(fields.next()? == path).then(|| SyncState {
+			etag: fields.next().unwrap_or_default().to_string(),
+			hash: fields.next().unwrap_or_default().to_string(),
+		})
+	})
+}
+
+/// Records what the file and its copy on the server were when they were synced
+pub fn set_sync_state(path: &Path, synced: &SyncState) {
+	let Ok(path) = path.canonicalize() else {
+		return;
+	};
+	let path = path.display().to_string();
+	let mut lines: Vec<String> = read("sync")
+		.unwrap_or_default()
+		.lines()
+		.filter(|line| line.split('\t').next() != Some(path.as_str()))
+		.map(str::to_string)
+		.collect();
+	lines.push(format!("{path}\t{}\t{}", synced.etag, synced.hash));
+	write("sync", &lines.join("\n"));
+}
+
+/// Returns the access URL of the [`SimpleFin`] bridge, if it has been connected to
+pub fn simplefin() -> Option<SimpleFin> {
+	let access_url = read("simplefin")?.trim().to_string();
+	(!access_url.is_empty()).then(|| SimpleFin::new(access_url))
+}
+
+/// Records the access URL of the [`SimpleFin`] bridge, or forgets it. As it holds the credentials,
+/// only the user can read it
+pub fn set_simplefin(bridge: Option<&SimpleFin>) {
+	let Some(dir) = state_dir() else {
+		return;
+	};
+	let path = dir.join("simplefin");
+	let Some(bridge) = bridge else {
+		let _ = fs::remove_file(path);
+		return;
+	};
+	let _ = fs::create_dir_all(&dir);
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+	if let Ok(mut file) = options.open(path) {
+		let _ = std::io::Write::write_all(&mut file, bridge.access_url().as_bytes());
+	}
+}
+
+/// Returns the exchange rates last fetched, if they have been. See [`rates`](crate::rates)
+pub fn exchange_rates() -> Option<ExchangeRates> {
+	let text = read("rates")?;
+	let mut lines = text.lines().map(|line| line.split_once('\t'));
+	let (base, date) = lines.next()??;
+	Some(ExchangeRates {
+		base: base.to_string(),
+		date: date.parse().ok()?,
+		rates: lines
+			.map(|line| {
+				let (currency, rate) = line?;
+				Some((currency.to_string(), rate.parse().ok()?))
+			})
+			.collect::<Option<_>>()?,
+	})
+}
+
+/// Records the exchange rates just fetched, as a line of the base currency and date followed by
+/// a line for each rate
+pub fn set_exchange_rates(rates: &ExchangeRates) {
+	let mut lines = vec![format!("{}\t{}", rates.base, rates.date)];
+	lines.extend(
+		rates
+			.rates
+			.iter()
+			.map(|(currency, rate)| format!("{currency}\t{rate}")),
+	);
+	write("rates", &lines.join("\n"));
+}
+
+/// Returns whether the exchange rates were last fetched long enough ago to fetch them again, or
+/// haven't been fetched at all
+pub fn exchange_rates_stale() -> bool {
+	state_dir()
+		.and_then(|dir| fs::metadata(dir.join("rates")).ok())
+		.and_then(|metadata| metadata.modified().ok())
+		.and_then(|modified| modified.elapsed().ok())
+		.is_none_or(|age| age > RATES_MAX_AGE)
+}
@@ -0,0 +1,228 @@
+use super::{TODAY, harness, labels, selected_row, transaction};
+use crate::{
+	bank::{BankAccount, BankTransaction},
+	model::{ImportSettings, Message, Model, Resolution, UpdateError},
+};
+
+#[test]
+fn inserts_a_row_from_the_form() {
+	let mut h = harness();
+	for keys in ["o", "i", "<C-u>2024-01-15<CR>", "Coffee<Tab>", "-3.50<CR>"] {
+		h.type_keys(keys).unwrap();
+	}
+	assert!(h.controller.state.popup.is_none());
+	// The sheet is ordered by date, so the row goes between the ones around its date
+	assert_eq!(labels(&h), ["Salary", "Rent", "Coffee", "Groceries"]);
+	assert_eq!(selected_row(&mut h), Some(2));
+	assert!(h.screen().contains("2024-01-15 Coffee"));
+	assert!(h.model.has_unsaved_changes());
+}
+
+#[test]
+fn inserts_a_row_on_today_by_default() {
+	let mut h = harness();
+	h.type_keys("ggO<CR>Coffee<Tab>-2<CR>").unwrap();
+	assert_eq!(labels(&h), ["Salary", "Rent", "Groceries", "Coffee"]);
+	assert!(h.screen().contains(&format!("{TODAY} Coffee")));
+}
+
+#[test]
+fn gives_new_rows_the_defaults_of_the_sheet() {
+	let mut h = harness();
+	// Make new amounts negative, and give new rows the Food category
+	h.type_keys("gVjjjj<CR>j<CR>Food<CR><Esc>").unwrap();
+	h.type_keys("O<CR>Coffee<Tab>3.5<CR>").unwrap();
+	let sheet = h.model.get_sheet(0).unwrap();
+	let coffee = sheet.transactions().last().unwrap();
+	assert_eq!(coffee.label, "Coffee");
+	assert!((coffee.amount + 3.5).abs() < 1e-9);
+	assert_eq!(coffee.category, "Food");
+}
+
+#[test]
+fn edits_the_selected_cell() {
+	let mut h = harness();
+	h.type_keys("ggll").unwrap();
+	h.type_keys("i<C-u>Paycheck<CR>").unwrap();
+	assert_eq!(labels(&h), ["Paycheck", "Rent", "Groceries"]);
+	assert!(h.screen().contains("Paycheck"));
+}
+
+#[test]
+fn keeps_the_popup_open_on_an_invalid_value() {
+	let mut h = harness();
+	h.type_keys("gglll").unwrap();
+	h.type_keys("i<C-u>lots<CR>").unwrap();
+	assert!(h.controller.state.popup.is_some());
+	assert!(h.screen().contains("invalid float literal"));
+	assert_eq!(labels(&h), ["Salary", "Rent", "Groceries"]);
+	assert!(!h.model.has_unsaved_changes());
+}
+
+#[test]
+fn deletes_and_restores_rows() {
+	let mut h = harness();
+	h.type_keys("ggdd").unwrap();
+	assert_eq!(labels(&h), ["Rent", "Groceries"]);
+	assert!(h.screen().contains("Deleted 1 row"));
+	h.type_keys("gD<CR>").unwrap();
+	assert_eq!(labels(&h), ["Salary", "Rent", "Groceries"]);
+}
+
+#[test]
+fn moves_rows_to_another_sheet() {
+	let mut h = harness();
+	h.type_keys("<C-t>ggMj<CR>").unwrap();
+	assert_eq!(labels(&h), ["Groceries"]);
+	assert!(h.screen().contains("Moved 2 rows to Sheet1"));
+	let moved: Vec<_> = h.model.get_sheet(1).unwrap().transactions()[..2]
+		.iter()
+		.map(|t| t.label.clone())
+		.collect();
+	assert_eq!(moved, ["Salary", "Rent"]);
+}
+
+#[test]
+fn puts_yanked_rows() {
+	let mut h = harness();
+	h.type_keys("yyP").unwrap();
+	assert_eq!(labels(&h), ["Salary", "Rent", "Groceries", "Groceries"]);
+}
+
+#[test]
+fn repeats_the_last_edit() {
+	let mut h = harness();
+	h.type_keys("ggll").unwrap();
+	h.type_keys("i<C-u>Paid<CR>").unwrap();
+	h.type_keys("j.").unwrap();
+	assert_eq!(labels(&h), ["Paid", "Paid", "Groceries"]);
+}
+
+#[test]
+fn imports_each_bank_transaction_once() {
+	let mut h = harness();
+	let fetched = |ids: &[(&str, &str, f64)]| BankAccount {
+		id: "checking".to_string(),
+		name: "Checking".to_string(),
+		transactions: ids
+			.iter()
+			.map(|(id, label, amount)| BankTransaction {
+				id: id.to_string(),
+				transaction: transaction("2024-01-25", label, *amount),
+			})
+			.collect(),
+	};
+	let pending = h
+		.model
+		.pending_bank_import("Sheet0", fetched(&[("t1", "Coffee", -4.0)]))
+		.unwrap();
+	h.model.finish_import(pending).unwrap();
+	assert_eq!(h.model.bank_link("Sheet0").unwrap().account, "checking");
+	// Fetching the same days again only imports what is new
+	let pending = h
+		.model
+		.pending_bank_import(
+			"Sheet0",
+			fetched(&[("t1", "Coffee", -4.0), ("t2", "Bagel", -3.0)]),
+		)
+		.unwrap();
+	h.model.finish_import(pending).unwrap();
+	assert_eq!(
+		labels(&h),
+		["Salary", "Rent", "Groceries", "Coffee", "Bagel"]
+	);
+}
+
+#[test]
+fn merges_into_the_transaction_a_conflict_was_found_with() {
+	let mut h = harness();
+	let settings = ImportSettings {
+		pattern: "bank.csv".to_string(),
+		preset: "generic".to_string(),
+		sheet: "Sheet0".to_string(),
+	};
+	let pending = |model: &Model| {
+		let imported = vec![transaction("2024-01-20", "GROCERIES 1234", -65.5)];
+		let mut pending = model.pending_import(settings.clone(), imported).unwrap();
+		pending.conflicts[0].resolution = Resolution::Merge;
+		pending
+	};
+	// Rows added while the import is pending move the one it conflicts with down
+	let merge = pending(&h.model);
+	let coffee = transaction("2024-01-01", "Coffee", -3.0);
+	h.model
+		.update(Message::InsertRow {
+			sheet: 0,
+			row: 0,
+			transaction: coffee,
+		})
+		.unwrap();
+	h.model.finish_import(merge).unwrap();
+	assert_eq!(labels(&h), ["Coffee", "Salary", "Rent", "GROCERIES 1234"]);
+
+	// Nothing is imported if the transaction has been deleted since
+	let merge = pending(&h.model);
+	h.model
+		.update(Message::DeleteRows {
+			sheet: 0,
+			rows: vec![3],
+		})
+		.unwrap();
+	assert!(matches!(
+		h.model.finish_import(merge),
+		Err(UpdateError::MergedTransaction(0))
+	));
+	assert_eq!(labels(&h), ["Coffee", "Salary", "Rent"]);
+}
+
+#[test]
+fn leaves_nothing_in_plaintext_once_encrypted() {
+	let dir = std::env::temp_dir().join(format!("budgeting-app-encrypt-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("budget.json").display().to_string();
+	let mut model = Model::scratch_with(vec![transaction("2024-01-05", "Salary", 2000.0)]);
+	model.set_filename(path.clone()).unwrap();
+	model.save(3).unwrap();
+	let insert = |label: &str| Message::InsertRow {
+		sheet: 0,
+		row: 0,
+		transaction: transaction("2024-01-10", label, -3.0),
+	};
+	model.update(insert("Coffee")).unwrap();
+	model.save(3).unwrap();
+
+	// The file is encrypted once it is saved, along with the backups and audit log written so far
+	// and the backup of the file in plaintext
+	model.set_passphrase(Some("hunter2")).unwrap();
+	model.update(insert("Tea")).unwrap();
+	model.save(3).unwrap();
+	let files: Vec<_> = std::fs::read_dir(&dir)
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.collect();
+	let contents: Vec<_> = files
+		.iter()
+		.map(|file| std::fs::read_to_string(file).unwrap())
+		.collect();
+	std::fs::remove_dir_all(dir).unwrap();
+	// The file, its two backups, audit log and lock
+	assert_eq!(files.len(), 5, "{files:?}");
+	for text in contents {
+		assert!(
+			!text.contains("Salary") && !text.contains("Coffee"),
+			"{text}"
+		);
+	}
+}
+
+#[test]
+fn fills_a_cell_over_the_rows_above_it() {
+	let mut h = harness();
+	// From the label of the last row, up to the first
+	h.type_keys("GllFgg<CR>").unwrap();
+	assert_eq!(labels(&h), ["Groceries", "Groceries", "Groceries"]);
+	// Two rows up from the middle one takes the value of the middle one, not the top one
+	let mut h = harness();
+	h.type_keys("ggjllF2k<CR>").unwrap();
+	assert_eq!(labels(&h), ["Rent", "Rent", "Groceries"]);
+}
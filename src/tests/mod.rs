@@ -0,0 +1,57 @@
+//! End-to-end tests of the program, which type keys into it through a [`Harness`] and check what
+//! it draws and what it does to the model
+
+mod editing;
+mod navigation;
+mod popups;
+
+use budgeting_core::clock;
+use chrono::NaiveDate;
+
+use crate::{
+	batch::Harness,
+	config::Config,
+	model::{Model, Transaction},
+};
+
+/// The date the tests run on, so what they do doesn't depend on when they are run
+const TODAY: &str = "2024-01-31";
+
+/// Returns a transaction on the date (as `YYYY-MM-DD`), with the label and amount
+fn transaction(date: &str, label: &str, amount: f64) -> Transaction {
+	Transaction {
+		date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+		label: label.to_string(),
+		amount,
+		..Transaction::default()
+	}
+}
+
+/// Starts the program on [`TODAY`] with the default config, on a scratch sheet of a few
+/// transactions
+fn harness() -> Harness {
+	clock::set_today(Some(NaiveDate::parse_from_str(TODAY, "%Y-%m-%d").unwrap()));
+	let model = Model::scratch_with(vec![
+		transaction("2024-01-05", "Salary", 2000.0),
+		transaction("2024-01-10", "Rent", -800.0),
+		transaction("2024-01-20", "Groceries", -65.5),
+	]);
+	Harness::new(model, &Config::default(), true).unwrap()
+}
+
+/// Returns the labels of the transactions of the selected sheet, in order
+fn labels(harness: &Harness) -> Vec<String> {
+	harness
+		.view
+		.get_selected_sheet(&harness.model)
+		.transactions()
+		.iter()
+		.map(|t| t.label.clone())
+		.collect()
+}
+
+/// Returns the row selected in the selected sheet
+fn selected_row(harness: &mut Harness) -> Option<usize> {
+	let sheet = harness.view.get_selected_sheet(&harness.model);
+	harness.view.get_selected_row(sheet)
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+use budgeting_core::clock;
+use chrono::{Days, NaiveDate};
+
+use super::{TODAY, harness, selected_row};
+use crate::{
+	batch::Harness,
+	config::Config,
+	model::{Model, Transaction},
+};
+
+#[test]
+fn starts_on_the_last_row() {
+	let mut h = harness();
+	assert_eq!(selected_row(&mut h), Some(2));
+	assert!(h.screen().contains("NORMAL  Sheet0"));
+}
+
+#[test]
+fn moves_between_rows() {
+	let mut h = harness();
+	h.type_keys("k").unwrap();
+	assert_eq!(selected_row(&mut h), Some(1));
+	h.type_keys("gg").unwrap();
+	assert_eq!(selected_row(&mut h), Some(0));
+	h.type_keys("2j").unwrap();
+	assert_eq!(selected_row(&mut h), Some(2));
+	// Moving past the end stays on the last row
+	h.type_keys("j").unwrap();
+	assert_eq!(selected_row(&mut h), Some(2));
+}
+
+#[test]
+fn shows_the_position_in_the_status_line() {
+	let mut h = harness();
+	h.type_keys("gg").unwrap();
+	let status = h.screen().lines().last().unwrap().to_string();
+	assert!(status.trim_end().ends_with("1/3"), "{status}");
+}
+
+#[test]
+fn moves_between_sheets() {
+	let mut h = harness();
+	h.type_keys("<C-t>L").unwrap();
+	assert_eq!(h.view.selected_sheet, 1);
+	assert!(h.screen().contains("Sheet1"));
+	h.type_keys("H").unwrap();
+	assert_eq!(h.view.selected_sheet, 0);
+}
+
+#[test]
+fn skips_archived_sheets() {
+	let mut h = harness();
+	h.type_keys("<C-t>L<C-t>Lgz").unwrap();
+	assert_eq!(h.view.selected_sheet, 1);
+	assert!(h.model.get_sheet(2).unwrap().archived);
+	assert!(!h.screen().contains("Sheet2"));
+	h.type_keys("LL").unwrap();
+	assert_eq!(h.view.selected_sheet, 1);
+	h.type_keys("gkL").unwrap();
+	assert!(h.screen().contains("Sheet2 (archived)"));
+	h.type_keys("gkgZ<CR>").unwrap();
+	assert_eq!(h.view.selected_sheet, 2);
+	assert!(!h.model.get_sheet(2).unwrap().archived);
+}
+
+#[test]
+fn counts_repeat_moves() {
+	let mut h = harness();
+	// The first move right selects the first column, as no cell is selected to begin with
+	h.type_keys("gg3l").unwrap();
+	let sheet = h.view.get_selected_sheet(&h.model);
+	assert_eq!(h.view.get_selected_cell(sheet), Some((0, 2)));
+}
+
+/// Starts the program on a scratch sheet of 100,000 transactions
+fn large_harness() -> Harness {
+	clock::set_today(Some(TODAY.parse().unwrap()));
+	let start = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+	let transactions = (0..100_000u32)
+		.map(|n| Transaction {
+			date: start + Days::new(u64::from(n / 10)),
+			label: format!("Row {n}"),
+			amount: -1.0,
+			..Transaction::default()
+		})
+		.collect();
+	let model = Model::scratch_with(transactions);
+	Harness::new(model, &Config::default(), true).unwrap()
+}
+
+#[test]
+fn keeps_the_selected_row_on_the_screen_in_large_sheets() {
+	let mut h = large_harness();
+	for (keys, row, label) in [
+		("gg", 0, "Row 0 "),
+		("G", 99_999, "Row 99999"),
+		("k", 99_998, "Row 99998"),
+		("gg", 0, "Row 0 "),
+		("j", 1, "Row 1 "),
+	] {
+		h.type_keys(keys).unwrap();
+		assert_eq!(selected_row(&mut h), Some(row));
+		assert!(h.screen().contains(label), "{label} isn't on the screen");
+	}
+}
+
+/// A benchmark rather than a test, as how long a key takes depends on the machine. Run it with
+/// `cargo test --release -- --ignored`
+#[test]
+#[ignore = "benchmark"]
+fn draws_large_sheets_as_quickly_as_small_ones() {
+	let mut h = large_harness();
+	// Only the rows on the screen are built, and the file isn't written out again to tell whether
+	// it has unsaved changes, so each move redraws in a few milliseconds
+	for keys in ["gg", "G", "k", "gg", "j"] {
+		let started = Instant::now();
+		h.type_keys(keys).unwrap();
+		let elapsed = started.elapsed();
+		assert!(
+			elapsed < Duration::from_millis(250),
+			"{keys} took {elapsed:?}"
+		);
+	}
+}
@@ -0,0 +1,204 @@
+use budgeting_core::clock;
+
+use super::{TODAY, harness, labels, transaction};
+use crate::{
+	batch::Harness,
+	config::Config,
+	controller::popup::Popup,
+	model::{AccountType, Message, Model},
+};
+
+#[test]
+fn shows_and_closes_help() {
+	let mut h = harness();
+	h.type_keys("?").unwrap();
+	assert!(h.screen().contains("Keymap help"));
+	h.type_keys("<Esc>").unwrap();
+	assert!(h.controller.state.popup.is_none());
+	assert!(!h.screen().contains("Keymap help"));
+}
+
+#[test]
+fn says_when_a_command_is_unknown() {
+	let mut h = harness();
+	h.type_keys("gx").unwrap();
+	assert!(h.screen().contains("Unknown command: gx"));
+}
+
+#[test]
+fn cannot_delete_the_main_sheet() {
+	let mut h = harness();
+	h.type_keys("<C-Del>").unwrap();
+	assert!(matches!(h.controller.state.popup, Some(Popup::Info(_))));
+	assert!(h.screen().contains("Main sheet cannot be deleted"));
+}
+
+#[test]
+fn confirms_deleting_a_sheet() {
+	let mut h = harness();
+	h.type_keys("<C-t>L<C-Del>").unwrap();
+	assert!(
+		h.screen()
+			.contains("Are you sure you want to delete this sheet?")
+	);
+	h.type_keys("n").unwrap();
+	assert_eq!(h.model.sheet_count(), 2);
+	h.type_keys("<C-Del>y").unwrap();
+	assert_eq!(h.model.sheet_count(), 1);
+}
+
+#[test]
+fn renames_a_sheet() {
+	let mut h = harness();
+	h.type_keys("<C-r><C-u>Checking<CR>").unwrap();
+	assert_eq!(h.model.sheet_titles(), ["Checking"]);
+	assert!(h.screen().contains("NORMAL  Checking"));
+}
+
+#[test]
+fn compares_a_sheet_with_another() {
+	let mut h = harness();
+	h.type_keys("<C-t>gCj<CR>").unwrap();
+	assert!(matches!(h.controller.state.popup, Some(Popup::Compare(_))));
+	let screen = h.screen();
+	assert!(screen.contains("3 added, 1 removed, 0 changed"));
+	assert!(screen.contains("+ 2024-01"));
+}
+
+#[test]
+fn merges_another_copy_of_the_file() {
+	let dir = std::env::temp_dir().join(format!("budgeting-app-merge-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("copy.json").display().to_string();
+	let mut theirs = Model::scratch_with(vec![
+		transaction("2024-01-05", "Salary", 2000.0),
+		transaction("2024-01-10", "Rent", -850.0),
+		transaction("2024-01-25", "Phone", -30.0),
+	]);
+	theirs.set_filename(path.clone()).unwrap();
+	theirs.save(0).unwrap();
+	drop(theirs);
+
+	let mut h = harness();
+	h.type_keys(&format!("gJ{path}<CR>")).unwrap();
+	assert!(matches!(h.controller.state.popup, Some(Popup::Merge(_))));
+	// Rent changed, Groceries is only here and Phone only in the copy. Take the copy's Rent and
+	// drop Groceries, keeping Phone as well
+	h.type_keys("tjty").unwrap();
+	std::fs::remove_dir_all(dir).unwrap();
+	assert!(h.controller.state.popup.is_none());
+	assert_eq!(labels(&h), ["Salary", "Rent", "Phone"]);
+	let sheet = h.view.get_selected_sheet(&h.model);
+	assert!(sheet.transactions()[1].amount < -800.0);
+}
+
+#[test]
+fn switches_to_a_file_that_is_already_open() {
+	let dir = std::env::temp_dir().join(format!("budgeting-app-open-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let save = |name: &str| {
+		let path = dir.join(name).display().to_string();
+		let mut model = Model::scratch_with(vec![transaction("2024-01-05", name, 1.0)]);
+		model.set_filename(path.clone()).unwrap();
+		model.save(0).unwrap();
+		(model, path)
+	};
+	let (first, first_path) = save("first.json");
+	let (second, second_path) = save("second.json");
+	drop(second);
+	clock::set_today(Some(TODAY.parse().unwrap()));
+	let mut h = Harness::new(first, &Config::default(), true).unwrap();
+
+	// The file being shown, named another way
+	let shown_path = dir.join(".").join("first.json").display().to_string();
+	h.type_keys(&format!("gW{shown_path}<CR>")).unwrap();
+	assert_eq!(h.controller.state.open_file_count(), 1);
+
+	h.type_keys(&format!("gW{second_path}<CR>")).unwrap();
+	assert_eq!(h.controller.state.open_file_count(), 2);
+	assert_eq!(labels(&h), ["second.json"]);
+	h.type_keys(&format!("gW{first_path}<CR>")).unwrap();
+	std::fs::remove_dir_all(dir).unwrap();
+	assert_eq!(h.controller.state.open_file_count(), 2);
+	assert_eq!(labels(&h), ["first.json"]);
+}
+
+#[test]
+fn converts_sheets_in_other_currencies_for_the_net_worth() {
+	let mut h = harness();
+	h.type_keys("<C-t>L").unwrap();
+	let sheet = h.view.selected_sheet;
+	h.model
+		.update(Message::InsertRow {
+			sheet,
+			row: 0,
+			transaction: transaction("2024-01-15", "Deposit", 100.0),
+		})
+		.unwrap();
+	// Keep the new sheet in euros, worth 1.10 dollars each
+	h.type_keys("gX<CR>eur<CR>jj<CR>1.1<CR>").unwrap();
+	assert!(h.screen().contains("EUR  1.1000 set by hand"));
+	h.type_keys("<Esc>").unwrap();
+	let total = h.model.net_worth(TODAY.parse().unwrap(), false, None).total;
+	assert!((total.balance - (2000.0 - 800.0 - 65.5 + 110.0)).abs() < 1e-9);
+}
+
+#[test]
+fn gives_rows_the_names_of_their_payees() {
+	let mut h = harness();
+	h.model
+		.update(Message::InsertRow {
+			sheet: 0,
+			row: 3,
+			transaction: transaction("2024-01-25", "AMZN Mktp US*2J4", -20.0),
+		})
+		.unwrap();
+	h.type_keys("gN<CR><C-u>Amazon = AMZN MKTP, AMAZON.COM<CR>")
+		.unwrap();
+	assert!(h.screen().contains("Amazon = AMZN MKTP, AMAZON.COM"));
+	h.type_keys("jj<CR>").unwrap();
+	assert_eq!(labels(&h), ["Salary", "Rent", "Groceries", "Amazon"]);
+}
+
+#[test]
+fn shows_what_is_owed_on_credit_card_sheets() {
+	let mut h = harness();
+	h.type_keys("<C-t>L").unwrap();
+	let sheet = h.view.selected_sheet;
+	h.model
+		.update(Message::InsertRow {
+			sheet,
+			row: 0,
+			transaction: transaction("2024-01-15", "Groceries", -40.0),
+		})
+		.unwrap();
+	h.type_keys("gVj<CR><C-u>-100<CR>k<CR>jj<CR><Esc>").unwrap();
+	let sheet = h.model.get_sheet(sheet).unwrap();
+	assert_eq!(sheet.account().kind, AccountType::CreditCard);
+	assert!((sheet.total().actual + 140.0).abs() < 1e-9);
+	assert!(h.screen().contains("Owed: $140.00"));
+}
+
+#[test]
+fn duplicates_a_sheet_a_month_later_without_its_amounts() {
+	let mut h = harness();
+	h.type_keys("gYjjj<CR><CR>").unwrap();
+	assert_eq!(h.view.selected_sheet, 1);
+	let copy = h.model.get_sheet(1).unwrap();
+	assert_eq!(copy.name, "Sheet0 copy");
+	let rows: Vec<_> = copy
+		.transactions()
+		.iter()
+		.map(|t| (t.date.to_string(), t.label.as_str(), t.amount))
+		.collect();
+	assert_eq!(
+		rows,
+		[
+			("2024-02-05".to_string(), "Salary", 0.0),
+			("2024-02-10".to_string(), "Rent", 0.0),
+			("2024-02-20".to_string(), "Groceries", 0.0),
+		]
+	);
+	// The original is left as it was
+	assert!((h.model.get_sheet(0).unwrap().total().actual - 1134.5).abs() < 1e-9);
+}
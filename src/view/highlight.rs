@@ -0,0 +1,159 @@
+//! Rules from the config file that style the rows of a sheet, like showing negative amounts in red
+//! or rent in bold. Each is a `[[highlight]]` table, like
+//!
+//! ```toml
+//! [[highlight]]
+//! tag = "rent"
+//! color = "magenta"
+//! bold = true
+//! ```
+
+use ratatui::style::{Color, Modifier, Style};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::model::{COLUMN_NAMES, Transaction, column_index};
+
+/// A rule styling the rows it matches. A rule matches a transaction when all of the conditions it
+/// has are met, so a rule with no conditions matches everything. When more than one rule matches,
+/// the later ones are drawn over the earlier ones
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Highlight {
+	/// Matches labels containing this text, ignoring case
+	pub contains: Option<String>,
+	/// Matches labels matching this regular expression
+	pub regex: Option<String>,
+	/// Matches amounts of at least this much
+	pub min_amount: Option<f64>,
+	/// Matches amounts of at most this much
+	pub max_amount: Option<f64>,
+	/// Matches transactions in this category, ignoring case
+	pub category: Option<String>,
+	/// Matches transactions with this tag, ignoring case and a leading `#`
+	pub tag: Option<String>,
+	/// Matches transactions that are (or aren't) pending
+	pub pending: Option<bool>,
+	/// Matches transactions that are (or aren't) scheduled
+	pub scheduled: Option<bool>,
+	/// The column to style, by its heading. The whole row is styled if this isn't given
+	pub column: Option<String>,
+	/// The colour of the text, written like the colours of the theme
+	pub color: Option<String>,
+	/// The colour behind the text
+	pub background: Option<String>,
+	pub bold: bool,
+	pub italic: bool,
+	pub underline: bool,
+	/// The compiled [`Highlight::regex`]
+	#[serde(skip)]
+	compiled: Option<Regex>,
+	/// The index of [`Highlight::column`]
+	#[serde(skip)]
+	column_index: Option<usize>,
+	/// The style made from the colours and modifiers
+	#[serde(skip)]
+	style: Style,
+}
+
+impl Highlight {
+	/// Compiles the regex, and checks the column and colours
+	///
+	/// # Errors
+	/// With the first of them that is invalid
+	pub fn compile(&mut self) -> Result<(), String> {
+		self.compiled = self
+			.regex
+			.as_deref()
+			.map(Regex::new)
+			.transpose()
+			.map_err(|_| {
+				format!(
+					"Invalid highlight regex \"{}\"",
+					self.regex.as_deref().unwrap_or_default()
+				)
+			})?;
+		self.column_index = self
+			.column
+			.as_deref()
+			.map(|name| {
+				column_index(name).ok_or_else(|| {
+					format!(
+						"Unknown column \"{name}\", expected one of {}",
+						COLUMN_NAMES.join(", ")
+					)
+				})
+			})
+			.transpose()?;
+
+		let parse = |color: &Option<String>| {
+			color
+				.as_deref()
+				.map(|color| {
+					color
+						.parse::<Color>()
+						.map_err(|_| format!("Invalid highlight colour \"{color}\""))
+				})
+				.transpose()
+		};
+		let mut style = Style::default();
+		if let Some(color) = parse(&self.color)? {
+			style = style.fg(color);
+		}
+		if let Some(color) = parse(&self.background)? {
+			style = style.bg(color);
+		}
+		for (set, modifier) in [
+			(self.bold, Modifier::BOLD),
+			(self.italic, Modifier::ITALIC),
+			(self.underline, Modifier::UNDERLINED),
+		] {
+			if set {
+				style = style.add_modifier(modifier);
+			}
+		}
+		self.style = style;
+		Ok(())
+	}
+
+	/// Returns whether the rule matches the transaction
+	pub fn matches(&self, transaction: &Transaction) -> bool {
+		self.contains.as_ref().is_none_or(|text| {
+			transaction
+				.label
+				.to_lowercase()
+				.contains(&text.to_lowercase())
+		}) && self
+			.compiled
+			.as_ref()
+			.is_none_or(|regex| regex.is_match(&transaction.label))
+			&& self.min_amount.is_none_or(|min| transaction.amount >= min)
+			&& self.max_amount.is_none_or(|max| transaction.amount <= max)
+			&& self
+				.category
+				.as_ref()
+				.is_none_or(|category| transaction.category.eq_ignore_ascii_case(category))
+			&& self.tag.as_ref().is_none_or(|tag| {
+				let tag = tag.trim_start_matches('#');
+				transaction
+					.tags
+					.iter()
+					.any(|t| t.trim_start_matches('#').eq_ignore_ascii_case(tag))
+			}) && self
+			.pending
+			.is_none_or(|pending| transaction.pending == pending)
+			&& self
+				.scheduled
+				.is_none_or(|scheduled| transaction.scheduled == scheduled)
+	}
+
+	/// Returns the index of the column the rule styles, or [`None`] if it styles the whole row
+	pub fn styled_column(&self) -> Option<usize> {
+		self.column_index
+	}
+
+	/// Returns the style the rule draws matching rows (or cells) in
+	pub fn style(&self) -> Style {
+		self.style
+	}
+}
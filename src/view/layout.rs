@@ -0,0 +1,121 @@
+//! Choosing what to show when the terminal is too small to show everything
+
+use ratatui::{
+	buffer::Buffer,
+	layout::{Direction, Rect},
+	style::Style,
+	text::Text,
+	widgets::{Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::view::theme::Theme;
+
+/// The smallest area a view can be drawn in without being cut off
+#[derive(Debug, Clone, Copy)]
+pub(super) struct MinSize {
+	pub width: u16,
+	pub height: u16,
+}
+
+impl MinSize {
+	/// Returns whether the given area is at least this size
+	pub fn fits(self, area: Rect) -> bool {
+		area.width >= self.width && area.height >= self.height
+	}
+
+	/// Renders a message over the area saying that it is smaller than this size
+	pub fn render_too_small(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+		Clear.render(area, buf);
+		Paragraph::new(Text::styled(
+			format!(
+				"Terminal too small ({}x{}, needs {}x{})",
+				area.width, area.height, self.width, self.height
+			),
+			Style::default().fg(theme.error),
+		))
+		.wrap(Wrap { trim: true })
+		.render(area, buf);
+	}
+
+	/// Returns the size needed to show two views of this size split in the given direction
+	pub fn split(self, direction: Direction) -> Self {
+		match direction {
+			Direction::Horizontal => Self {
+				width: self.width * 2,
+				..self
+			},
+			Direction::Vertical => Self {
+				height: self.height * 2,
+				..self
+			},
+		}
+	}
+}
+
+/// A view that needs a minimum amount of space to be drawn
+pub(super) trait MinimumSize {
+	/// The smallest area the view can be drawn in
+	const MIN_SIZE: MinSize;
+}
+
+/// How many of the optional boxes around the table are shown. Boxes are dropped one at a time
+/// as the terminal gets shorter, so the table always has room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Chrome {
+	/// Only the table
+	None,
+	/// The tabs listing the sheets
+	Tabs,
+	/// The tabs, and the header with the filename and help hint
+	Header,
+	/// The tabs, the header, and the preview of the selected cell above the table
+	Full,
+}
+
+/// Which of the optional parts of the main screen are shown
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ScreenLayout {
+	pub chrome: Chrome,
+	/// Whether the line numbers to the left of the table are shown
+	pub gutter: bool,
+}
+
+impl ScreenLayout {
+	/// The height of the header, the cell preview and the tabs, which are all bordered boxes
+	pub const BOX_HEIGHT: u16 = 3;
+	/// The height of the footer
+	pub const FOOTER_HEIGHT: u16 = 1;
+	/// The narrowest the terminal can be while still showing the line numbers
+	const GUTTER_WIDTH: u16 = 40;
+
+	/// Picks what to show in the given area, around a table of the given minimum size. The cell
+	/// preview goes first, then the header, then the tabs, and the line numbers go when the
+	/// terminal is too narrow
+	pub fn for_area(area: Rect, table: MinSize) -> Self {
+		let spare = area
+			.height
+			.saturating_sub(table.height + Self::FOOTER_HEIGHT);
+		let chrome = match spare / Self::BOX_HEIGHT {
+			0 => Chrome::None,
+			1 => Chrome::Tabs,
+			2 => Chrome::Header,
+			_ => Chrome::Full,
+		};
+		Self {
+			chrome,
+			gutter: area.width >= Self::GUTTER_WIDTH,
+		}
+	}
+
+	pub fn tabs(self) -> bool {
+		self.chrome >= Chrome::Tabs
+	}
+
+	pub fn header(self) -> bool {
+		self.chrome >= Chrome::Header
+	}
+
+	pub fn cell_preview(self) -> bool {
+		self.chrome >= Chrome::Full
+	}
+}
@@ -1,60 +1,152 @@
 //! This module reads from the model and displays the relevant information to the user
-use std::{collections::HashMap, fmt::Display};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Display,
+};
 
+use budgeting_core::{clock, rates::ExchangeRates};
+use chrono::{Datelike, Days, NaiveDate};
 use ratatui::{
 	Frame,
-	layout::{Constraint, Layout},
-	style::{Color, Style},
+	layout::{Constraint, Layout, Rect},
+	style::Style,
 	symbols,
-	text::Text,
+	text::{Line, Text},
 	widgets::{Block, Borders, Paragraph, Tabs},
 };
 
 use crate::{
 	controller::ControllerState,
-	model::{Model, Sheet, SheetId, Transaction},
-	view::{rendering::SheetWidget, states::SheetState},
+	model::{ColumnDefaults, Filter, GroupHeading, Model, Sheet, SheetId, TableRow, Transaction},
+	view::{
+		layout::{MinimumSize, ScreenLayout},
+		rendering::{DashboardWidget, Pane, PopupWidget, SheetWidget},
+		split::Split,
+		states::{JumpList, SheetState},
+		status::KeyHints,
+		toast::Toasts,
+	},
 };
 
+mod highlight;
+mod layout;
 mod rendering;
+mod split;
 mod states;
+mod status;
+mod theme;
+mod toast;
+
+pub use budgeting_core::export::{
+	export_forecast, export_html, export_report_csv, transactions_csv,
+};
+pub use highlight::Highlight;
+pub use theme::{Theme, ThemeConfig};
+
+pub use crate::model::format_currency;
 
 /// The height of the rows of a sheet when displayed as a table
 const ITEM_HEIGHT: u16 = 1;
-/// The currency symbol used in front of the amounts
-const CURRENCY_SYMBOL: char = '$';
 
-impl Display for ControllerState {
+/// How the rows of every sheet are drawn. These are kept for the session, rather than saved with
+/// the sheets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowOptions {
+	/// Whether every other row has a background, to make the rows easier to follow
+	pub striped: bool,
+	pub density: RowDensity,
+}
+
+/// How much room the rows of a sheet are given
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RowDensity {
+	/// A line for each row, fitting the most rows on the screen
+	#[default]
+	Compact,
+	/// A blank line under each row, so they are easier to tell apart
+	Comfortable,
+}
+
+impl RowDensity {
+	/// Returns the height of each row, in lines
+	pub fn height(self) -> u16 {
+		match self {
+			RowDensity::Compact => ITEM_HEIGHT,
+			RowDensity::Comfortable => ITEM_HEIGHT + 1,
+		}
+	}
+
+	/// Returns the other density
+	#[must_use]
+	pub fn toggled(self) -> Self {
+		match self {
+			RowDensity::Compact => RowDensity::Comfortable,
+			RowDensity::Comfortable => RowDensity::Compact,
+		}
+	}
+}
+
+impl Display for RowDensity {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		let chars: String = self.last_chars.iter().collect();
-		let nums: String = self
-			.last_nums
-			.iter()
-			.map(std::string::ToString::to_string)
-			.collect();
-		write!(f, "{chars}{nums}")
+		match self {
+			RowDensity::Compact => write!(f, "compact"),
+			RowDensity::Comfortable => write!(f, "comfortable"),
+		}
+	}
+}
+
+/// Which sheets are shown in the tabs and moved between
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShownSheets {
+	/// Every sheet but the archived ones
+	#[default]
+	Unarchived,
+	/// Every sheet, archived or not
+	All,
+}
+
+impl ShownSheets {
+	/// Returns the other choice of sheets
+	#[must_use]
+	pub fn toggled(self) -> Self {
+		match self {
+			ShownSheets::Unarchived => ShownSheets::All,
+			ShownSheets::All => ShownSheets::Unarchived,
+		}
 	}
 }
 
-/// A helper function to format currency according to accounting formatting
-/// E.g. -10.0 becomes "$(10.00)" and 10.0 becomes "$10.00"
-fn format_currency(a: f64) -> String {
-	if a >= 0.0 {
-		format!("{CURRENCY_SYMBOL}{a:05.2}")
-	} else {
-		format!("{}({:05.2})", CURRENCY_SYMBOL, -a)
+/// A span of dates that the rows can be moved through a whole one of at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatePeriod {
+	/// A week, starting on Monday like the calendar
+	Week,
+	Month,
+}
+
+impl DatePeriod {
+	/// Returns the first day of the period the date is in
+	fn start(self, date: NaiveDate) -> NaiveDate {
+		match self {
+			DatePeriod::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+			DatePeriod::Month => date.with_day(1).expect("Every month has a first day"),
+		}
 	}
 }
 
-pub fn get_string_of_transaction_member(transaction: &Transaction, index: usize) -> String {
-	match index {
-		0 => transaction.date.to_string(),
-		1 => transaction.label.clone(),
-		2 => transaction.amount.to_string(),
-		_ => String::new(),
+impl Display for DatePeriod {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DatePeriod::Week => write!(f, "week"),
+			DatePeriod::Month => write!(f, "month"),
+		}
 	}
 }
 
+pub fn get_string_of_transaction_member(transaction: &Transaction, index: usize) -> String {
+	transaction.member_string(index)
+}
+
 /// Represents the view of the user
 #[derive(Default)]
 pub struct View {
@@ -62,6 +154,28 @@ pub struct View {
 	sheet_states: HashMap<SheetId, SheetState>,
 	/// The currently selected sheet. See [`Model::get_sheet`] for indexing logic
 	pub selected_sheet: usize,
+	/// Whether the balance shown under the sheet is the projected balance, including scheduled
+	/// transactions, rather than the actual balance
+	pub projected: bool,
+	/// Whether the program was started with `--safe-mode`, without the user's config and rules
+	pub safe_mode: bool,
+	/// Whether the net worth of every sheet is shown in place of the selected sheet
+	pub dashboard: bool,
+	/// Whether archived sheets are shown in the tabs and moved between like the others
+	pub shown_sheets: ShownSheets,
+	/// The columns shown in every sheet, and their widths, unless the sheet sets its own
+	pub columns: ColumnDefaults,
+	/// The colours the view is drawn in
+	pub theme: Theme,
+	/// The rules that style the rows of sheets. See [`Highlight`]
+	pub highlights: Vec<Highlight>,
+	/// Whether the rows of sheets are striped, and how tall they are
+	pub row_options: RowOptions,
+	/// The exchange rates last fetched, which sheets in other currencies are converted at unless
+	/// the file sets their rates by hand
+	pub rates: Option<ExchangeRates>,
+	/// The second pane, if the view is split to show two sheets at once
+	split: Option<Split>,
 }
 
 impl View {
@@ -70,6 +184,23 @@ impl View {
 		Self::default()
 	}
 
+	/// Forgets the state of every sheet and selects the first one, for when another file is
+	/// opened. Settings like the projected balance are kept
+	pub fn reset(&mut self) {
+		self.sheet_states.clear();
+		self.selected_sheet = 0;
+		self.dashboard = false;
+		self.split = None;
+	}
+
+	/// Forgets the sizes the sheets were last rendered at, for when the terminal is resized. They
+	/// are laid out again at the new size the next time they are rendered
+	pub fn resize(&mut self) {
+		for state in self.sheet_states.values_mut() {
+			state.forget_area();
+		}
+	}
+
 	/// Gets the `selected_sheet` from the model, and unwraps it as `selected_sheet` should always be
 	/// valid
 	// NOTE: Maybe unwrap or get the main sheet? Not sure how this will interact with deleting
@@ -80,12 +211,145 @@ impl View {
 			.unwrap_or(model.get_main_sheet())
 	}
 
+	/// Returns the selected cell, where the row is the index of the transaction in the sheet and
+	/// the column is the index of the member (not the displayed row and column, which can differ
+	/// when the sheet is filtered, sorted or has hidden columns)
 	pub fn get_selected_cell(&mut self, sheet: &Sheet) -> Option<(usize, usize)> {
-		self.get_state_of(sheet).table_state.selected_cell()
+		let state = self.get_state_of(sheet);
+		let (row, col) = state.table_state.selected_cell()?;
+		let col = *sheet.display.visible_columns(&self.columns).get(col)?;
+		Some((sheet.displayed_row_index(row)?, col))
 	}
 
+	/// Returns the index of the transaction in the selected row. See [`View::get_selected_cell`]
 	pub fn get_selected_row(&mut self, sheet: &Sheet) -> Option<usize> {
-		self.get_state_of(sheet).table_state.selected()
+		let state = self.get_state_of(sheet);
+		let row = state.table_state.selected()?;
+		sheet.displayed_row_index(row)
+	}
+
+	/// Returns the selected row as displayed, which can differ from the index of its transaction
+	/// when the sheet is filtered or sorted. See [`View::get_selected_row`]
+	pub fn get_selected_display_row(&mut self, model: &Model) -> Option<usize> {
+		self.get_state_of(self.get_selected_sheet(model))
+			.table_state
+			.selected()
+	}
+
+	/// Selects the given displayed row. See [`View::get_selected_display_row`]
+	pub fn select_display_row(&mut self, row: usize, model: &Model) {
+		self.get_state_of(self.get_selected_sheet(model))
+			.scroll_to_row(row);
+	}
+
+	/// Selects the row displaying the transaction with the given index, or the heading of its
+	/// group if it is folded, if it is shown. See [`View::get_selected_row`]
+	pub fn select_row(&mut self, row: usize, model: &Model) {
+		let sheet = self.get_selected_sheet(model);
+		let state = self.get_state_of(sheet);
+		if let Some(display_row) = sheet.table_rows().iter().position(|r| match r {
+			TableRow::Transaction(index) => *index == row,
+			TableRow::Heading(heading) => heading.folded && heading.rows.contains(&row),
+		}) {
+			state.scroll_to_row(display_row);
+		}
+	}
+
+	/// Returns the indices of the transactions in the selected row and the `count - 1` rows
+	/// displayed below it, skipping any rows hidden by a filter. A group heading stands for every
+	/// row in its group
+	pub fn get_selected_rows(&mut self, sheet: &Sheet, count: usize) -> Vec<usize> {
+		let state = self.get_state_of(sheet);
+		let Some(row) = state.table_state.selected() else {
+			return vec![];
+		};
+		let mut rows = vec![];
+		for table_row in sheet.table_rows().into_iter().skip(row).take(count) {
+			match table_row {
+				TableRow::Transaction(index) => rows.push(index),
+				TableRow::Heading(heading) => rows.extend(heading.rows),
+			}
+		}
+		// An unfolded heading is followed by the rows it already stands for
+		let mut seen = HashSet::new();
+		rows.retain(|row| seen.insert(*row));
+		rows
+	}
+
+	/// Returns the heading of the group the selected row is in, or of the selected heading
+	fn selected_group(&mut self, model: &Model) -> Option<GroupHeading> {
+		let sheet = self.get_selected_sheet(model);
+		let selected = self.get_state_of(sheet).table_state.selected()?;
+		let table_rows = sheet.table_rows();
+		table_rows[..=selected.min(table_rows.len().checked_sub(1)?)]
+			.iter()
+			.rev()
+			.find_map(|row| match row {
+				TableRow::Heading(heading) => Some(heading.clone()),
+				TableRow::Transaction(_) => None,
+			})
+	}
+
+	/// Selects the heading of the group with the given name, if it is shown
+	fn select_heading(&mut self, key: &str, model: &Model) {
+		let sheet = self.get_selected_sheet(model);
+		let state = self.get_state_of(sheet);
+		if let Some(display_row) = sheet
+			.table_rows()
+			.iter()
+			.position(|r| matches!(r, TableRow::Heading(heading) if heading.key == key))
+		{
+			state.scroll_to_row(display_row);
+		}
+	}
+
+	/// Folds the group of the selected row, unfolds it, or toggles it if given [`None`], and
+	/// selects its heading. Returns whether the rows are grouped
+	pub fn fold(&mut self, folded: Option<bool>, model: &mut Model) -> bool {
+		let Some(group) = self.selected_group(model) else {
+			return false;
+		};
+		let folded = folded.unwrap_or(!group.folded);
+		if model
+			.set_folded(self.selected_sheet, &group.key, folded)
+			.is_err()
+		{
+			return false;
+		}
+		self.select_heading(&group.key, model);
+		true
+	}
+
+	/// Folds every group of the selected sheet, or unfolds them all, keeping the selected row (or
+	/// the heading of its group) selected. Returns whether the rows are grouped
+	pub fn fold_all(&mut self, folded: bool, model: &mut Model) -> bool {
+		let Some(group) = self.selected_group(model) else {
+			return false;
+		};
+		let row = self.get_selected_row(self.get_selected_sheet(model));
+		if model.set_all_folded(self.selected_sheet, folded).is_err() {
+			return false;
+		}
+		match row {
+			Some(row) => self.select_row(row, model),
+			None => self.select_heading(&group.key, model),
+		}
+		true
+	}
+
+	/// Applies a filter to the selected sheet, or removes it if given [`None`]. Selects the first
+	/// row, as the previously selected row may no longer be shown
+	pub fn set_filter(&mut self, filter: Option<Filter>, model: &mut Model) {
+		self.remember_jump(model);
+		if model.set_filter(self.selected_sheet, filter).is_ok() {
+			self.get_state_of(self.get_selected_sheet(model))
+				.scroll_to_row(0);
+		}
+	}
+
+	/// Returns the filter applied to the selected sheet, if any
+	pub fn get_filter<'a>(&self, model: &'a Model) -> Option<&'a Filter> {
+		self.get_selected_sheet(model).display.filter.as_ref()
 	}
 
 	/// Finds the stored state of a given sheet, or creates a new state to track as this is the
@@ -97,66 +361,374 @@ impl View {
 	}
 
 	/// Renders the view for the user
+	/// Renders the view for the user. When the terminal is small, the optional parts of the
+	/// screen are dropped to make room for the table (see [`ScreenLayout`])
 	pub fn render(&mut self, frame: &mut Frame, model: &Model, controller_state: &ControllerState) {
+		let min_size = SheetWidget::MIN_SIZE;
+		if !min_size.fits(frame.area()) {
+			min_size.render_too_small(frame.area(), frame.buffer_mut(), &self.theme);
+			return;
+		}
+		// Both panes of a split are only shown if there is room for them, otherwise only the
+		// focused one is
+		let split_size = self
+			.split
+			.as_ref()
+			.map(|split| min_size.split(split.direction))
+			.filter(|size| size.fits(frame.area()));
+		let min_size = split_size.unwrap_or(min_size);
+		let layout = ScreenLayout::for_area(frame.area(), min_size);
+		let box_height =
+			|shown: bool| Constraint::Length(if shown { ScreenLayout::BOX_HEIGHT } else { 0 });
 		let [header, sheet_area, sheets_list, footer] = Layout::vertical([
-			Constraint::Length(3),
-			Constraint::Min(5),
-			Constraint::Length(3),
-			Constraint::Length(1),
+			box_height(layout.header()),
+			Constraint::Min(min_size.height),
+			box_height(layout.tabs()),
+			Constraint::Length(ScreenLayout::FOOTER_HEIGHT),
 		])
 		.areas(frame.area());
 
+		if split_size.is_some() {
+			self.render_panes(frame, model, layout, sheet_area);
+		} else {
+			self.render_focused(frame, model, layout, sheet_area, Pane::Whole);
+		}
+		if layout.header() {
+			self.render_title(frame, model, header);
+		}
+		if layout.tabs() {
+			self.render_tabs(frame, model, sheets_list);
+		}
+		// After the sheet, which keeps the selected row within the rows shown
+		let status_line = self.status_line(model, controller_state);
+		frame.render_widget(status_line, footer);
+		if !controller_state.key_hints.is_empty() {
+			let hints = KeyHints {
+				keys: controller_state.pending_keys(),
+				hints: &controller_state.key_hints,
+				theme: self.theme,
+			};
+			let area = Rect {
+				height: footer.y - frame.area().y,
+				..frame.area()
+			};
+			frame.render_widget(hints, area);
+		}
+
+		if let Some(popup) = controller_state.popup.as_ref() {
+			frame.render_widget(
+				PopupWidget {
+					popup,
+					theme: self.theme,
+				},
+				frame.area(),
+			);
+		}
+		frame.render_widget(
+			Toasts {
+				notifications: &controller_state.notifications,
+				theme: self.theme,
+			},
+			sheet_area,
+		);
+	}
+
+	/// Renders the filename and help hint at the top of the screen
+	fn render_title(&self, frame: &mut Frame, model: &Model, area: Rect) {
 		let [title_area, hint_area] =
-			Layout::horizontal([Constraint::Fill(1), Constraint::Length(10)]).areas(header);
+			Layout::horizontal([Constraint::Fill(1), Constraint::Length(10)]).areas(area);
 
-		let title_block = Block::default()
+		let mut title_block = Block::default()
 			.borders(Borders::ALL)
 			.style(Style::default());
+		if self.safe_mode {
+			title_block = title_block.title(
+				Line::from("Safe mode - default config, no rules")
+					.style(Style::default().fg(self.theme.notice))
+					.right_aligned(),
+			);
+		}
+		if model.is_read_only() {
+			title_block = title_block.title(
+				Line::from("Read-only - open in another instance")
+					.style(Style::default().fg(self.theme.notice))
+					.right_aligned(),
+			);
+		}
 		let title = Paragraph::new(Text::styled(
 			model.filename.as_deref().unwrap_or("scratch"),
-			Style::default().fg(Color::Green),
+			Style::default().fg(self.theme.accent),
 		))
 		.block(title_block);
 
 		frame.render_widget(title, title_area);
 
 		let hint_block = Block::default().borders(Borders::ALL);
-		let hint = Paragraph::new(Text::styled("<?> help", Style::default().fg(Color::Green)))
-			.block(hint_block);
+		let hint = Paragraph::new(Text::styled(
+			"<?> help",
+			Style::default().fg(self.theme.accent),
+		))
+		.block(hint_block);
 
 		frame.render_widget(hint, hint_area);
+	}
 
-		let sheet = self.get_selected_sheet(model);
+	/// Renders the selected sheet, or the dashboard if it is shown
+	fn render_focused(
+		&mut self,
+		frame: &mut Frame,
+		model: &Model,
+		layout: ScreenLayout,
+		area: Rect,
+		pane: Pane,
+	) {
+		if self.dashboard {
+			self.render_dashboard(frame, model, area);
+		} else {
+			self.render_sheet(frame, model, layout, area, pane);
+		}
+	}
 
-		let sheet_state = self.get_state_of(sheet);
+	/// Renders the selected sheet
+	fn render_sheet(
+		&mut self,
+		frame: &mut Frame,
+		model: &Model,
+		layout: ScreenLayout,
+		area: Rect,
+		pane: Pane,
+	) {
+		let sheet = self.get_selected_sheet(model);
+		let projected = self.projected;
+		let theme = self.theme;
+		let (columns, widths) = self.shown_columns(sheet);
+
+		// Only the states are borrowed, rather than using `get_state_of`, so the highlights can be
+		// borrowed alongside them
+		let sheet_state = self
+			.sheet_states
+			.entry(sheet.name.clone())
+			.or_insert_with(|| SheetState::new(sheet));
+
+		let sheet_widget = SheetWidget {
+			sheet,
+			rows: sheet.table_rows(),
+			columns,
+			widths,
+			cell_preview: layout.cell_preview(),
+			gutter: layout.gutter,
+			projected,
+			pane,
+			theme,
+			highlights: &self.highlights,
+			row_options: self.row_options,
+		};
+
+		frame.render_stateful_widget(sheet_widget, area, sheet_state);
+	}
 
-		let sheet_widget = SheetWidget { sheet };
+	/// Returns the columns of the sheet that are shown, in order, and the width set for each
+	fn shown_columns(&self, sheet: &Sheet) -> (Vec<usize>, Vec<Option<u16>>) {
+		let columns = sheet.display.visible_columns(&self.columns);
+		let widths = columns
+			.iter()
+			.map(|col| sheet.display.column_width(*col, &self.columns))
+			.collect();
+		(columns, widths)
+	}
 
-		frame.render_stateful_widget(sheet_widget, sheet_area, sheet_state);
+	/// Renders the net worth of every sheet, with the selected sheet highlighted
+	fn render_dashboard(&self, frame: &mut Frame, model: &Model, area: Rect) {
+		let net_worth = model.net_worth(clock::today(), self.projected, self.rates.as_ref());
+		frame.render_widget(
+			DashboardWidget {
+				net_worth: &net_worth,
+				selected: self.selected_sheet,
+				projected: self.projected,
+				base: &model.currencies.base,
+				theme: self.theme,
+			},
+			area,
+		);
+	}
 
-		let tabs = Tabs::new(model.sheet_titles())
-			.block(Block::bordered().title_top("Sheets"))
-			.highlight_style(Style::default().fg(Color::Yellow))
-			.select(self.selected_sheet)
+	/// Renders the tabs listing every shown sheet, with the selected one highlighted
+	fn render_tabs(&self, frame: &mut Frame, model: &Model, area: Rect) {
+		let shown: Vec<usize> = (0..model.sheet_count())
+			.filter(|index| self.is_sheet_shown(*index, model))
+			.collect();
+		let titles = shown
+			.iter()
+			.filter_map(|index| model.get_sheet(*index))
+			.map(|sheet| {
+				if sheet.archived {
+					format!("{} (archived)", sheet.name)
+				} else {
+					sheet.name.clone()
+				}
+			});
+		let title = match self.shown_sheets {
+			ShownSheets::Unarchived => "Sheets",
+			ShownSheets::All => "Sheets (with archived)",
+		};
+		let tabs = Tabs::new(titles)
+			.block(Block::bordered().title_top(title))
+			.highlight_style(Style::default().fg(self.theme.notice))
+			.select(shown.iter().position(|index| *index == self.selected_sheet))
 			.divider(symbols::DOT)
 			.padding(" | ", " | ");
 
-		frame.render_widget(tabs, sheets_list);
-
-		let controller_text = Text::from(format!("{controller_state}"));
-		frame.render_widget(controller_text, footer);
+		frame.render_widget(tabs, area);
+	}
 
-		if let Some(popup) = controller_state.popup.as_ref() {
-			frame.render_widget(popup, frame.area());
+	/// Remembers the selected row of the selected sheet as jumped away from, before a big motion.
+	/// See [`JumpList`]
+	fn remember_jump(&mut self, model: &Model) {
+		let sheet = self.get_selected_sheet(model);
+		if let Some(row) = self.get_selected_row(sheet) {
+			self.get_state_of(sheet).jumps.push(row);
 		}
 	}
 
+	/// Selects the row the jump list of the selected sheet gives, from the selected one. Returns
+	/// whether it gave one
+	fn follow_jump(
+		&mut self,
+		model: &Model,
+		follow: impl FnOnce(&mut JumpList, usize) -> Option<usize>,
+	) -> bool {
+		let sheet = self.get_selected_sheet(model);
+		let Some(from) = self.get_selected_row(sheet) else {
+			return false;
+		};
+		let Some(row) = follow(&mut self.get_state_of(sheet).jumps, from) else {
+			return false;
+		};
+		self.select_row(row, model);
+		true
+	}
+
+	/// Goes back to the row jumped away from before the selected one. Returns whether there was
+	/// one. See [`JumpList::back`]
+	pub fn jump_back(&mut self, model: &Model) -> bool {
+		self.follow_jump(model, JumpList::back)
+	}
+
+	/// Goes forward to the row gone back from. Returns whether there was one. See
+	/// [`JumpList::forward`]
+	pub fn jump_forward(&mut self, model: &Model) -> bool {
+		self.follow_jump(model, |jumps, _| jumps.forward())
+	}
+
+	/// Goes to the row last jumped away from, which is a jump itself. Returns whether there was
+	/// one. See [`JumpList::last`]
+	pub fn jump_to_last(&mut self, model: &Model) -> bool {
+		self.follow_jump(model, JumpList::last)
+	}
+
+	/// Marks the selected row of the selected sheet with the letter, replacing any row marked with
+	/// it before. Returns whether there was a row to mark
+	pub fn set_mark(&mut self, letter: char, model: &Model) -> bool {
+		let sheet = self.get_selected_sheet(model);
+		let Some(row) = self.get_selected_row(sheet) else {
+			return false;
+		};
+		let id = sheet.transactions()[row].id;
+		self.get_state_of(sheet).marks.insert(letter, id);
+		true
+	}
+
+	/// Goes to the row marked with the letter in the selected sheet, which is a jump. Returns
+	/// whether the mark is set and its row is still in the sheet
+	pub fn jump_to_mark(&mut self, letter: char, model: &Model) -> bool {
+		let sheet = self.get_selected_sheet(model);
+		let Some(row) = self
+			.get_state_of(sheet)
+			.marks
+			.get(&letter)
+			.and_then(|id| sheet.row_of(*id))
+		else {
+			return false;
+		};
+		self.remember_jump(model);
+		self.select_row(row, model);
+		true
+	}
+
+	/// Moves to the first row of the next period, or going back, to the first row of the period
+	/// of the selected row, or of the period before it if already there. Rows are moved through in
+	/// the order they are displayed, by the period of their dates (a group heading is in the
+	/// period of its first row), so this is the start of each period when sorted by date. Returns
+	/// whether there was such a row
+	pub fn go_to_period_start(&mut self, period: DatePeriod, forward: bool, model: &Model) -> bool {
+		let sheet = self.get_selected_sheet(model);
+		let Some(selected) = self.get_selected_display_row(model) else {
+			return false;
+		};
+		let starts: Vec<NaiveDate> = sheet
+			.table_rows()
+			.iter()
+			.map(|row| {
+				let index = match row {
+					TableRow::Transaction(index) => *index,
+					TableRow::Heading(heading) => heading.rows[0],
+				};
+				period.start(sheet.transactions()[index].date)
+			})
+			.collect();
+		let Some(current) = starts.get(selected).copied() else {
+			return false;
+		};
+		// The first row of the run of rows in the same period as the given one
+		let run_start = |row: usize| {
+			starts[..row]
+				.iter()
+				.rposition(|start| *start != starts[row])
+				.map_or(0, |i| i + 1)
+		};
+		let target = if forward {
+			starts[selected..]
+				.iter()
+				.position(|start| *start != current)
+				.map(|i| selected + i)
+		} else {
+			match run_start(selected) {
+				first if first < selected => Some(first),
+				0 => None,
+				first => Some(run_start(first - 1)),
+			}
+		};
+		let Some(target) = target else {
+			return false;
+		};
+		self.remember_jump(model);
+		self.select_display_row(target, model);
+		true
+	}
+
 	/// Scroll to the given row
 	pub fn jump_to_row(&mut self, row: usize, model: &Model) {
+		self.remember_jump(model);
 		self.get_state_of(self.get_selected_sheet(model))
 			.scroll_to_row(row.saturating_sub(1));
 	}
 
+	/// Goes to the first transaction dated on or after the date, or the last one dated before it
+	/// if there is none, which is a jump. Returns whether the sheet has any transactions
+	pub fn go_to_date(&mut self, date: NaiveDate, model: &Model) -> bool {
+		let transactions = self.get_selected_sheet(model).transactions();
+		let after = (0..transactions.len())
+			.filter(|row| transactions[*row].date >= date)
+			.min_by_key(|row| transactions[*row].date);
+		let before = || (0..transactions.len()).max_by_key(|row| transactions[*row].date);
+		let Some(row) = after.or_else(before) else {
+			return false;
+		};
+		self.remember_jump(model);
+		self.select_row(row, model);
+		true
+	}
+
 	/// Scroll to the next row
 	pub fn next_row(&mut self, model: &Model) {
 		self.down_by(1, model);
@@ -169,15 +741,18 @@ impl View {
 
 	/// Scroll to the first row
 	pub fn first_row(&mut self, model: &Model) {
+		self.remember_jump(model);
 		self.get_state_of(self.get_selected_sheet(model))
 			.scroll_to_row(0);
 	}
 
 	/// Scroll to the last row
 	pub fn last_row(&mut self, model: &Model) {
+		self.remember_jump(model);
 		let sheet = self.get_selected_sheet(model);
-		self.get_state_of(sheet)
-			.scroll_to_row(sheet.transactions.len().saturating_sub(1));
+		let state = self.get_state_of(sheet);
+		let last = sheet.displayed_row_count().saturating_sub(1);
+		state.scroll_to_row(last);
 	}
 
 	/// Move the cursor to the next column
@@ -201,12 +776,23 @@ impl View {
 			.table_state
 			.selected()
 			.unwrap_or(0)
-			.saturating_sub(count)
-			.max(0);
+			.saturating_sub(count);
 
 		state.scroll_to_row(new);
 	}
 
+	/// Selects the row at the given position on the screen, if there is one there
+	pub fn click(&mut self, column: u16, row: u16, model: &Model) {
+		self.focus_pane_at(column, row, model);
+		let sheet = self.get_selected_sheet(model);
+		let state = self.get_state_of(sheet);
+		if let Some(display_row) = state.row_at(column, row)
+			&& display_row < sheet.displayed_row_count()
+		{
+			state.scroll_to_row(display_row);
+		}
+	}
+
 	/// Scroll down by a count
 	pub fn down_by(&mut self, count: usize, model: &Model) {
 		let sheet = self.get_selected_sheet(model);
@@ -216,7 +802,7 @@ impl View {
 			.selected()
 			.unwrap_or(0)
 			.saturating_add(count)
-			.min(sheet.transactions.len() - 1);
+			.min(sheet.displayed_row_count().saturating_sub(1));
 
 		state.scroll_to_row(new);
 	}
@@ -239,22 +825,56 @@ impl View {
 		self.down_by(count.max(1) as usize, model);
 	}
 
-	/// Switch to the next sheet
-	pub fn next_sheet(&mut self, model: &Model) {
-		let count = model.sheet_count();
-		if count > 0 {
-			self.selected_sheet = (self.selected_sheet + 1) % count;
+	/// Switch to the sheet with the given index
+	pub fn go_to_sheet(&mut self, index: usize, model: &Model) {
+		self.remember_jump(model);
+		if index < model.sheet_count() {
+			self.selected_sheet = index;
 		}
 	}
 
-	/// Switch to the previous sheet
+	/// Switch to the next shown sheet
+	pub fn next_sheet(&mut self, model: &Model) {
+		self.step_sheet(true, model);
+	}
+
+	/// Switch to the previous shown sheet
 	pub fn previous_sheet(&mut self, model: &Model) {
+		self.step_sheet(false, model);
+	}
+
+	/// Switch to the next or previous sheet that is shown, skipping archived sheets unless they
+	/// are shown too
+	fn step_sheet(&mut self, forward: bool, model: &Model) {
+		self.remember_jump(model);
 		let count = model.sheet_count();
-		if count > 0 {
-			self.selected_sheet = (self.selected_sheet + count - 1) % count;
+		let mut index = self.selected_sheet;
+		for _ in 0..count {
+			index = if forward {
+				(index + 1) % count
+			} else {
+				(index + count - 1) % count
+			};
+			if self.is_sheet_shown(index, model) {
+				self.selected_sheet = index;
+				return;
+			}
 		}
 	}
 
+	/// Returns whether the sheet with the given index is shown in the tabs, which archived sheets
+	/// only are when [`View::shown_sheets`] is [`ShownSheets::All`]
+	pub fn is_sheet_shown(&self, index: usize, model: &Model) -> bool {
+		self.shown_sheets == ShownSheets::All
+			|| model.get_sheet(index).is_some_and(|sheet| !sheet.archived)
+	}
+
+	/// Scrolls the columns right or left by one, when they don't all fit on the screen
+	pub fn scroll_columns(&mut self, right: bool, model: &Model) {
+		self.get_state_of(self.get_selected_sheet(model))
+			.scroll_columns(right);
+	}
+
 	pub fn deselect_cell(&mut self, model: &Model) {
 		self.get_state_of(self.get_selected_sheet(model))
 			.deselect_cell();
@@ -1,18 +1,32 @@
+use std::ops::Range;
+
+use budgeting_core::clock;
+use chrono::{Datelike, Months, NaiveDate};
 use ratatui::{
 	buffer::Buffer,
 	layout::{Alignment, Constraint, Flex, Layout, Rect},
-	style::{Color, Modifier, Style},
-	text::{Line, Text},
+	style::{Modifier, Style},
+	symbols,
+	text::{Line, Span, Text},
 	widgets::{
-		Block, BorderType, Borders, Cell, Clear, Padding, Paragraph, Row, Scrollbar,
-		ScrollbarOrientation, ScrollbarState, StatefulWidget, Table, TableState, Widget, Wrap,
+		Axis, Block, BorderType, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, List,
+		ListState, Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState,
+		StatefulWidget, Table, TableState, Widget, Wrap,
 	},
 };
 
 use crate::{
 	controller::popup::{self, Popup},
-	model::Sheet,
-	view::{ITEM_HEIGHT, SheetState},
+	model::{
+		AccountType, COLUMN_NAMES, GroupHeading, HISTORY_MONTHS, NetWorth, Pick, ReportKind,
+		Resolution, ReviewReason, RowDiff, RowOrder, Sheet, TableRow, Transaction, Worth,
+	},
+	view::{
+		RowOptions, SheetState,
+		highlight::Highlight,
+		layout::{MinSize, MinimumSize},
+		theme::Theme,
+	},
 };
 
 const NUMBER_PADDING_RIGHT: u16 = 2;
@@ -26,28 +40,1194 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
 	area
 }
 
-impl Widget for &Popup {
+/// A temporary wrapper around a [Popup], for the purpose of rendering it in the colours of the
+/// theme
+pub(super) struct PopupWidget<'a> {
+	pub popup: &'a Popup,
+	pub theme: Theme,
+}
+
+impl Widget for PopupWidget<'_> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
-		match self {
-			Popup::Input(p) => InputWidget { popup: p }.render(area, buf),
-			Popup::Info(p) => InfoWidget { popup: p }.render(area, buf),
-			Popup::Confirm(p) => ConfirmWidget { popup: p }.render(area, buf),
+		let theme = self.theme;
+		let min_size = match self.popup {
+			Popup::List(_) => ListWidget::MIN_SIZE,
+			Popup::Forecast(_) => ForecastWidget::MIN_SIZE,
+			Popup::Diff(_) => DiffWidget::MIN_SIZE,
+			Popup::Compare(_) => CompareWidget::MIN_SIZE,
+			Popup::Merge(_) => MergeWidget::MIN_SIZE,
+			Popup::Duplicates(_) => DuplicatesWidget::MIN_SIZE,
+			Popup::Review(_) => ReviewWidget::MIN_SIZE,
+			Popup::Report(_) => ReportWidget::MIN_SIZE,
+			Popup::Start(_) => StartWidget::MIN_SIZE,
+			Popup::Switcher(_) => SwitcherWidget::MIN_SIZE,
+			Popup::DatePicker(_) => DatePickerWidget::MIN_SIZE,
+			Popup::Input(_)
+			| Popup::Form(_)
+			| Popup::Info(_)
+			| Popup::Confirm(_)
+			| Popup::Progress(_) => MinSize {
+				width: 0,
+				height: 0,
+			},
+		};
+		if !min_size.fits(area) {
+			min_size.render_too_small(area, buf, &theme);
+			return;
+		}
+		match self.popup {
+			Popup::Input(p) => InputWidget { popup: p, theme }.render(area, buf),
+			Popup::Form(p) => FormWidget { popup: p, theme }.render(area, buf),
+			Popup::DatePicker(p) => DatePickerWidget { popup: p, theme }.render(area, buf),
+			Popup::Info(p) => InfoWidget { popup: p, theme }.render(area, buf),
+			Popup::Confirm(p) => ConfirmWidget { popup: p, theme }.render(area, buf),
+			Popup::List(p) => ListWidget { popup: p, theme }.render(area, buf),
+			Popup::Forecast(p) => ForecastWidget { popup: p, theme }.render(area, buf),
+			Popup::Diff(p) => DiffWidget { popup: p, theme }.render(area, buf),
+			Popup::Compare(p) => CompareWidget { popup: p, theme }.render(area, buf),
+			Popup::Merge(p) => MergeWidget { popup: p, theme }.render(area, buf),
+			Popup::Duplicates(p) => DuplicatesWidget { popup: p, theme }.render(area, buf),
+			Popup::Review(p) => ReviewWidget { popup: p, theme }.render(area, buf),
+			Popup::Report(p) => ReportWidget { popup: p, theme }.render(area, buf),
+			Popup::Start(p) => StartWidget { popup: p, theme }.render(area, buf),
+			Popup::Switcher(p) => SwitcherWidget { popup: p, theme }.render(area, buf),
+			Popup::Progress(p) => ProgressWidget { popup: p, theme }.render(area, buf),
 		}
 	}
 }
 
 pub(super) struct ConfirmWidget<'a> {
 	pub popup: &'a popup::Confirm,
+	pub theme: Theme,
+}
+
+impl Widget for ConfirmWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		const BOX_HEIGHT: u16 = 7;
+		let center = center(
+			area,
+			Constraint::Percentage(50),
+			Constraint::Length(BOX_HEIGHT),
+		);
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone());
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+
+		block.render(center, buf);
+
+		let rows: [Rect; 5] = Layout::vertical([Constraint::Length(1); 5]).areas(inner);
+		Paragraph::new(self.popup.prompt().clone())
+			.alignment(Alignment::Center)
+			.render(rows[1], buf);
+		Paragraph::new("[y]    [n]")
+			.alignment(Alignment::Center)
+			.render(rows[3], buf);
+	}
+}
+
+pub(super) struct ProgressWidget<'a> {
+	pub popup: &'a popup::Progress,
+	pub theme: Theme,
+}
+
+impl Widget for ProgressWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		const BOX_HEIGHT: u16 = 6;
+		/// The frames of the spinner shown when it isn't known how much work there is
+		const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+		let center = center(
+			area,
+			Constraint::Percentage(50),
+			Constraint::Length(BOX_HEIGHT),
+		);
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.padding(Padding::horizontal(1));
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let rows: [Rect; 4] = Layout::vertical([Constraint::Length(1); 4]).areas(inner);
+		Paragraph::new(self.popup.text().clone()).render(rows[1], buf);
+		let elapsed = self.popup.elapsed();
+		if let Some(ratio) = self.popup.ratio() {
+			Gauge::default()
+				.ratio(ratio)
+				.gauge_style(Style::default().fg(self.theme.accent))
+				.render(rows[2], buf);
+		} else {
+			let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+			Line::from(vec![
+				Span::styled(frame, Style::default().fg(self.theme.accent)),
+				Span::styled(
+					format!(" {}s", elapsed.as_secs()),
+					Style::default().fg(self.theme.dim),
+				),
+			])
+			.render(rows[2], buf);
+		}
+	}
+}
+
+pub(super) struct InfoWidget<'a> {
+	pub popup: &'a popup::Info,
+	pub theme: Theme,
+}
+
+impl Widget for InfoWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(70), Constraint::Percentage(70));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone());
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		// The search being typed, or the last one, is shown at the bottom
+		if let Some(search) = self.popup.search() {
+			block = block.title_bottom(
+				Line::from(format!("/{search}█")).style(Style::default().fg(self.theme.notice)),
+			);
+		} else if let Some(query) = self.popup.query() {
+			block = block.title_bottom(
+				Line::from(format!("/{query} <n N>")).style(Style::default().fg(self.theme.notice)),
+			);
+		}
+
+		// Only long text needs the keys that scroll it
+		let line_count = self.popup.text().lines().count();
+		if line_count > usize::from(block.inner(center).height) || self.popup.scroll() > 0 {
+			block = block.title_bottom(
+				Line::from(format!(
+					"{}/{line_count} <j k> scroll </> search",
+					self.popup.scroll() + 1
+				))
+				.right_aligned(),
+			);
+		}
+
+		let match_style = Style::default()
+			.fg(self.theme.notice)
+			.add_modifier(Modifier::BOLD);
+		let lines: Vec<Line> = self
+			.popup
+			.text()
+			.lines()
+			.skip(self.popup.scroll())
+			.map(|line| {
+				if self.popup.matches(line) {
+					Line::styled(line.to_string(), match_style)
+				} else {
+					Line::from(line.to_string())
+				}
+			})
+			.collect();
+		Paragraph::new(lines)
+			.wrap(Wrap { trim: false })
+			.block(block)
+			.render(center, buf);
+	}
+}
+
+pub(super) struct ListWidget<'a> {
+	pub popup: &'a popup::List,
+	pub theme: Theme,
+}
+
+impl MinimumSize for ListWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 30,
+		height: 8,
+	};
+}
+
+impl Widget for ListWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(70), Constraint::Percentage(70));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone());
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let [header, items] = Layout::vertical([
+			Constraint::Length(u16::from(self.popup.header().is_some())),
+			Constraint::Fill(1),
+		])
+		.areas(inner);
+
+		if let Some(text) = self.popup.header() {
+			Paragraph::new(text.clone())
+				.style(Style::default().fg(self.theme.accent))
+				.render(header, buf);
+		}
+
+		// The state is cloned as rendering only needs to adjust the offset, which is recalculated
+		// from the selection every frame anyway
+		let mut state = self.popup.list_state.clone();
+		StatefulWidget::render(
+			List::new(self.popup.items().clone()).highlight_style(
+				Style::default()
+					.bg(self.theme.selection_bg)
+					.fg(self.theme.selection_fg),
+			),
+			items,
+			buf,
+			&mut state,
+		);
+	}
+}
+
+pub(super) struct StartWidget<'a> {
+	pub popup: &'a popup::Start,
+	pub theme: Theme,
+}
+
+impl MinimumSize for StartWidget<'_> {
+	/// Room for the keys at the bottom, and a few files
+	const MIN_SIZE: MinSize = MinSize {
+		width: 60,
+		height: 8,
+	};
+}
+
+impl Widget for StartWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(70), Constraint::Percentage(70));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from("<enter> open, <n> new file, <s> scratch session, <q> quit")
+					.right_aligned(),
+			)
+			.padding(Padding::horizontal(1));
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let [header, items] =
+			Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner);
+		Paragraph::new("Recent files")
+			.style(Style::default().fg(self.theme.accent))
+			.render(header, buf);
+
+		if self.popup.files().is_empty() {
+			Paragraph::new("No files opened yet - press n to start a new one")
+				.style(Style::default().fg(self.theme.dim))
+				.render(items, buf);
+			return;
+		}
+
+		// The state is cloned as rendering only needs to adjust the offset, which is recalculated
+		// from the selection every frame anyway
+		let mut state = self.popup.list_state.clone();
+		let files: Vec<String> = self
+			.popup
+			.files()
+			.iter()
+			.map(|file| file.display().to_string())
+			.collect();
+		StatefulWidget::render(
+			List::new(files).highlight_style(
+				Style::default()
+					.bg(self.theme.selection_bg)
+					.fg(self.theme.selection_fg),
+			),
+			items,
+			buf,
+			&mut state,
+		);
+	}
+}
+
+pub(super) struct SwitcherWidget<'a> {
+	pub popup: &'a popup::Switcher,
+	pub theme: Theme,
+}
+
+impl MinimumSize for SwitcherWidget<'_> {
+	/// Room for the filter and a few files
+	const MIN_SIZE: MinSize = MinSize {
+		width: 40,
+		height: 8,
+	};
+}
+
+impl Widget for SwitcherWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(70), Constraint::Percentage(60));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from("<up down> select, <enter> open, <esc> close").right_aligned(),
+			);
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let [query, separator, items] = Layout::vertical([
+			Constraint::Length(1),
+			Constraint::Length(1),
+			Constraint::Fill(1),
+		])
+		.areas(inner);
+		self.popup.text_area.render(query, buf);
+		Block::default()
+			.borders(Borders::TOP)
+			.border_style(Style::default().fg(self.theme.dim))
+			.render(separator, buf);
+
+		let matches = self.popup.matches();
+		if matches.is_empty() {
+			let query = self.popup.query();
+			let text = if query.trim().is_empty() {
+				"No other files opened recently - type a path to open".to_string()
+			} else {
+				format!("No matching files - <enter> opens {}", query.trim())
+			};
+			Paragraph::new(text)
+				.style(Style::default().fg(self.theme.dim))
+				.render(items, buf);
+			return;
+		}
+
+		// The state is cloned as rendering only needs to adjust the offset, which is recalculated
+		// from the selection every frame anyway
+		let mut state = self.popup.list_state.clone();
+		let files: Vec<String> = matches
+			.iter()
+			.map(|file| file.display().to_string())
+			.collect();
+		StatefulWidget::render(
+			List::new(files).highlight_style(
+				Style::default()
+					.bg(self.theme.selection_bg)
+					.fg(self.theme.selection_fg),
+			),
+			items,
+			buf,
+			&mut state,
+		);
+	}
+}
+
+pub(super) struct ForecastWidget<'a> {
+	pub popup: &'a popup::Forecast,
+	pub theme: Theme,
+}
+
+impl MinimumSize for ForecastWidget<'_> {
+	/// The chart is unreadable any smaller than this, as the popup takes up 80% of the screen
+	const MIN_SIZE: MinSize = MinSize {
+		width: 50,
+		height: 15,
+	};
+}
+
+impl Widget for ForecastWidget<'_> {
+	#[allow(clippy::cast_precision_loss)]
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		/// The maximum amount of upcoming transactions listed under the chart
+		const MAX_UPCOMING: usize = 8;
+
+		let center = center(area, Constraint::Percentage(80), Constraint::Percentage(80));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone());
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let forecast = self.popup.forecast();
+		let (Some((first, _)), Some((last, _))) = (forecast.points.first(), forecast.points.last())
+		else {
+			Paragraph::new("No transactions to forecast").render(inner, buf);
+			return;
+		};
+
+		let upcoming_height = forecast.upcoming.len().min(MAX_UPCOMING);
+		let [chart_area, upcoming_area] = Layout::vertical([
+			Constraint::Fill(1),
+			Constraint::Length(u16::try_from(upcoming_height).unwrap_or(0) + 1),
+		])
+		.areas(inner);
+
+		let x = |date: &NaiveDate| (*date - *first).num_days() as f64;
+		let balance: Vec<(f64, f64)> = forecast.points.iter().map(|(d, b)| (x(d), *b)).collect();
+		let upcoming: Vec<(f64, f64)> = forecast
+			.upcoming
+			.iter()
+			.map(|u| (x(&u.date), u.balance))
+			.collect();
+		let max_x = x(last).max(1.0);
+		let min_line: Vec<(f64, f64)> = self
+			.popup
+			.min_balance()
+			.map(|min| vec![(0.0, min), (max_x, min)])
+			.unwrap_or_default();
+
+		let (mut min_y, mut max_y) = balance
+			.iter()
+			.chain(min_line.iter())
+			.fold((f64::MAX, f64::MIN), |(lo, hi), (_, y)| {
+				(lo.min(*y), hi.max(*y))
+			});
+		if (max_y - min_y).abs() < f64::EPSILON {
+			min_y -= 1.0;
+			max_y += 1.0;
+		}
+
+		let mut datasets = vec![
+			Dataset::default()
+				.name("Balance")
+				.marker(symbols::Marker::Braille)
+				.graph_type(GraphType::Line)
+				.style(Style::default().fg(self.theme.accent))
+				.data(&balance),
+			Dataset::default()
+				.name("Upcoming")
+				.marker(symbols::Marker::Dot)
+				.graph_type(GraphType::Scatter)
+				.style(Style::default().fg(self.theme.notice))
+				.data(&upcoming),
+		];
+		if !min_line.is_empty() {
+			datasets.push(
+				Dataset::default()
+					.name("Minimum")
+					.marker(symbols::Marker::Braille)
+					.graph_type(GraphType::Line)
+					.style(Style::default().fg(self.theme.error))
+					.data(&min_line),
+			);
+		}
+
+		Chart::new(datasets)
+			.x_axis(
+				Axis::default()
+					.bounds([0.0, max_x])
+					.labels([first.to_string(), last.to_string()]),
+			)
+			.y_axis(Axis::default().bounds([min_y, max_y]).labels([
+				crate::view::format_currency(min_y),
+				crate::view::format_currency(max_y),
+			]))
+			.render(chart_area, buf);
+
+		self.render_upcoming(upcoming_area, buf, MAX_UPCOMING);
+	}
+}
+
+impl ForecastWidget<'_> {
+	/// Renders the list of upcoming transactions, with the ones that would take the balance below
+	/// the minimum highlighted
+	fn render_upcoming(&self, area: Rect, buf: &mut Buffer, max: usize) {
+		let forecast = self.popup.forecast();
+		let mut lines = vec![Line::from("Upcoming").style(Style::default().fg(self.theme.accent))];
+		lines.extend(forecast.upcoming.iter().take(max).map(|u| {
+			let below = self.popup.min_balance().is_some_and(|min| u.balance < min);
+			Line::from(format!(
+				"{}  {}  {} -> {}",
+				u.date,
+				u.label,
+				crate::view::format_currency(u.amount),
+				crate::view::format_currency(u.balance)
+			))
+			.style(if below {
+				Style::default().fg(self.theme.error)
+			} else {
+				Style::default()
+			})
+		}));
+		Paragraph::new(lines).render(area, buf);
+	}
+}
+
+pub(super) struct DiffWidget<'a> {
+	pub popup: &'a popup::Diff,
+	pub theme: Theme,
+}
+
+impl MinimumSize for DiffWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 40,
+		height: 8,
+	};
+}
+
+impl Widget for DiffWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		const COLUMNS: [&str; 5] = ["Date", "Label", "Amount", "Category", "Tags"];
+
+		let center = center(area, Constraint::Percentage(80), Constraint::Percentage(60));
+		Clear.render(center, buf);
+
+		let edits = self.popup.edits();
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from(format!(
+					"{} cells will change - <y> apply, <n> cancel",
+					edits.len()
+				))
+				.right_aligned(),
+			);
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let lines: Vec<Line> = edits
+			.iter()
+			.skip(self.popup.offset())
+			.map(|edit| {
+				Line::from(vec![
+					Span::raw(format!(
+						"{:>5}  {:<6}  ",
+						edit.row + 1,
+						COLUMNS.get(edit.col).unwrap_or(&"")
+					)),
+					Span::styled(
+						edit.before.clone(),
+						Style::default()
+							.fg(self.theme.negative)
+							.add_modifier(Modifier::CROSSED_OUT),
+					),
+					Span::raw(" → "),
+					Span::styled(edit.after.clone(), Style::default().fg(self.theme.positive)),
+				])
+			})
+			.collect();
+		Paragraph::new(lines).render(inner, buf);
+	}
+}
+
+pub(super) struct CompareWidget<'a> {
+	pub popup: &'a popup::Compare,
+	pub theme: Theme,
+}
+
+impl MinimumSize for CompareWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 50,
+		height: 8,
+	};
+}
+
+/// Returns the line for one transaction, marked by how it differs between two versions of a sheet.
+/// Changed cells show what they were before what they are now
+fn row_diff_line(row: &RowDiff, theme: Theme) -> Line<'static> {
+	/// How wide each column is padded to, so the rows line up unless a cell changed
+	const WIDTHS: [usize; 5] = [10, 20, 10, 12, 0];
+
+	let removed = Style::default()
+		.fg(theme.negative)
+		.add_modifier(Modifier::CROSSED_OUT);
+	let (marker, transaction, style) = match row {
+		RowDiff::Same(t) => ("  ", t, Style::default().fg(theme.dim)),
+		RowDiff::Added(t) => ("+ ", t, Style::default().fg(theme.positive)),
+		RowDiff::Removed(t) => ("- ", t, removed),
+		RowDiff::Changed { after, .. } => ("~ ", after, Style::default()),
+	};
+	let mut spans = vec![Span::styled(
+		marker,
+		if matches!(row, RowDiff::Changed { .. }) {
+			Style::default().fg(theme.notice)
+		} else {
+			style
+		},
+	)];
+	let changed = row.changed_columns();
+	for (col, width) in WIDTHS.into_iter().enumerate() {
+		let after = transaction.member_string(col);
+		if col > 0 {
+			spans.push(Span::raw("  "));
+		}
+		if let RowDiff::Changed { before, .. } = row
+			&& changed.contains(&col)
+		{
+			spans.push(Span::styled(before.member_string(col), removed));
+			spans.push(Span::raw(" → "));
+			spans.push(Span::styled(after, Style::default().fg(theme.positive)));
+		} else {
+			let cell = if col == 2 {
+				format!("{after:>width$}")
+			} else {
+				format!("{after:<width$}")
+			};
+			spans.push(Span::styled(cell, style));
+		}
+	}
+	Line::from(spans)
+}
+
+impl Widget for CompareWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(90), Constraint::Percentage(70));
+		Clear.render(center, buf);
+
+		let (added, removed, changed) = self.popup.counts();
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from(format!(
+					"{added} added, {removed} removed, {changed} changed - <j k> scroll, <a> {} unchanged, <q> close",
+					if self.popup.show_same() { "hide" } else { "show" }
+				))
+				.right_aligned(),
+			);
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let mut lines: Vec<Line> = self
+			.popup
+			.shown_rows()
+			.skip(self.popup.offset())
+			.map(|row| row_diff_line(row, self.theme))
+			.collect();
+		if lines.is_empty() && self.popup.offset() == 0 {
+			lines.push(Line::from("No differences").style(Style::default().fg(self.theme.dim)));
+		}
+		Paragraph::new(lines).render(inner, buf);
+	}
+}
+
+pub(super) struct MergeWidget<'a> {
+	pub popup: &'a popup::Merge,
+	pub theme: Theme,
+}
+
+impl MinimumSize for MergeWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 60,
+		height: 8,
+	};
+}
+
+impl Widget for MergeWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(90), Constraint::Percentage(70));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from(
+					"<m> mine, <t> theirs, <b> both (capital for all) - <y> merge, <q> cancel",
+				)
+				.right_aligned(),
+			);
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		// Each sheet's rows are under a heading, which can't be selected
+		let mut lines = vec![];
+		let mut selected = None;
+		let mut row_index = 0;
+		for merge in self.popup.merges() {
+			lines.push(
+				Line::from(format!("Sheet {}", merge.name))
+					.style(Style::default().fg(self.theme.accent)),
+			);
+			for row in &merge.rows {
+				if row_index == self.popup.selected() {
+					selected = Some(lines.len());
+				}
+				row_index += 1;
+				let (pick, style) = match row.pick {
+					Pick::Mine => ("[mine]   ", Style::default()),
+					Pick::Theirs => ("[theirs] ", Style::default().fg(self.theme.notice)),
+					Pick::Both => ("[both]   ", Style::default().fg(self.theme.positive)),
+				};
+				let mut line = row_diff_line(&row.diff, self.theme);
+				line.spans.insert(0, Span::styled(pick, style));
+				lines.push(line);
+			}
+		}
+		let mut state = ListState::default().with_selected(selected);
+		StatefulWidget::render(
+			List::new(lines).highlight_style(
+				Style::default()
+					.bg(self.theme.selection_bg)
+					.fg(self.theme.selection_fg),
+			),
+			inner,
+			buf,
+			&mut state,
+		);
+	}
+}
+
+pub(super) struct DuplicatesWidget<'a> {
+	pub popup: &'a popup::Duplicates,
+	pub theme: Theme,
+}
+
+impl MinimumSize for DuplicatesWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 60,
+		height: 8,
+	};
+}
+
+impl Widget for DuplicatesWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(90), Constraint::Percentage(60));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from(
+					"<s> skip, <a> keep both, <m> merge (capital for all) - <y> import, <q> cancel",
+				)
+				.right_aligned(),
+			);
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let describe = |t: &Transaction| {
+			format!(
+				"{} {:>12} {}",
+				t.date.format(DATE_FORMAT_STRING),
+				crate::view::format_currency(t.amount),
+				t.label
+			)
+		};
+		let items = self.popup.pending().conflicts.iter().map(|conflict| {
+			let (resolution, style) = match conflict.resolution {
+				Resolution::Skip => ("skip ", Style::default().fg(self.theme.negative)),
+				Resolution::Keep => ("keep ", Style::default().fg(self.theme.positive)),
+				Resolution::Merge => ("merge", Style::default().fg(self.theme.notice)),
+			};
+			Line::from(vec![
+				Span::styled(format!("[{resolution}] "), style),
+				Span::raw(describe(&conflict.imported)),
+				Span::styled(
+					format!("  ≈ {}", conflict.existing.label),
+					Style::default().fg(self.theme.dim),
+				),
+			])
+		});
+
+		let mut state = self.popup.list_state().clone();
+		StatefulWidget::render(
+			List::new(items)
+				.block(block)
+				.highlight_style(Style::default().bg(self.theme.selection_bg)),
+			center,
+			buf,
+			&mut state,
+		);
+	}
+}
+
+pub(super) struct ReportWidget<'a> {
+	pub popup: &'a popup::Report,
+	pub theme: Theme,
+}
+
+impl MinimumSize for ReportWidget<'_> {
+	/// Room for the columns of the breakdowns
+	const MIN_SIZE: MinSize = MinSize {
+		width: 90,
+		height: 12,
+	};
+}
+
+impl Widget for ReportWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Length(90), Constraint::Percentage(80));
+		Clear.render(center, buf);
+
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title_bottom(
+				Line::from("<j k> scroll, <r> another range, <e> export to CSV, <q> close")
+					.right_aligned(),
+			)
+			.padding(Padding::horizontal(1));
+
+		if let Some(subtitle) = self.popup.subtitle() {
+			block = block.title(Line::from(subtitle.clone()).right_aligned());
+		}
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let lines = match self.popup.kind() {
+			ReportKind::Summary => self.summary_lines(),
+			ReportKind::Payees => self.payee_lines(),
+		};
+		Paragraph::new(lines)
+			.scroll((self.popup.scroll(), 0))
+			.block(block)
+			.render(center, buf);
+	}
+}
+
+impl ReportWidget<'_> {
+	/// The width of the name column of the breakdowns
+	const NAME_WIDTH: usize = 24;
+	/// The width of each amount column of the breakdowns
+	const AMOUNT_WIDTH: usize = 14;
+
+	/// Returns a row of a breakdown - the name, cut to fit its column, then the other columns
+	fn row(name: &str, columns: &[String]) -> Line<'static> {
+		let name: String = name.chars().take(Self::NAME_WIDTH).collect();
+		Line::raw(format!(
+			"{name:<w$}{}",
+			columns.join(""),
+			w = Self::NAME_WIDTH
+		))
+	}
+
+	/// Returns the heading row of a breakdown
+	fn header(name: &str, columns: &[&str]) -> Line<'static> {
+		let columns: Vec<String> = columns
+			.iter()
+			.map(|c| format!("{c:>w$}", w = Self::AMOUNT_WIDTH))
+			.collect();
+		Self::row(name, &columns).style(Style::default().add_modifier(Modifier::BOLD))
+	}
+
+	/// Returns the amount right-aligned in a column
+	fn amount(amount: f64) -> String {
+		format!(
+			"{:>w$}",
+			crate::view::format_currency(amount),
+			w = Self::AMOUNT_WIDTH
+		)
+	}
+
+	/// Returns the title of a section of the report
+	fn heading(&self, text: &str) -> Line<'static> {
+		Line::styled(text.to_string(), Style::default().fg(self.theme.accent))
+	}
+
+	/// Returns the lines of the payees report - how much was spent, then how much went to each
+	/// payee and what share of the spending that was
+	fn payee_lines(&self) -> Vec<Line<'static>> {
+		let report = self.popup.report();
+		let spent = -report.total.expenses;
+		let mut lines = vec![
+			self.heading(&format!(
+				"Spent {} with {} payees",
+				crate::view::format_currency(spent),
+				report.payees.len()
+			)),
+			Line::default(),
+			Self::header("Payee", &["Count", "Spent", "Share"]),
+		];
+		lines.extend(report.payees.iter().map(|(name, totals)| {
+			let share = if spent > 0.0 {
+				-totals.expenses / spent * 100.0
+			} else {
+				0.0
+			};
+			Self::row(
+				if name.is_empty() { "(no label)" } else { name },
+				&[
+					format!("{:>w$}", totals.count, w = Self::AMOUNT_WIDTH),
+					Self::amount(-totals.expenses),
+					format!("{:>w$}", format!("{share:.1}%"), w = Self::AMOUNT_WIDTH),
+				],
+			)
+		}));
+		lines
+	}
+
+	/// Returns the lines of the report - the totals, then the breakdowns by category and by month
+	fn summary_lines(&self) -> Vec<Line<'static>> {
+		let report = self.popup.report();
+		let total = &report.total;
+		let mut lines = vec![
+			self.heading(&format!("Totals ({} transactions)", total.count)),
+			Self::row("Income", &[Self::amount(total.income)]),
+			Self::row("Expenses", &[Self::amount(total.expenses)]),
+			Self::row("Net", &[Self::amount(total.net())]),
+			Line::default(),
+			self.heading("By category"),
+			Self::header("Category", &["Income", "Expenses", "Net", "Count"]),
+		];
+		lines.extend(report.categories.iter().map(|(name, totals)| {
+			Self::row(
+				if name.is_empty() {
+					"(uncategorised)"
+				} else {
+					name
+				},
+				&[
+					Self::amount(totals.income),
+					Self::amount(totals.expenses),
+					Self::amount(totals.net()),
+					format!("{:>w$}", totals.count, w = Self::AMOUNT_WIDTH),
+				],
+			)
+		}));
+		lines.extend([
+			Line::default(),
+			self.heading("By month"),
+			Self::header("Month", &["Income", "Expenses", "Net", "Change"]),
+		]);
+		lines.extend(
+			report
+				.months
+				.iter()
+				.enumerate()
+				.map(|(i, (month, totals))| {
+					Self::row(
+						&month.format("%Y-%m").to_string(),
+						&[
+							Self::amount(totals.income),
+							Self::amount(totals.expenses),
+							Self::amount(totals.net()),
+							report.month_change(i).map_or_else(
+								|| format!("{:>w$}", "-", w = Self::AMOUNT_WIDTH),
+								Self::amount,
+							),
+						],
+					)
+				}),
+		);
+		lines
+	}
+}
+
+pub(super) struct ReviewWidget<'a> {
+	pub popup: &'a popup::Review,
+	pub theme: Theme,
+}
+
+impl MinimumSize for ReviewWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 50,
+		height: 14,
+	};
+}
+
+impl Widget for ReviewWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let center = center(area, Constraint::Percentage(70), Constraint::Length(14));
+		Clear.render(center, buf);
+
+		let item = self.popup.current();
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(self.popup.title().clone())
+			.title(
+				Line::from(format!(
+					"{} of {}",
+					self.popup.position() + 1,
+					self.popup.items().len()
+				))
+				.right_aligned(),
+			)
+			.title_bottom(
+				Line::from("<c> categorise, <x> cleared, <s> split, <n> skip, <q> stop")
+					.right_aligned(),
+			)
+			.padding(Padding::horizontal(1));
+
+		if let Some(error) = self.popup.error() {
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		}
+
+		let t = &item.transaction;
+		let field = |name: &str, value: String| {
+			Line::from(vec![
+				Span::styled(
+					format!("{name:<10}"),
+					Style::default().fg(self.theme.accent),
+				),
+				Span::raw(value),
+			])
+		};
+		let mut lines = vec![
+			field("Date", t.date.format(DATE_FORMAT_STRING).to_string()),
+			field("Label", t.label.clone()),
+			field("Amount", crate::view::format_currency(t.amount)),
+			field("Category", t.category.clone()),
+			field("Tags", t.tags.join(", ")),
+			field(
+				"Status",
+				if t.pending { "Pending" } else { "Cleared" }.to_string(),
+			),
+			Line::default(),
+		];
+		lines.extend(item.reasons.iter().map(|reason| {
+			let text = match reason {
+				ReviewReason::Unusual { typical } => format!(
+					"• {reason} (usually {})",
+					crate::view::format_currency(*typical)
+				),
+				_ => format!("• {reason}"),
+			};
+			Line::styled(text, Style::default().fg(self.theme.notice))
+		}));
+		Paragraph::new(lines).block(block).render(center, buf);
+	}
+}
+
+/// A temporary wrapper around a [Popup], for the purpose of rendering
+pub(super) struct InputWidget<'a> {
+	pub popup: &'a popup::Input,
+	pub theme: Theme,
 }
 
-impl Widget for ConfirmWidget<'_> {
+impl Widget for InputWidget<'_> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
-		const BOX_HEIGHT: u16 = 7;
-		let center = center(
-			area,
-			Constraint::Percentage(50),
-			Constraint::Length(BOX_HEIGHT),
-		);
+		let center = center(area, Constraint::Percentage(50), Constraint::Length(3));
 		Clear.render(center, buf);
 
 		let mut block = Block::default()
@@ -59,32 +1239,76 @@ impl Widget for ConfirmWidget<'_> {
 			block = block.title(Line::from(subtitle.clone()).right_aligned());
 		}
 
+		// An error from submitting the text is shown until it is changed, and the preview of the
+		// text as it is typed otherwise
 		if let Some(error) = self.popup.error() {
-			block = block
-				.title_bottom(Line::from(error.clone()).style(Style::default().fg(Color::Red)));
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
+		} else if let Some(preview) = self.popup.preview() {
+			block = block.title_bottom(match preview {
+				Ok(preview) => {
+					Line::from(format!("= {preview}")).style(Style::default().fg(self.theme.info))
+				}
+				Err(error) => Line::from(error).style(Style::default().fg(self.theme.error)),
+			});
 		}
 
 		let inner = block.inner(center);
 
 		block.render(center, buf);
+		self.popup.text_area.render(inner, buf);
 
-		let rows: [Rect; 5] = Layout::vertical([Constraint::Length(1); 5]).areas(inner);
-		Paragraph::new(self.popup.prompt().clone())
-			.alignment(Alignment::Center)
-			.render(rows[1], buf);
-		Paragraph::new("[y]    [n]")
-			.alignment(Alignment::Center)
-			.render(rows[3], buf);
+		let suggestions = self.popup.suggestions();
+		if suggestions.is_empty() {
+			return;
+		}
+		let height = u16::try_from(suggestions.len()).unwrap_or(u16::MAX) + 2;
+		let list_area = Rect {
+			y: center.bottom(),
+			height: height.min(area.bottom().saturating_sub(center.bottom())),
+			..center
+		};
+		Clear.render(list_area, buf);
+		let lines: Vec<Line> = suggestions
+			.iter()
+			.enumerate()
+			.map(|(i, value)| {
+				if self.popup.suggestion() == Some(i) {
+					Line::styled(
+						value.as_str(),
+						Style::default()
+							.bg(self.theme.selection_bg)
+							.fg(self.theme.selection_fg),
+					)
+				} else {
+					Line::raw(value.as_str())
+				}
+			})
+			.collect();
+		Paragraph::new(lines)
+			.block(
+				Block::default()
+					.borders(Borders::ALL)
+					.border_type(BorderType::Rounded)
+					.border_style(Style::default().fg(self.theme.dim))
+					.title_bottom(Line::from("<Tab> complete <Up Down> choose").right_aligned()),
+			)
+			.render(list_area, buf);
 	}
 }
 
-pub(super) struct InfoWidget<'a> {
-	pub popup: &'a popup::Info,
+/// A form of several text inputs, each on its own line after its name
+pub(super) struct FormWidget<'a> {
+	pub popup: &'a popup::Form,
+	pub theme: Theme,
 }
 
-impl Widget for InfoWidget<'_> {
+impl Widget for FormWidget<'_> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
-		let center = center(area, Constraint::Percentage(70), Constraint::Percentage(70));
+		let fields = self.popup.fields();
+		let height = u16::try_from(fields.len()).unwrap_or(u16::MAX) + 2;
+		let center = center(area, Constraint::Percentage(50), Constraint::Length(height));
 		Clear.render(center, buf);
 
 		let mut block = Block::default()
@@ -97,25 +1321,88 @@ impl Widget for InfoWidget<'_> {
 		}
 
 		if let Some(error) = self.popup.error() {
-			block = block
-				.title_bottom(Line::from(error.clone()).style(Style::default().fg(Color::Red)));
+			block = block.title_bottom(
+				Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			);
 		}
 
-		Paragraph::new(self.popup.text().clone())
-			.wrap(Wrap { trim: false })
-			.block(block)
-			.render(center, buf);
+		let inner = block.inner(center);
+		block.render(center, buf);
+
+		let name_width = fields
+			.iter()
+			.map(|(name, _)| name.chars().count() + 2)
+			.max()
+			.unwrap_or(0);
+		let rows = Layout::vertical(vec![Constraint::Length(1); fields.len()]).split(inner);
+		for (i, ((name, text_area), row)) in fields.iter().zip(rows.iter()).enumerate() {
+			let [name_area, text_area_area] = Layout::horizontal([
+				Constraint::Length(u16::try_from(name_width).unwrap_or(u16::MAX)),
+				Constraint::Fill(1),
+			])
+			.areas(*row);
+			if i == self.popup.focused() {
+				Line::styled(
+					format!("{name}:"),
+					Style::default()
+						.fg(self.theme.accent)
+						.add_modifier(Modifier::BOLD),
+				)
+				.render(name_area, buf);
+				text_area.render(text_area_area, buf);
+			} else {
+				Line::styled(format!("{name}:"), Style::default().fg(self.theme.dim))
+					.render(name_area, buf);
+				Line::raw(text_area.lines().join(" ")).render(text_area_area, buf);
+			}
+		}
 	}
 }
 
-/// A temporary wrapper around a [Popup], for the purpose of rendering
-pub(super) struct InputWidget<'a> {
-	pub popup: &'a popup::Input,
+/// A calendar of the month of the selected date, with weeks starting on Monday
+pub(super) struct DatePickerWidget<'a> {
+	pub popup: &'a popup::DatePicker,
+	pub theme: Theme,
 }
 
-impl Widget for InputWidget<'_> {
+impl MinimumSize for DatePickerWidget<'_> {
+	const MIN_SIZE: MinSize = MinSize {
+		width: 36,
+		height: 11,
+	};
+}
+
+impl DatePickerWidget<'_> {
+	/// Returns the weeks of the month of the given date, each as the days of the month from
+	/// Monday to Sunday, which are [`None`] outside the month
+	fn weeks(date: NaiveDate) -> Vec<[Option<u32>; 7]> {
+		let first = date.with_day(1).unwrap_or(date);
+		let days = first
+			.checked_add_months(Months::new(1))
+			.and_then(|next| next.pred_opt())
+			.map_or(28, |last| last.day());
+		let offset = first.weekday().num_days_from_monday();
+		let mut weeks = vec![];
+		for day in 1..=days {
+			let cell = offset + day - 1;
+			if cell.is_multiple_of(7) || weeks.is_empty() {
+				weeks.push([None; 7]);
+			}
+			if let Some(week) = weeks.last_mut() {
+				week[(cell % 7) as usize] = Some(day);
+			}
+		}
+		weeks
+	}
+}
+
+impl Widget for DatePickerWidget<'_> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
-		let center = center(area, Constraint::Percentage(50), Constraint::Length(3));
+		let center = center(
+			area,
+			Constraint::Length(Self::MIN_SIZE.width),
+			Constraint::Length(Self::MIN_SIZE.height),
+		);
 		Clear.render(center, buf);
 
 		let mut block = Block::default()
@@ -127,89 +1414,615 @@ impl Widget for InputWidget<'_> {
 			block = block.title(Line::from(subtitle.clone()).right_aligned());
 		}
 
-		if let Some(error) = self.popup.error() {
-			block = block
-				.title_bottom(Line::from(error.clone()).style(Style::default().fg(Color::Red)));
-		}
+		block = block.title_bottom(match self.popup.error() {
+			Some(error) => Line::from(error.clone()).style(Style::default().fg(self.theme.error)),
+			None => Line::from("<H L> month <t>oday <i> type")
+				.style(Style::default().fg(self.theme.dim)),
+		});
 
 		let inner = block.inner(center);
-
 		block.render(center, buf);
-		self.popup.text_area.render(inner, buf);
+
+		let date = self.popup.date();
+		let today = clock::today();
+		let mut lines = vec![
+			Line::styled(
+				date.format("%B %Y").to_string(),
+				Style::default()
+					.fg(self.theme.accent)
+					.add_modifier(Modifier::BOLD),
+			),
+			Line::styled(
+				"Mo  Tu  We  Th  Fr  Sa  Su",
+				Style::default().fg(self.theme.dim),
+			),
+		];
+		for week in Self::weeks(date) {
+			let spans: Vec<Span> = week
+				.iter()
+				.map(|day| {
+					let Some(day) = day else {
+						return Span::raw("  ");
+					};
+					let mut style = Style::default();
+					if date.with_day(*day) == Some(today) {
+						style = style
+							.fg(self.theme.accent)
+							.add_modifier(Modifier::UNDERLINED);
+					}
+					if *day == date.day() {
+						style = style
+							.bg(self.theme.selection_bg)
+							.fg(self.theme.selection_fg);
+					}
+					Span::styled(format!("{day:>2}"), style)
+				})
+				.flat_map(|span| [Span::raw("  "), span])
+				.skip(1)
+				.collect();
+			lines.push(Line::from(spans));
+		}
+		Paragraph::new(lines)
+			.alignment(Alignment::Center)
+			.render(inner, buf);
+	}
+}
+
+/// The balance of every sheet and their total, shown in place of a sheet
+pub(super) struct DashboardWidget<'a> {
+	pub net_worth: &'a NetWorth,
+	/// The index of the selected sheet, which is highlighted
+	pub selected: usize,
+	/// Whether the balances include scheduled transactions
+	pub projected: bool,
+	/// The base currency of the file, which the total is in
+	pub base: &'a str,
+	pub theme: Theme,
+}
+
+impl DashboardWidget<'_> {
+	/// The characters of a sparkline, from lowest to highest
+	const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+	/// Returns the values drawn as a line of bars, scaled between their lowest and highest
+	fn sparkline(values: &[f64]) -> String {
+		let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+		let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+		let top = Self::SPARKS.len() - 1;
+		values
+			.iter()
+			.map(|value| {
+				if max <= min {
+					return Self::SPARKS[0];
+				}
+				#[allow(
+					clippy::cast_possible_truncation,
+					clippy::cast_sign_loss,
+					clippy::cast_precision_loss
+				)]
+				let level = ((value - min) / (max - min) * top as f64).round() as usize;
+				Self::SPARKS[level.min(top)]
+			})
+			.collect()
+	}
+
+	/// Returns the row of the table for a sheet, or the total
+	fn row(&self, worth: &Worth) -> Row<'static> {
+		let change = worth.change();
+		let change_style = Style::default().fg(if change < 0.0 {
+			self.theme.negative
+		} else {
+			self.theme.positive
+		});
+		let name = match &worth.currency {
+			Some(currency) => format!("{} ({currency})", worth.name),
+			None => worth.name.clone(),
+		};
+		Row::new([
+			Cell::from(name),
+			Cell::from(Line::from(crate::view::format_currency(worth.balance)).right_aligned()),
+			Cell::from(Self::sparkline(&worth.history)).style(Style::default().fg(self.theme.info)),
+			Cell::from(Line::from(crate::view::format_currency(change)).right_aligned())
+				.style(change_style),
+		])
+	}
+}
+
+impl Widget for DashboardWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let mut block = Block::default()
+			.borders(Borders::ALL)
+			.title("Net worth")
+			.title_bottom(
+				Line::from("<j k> select, <enter> open the sheet, <gd> back").right_aligned(),
+			);
+		if !self.net_worth.unconverted.is_empty() {
+			block = block.title_bottom(
+				Line::from(format!(
+					"No exchange rate (gX) for {}, left out of the total",
+					self.net_worth.unconverted.join(", ")
+				))
+				.style(Style::default().fg(self.theme.negative)),
+			);
+		}
+		if self.projected {
+			block = block.title(
+				Line::from("Projected balances")
+					.style(Style::default().fg(self.theme.info))
+					.right_aligned(),
+			);
+		}
+
+		let header = Row::new([
+			Cell::from("Sheet"),
+			Cell::from(Line::from("Balance").right_aligned()),
+			Cell::from(format!("Last {HISTORY_MONTHS} months")),
+			Cell::from(Line::from("Change").right_aligned()),
+		])
+		.style(Style::default().add_modifier(Modifier::BOLD));
+		let mut rows: Vec<Row> = self
+			.net_worth
+			.sheets
+			.iter()
+			.map(|worth| self.row(worth))
+			.collect();
+		let mut total = self.net_worth.total.clone();
+		if self
+			.net_worth
+			.sheets
+			.iter()
+			.any(|worth| worth.currency.is_some())
+		{
+			total.name = format!("Total ({})", self.base);
+		}
+		rows.push(
+			self.row(&total)
+				.style(Style::default().add_modifier(Modifier::BOLD))
+				.top_margin(1),
+		);
+
+		#[allow(clippy::cast_possible_truncation)]
+		let sparkline_width = HISTORY_MONTHS.max(14) as u16;
+		let widths = [
+			Constraint::Fill(1),
+			Constraint::Length(14),
+			Constraint::Length(sparkline_width),
+			Constraint::Length(14),
+		];
+		let mut state = TableState::default().with_selected(Some(self.selected));
+		StatefulWidget::render(
+			Table::new(rows, widths)
+				.header(header)
+				.block(block)
+				.column_spacing(2)
+				.row_highlight_style(Style::default().bg(self.theme.selection_bg)),
+			area,
+			buf,
+			&mut state,
+		);
 	}
 }
 
 /// A temporary wrapper around a [Sheet], for the purpose of rendering
 pub(super) struct SheetWidget<'a> {
 	pub sheet: &'a Sheet,
+	/// The rows of the table, in order. See [`Sheet::table_rows`]
+	pub rows: Vec<TableRow>,
+	/// The indices of the members to show as columns, in order. See
+	/// [`crate::model::SheetDisplay::visible_columns`]
+	pub columns: Vec<usize>,
+	/// The width set for each shown column, if any. Columns without one fit their contents
+	pub widths: Vec<Option<u16>>,
+	/// Whether to show the contents of the selected cell above the table
+	pub cell_preview: bool,
+	/// Whether to show the line numbers to the left of the table
+	pub gutter: bool,
+	/// Whether to show the projected balance rather than the actual one
+	pub projected: bool,
+	/// Whether the sheet fills the view or is in one of its panes
+	pub pane: Pane,
+	pub theme: Theme,
+	/// The rules that style the rows. See [`Highlight`]
+	pub highlights: &'a [Highlight],
+	/// Whether every other row has a background, and how tall the rows are
+	pub row_options: RowOptions,
+}
+
+/// Where a sheet is shown, when the view can be split into two panes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Pane {
+	/// The view isn't split
+	Whole,
+	/// The pane the keys act on
+	Focused,
+	/// The other pane, which is dimmed
+	Unfocused,
+}
+
+impl MinimumSize for SheetWidget<'_> {
+	/// Room for the date, a few characters of the label and the amount, and a single row
+	const MIN_SIZE: MinSize = MinSize {
+		width: 30,
+		height: 4,
+	};
 }
 
 impl StatefulWidget for SheetWidget<'_> {
 	type State = SheetState;
 
 	fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-		let [header, table] =
-			Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(area);
+		let [header, table] = Layout::vertical([
+			Constraint::Length(if self.cell_preview { 3 } else { 0 }),
+			Constraint::Fill(1),
+		])
+		.areas(area);
 		let [table, scrollbar] =
 			Layout::horizontal([Constraint::Fill(1), Constraint::Length(2)]).areas(table);
 
-		state.update_visible_row_num(table);
-		self.render_header(header, buf, &state.table_state);
-		self.render_table(table, buf, &mut state.table_state);
+		state.update_visible_row_num(table, self.row_options.density.height(), self.rows.len());
+		if self.cell_preview {
+			self.render_header(header, buf, &state.table_state);
+		}
+		self.render_table(table, buf, state);
 		Self::render_scrollbar(scrollbar, buf, &mut state.scroll_state);
 	}
 }
 
 #[allow(clippy::cast_possible_truncation)]
 impl SheetWidget<'_> {
-	/// Renders the title of the sheet
+	/// Returns the widths of the shown columns of the table, using the width set for a column
+	/// over fitting its contents
+	fn column_widths(&self) -> Vec<Constraint> {
+		let widths = [
+			// date
+			Constraint::Length(10),
+			// label
+			Constraint::Fill(1),
+			// amount
+			Constraint::Length(
+				u16::try_from(
+					format!(
+						"{:05.2}",
+						self.sheet
+							.transactions()
+							.iter()
+							.map(|t| t.amount.abs())
+							.max_by(f64::total_cmp)
+							.unwrap_or(0.0)
+					)
+					.len(),
+				)
+				// +1 for currency symbol, +2 for parens on negatives
+				.unwrap_or(u16::MAX)
+					+ 3,
+			),
+			// category
+			Constraint::Length(self.text_width(|t| t.category.chars().count(), "Category")),
+			// tags
+			Constraint::Length(self.text_width(
+				|t| {
+					// The width of the tags joined with ", ", without joining them
+					t.tags.iter().map(|tag| tag.chars().count()).sum::<usize>()
+						+ 2 * t.tags.len().saturating_sub(1)
+				},
+				"Tags",
+			)),
+		];
+		self.columns
+			.iter()
+			.zip(&self.widths)
+			.map(|(col, width)| width.map_or(widths[*col], Constraint::Length))
+			.collect()
+	}
+
+	/// Returns the width of a text column - the width of its widest value, so empty columns only
+	/// take up the width of their heading, capped so the label always has room
+	fn text_width(
+		&self,
+		width: impl Fn(&crate::model::Transaction) -> usize,
+		heading: &str,
+	) -> u16 {
+		/// The widest a text column can be
+		const MAX_WIDTH: usize = 20;
+		let widest = self
+			.sheet
+			.transactions()
+			.iter()
+			.map(width)
+			.max()
+			.unwrap_or(0);
+		widest.max(heading.len()).min(MAX_WIDTH) as u16
+	}
+
+	/// Returns the line summing up the month of the selected row (or of the first row under the
+	/// selected heading), for the top of the header
+	fn month_summary(&self, state: &TableState) -> Option<Line<'static>> {
+		let index = match self.rows.get(state.selected()?)? {
+			TableRow::Transaction(index) => *index,
+			TableRow::Heading(heading) => heading.rows[0],
+		};
+		let date = self.sheet.transactions().get(index)?.date;
+		let totals = self.sheet.month_totals(date);
+		let net_color = if totals.net() < 0.0 {
+			self.theme.negative
+		} else {
+			self.theme.positive
+		};
+		Some(Line::from(vec![
+			Span::raw(format!(" {}: ", date.format("%B %Y"))),
+			Span::styled(
+				format!("in {}", crate::view::format_currency(totals.income)),
+				Style::default().fg(self.theme.positive),
+			),
+			Span::raw(" · "),
+			Span::styled(
+				format!("out {}", crate::view::format_currency(totals.expenses)),
+				Style::default().fg(self.theme.negative),
+			),
+			Span::raw(" · "),
+			Span::styled(
+				format!("net {} ", crate::view::format_currency(totals.net())),
+				Style::default().fg(net_color),
+			),
+		]))
+	}
+
+	/// Renders the title of the sheet, with a summary of the month of the selected row
 	fn render_header(&self, area: Rect, buf: &mut Buffer, state: &TableState) {
 		// Display the contents of the selected cell, or nothing
-		let title_block = Block::default()
+		let mut title_block = Block::default()
 			.borders(Borders::ALL)
 			.style(Style::default());
+		if let Some(summary) = self.month_summary(state) {
+			title_block = title_block.title(summary.right_aligned());
+		}
 
 		let text = if let Some((row, col)) = state.selected_cell() {
-			let t = match self.sheet.transactions.get(row) {
+			let t = match self.rows.get(row).and_then(|row| match row {
+				TableRow::Transaction(i) => self.sheet.transactions().get(*i),
+				TableRow::Heading(_) => None,
+			}) {
 				Some(t) => t,
 				None => &crate::model::Transaction::default(),
 			};
-			crate::view::get_string_of_transaction_member(t, col)
+			self.columns
+				.get(col)
+				.map(|col| crate::view::get_string_of_transaction_member(t, *col))
+				.unwrap_or_default()
 		} else {
 			String::new()
 		};
 
-		Paragraph::new(Text::styled(text, Style::default().fg(Color::Green)))
+		Paragraph::new(Text::styled(text, Style::default().fg(self.theme.accent)))
 			.block(title_block)
 			.render(area, buf);
 	}
 
-	/// Renders the table portion of the sheet.
+	/// Returns the row of headings of the shown columns, with an arrow on the one the rows are
+	/// sorted by
+	fn header_row(&self, columns: &[usize]) -> Row<'static> {
+		let display = &self.sheet.display;
+		Row::new(columns.iter().map(|col| {
+			let mut heading = COLUMN_NAMES[*col].to_string();
+			if let Some(sort) = display.sort.filter(|sort| sort.column == *col) {
+				heading.push_str(if sort.descending { " ↓" } else { " ↑" });
+			}
+			// The amount is right aligned, so its heading is too
+			if *col == 2 {
+				Cell::from(Text::from(heading).alignment(Alignment::Right))
+			} else {
+				Cell::from(heading)
+			}
+		}))
+		.height(1)
+	}
+
+	/// Returns the row of a group heading, with the name of the group and the amount of rows in it
+	/// at the start, and their subtotal under the amount
+	fn heading_row(&self, columns: &[usize], heading: &GroupHeading) -> Row<'static> {
+		let marker = if heading.folded { '▸' } else { '▾' };
+		let name = if heading.key.is_empty() {
+			"Uncategorised"
+		} else {
+			&heading.key
+		};
+		let count = match heading.rows.len() {
+			1 => "1 row".to_string(),
+			n => format!("{n} rows"),
+		};
+		let subtotal: f64 = heading
+			.rows
+			.iter()
+			.map(|row| self.sheet.transactions()[*row].amount)
+			.sum();
+		let cells = columns.iter().enumerate().map(|(i, col)| match (i, col) {
+			(0, 1) => Cell::from(format!("{marker} {name} · {count}")),
+			(0, _) => Cell::from(format!("{marker} {name}")),
+			(_, 1) => Cell::from(count.clone()),
+			(_, 2) => Cell::from(
+				Text::from(crate::view::format_currency(subtotal)).alignment(Alignment::Right),
+			),
+			_ => Cell::default(),
+		});
+		Row::new(cells)
+			.style(
+				Style::default()
+					.fg(self.theme.accent)
+					.add_modifier(Modifier::BOLD),
+			)
+			.height(self.row_options.density.height())
+	}
+
+	/// Returns the given range of the rows of the table, with only the shown columns
+	fn table_rows(&self, columns: &[usize], range: Range<usize>) -> Vec<Row<'_>> {
+		let first_unordered = self.sheet.first_unordered_item();
+
+		self.rows[range.clone()]
+			.iter()
+			.zip(range)
+			.map(|(row, i)| {
+				let index = match row {
+					TableRow::Transaction(index) => *index,
+					TableRow::Heading(heading) => return self.heading_row(columns, heading),
+				};
+				let transaction = &self.sheet.transactions()[index];
+				let mut cell_styles = [Style::default(); COLUMN_NAMES.len()];
+				if first_unordered.is_some_and(|first| index >= first) {
+					cell_styles[0] = cell_styles[0].fg(self.theme.error);
+				}
+				let mut style = if self.row_options.striped && i % 2 == 1 {
+					Style::default().bg(self.theme.stripe)
+				} else {
+					Style::default()
+				};
+				if transaction.pending {
+					style = style.fg(self.theme.pending).add_modifier(Modifier::ITALIC);
+				}
+				if transaction.scheduled {
+					style = style.fg(self.theme.dim);
+				}
+				for highlight in self.highlights.iter().filter(|h| h.matches(transaction)) {
+					match highlight.styled_column() {
+						Some(col) => cell_styles[col] = cell_styles[col].patch(highlight.style()),
+						None => style = style.patch(highlight.style()),
+					}
+				}
+				let mut cells = [
+					// date
+					Cell::from(transaction.date.to_string()),
+					// label
+					Cell::from(transaction.label.clone()),
+					// amount
+					Cell::from(
+						Text::from(crate::view::format_currency(transaction.amount))
+							.alignment(Alignment::Right),
+					),
+					// category
+					Cell::from(transaction.category.clone()),
+					// tags
+					Cell::from(transaction.tags.join(", ")),
+				];
+				for (cell, cell_style) in cells.iter_mut().zip(cell_styles) {
+					*cell = std::mem::take(cell).style(cell_style);
+				}
+				Row::new(columns.iter().map(|col| std::mem::take(&mut cells[*col])))
+					.style(style)
+					.height(self.row_options.density.height())
+			})
+			.collect()
+	}
+
+	/// Returns the line showing the actual or projected balance of the sheet
+	fn balance_line(&self) -> Line<'static> {
+		let scheduled = self.sheet.scheduled_count();
+		let account = self.sheet.account();
+		let (projected, actual) = match account.kind {
+			AccountType::CreditCard => ("Projected owed", "Owed"),
+			_ => ("Projected balance", "Balance"),
+		};
+		let text = if self.projected {
+			format!(
+				"{projected}: {} ({scheduled} scheduled)",
+				crate::view::format_currency(account.shown_balance(self.sheet.total().projected))
+			)
+		} else {
+			format!(
+				"{actual}: {}",
+				crate::view::format_currency(account.shown_balance(self.sheet.total().actual))
+			)
+		};
+		let style = if self.projected {
+			Style::default().fg(self.theme.info)
+		} else {
+			Style::default()
+		};
+		Line::styled(text, style)
+	}
+
+	/// Returns the block around the table, with the name of the sheet when the view is split, any
+	/// settings that change how the rows are shown, the balance, and how many columns are off the
+	/// screen to either side
+	fn table_block(&self, hidden_left: usize, hidden_right: usize) -> Block<'static> {
+		let display = &self.sheet.display;
+		let mut block = Block::default().borders(if self.gutter {
+			Borders::TOP | Borders::RIGHT | Borders::BOTTOM
+		} else {
+			Borders::ALL
+		});
+		match self.pane {
+			Pane::Whole => {}
+			Pane::Focused => {
+				block = block.title(
+					Line::from(self.sheet.name.clone())
+						.style(Style::default().fg(self.theme.notice)),
+				);
+			}
+			Pane::Unfocused => {
+				block = block
+					.title(self.sheet.name.clone())
+					.border_style(Style::default().fg(self.theme.dim));
+			}
+		}
+		if self.sheet.order == RowOrder::Manual {
+			block = block
+				.title(Line::from("Manual order").style(Style::default().fg(self.theme.notice)));
+		}
+		if let Some(grouping) = display.grouping {
+			block = block.title(
+				Line::from(format!(
+					"Grouped by {}",
+					grouping.to_string().to_lowercase()
+				))
+				.style(Style::default().fg(self.theme.notice)),
+			);
+		}
+		block = block.title_bottom(self.balance_line().right_aligned());
+		let mut hidden = vec![];
+		if hidden_left > 0 {
+			hidden.push(format!("◀ {hidden_left} more"));
+		}
+		if hidden_right > 0 {
+			hidden.push(format!("{hidden_right} more ▶"));
+		}
+		if !hidden.is_empty() {
+			block = block.title_bottom(
+				Line::from(hidden.join(" · ")).style(Style::default().fg(self.theme.info)),
+			);
+		}
+		if let Some(filter) = &display.filter {
+			block = block.title(
+				Line::from(format!("Filter: {filter}"))
+					.style(Style::default().fg(self.theme.notice))
+					.right_aligned(),
+			);
+		}
+		block
+	}
+
+	/// Renders the table portion of the sheet. Only the columns that fit are rendered, scrolled
+	/// so the selected one is on the screen.
 	/// This is the most complicated method, as it has to be very reactive to both the state of
 	/// the view and the state of the model
-	fn render_table(&self, area: Rect, buf: &mut Buffer, state: &mut TableState) {
-		let header_style = Style::default().fg(Color::Green);
+	fn render_table(&self, area: Rect, buf: &mut Buffer, state: &mut SheetState) {
+		/// The narrowest the label can be squeezed to before columns are scrolled off the screen
+		const LABEL_MIN_WIDTH: u16 = 15;
 
-		let selected_row_style = Style::default().bg(Color::Black);
+		let header_style = Style::default().fg(self.theme.accent);
+
+		let selected_row_style = Style::default().bg(self.theme.selected_row);
 
 		let selected_cell_style = Style::default()
 			.add_modifier(Modifier::BOLD)
-			.bg(Color::DarkGray)
-			.fg(Color::Blue);
-
-		let header = Row::new(vec![
-			Cell::from("Date"),
-			Cell::from("Label"),
-			Cell::from(Text::from("Amount").alignment(Alignment::Right)),
-		])
-		.style(header_style)
-		.height(1);
+			.bg(self.theme.selection_bg)
+			.fg(self.theme.selection_fg);
 
 		let [number_area, sheet_area] = Layout::horizontal([
 			// line number
 			Constraint::Length({
-				let len = self.sheet.transactions.len();
-				if len == 0 {
+				let len = self.rows.len();
+				if !self.gutter {
+					0
+				} else if len == 0 {
 					1
 				} else {
 					// +1 for extra digit, +1 again for border
@@ -221,71 +2034,50 @@ impl SheetWidget<'_> {
 		])
 		.areas(area);
 
-		let unordered_indices = self.sheet.unordered_items();
-
-		let rows: Vec<Row> = self
-			.sheet
-			.transactions
+		let widths = self.column_widths();
+		let min_widths: Vec<u16> = widths
 			.iter()
-			.enumerate()
-			.map(|(index, transaction)| {
-				Row::new(vec![
-					// date
-					Cell::from(transaction.date.to_string()).style(
-						if unordered_indices.contains(&index) {
-							Style::default().fg(Color::Red)
-						} else {
-							Style::default()
-						},
-					),
-					// label
-					Cell::from(transaction.label.clone()),
-					// amount
-					Cell::from(
-						Text::from(crate::view::format_currency(transaction.amount))
-							.alignment(Alignment::Right),
-					),
-				])
-				.height(ITEM_HEIGHT)
+			.map(|width| match width {
+				Constraint::Length(width) => *width,
+				_ => LABEL_MIN_WIDTH,
 			})
 			.collect();
+		let available = self.table_block(0, 0).inner(sheet_area).width;
+		let shown = state.fit_columns(&min_widths, available);
+		let block = self.table_block(shown.start, self.columns.len() - shown.end);
+		let columns = &self.columns[shown.clone()];
+		let header = self.header_row(columns).style(header_style);
+		let window = state.visible_rows(self.rows.len());
+		let rows = self.table_rows(columns, window.clone());
 
-		let widths = [
-			// date
-			Constraint::Length(10),
-			// label
-			Constraint::Fill(1),
-			// amount
-			Constraint::Length(
-				u16::try_from(
-					format!(
-						"{:05.2}",
-						self.sheet
-							.transactions
-							.iter()
-							.map(|t| t.amount.abs())
-							.max_by(f64::total_cmp)
-							.unwrap_or(0.0)
-					)
-					.len(),
-				)
-				// +1 for currency symbol, +2 for parens on negatives
-				.unwrap_or(u16::MAX)
-					+ 3,
-			),
-		];
+		// The table only knows about the rows and columns on the screen, so its selected row and
+		// column are relative to the first of them
+		let selected_column = state.table_state.selected_column();
+		let mut table_state = state
+			.table_state
+			.clone()
+			.with_offset(0)
+			.with_selected(state.table_state.selected().map(|row| row - window.start))
+			.with_selected_column(selected_column.map(|col| col - shown.start));
 		StatefulWidget::render(
-			Table::new(rows, widths)
+			Table::new(rows, widths[shown].to_vec())
 				.header(header)
-				.block(Block::default().borders(Borders::TOP | Borders::RIGHT | Borders::BOTTOM))
+				.block(block)
 				.row_highlight_style(selected_row_style)
 				.cell_highlight_style(selected_cell_style),
 			sheet_area,
 			buf,
-			state,
+			&mut table_state,
 		);
+		let selected_row = table_state.selected().map(|row| row + window.start);
+		state.table_state = table_state
+			.with_offset(window.start)
+			.with_selected(selected_row)
+			.with_selected_column(selected_column);
 
-		self.render_numbers(number_area, buf, state, selected_row_style);
+		if self.gutter {
+			self.render_numbers(number_area, buf, &state.table_state, selected_row_style);
+		}
 	}
 
 	/// Renders the line numbers on the left hand side of the screen
@@ -298,17 +2090,18 @@ impl SheetWidget<'_> {
 		state: &TableState,
 		selected_row_style: Style,
 	) {
+		let height = self.row_options.density.height();
 		let start = state.offset();
 		let end = self
-			.sheet
-			.transactions
+			.rows
 			.len()
 			// -3 To align with the table (-2 for top and bottom borders, -1 for the headings)
-			.min(start + area.height as usize - 3);
+			.min(start + usize::from(area.height.saturating_sub(3) / height));
 		let cursor_position = state.selected();
-		let mut row_numbers: Vec<Line> = Vec::with_capacity(self.sheet.transactions.len());
+		let mut row_numbers: Vec<Line> = Vec::with_capacity((end - start) * height as usize);
 
 		for i in start..end {
+			let selected = cursor_position == Some(i);
 			row_numbers.push({
 				match cursor_position {
 					Some(pos) if pos == i => {
@@ -320,6 +2113,14 @@ impl SheetWidget<'_> {
 					None => Line::from((i + 1).to_string()),
 				}
 			});
+			// The lines under the number line up with the rest of the row
+			for _ in 1..height {
+				row_numbers.push(if selected {
+					Line::from(" ".repeat(area.width as usize)).style(selected_row_style)
+				} else {
+					Line::default()
+				});
+			}
 		}
 		Paragraph::new(row_numbers)
 			.block(
@@ -0,0 +1,139 @@
+//! Showing a second sheet alongside the selected one, in panes split side by side or one above
+//! the other. Only one pane has focus, and the keys act on the sheet in it
+
+use ratatui::{
+	Frame,
+	layout::{Constraint, Direction, Layout, Rect},
+};
+
+use crate::{
+	model::{Model, Sheet},
+	view::{
+		View,
+		layout::ScreenLayout,
+		rendering::{Pane, SheetWidget},
+		states::SheetState,
+	},
+};
+
+/// The pane without focus when the view is split
+pub struct Split {
+	/// Whether the panes are side by side ([`Direction::Horizontal`]) or one above the other
+	/// ([`Direction::Vertical`])
+	pub direction: Direction,
+	/// Whether the focused pane is the first (left or top) one
+	pub focus_first: bool,
+	/// The sheet shown in the pane. See [`Model::get_sheet`] for indexing logic
+	sheet: usize,
+	/// The state of the sheet in the pane. It is kept apart from the states of the selected
+	/// sheets, so both panes can show the same sheet scrolled to different rows
+	state: SheetState,
+}
+
+impl Split {
+	/// Gets the sheet shown in the pane, or the main sheet if it has since been deleted
+	fn get_sheet<'a>(&self, model: &'a Model) -> &'a Sheet {
+		model
+			.get_sheet(self.sheet)
+			.unwrap_or(model.get_main_sheet())
+	}
+}
+
+impl View {
+	/// Splits the view in the given direction, with the selected sheet shown in both panes. If
+	/// the view is already split, only the direction changes
+	pub fn split(&mut self, direction: Direction, model: &Model) {
+		if let Some(split) = &mut self.split {
+			split.direction = direction;
+			return;
+		}
+		let sheet = self.get_selected_sheet(model);
+		let mut state = SheetState::new(sheet);
+		if let Some(row) = self.get_state_of(sheet).table_state.selected() {
+			state.scroll_to_row(row);
+		}
+		self.split = Some(Split {
+			direction,
+			focus_first: true,
+			sheet: self.selected_sheet,
+			state,
+		});
+	}
+
+	/// Closes the pane without focus, leaving the focused one
+	pub fn close_split(&mut self) {
+		self.split = None;
+	}
+
+	/// Moves the focus to the other pane. The sheet in it becomes the selected sheet, and its
+	/// state is swapped with the state of the previously selected sheet, so each pane stays where
+	/// it was
+	pub fn switch_pane(&mut self, model: &Model) {
+		let Some(mut split) = self.split.take() else {
+			return;
+		};
+		let focused_name = self.get_selected_sheet(model).name.clone();
+		let focused_state = self
+			.sheet_states
+			.remove(&focused_name)
+			.unwrap_or_else(|| SheetState::new(self.get_selected_sheet(model)));
+		let other_name = split.get_sheet(model).name.clone();
+		let other_state = std::mem::replace(&mut split.state, focused_state);
+		self.sheet_states.insert(other_name, other_state);
+		std::mem::swap(&mut self.selected_sheet, &mut split.sheet);
+		split.focus_first = !split.focus_first;
+		self.split = Some(split);
+	}
+
+	/// Moves the focus to the other pane if the given position on the screen is over its table,
+	/// so clicking a row in it selects that row
+	pub(super) fn focus_pane_at(&mut self, column: u16, row: u16, model: &Model) {
+		if self
+			.split
+			.as_ref()
+			.is_some_and(|split| split.state.row_at(column, row).is_some())
+		{
+			self.switch_pane(model);
+		}
+	}
+
+	/// Renders both panes, the focused one showing the selected sheet (or the dashboard)
+	pub(super) fn render_panes(
+		&mut self,
+		frame: &mut Frame,
+		model: &Model,
+		layout: ScreenLayout,
+		area: Rect,
+	) {
+		let projected = self.projected;
+		let theme = self.theme;
+		let Some(sheet) = self.split.as_ref().map(|split| split.get_sheet(model)) else {
+			return;
+		};
+		let (columns, widths) = self.shown_columns(sheet);
+		let Some(split) = self.split.as_mut() else {
+			return;
+		};
+		let [first, second] = Layout::new(split.direction, [Constraint::Fill(1); 2]).areas(area);
+		let (focused, other) = if split.focus_first {
+			(first, second)
+		} else {
+			(second, first)
+		};
+		let sheet_widget = SheetWidget {
+			sheet,
+			rows: sheet.table_rows(),
+			columns,
+			widths,
+			cell_preview: layout.cell_preview(),
+			gutter: layout.gutter,
+			projected,
+			pane: Pane::Unfocused,
+			theme,
+			highlights: &self.highlights,
+			row_options: self.row_options,
+		};
+		frame.render_stateful_widget(sheet_widget, other, &mut split.state);
+		self.render_focused(frame, model, layout, focused, Pane::Focused);
+	}
+}
@@ -1,9 +1,14 @@
+use std::{collections::HashMap, ops::Range};
+
 use ratatui::{
 	layout::{self},
 	widgets::{ScrollbarState, TableState},
 };
 
-use crate::{model::Sheet, view::ITEM_HEIGHT};
+use crate::{
+	model::{Sheet, TransactionId},
+	view::ITEM_HEIGHT,
+};
 
 /// A struct to track the view states of sheets
 pub struct SheetState {
@@ -14,6 +19,80 @@ pub struct SheetState {
 	/// The number of visible rows on the screen. This is used for scrolling up and down by half
 	/// the visible rows
 	pub visible_row_num: u16,
+	/// The area the table was last rendered in, used to find the row that was clicked on
+	pub table_area: layout::Rect,
+	/// The height of each row when the table was last rendered. See
+	/// [`crate::view::RowDensity`]
+	row_height: u16,
+	/// The first of the shown columns that is on screen, when they don't all fit
+	pub column_offset: usize,
+	/// The widths the shown columns need, as of when the table was last rendered. See
+	/// [`SheetState::fit_columns`]
+	column_widths: Vec<u16>,
+	/// The width available for the columns when the table was last rendered
+	available_width: u16,
+	/// The rows jumped away from, to go back to. See [`JumpList`]
+	pub jumps: JumpList,
+	/// The rows marked with `m` and a letter, by the identity of their transactions, so the marks
+	/// stay on them wherever they are moved to
+	pub marks: HashMap<char, TransactionId>,
+}
+
+/// The rows of a sheet that were jumped away from, with big motions like `gg`, `G` and searches,
+/// to go back to with `<C-o>` (and forward again with `<C-i>`), like vim's jump list. Rows are
+/// kept as the indices of their transactions, so they are the same rows when the sheet is
+/// filtered or sorted differently
+#[derive(Debug, Default)]
+pub struct JumpList {
+	/// The rows jumped away from, oldest first
+	rows: Vec<usize>,
+	/// How far back through `rows` going back has got, which is `rows.len()` until it has
+	current: usize,
+}
+
+impl JumpList {
+	/// The most jumps remembered. Older ones are forgotten to make room for new ones
+	const LIMIT: usize = 100;
+
+	/// Remembers the row as jumped away from, forgetting any jumps that were gone back past
+	pub fn push(&mut self, row: usize) {
+		self.rows.truncate(self.current);
+		self.rows.retain(|r| *r != row);
+		self.rows.push(row);
+		if self.rows.len() > Self::LIMIT {
+			self.rows.remove(0);
+		}
+		self.current = self.rows.len();
+	}
+
+	/// Returns the row to go back to from the given one, if there is one. The first time back,
+	/// the row gone back from is remembered, so it can be come forward to again
+	pub fn back(&mut self, from: usize) -> Option<usize> {
+		if self.current == self.rows.len() {
+			self.rows.retain(|r| *r != from);
+			self.rows.push(from);
+			self.current = self.rows.len() - 1;
+		}
+		self.current = self.current.checked_sub(1)?;
+		Some(self.rows[self.current])
+	}
+
+	/// Returns the row to go forward to again, after going back, if there is one
+	pub fn forward(&mut self) -> Option<usize> {
+		(self.current + 1 < self.rows.len()).then(|| {
+			self.current += 1;
+			self.rows[self.current]
+		})
+	}
+
+	/// Returns the row last jumped away from, remembering the given row in its place, so going
+	/// to the last jump twice comes back to the same row, like vim's `''`
+	pub fn last(&mut self, from: usize) -> Option<usize> {
+		let row = *self.rows.last()?;
+		self.current = self.rows.len();
+		self.push(from);
+		Some(row)
+	}
 }
 
 impl SheetState {
@@ -23,12 +102,19 @@ impl SheetState {
 	pub fn new(sheet: &Sheet) -> Self {
 		Self {
 			table_state: TableState::default()
-				.with_selected(sheet.transactions.len().saturating_sub(1)),
+				.with_selected(sheet.transactions().len().saturating_sub(1)),
 			scroll_state: ScrollbarState::new(
-				(sheet.transactions.len().saturating_sub(1)) * ITEM_HEIGHT as usize,
+				(sheet.transactions().len().saturating_sub(1)) * ITEM_HEIGHT as usize,
 			)
-			.position(sheet.transactions.len().saturating_sub(1) * ITEM_HEIGHT as usize),
+			.position(sheet.transactions().len().saturating_sub(1) * ITEM_HEIGHT as usize),
 			visible_row_num: 0,
+			table_area: layout::Rect::default(),
+			row_height: ITEM_HEIGHT,
+			column_offset: 0,
+			column_widths: vec![],
+			available_width: 0,
+			jumps: JumpList::default(),
+			marks: HashMap::new(),
 		}
 	}
 
@@ -38,13 +124,134 @@ impl SheetState {
 		self.scroll_state = self.scroll_state.position(row * ITEM_HEIGHT as usize);
 	}
 
-	/// updates the number of visible row according to the given areas height - 2 (as the table is
-	/// bordered which takes up 2 rows worth of height)
-	pub fn update_visible_row_num(&mut self, area: layout::Rect) {
-		self.visible_row_num = area.height - 3;
+	/// updates the number of visible row according to the given areas height - 3 (as the table is
+	/// bordered which takes up 2 rows worth of height, and has a row of headings) and the height of
+	/// each row, and remembers the area for mouse clicks. If the area changed, the table is
+	/// scrolled up as far as its `row_count` rows allow, so a taller area isn't left partly empty
+	pub fn update_visible_row_num(
+		&mut self,
+		area: layout::Rect,
+		row_height: u16,
+		row_count: usize,
+	) {
+		self.row_height = row_height.max(1);
+		self.visible_row_num = area.height.saturating_sub(3) / self.row_height;
+		if area != self.table_area {
+			let max_offset = row_count.saturating_sub(usize::from(self.visible_row_num));
+			*self.table_state.offset_mut() = self.table_state.offset().min(max_offset);
+		}
+		self.table_area = area;
+	}
+
+	/// Scrolls the table so the selected row is on the screen, the same way the table would when
+	/// rendered, returning the range of its `row_count` rows that are on the screen. Only these
+	/// rows are built when rendering, so large sheets draw as quickly as small ones
+	pub fn visible_rows(&mut self, row_count: usize) -> Range<usize> {
+		if self.table_state.selected().is_some_and(|s| s >= row_count) {
+			self.table_state.select(Some(row_count.saturating_sub(1)));
+		}
+		let visible = usize::from(self.visible_row_num);
+		// Rows below the last one aren't scrolled to, so removing rows doesn't leave space empty
+		let mut offset = self
+			.table_state
+			.offset()
+			.min(row_count.saturating_sub(visible.max(1)));
+		if let Some(selected) = self.table_state.selected() {
+			// At least the selected row is kept in the range, even if none fit
+			offset = offset
+				.max((selected + 1).saturating_sub(visible.max(1)))
+				.min(selected);
+		}
+		*self.table_state.offset_mut() = offset;
+		offset..(offset + visible).min(row_count).max(offset)
+	}
+
+	/// Forgets the area the table was last rendered in, for when the terminal is resized, so it
+	/// isn't used (e.g. to find the row that was clicked on) until the table is rendered again
+	pub fn forget_area(&mut self) {
+		self.table_area = layout::Rect::default();
+		self.visible_row_num = 0;
+	}
+
+	/// Returns the displayed row at the given position on the screen, if it is over a row of the
+	/// table
+	pub fn row_at(&self, column: u16, row: u16) -> Option<usize> {
+		let area = self.table_area;
+		// The first row is below the top border and the headings
+		let first = area.y + 2;
+		let inside = (area.x..area.right()).contains(&column)
+			&& (first..area.bottom().saturating_sub(1)).contains(&row);
+		inside.then(|| self.table_state.offset() + usize::from((row - first) / self.row_height))
 	}
 
 	pub fn deselect_cell(&mut self) {
 		self.table_state.select_column(None);
 	}
+
+	/// Scrolls the columns so the selected one is on the screen, given the width of each shown
+	/// column and the width available for them. Columns are only scrolled past when they don't all
+	/// fit. Returns the range of the columns that are on the screen
+	pub fn fit_columns(&mut self, widths: &[u16], available: u16) -> Range<usize> {
+		self.column_widths = widths.to_vec();
+		self.available_width = available;
+		if widths.is_empty() {
+			return 0..0;
+		}
+		let mut start = self.column_offset.min(widths.len() - 1);
+		if let Some(selected) = self.table_state.selected_column() {
+			// Moving left with no column selected selects the last possible one, so the selection
+			// is kept to the columns that are shown
+			let selected = selected.min(widths.len() - 1);
+			self.table_state.select_column(Some(selected));
+			start = start.min(selected);
+			while self.columns_end(start) <= selected {
+				start += 1;
+			}
+		}
+		while start > 0 && self.columns_end(start - 1) == widths.len() {
+			start -= 1;
+		}
+		self.column_offset = start;
+		start..self.columns_end(start)
+	}
+
+	/// Returns the end of the columns that fit on the screen when starting from the given one, as
+	/// of when the table was last rendered. At least one column always does, even if it is cut
+	/// off
+	fn columns_end(&self, start: usize) -> usize {
+		let mut used = 0;
+		let mut end = start;
+		for width in self.column_widths.iter().skip(start) {
+			// Every column after the first is spaced from the one before it
+			let needed = if end == start { *width } else { width + 1 };
+			if end > start && used + needed > self.available_width {
+				break;
+			}
+			used += needed;
+			end += 1;
+		}
+		end
+	}
+
+	/// Scrolls the columns right or left by one. The selected column moves along if it would go
+	/// off the screen
+	pub fn scroll_columns(&mut self, right: bool) {
+		let offset = if right {
+			if self.columns_end(self.column_offset) >= self.column_widths.len() {
+				return;
+			}
+			self.column_offset + 1
+		} else {
+			let Some(offset) = self.column_offset.checked_sub(1) else {
+				return;
+			};
+			offset
+		};
+		self.column_offset = offset;
+		let last = self.columns_end(offset).saturating_sub(1);
+		if let Some(selected) = self.table_state.selected_column() {
+			self.table_state
+				.select_column(Some(selected.clamp(offset, last.max(offset))));
+		}
+	}
 }
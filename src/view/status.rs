@@ -0,0 +1,219 @@
+//! The line at the bottom of the screen, showing the mode, the command being typed, the selected
+//! sheet and row, and whether there are unsaved changes. While a key sequence is being typed, the
+//! keys that can follow it are listed above the line
+
+use std::time::Duration;
+
+use ratatui::{
+	buffer::Buffer,
+	layout::{Constraint, Layout, Rect},
+	style::{Modifier, Style},
+	text::{Line, Span},
+	widgets::{Block, BorderType, Borders, Clear, Widget},
+};
+
+use crate::{
+	controller::{ControllerState, Mode},
+	model::Model,
+	view::{View, theme::Theme},
+};
+
+/// The status line, built from the state of the controller and the view by
+/// [`View::status_line`]
+pub(super) struct StatusLine {
+	pub mode: Mode,
+	/// The keys of the command being typed, with any counts. See
+	/// [`ControllerState::pending_keys`]
+	pub keys: String,
+	/// How long is left to finish typing the keys. See [`ControllerState::key_time_left`]
+	pub key_time_left: Option<Duration>,
+	/// The name of the selected sheet, or of the dashboard when it is shown
+	pub sheet: String,
+	/// The selected row (counting from 1) and the amount of rows shown, when a sheet is shown
+	pub position: Option<(usize, usize)>,
+	/// Whether the file has changed since it was last saved
+	pub unsaved: bool,
+	/// Reminders of settings that change how the program behaves, like the mouse being released
+	pub notes: Vec<String>,
+	pub theme: Theme,
+}
+
+impl View {
+	/// Returns the status line for the current state of the program
+	pub(super) fn status_line(&mut self, model: &Model, cs: &ControllerState) -> StatusLine {
+		let mut notes = vec![];
+		if !cs.mouse_capture {
+			notes.push("[mouse released - <gm> to capture]".to_string());
+		}
+		if cs.open_file_count() > 1 {
+			notes.push(format!(
+				"[{} files open - <]w> <[w> to switch]",
+				cs.open_file_count()
+			));
+		}
+		let (sheet, position) = if self.dashboard {
+			("Dashboard".to_string(), None)
+		} else {
+			let sheet = self.get_selected_sheet(model);
+			let total = sheet.displayed_row_count();
+			let position = self
+				.get_selected_display_row(model)
+				.filter(|_| total > 0)
+				.map(|row| (row + 1, total));
+			(sheet.name.clone(), position)
+		};
+		StatusLine {
+			mode: cs.mode(),
+			keys: cs.pending_keys(),
+			key_time_left: cs.key_time_left(),
+			sheet,
+			position,
+			unsaved: model.has_unsaved_changes(),
+			notes,
+			theme: self.theme,
+		}
+	}
+}
+
+impl StatusLine {
+	/// Returns the style the mode is shown in, which stands out more the further it is from
+	/// normal mode
+	fn mode_style(&self) -> Style {
+		let color = match self.mode {
+			Mode::Normal => self.theme.accent,
+			Mode::Operator => self.theme.notice,
+			Mode::Insert => self.theme.positive,
+			Mode::Popup => self.theme.info,
+		};
+		Style::default()
+			.fg(color)
+			.add_modifier(Modifier::REVERSED | Modifier::BOLD)
+	}
+}
+
+impl Widget for StatusLine {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let mut left = vec![
+			Span::styled(format!(" {} ", self.mode), self.mode_style()),
+			Span::raw(format!(" {}", self.sheet)),
+		];
+		if self.unsaved {
+			left.push(Span::styled(" [+]", Style::default().fg(self.theme.notice)));
+		}
+		for note in &self.notes {
+			left.push(Span::styled(
+				format!(" {note}"),
+				Style::default().fg(self.theme.dim),
+			));
+		}
+
+		let mut right = vec![Span::styled(
+			self.keys.clone(),
+			Style::default().fg(self.theme.accent),
+		)];
+		if let Some(time_left) = self.key_time_left {
+			right.push(Span::styled(
+				format!(" {:.1}s", time_left.as_secs_f64()),
+				Style::default().fg(self.theme.dim),
+			));
+		}
+		if let Some((row, total)) = self.position {
+			right.push(Span::raw(format!("  {row}/{total} ")));
+		}
+		let right = Line::from(right).right_aligned();
+
+		let [left_area, right_area] = Layout::horizontal([
+			Constraint::Fill(1),
+			Constraint::Length(u16::try_from(right.width()).unwrap_or(u16::MAX)),
+		])
+		.areas(area);
+		Line::from(left).render(left_area, buf);
+		right.render(right_area, buf);
+	}
+}
+
+/// The keys that can follow the key sequence being typed, and what each does, in as many columns
+/// as are needed to fit above the status line. See [`ControllerState::key_hints`]
+pub(super) struct KeyHints<'a> {
+	/// The keys typed so far
+	pub keys: String,
+	pub hints: &'a [(String, String)],
+	pub theme: Theme,
+}
+
+impl KeyHints<'_> {
+	/// Returns the area the hints are drawn in, across the bottom of the given area
+	fn area(&self, area: Rect) -> Rect {
+		let (rows, columns, width) = self.grid(area);
+		let width = u16::try_from(columns * width + 2).map_or(area.width, |w| w.min(area.width));
+		let height = u16::try_from(rows + 2).map_or(area.height, |h| h.min(area.height));
+		Rect {
+			x: area.x,
+			y: area.bottom() - height,
+			width,
+			height,
+		}
+	}
+
+	/// Returns how many rows and columns the hints are laid out in, and the width of each column,
+	/// given the area they can take up
+	fn grid(&self, area: Rect) -> (usize, usize, usize) {
+		/// The space between the columns
+		const GAP: usize = 3;
+		// Up to half the area, leaving room for the borders
+		let max_rows = usize::from(area.height / 2).saturating_sub(2).max(1);
+		let rows = self.hints.len().clamp(1, max_rows);
+		let columns = self.hints.len().div_ceil(rows).max(1);
+		let width = self
+			.hints
+			.iter()
+			.map(|(keys, description)| keys.chars().count() + description.chars().count() + 1)
+			.max()
+			.unwrap_or(0)
+			+ GAP;
+		(rows, columns, width)
+	}
+}
+
+impl Widget for KeyHints<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let (rows, _, width) = self.grid(area);
+		let area = self.area(area);
+		Clear.render(area, buf);
+		let block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.title(Line::from(self.keys.clone()).style(Style::default().fg(self.theme.accent)));
+		let inner = block.inner(area);
+		block.render(area, buf);
+
+		let key_style = Style::default().fg(self.theme.accent);
+		for (i, (keys, description)) in self.hints.iter().enumerate() {
+			let (Ok(x), Ok(y)) = (u16::try_from(i / rows * width), u16::try_from(i % rows)) else {
+				continue;
+			};
+			if x >= inner.width {
+				break;
+			}
+			let description_style = if description.starts_with('+') {
+				Style::default().fg(self.theme.info)
+			} else {
+				Style::default()
+			};
+			Line::from(vec![
+				Span::styled(keys.clone(), key_style),
+				Span::raw(" "),
+				Span::styled(description.clone(), description_style),
+			])
+			.render(
+				Rect {
+					x: inner.x + x,
+					y: inner.y + y,
+					width: inner.width - x,
+					height: 1,
+				},
+				buf,
+			);
+		}
+	}
+}
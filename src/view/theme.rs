@@ -0,0 +1,174 @@
+//! The colours the program is drawn in. They are picked from a preset in the config file, and any
+//! of them can be changed on top of it
+
+use std::collections::BTreeMap;
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+/// The colours of the program, by what they are used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+	/// Titles, headings, and the contents of the selected cell
+	pub accent: Color,
+	/// Money coming in, and things being added or kept
+	pub positive: Color,
+	/// Money going out, and things being removed or skipped
+	pub negative: Color,
+	/// Errors, and rows that need fixing
+	pub error: Color,
+	/// Settings that change what is shown, like the filter, and the selected sheet
+	pub notice: Color,
+	/// Projected balances and trends
+	pub info: Color,
+	/// Text that matters less, like scheduled transactions and the pane without focus
+	pub dim: Color,
+	/// Pending transactions
+	pub pending: Color,
+	/// The text of the selected cell or item
+	pub selection_fg: Color,
+	/// The background of the selected cell or item
+	pub selection_bg: Color,
+	/// The background of the selected row of a sheet
+	pub selected_row: Color,
+	/// The background of every other row of a sheet, when they are striped
+	pub stripe: Color,
+}
+
+impl Default for Theme {
+	fn default() -> Self {
+		ThemePreset::Dark.theme()
+	}
+}
+
+impl Theme {
+	/// The names of the colours, as they are set in the config file
+	const NAMES: [&str; 12] = [
+		"accent",
+		"positive",
+		"negative",
+		"error",
+		"notice",
+		"info",
+		"dim",
+		"pending",
+		"selection_fg",
+		"selection_bg",
+		"selected_row",
+		"stripe",
+	];
+
+	/// Returns the colour with the given name, if there is one. See [`Theme::NAMES`]
+	fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+		Some(match name {
+			"accent" => &mut self.accent,
+			"positive" => &mut self.positive,
+			"negative" => &mut self.negative,
+			"error" => &mut self.error,
+			"notice" => &mut self.notice,
+			"info" => &mut self.info,
+			"dim" => &mut self.dim,
+			"pending" => &mut self.pending,
+			"selection_fg" => &mut self.selection_fg,
+			"selection_bg" => &mut self.selection_bg,
+			"selected_row" => &mut self.selected_row,
+			"stripe" => &mut self.stripe,
+			_ => return None,
+		})
+	}
+}
+
+/// The themes built into the program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreset {
+	/// For terminals with a dark background, using the terminal's own colours
+	#[default]
+	Dark,
+	/// For terminals with a light background, using the terminal's own colours
+	Light,
+	/// The Solarized palette, which works on a dark or light background
+	Solarized,
+}
+
+impl ThemePreset {
+	/// Returns the colours of the preset
+	pub fn theme(self) -> Theme {
+		match self {
+			ThemePreset::Dark => Theme {
+				accent: Color::Green,
+				positive: Color::Green,
+				negative: Color::Red,
+				error: Color::Red,
+				notice: Color::Yellow,
+				info: Color::Cyan,
+				dim: Color::DarkGray,
+				pending: Color::Gray,
+				selection_fg: Color::Blue,
+				selection_bg: Color::DarkGray,
+				selected_row: Color::Black,
+				stripe: Color::Indexed(235),
+			},
+			ThemePreset::Light => Theme {
+				accent: Color::Blue,
+				positive: Color::Green,
+				negative: Color::Red,
+				error: Color::Red,
+				notice: Color::Magenta,
+				info: Color::Cyan,
+				dim: Color::Gray,
+				pending: Color::DarkGray,
+				selection_fg: Color::White,
+				selection_bg: Color::Blue,
+				selected_row: Color::Rgb(224, 224, 224),
+				stripe: Color::Rgb(242, 242, 242),
+			},
+			ThemePreset::Solarized => Theme {
+				accent: Color::Rgb(0x85, 0x99, 0x00),
+				positive: Color::Rgb(0x85, 0x99, 0x00),
+				negative: Color::Rgb(0xdc, 0x32, 0x2f),
+				error: Color::Rgb(0xdc, 0x32, 0x2f),
+				notice: Color::Rgb(0xb5, 0x89, 0x00),
+				info: Color::Rgb(0x2a, 0xa1, 0x98),
+				dim: Color::Rgb(0x58, 0x6e, 0x75),
+				pending: Color::Rgb(0x83, 0x94, 0x96),
+				selection_fg: Color::Rgb(0x26, 0x8b, 0xd2),
+				selection_bg: Color::Rgb(0x07, 0x36, 0x42),
+				selected_row: Color::Rgb(0x07, 0x36, 0x42),
+				stripe: Color::Rgb(0x03, 0x30, 0x3c),
+			},
+		}
+	}
+}
+
+/// The theme as it is set in the config file - a preset, and any colours changed on top of it
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+	pub preset: ThemePreset,
+	/// The colours that replace the preset's, by name (see [`Theme`]). A colour is a name like
+	/// `red` or `dark gray`, a hex code like `#859900`, or the index of a terminal colour
+	pub colors: BTreeMap<String, String>,
+}
+
+impl ThemeConfig {
+	/// Returns the colours of the preset, with the ones that were changed replaced
+	///
+	/// # Errors
+	/// With the first colour that isn't one, or whose name isn't in [`Theme`]
+	pub fn theme(&self) -> Result<Theme, String> {
+		let mut theme = self.preset.theme();
+		for (name, value) in &self.colors {
+			let color = theme.color_mut(name).ok_or_else(|| {
+				format!(
+					"Unknown colour \"{name}\", expected one of {}",
+					Theme::NAMES.join(", ")
+				)
+			})?;
+			*color = value
+				.parse()
+				.map_err(|_| format!("Invalid colour \"{value}\" for {name}"))?;
+		}
+		Ok(theme)
+	}
+}
@@ -0,0 +1,62 @@
+//! Messages shown briefly in the top right corner of the sheet, like that the file was saved. See
+//! [`ControllerState::notify`](crate::controller::ControllerState::notify)
+
+use std::collections::VecDeque;
+
+use ratatui::{
+	buffer::Buffer,
+	layout::Rect,
+	style::Style,
+	text::Line,
+	widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph, Widget},
+};
+
+use crate::{controller::Notification, view::theme::Theme};
+
+/// The messages that haven't expired yet, stacked from the top with the oldest first
+pub(super) struct Toasts<'a> {
+	pub notifications: &'a VecDeque<Notification>,
+	pub theme: Theme,
+}
+
+impl Widget for Toasts<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		/// The height of each message, with its borders
+		const HEIGHT: u16 = 3;
+		let mut y = area.y;
+		for notification in self
+			.notifications
+			.iter()
+			.filter(|n| n.shown_at.elapsed() < Notification::DURATION)
+		{
+			if y + HEIGHT > area.bottom() {
+				break;
+			}
+			let width = u16::try_from(notification.message.chars().count() + 4)
+				.unwrap_or(u16::MAX)
+				.min(area.width);
+			let toast_area = Rect {
+				x: area.right() - width,
+				y,
+				width,
+				height: HEIGHT,
+			};
+			let color = if notification.error {
+				self.theme.error
+			} else {
+				self.theme.info
+			};
+			Clear.render(toast_area, buf);
+			Paragraph::new(Line::from(notification.message.as_str()))
+				.block(
+					Block::default()
+						.borders(Borders::ALL)
+						.border_type(BorderType::Rounded)
+						.border_style(Style::default().fg(color))
+						.padding(Padding::horizontal(1)),
+				)
+				.render(toast_area, buf);
+			y += HEIGHT;
+		}
+	}
+}
@@ -0,0 +1,63 @@
+//! Watching the open file for changes made to it on disk, e.g. by another program. The watcher
+//! only says that the file was touched, with an [`AppEvent::FileChanged`]; whether it really
+//! changed (rather than being saved by this instance) is worked out by the main loop. See
+//! [`Model::read_disk`](crate::model::Model::read_disk)
+
+use std::{
+	path::{Path, PathBuf},
+	sync::mpsc::Sender,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::events::AppEvent;
+
+/// Watches the file being shown, following it as another is opened
+pub struct FileWatcher {
+	events: Sender<AppEvent>,
+	/// The file being watched, if any
+	path: Option<PathBuf>,
+	/// Dropping it stops the watching
+	watcher: Option<RecommendedWatcher>,
+}
+
+impl FileWatcher {
+	pub fn new(events: Sender<AppEvent>) -> Self {
+		Self {
+			events,
+			path: None,
+			watcher: None,
+		}
+	}
+
+	/// Watches the file with the given name instead of the one being watched, if it isn't already.
+	/// The folder it is in is watched, rather than the file, so it's still watched if it is
+	/// replaced by another (as many programs save files). If it can't be watched (e.g. it doesn't
+	/// exist yet), nothing is
+	pub fn follow(&mut self, filename: Option<&str>) {
+		let path = filename.and_then(|filename| Path::new(filename).canonicalize().ok());
+		if path == self.path {
+			return;
+		}
+		self.watcher = path.as_ref().and_then(|path| self.watch(path.clone()));
+		self.path = path;
+	}
+
+	fn watch(&self, path: PathBuf) -> Option<RecommendedWatcher> {
+		let folder = path.parent()?.to_path_buf();
+		let events = self.events.clone();
+		let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+			let Ok(event) = event else {
+				return;
+			};
+			let touched = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+				&& event.paths.contains(&path);
+			if touched {
+				let _ = events.send(AppEvent::FileChanged);
+			}
+		})
+		.ok()?;
+		watcher.watch(&folder, RecursiveMode::NonRecursive).ok()?;
+		Some(watcher)
+	}
+}